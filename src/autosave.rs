@@ -0,0 +1,217 @@
+//! The game being played, saved after every move so that a crash or an
+//! accidental Ctrl-C doesn't lose it, and offered for resuming next time.
+
+use crate::clock::{Clock, TimeControl};
+use crate::config::{Opponent, Side};
+use crate::core::{ChessColour, Move};
+use crate::game::ChessGame;
+use crate::variant::Variant;
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::Duration;
+
+/// A game in progress with the settings it was started with, e.g.
+///
+/// ```toml
+/// opponent = "solver"
+/// colour = "white"
+/// engine = "stockfish"
+/// skill = 5
+/// chess960 = false
+/// time = "5+3"
+/// white_ms = 271350
+/// black_ms = 288020
+/// moves = ["e2e4", "c7c5", "g1f3"]
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SavedGame {
+    pub opponent: Opponent,
+    /// The side the user plays.
+    pub colour: Side,
+    pub engine: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub elo: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub skill: Option<u8>,
+    #[serde(default)]
+    pub chess960: bool,
+    /// The position the game started from, if not the usual one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fen: Option<String>,
+    /// The time control, as given to `--time`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub time: Option<String>,
+    /// The time each side had left, in milliseconds.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub white_ms: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub black_ms: Option<u64>,
+    /// The moves played so far, in UCI notation.
+    #[serde(default)]
+    pub moves: Vec<String>,
+}
+
+impl SavedGame {
+    /// Record the moves and clocks of `game`.
+    pub fn update(&mut self, game: &ChessGame) {
+        // a Chess960 game's starting position was picked at random
+        self.fen = game.start_fen().map(str::to_string);
+        self.moves = game.played_moves().iter().map(|mv| mv.to_uci()).collect();
+        let remaining = |colour| {
+            game.clock()
+                .map(|clock: &Clock| clock.remaining(colour).as_millis() as u64)
+        };
+        self.white_ms = remaining(ChessColour::White);
+        self.black_ms = remaining(ChessColour::Black);
+    }
+
+    /// The time control, if the game had one.
+    pub fn time_control(&self) -> anyhow::Result<Option<TimeControl>> {
+        Ok(self
+            .time
+            .as_deref()
+            .map(TimeControl::from_str)
+            .transpose()?)
+    }
+
+    /// The game as it was saved, with its clock if it had one.
+    pub fn game(&self) -> anyhow::Result<ChessGame> {
+        let variant = if self.chess960 {
+            Variant::Chess960
+        } else {
+            Variant::Standard
+        };
+        let mut game = match &self.fen {
+            Some(fen) => ChessGame::from_fen(fen, variant)?,
+            None => ChessGame::default(),
+        };
+        // the clock is set afterwards, so that replaying the moves doesn't
+        // charge for them again
+        for mv in &self.moves {
+            game.make_move(&Move::from_uci(mv)?)
+                .with_context(|| format!("could not replay the saved move {mv}"))?;
+        }
+        if let Some(control) = self.time_control()? {
+            let initial = control.initial().as_millis() as u64;
+            let remaining = |ms: Option<u64>| Duration::from_millis(ms.unwrap_or(initial));
+            game.set_clock(Clock::with_remaining(
+                control,
+                remaining(self.white_ms),
+                remaining(self.black_ms),
+            ));
+        }
+        Ok(game)
+    }
+
+    /// Who the game is against and how far it got, for offering to resume it.
+    pub fn describe(&self) -> String {
+        let opponent = match self.opponent {
+            Opponent::Stockfish => self.engine.as_str(),
+            Opponent::Solver => "the solver",
+            Opponent::Human => "another person",
+        };
+        let colour = match self.colour {
+            Side::Black => "Black",
+            Side::White | Side::Random => "White",
+        };
+        format!(
+            "as {colour} against {opponent}, at move {}",
+            self.moves.len() / 2 + 1
+        )
+    }
+
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("could not read the saved game {}", path.display()))?;
+        toml::from_str(&text).with_context(|| format!("invalid saved game {}", path.display()))
+    }
+
+    /// Write the game to `path`, by way of a temporary file so that a crash
+    /// while writing doesn't lose the previous save.
+    pub fn save(&self, path: &Path) -> anyhow::Result<()> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)
+                .with_context(|| format!("could not create {}", dir.display()))?;
+        }
+        let partial = path.with_extension("partial");
+        std::fs::write(&partial, toml::to_string(self)?)
+            .with_context(|| format!("could not save the game to {}", partial.display()))?;
+        std::fs::rename(&partial, path)
+            .with_context(|| format!("could not save the game to {}", path.display()))
+    }
+}
+
+/// `chess/autosave.toml` in the data directory.
+pub fn default_path() -> Option<PathBuf> {
+    Some(crate::config::data_dir()?.join("autosave.toml"))
+}
+
+/// Keeps the file at `path` up to date with the game as it is played.
+pub struct Autosave {
+    path: PathBuf,
+    saved: SavedGame,
+}
+
+impl Autosave {
+    pub fn new(path: PathBuf, saved: SavedGame) -> Self {
+        Self { path, saved }
+    }
+
+    /// Save `game` as it stands, or once it is over, remove the save as there
+    /// is nothing left to resume. Failing to save only warns, as it's no
+    /// reason to stop playing.
+    pub fn record(&mut self, game: &ChessGame) {
+        let saved = if game.result().is_some() {
+            match std::fs::remove_file(&self.path) {
+                Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+                _ => Ok(()),
+            }
+        } else {
+            self.saved.update(game);
+            self.saved.save(&self.path)
+        };
+        if let Err(e) = saved {
+            tracing::warn!("Could not autosave the game: {e:#}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_save_and_load() {
+        let saved: SavedGame = toml::from_str(
+            r#"
+            opponent = "solver"
+            colour = "black"
+            engine = "stockfish"
+            time = "5+3"
+            white_ms = 271350
+            moves = ["e2e4"]
+            "#,
+        )
+        .unwrap();
+        assert_eq!(saved.describe(), "as Black against the solver, at move 1");
+        let mut game = saved.game().unwrap();
+        assert_eq!(game.played_moves(), [Move::from_uci("e2e4").unwrap()]);
+        let clock = game.clock().unwrap();
+        assert_eq!(clock.remaining(ChessColour::White).as_millis(), 271350);
+        assert_eq!(clock.remaining(ChessColour::Black).as_secs(), 300);
+
+        let path = std::env::temp_dir().join(format!("autosave-{}.toml", std::process::id()));
+        let mut autosave = Autosave::new(path.clone(), saved);
+        game.make_move(&Move::from_uci("c7c5").unwrap()).unwrap();
+        autosave.record(&game);
+        let loaded = SavedGame::load(&path).unwrap();
+        assert_eq!(loaded.moves, ["e2e4", "c7c5"]);
+        assert_eq!(loaded.game().unwrap().fen(), game.fen());
+
+        game.resign(ChessColour::Black).unwrap();
+        autosave.record(&game);
+        assert!(!path.exists());
+    }
+}
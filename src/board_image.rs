@@ -0,0 +1,439 @@
+//! Pictures of the board to share: a PNG of a position, or an animated GIF
+//! of a whole game. The pieces are drawn from small built-in sprites, so no
+//! fonts or image files are needed.
+
+use crate::core::{ChessPiece, ChessPieceKind, Position, Square};
+use crate::game::ChessGame;
+use crate::render::{self, Annotation, RenderOptions, Rgb};
+use anyhow::Context;
+use image::codecs::gif::{GifEncoder, Repeat};
+use image::{Delay, Frame, ImageFormat, Pixel, Rgba, RgbaImage};
+use std::path::Path;
+use std::time::Duration;
+
+/// The width and height of a square in pixels, a whole multiple of the
+/// sprites' size.
+const SQUARE_SIZE: u32 = 48;
+const SPRITE_SIZE: u32 = 16;
+
+/// How long each position of a game is shown for in a GIF.
+pub const FRAME_DELAY: Duration = Duration::from_secs(1);
+
+const OUTLINE: Rgba<u8> = Rgba([32, 32, 32, 255]);
+
+/// The width of an arrow's shaft, and the length and half the width of its
+/// head, in pixels.
+const ARROW_WIDTH: f32 = 10.0;
+const ARROW_HEAD_LENGTH: f32 = 20.0;
+const ARROW_HEAD_WIDTH: f32 = 14.0;
+
+/// How opaque arrows are, so the pieces under them still show.
+const ARROW_ALPHA: u8 = 200;
+
+// `#` is the outline and `o` the piece's own colour
+const PAWN: [&str; 16] = [
+    "................",
+    "................",
+    "................",
+    "......####......",
+    ".....#oooo#.....",
+    ".....#oooo#.....",
+    "......#oo#......",
+    ".....#oooo#.....",
+    "......#oo#......",
+    "......#oo#......",
+    ".....#oooo#.....",
+    "....#oooooo#....",
+    "...#oooooooo#...",
+    "...##########...",
+    "................",
+    "................",
+];
+const KNIGHT: [&str; 16] = [
+    "................",
+    "................",
+    "......##........",
+    ".....#oo##......",
+    "....#oooooo#....",
+    "...#oooooooo#...",
+    "..#oo#ooooooo#..",
+    "..###.#oooooo#..",
+    "......#oooooo#..",
+    ".....#ooooooo#..",
+    "....#oooooooo#..",
+    "...#ooooooooo#..",
+    "...#ooooooooo#..",
+    "...###########..",
+    "................",
+    "................",
+];
+const BISHOP: [&str; 16] = [
+    "................",
+    ".......##.......",
+    "......#oo#......",
+    ".....#oooo#.....",
+    "....#oo#ooo#....",
+    "....#ooo#oo#....",
+    "....#oooooo#....",
+    ".....#oooo#.....",
+    "......#oo#......",
+    ".....######.....",
+    "......#oo#......",
+    ".....#oooo#.....",
+    "...#oooooooo#...",
+    "...##########...",
+    "................",
+    "................",
+];
+const ROOK: [&str; 16] = [
+    "................",
+    "................",
+    "...##..##..##...",
+    "...#o##oo##o#...",
+    "...#oooooooo#...",
+    "....#oooooo#....",
+    ".....#oooo#.....",
+    ".....#oooo#.....",
+    ".....#oooo#.....",
+    ".....#oooo#.....",
+    "....#oooooo#....",
+    "...#oooooooo#...",
+    "...#oooooooo#...",
+    "...##########...",
+    "................",
+    "................",
+];
+const QUEEN: [&str; 16] = [
+    "................",
+    "..#....##....#..",
+    "..#o#.#oo#.#o#..",
+    "..#oo#oooo#oo#..",
+    "..#oooooooooo#..",
+    "...#oooooooo#...",
+    "...#oooooooo#...",
+    "....#oooooo#....",
+    "....#oooooo#....",
+    "....########....",
+    ".....#oooo#.....",
+    "....#oooooo#....",
+    "...#oooooooo#...",
+    "...##########...",
+    "................",
+    "................",
+];
+const KING: [&str; 16] = [
+    ".......##.......",
+    "......####......",
+    ".......##.......",
+    "....###oo###....",
+    "...#ooo##ooo#...",
+    "..#oooo##oooo#..",
+    "..#oooooooooo#..",
+    "...#oooooooo#...",
+    "....#oooooo#....",
+    "....########....",
+    ".....#oooo#.....",
+    "....#oooooo#....",
+    "...#oooooooo#...",
+    "...##########...",
+    "................",
+    "................",
+];
+
+fn sprite(kind: ChessPieceKind) -> &'static [&'static str; 16] {
+    match kind {
+        ChessPieceKind::Pawn => &PAWN,
+        ChessPieceKind::Knight => &KNIGHT,
+        ChessPieceKind::Bishop => &BISHOP,
+        ChessPieceKind::Rook => &ROOK,
+        ChessPieceKind::Queen => &QUEEN,
+        ChessPieceKind::King => &KING,
+    }
+}
+
+/// A picture of `game`'s current position with `annotations` drawn on it,
+/// later ones over earlier ones, in `options`' theme and from Black's side if
+/// flipped.
+pub fn position_image(
+    game: &ChessGame,
+    annotations: &[Annotation],
+    options: &RenderOptions,
+) -> RgbaImage {
+    let theme = &options.theme;
+    let board = game.get_board();
+    let checked_king = render::checked_king(game);
+    let mut image = RgbaImage::new(8 * SQUARE_SIZE, 8 * SQUARE_SIZE);
+    for row in 0..8 {
+        for column in 0..8 {
+            // where the square is drawn, from the top left
+            let (x, y) = screen_square(row, column, options.flipped);
+            // arrows are drawn over the pieces afterwards instead
+            let square = annotations.iter().rev().find(|annotation| {
+                matches!(annotation, Annotation::Square(..)) && annotation.covers(row, column)
+            });
+            let in_check = checked_king.is_some_and(|king| {
+                usize::from(king.row) == row && usize::from(king.column) == column
+            });
+            let colour = if in_check {
+                rgba(theme.check)
+            } else if let Some(square) = square {
+                rgba(square.colour())
+            } else {
+                rgba(theme.square(row, column))
+            };
+            let (left, top) = (x as u32 * SQUARE_SIZE, y as u32 * SQUARE_SIZE);
+            for dy in 0..SQUARE_SIZE {
+                for dx in 0..SQUARE_SIZE {
+                    image.put_pixel(left + dx, top + dy, colour);
+                }
+            }
+            if let Some(piece) = &board
+                .cell(Square::new(row, column).expect("on the board"))
+                .piece
+            {
+                draw_piece(
+                    &mut image,
+                    piece,
+                    rgba(theme.piece(piece.colour)),
+                    left,
+                    top,
+                );
+            }
+        }
+    }
+    for annotation in annotations {
+        if let Annotation::Arrow(from, to, colour) = annotation {
+            let (from, to) = (centre(from, options.flipped), centre(to, options.flipped));
+            draw_arrow(&mut image, from, to, translucent(*colour));
+        }
+    }
+    image
+}
+
+/// Where the square in `row` and `column` is drawn, counted in squares from
+/// the top left.
+fn screen_square(row: usize, column: usize, flipped: bool) -> (usize, usize) {
+    if flipped {
+        (7 - column, row)
+    } else {
+        (column, 7 - row)
+    }
+}
+
+/// The middle of `pos`'s square in pixels.
+fn centre(pos: &Position, flipped: bool) -> (f32, f32) {
+    let (x, y) = screen_square(usize::from(pos.row), usize::from(pos.column), flipped);
+    let middle = |i: usize| (i as f32 + 0.5) * SQUARE_SIZE as f32;
+    (middle(x), middle(y))
+}
+
+/// Draw an arrow from `from` to `to`, in pixels, with its head ending at `to`.
+fn draw_arrow(image: &mut RgbaImage, from: (f32, f32), to: (f32, f32), colour: Rgba<u8>) {
+    let (dx, dy) = (to.0 - from.0, to.1 - from.1);
+    let length = dx.hypot(dy);
+    if length == 0.0 {
+        return;
+    }
+    let (ux, uy) = (dx / length, dy / length);
+    for y in 0..image.height() {
+        for x in 0..image.width() {
+            let (px, py) = (x as f32 + 0.5 - from.0, y as f32 + 0.5 - from.1);
+            // how far the pixel is along the arrow, and to either side of it
+            let along = px * ux + py * uy;
+            let across = (py * ux - px * uy).abs();
+            let inside = if !(0.0..=length).contains(&along) {
+                false
+            } else if along > length - ARROW_HEAD_LENGTH {
+                across <= ARROW_HEAD_WIDTH * (length - along) / ARROW_HEAD_LENGTH
+            } else {
+                across <= ARROW_WIDTH / 2.0
+            };
+            if inside {
+                image.get_pixel_mut(x, y).blend(&colour);
+            }
+        }
+    }
+}
+
+fn rgba(Rgb(r, g, b): Rgb) -> Rgba<u8> {
+    Rgba([r, g, b, 255])
+}
+
+fn translucent(Rgb(r, g, b): Rgb) -> Rgba<u8> {
+    Rgba([r, g, b, ARROW_ALPHA])
+}
+
+fn draw_piece(image: &mut RgbaImage, piece: &ChessPiece, fill: Rgba<u8>, left: u32, top: u32) {
+    let scale = SQUARE_SIZE / SPRITE_SIZE;
+    for (sy, line) in sprite(piece.kind).iter().enumerate() {
+        for (sx, pixel) in line.bytes().enumerate() {
+            let colour = match pixel {
+                b'#' => OUTLINE,
+                b'o' => fill,
+                _ => continue,
+            };
+            for dy in 0..scale {
+                for dx in 0..scale {
+                    let x = left + sx as u32 * scale + dx;
+                    let y = top + sy as u32 * scale + dy;
+                    image.put_pixel(x, y, colour);
+                }
+            }
+        }
+    }
+}
+
+/// Save the current position of `game` to `path` as a PNG, with both
+/// players' last moves picked out.
+pub fn save_png(game: &ChessGame, path: &Path, options: &RenderOptions) -> anyhow::Result<()> {
+    position_image(game, &render::last_moves(game, &options.theme), options)
+        .save_with_format(path, ImageFormat::Png)
+        .with_context(|| format!("could not save {}", path.display()))
+}
+
+/// [`position_image`] encoded as a PNG, to send rather than save.
+pub fn png(
+    game: &ChessGame,
+    annotations: &[Annotation],
+    options: &RenderOptions,
+) -> anyhow::Result<Vec<u8>> {
+    let mut png = Vec::new();
+    position_image(game, annotations, options)
+        .write_to(&mut std::io::Cursor::new(&mut png), ImageFormat::Png)
+        .context("could not encode the board as a PNG")?;
+    Ok(png)
+}
+
+/// Save every position of `game`, from the start, to `path` as an animated
+/// GIF that shows each for `delay`.
+pub fn save_gif(
+    game: &ChessGame,
+    path: &Path,
+    options: &RenderOptions,
+    delay: Duration,
+) -> anyhow::Result<()> {
+    let mut replay = game.start_position()?;
+    let delay = Delay::from_saturating_duration(delay);
+    let mut frames = vec![Frame::from_parts(
+        position_image(
+            &replay,
+            &render::last_moves(&replay, &options.theme),
+            options,
+        ),
+        0,
+        0,
+        delay,
+    )];
+    for mv in game.played_moves() {
+        replay.make_move(mv)?;
+        frames.push(Frame::from_parts(
+            position_image(
+                &replay,
+                &render::last_moves(&replay, &options.theme),
+                options,
+            ),
+            0,
+            0,
+            delay,
+        ));
+    }
+    let file = std::fs::File::create(path)
+        .with_context(|| format!("could not create {}", path.display()))?;
+    let mut encoder = GifEncoder::new(std::io::BufWriter::new(file));
+    encoder.set_repeat(Repeat::Infinite)?;
+    encoder
+        .encode_frames(frames)
+        .with_context(|| format!("could not save {}", path.display()))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::core::Move;
+    use crate::render::Theme;
+
+    #[test]
+    fn test_sprites_are_square() {
+        for kind in [
+            ChessPieceKind::Pawn,
+            ChessPieceKind::Knight,
+            ChessPieceKind::Bishop,
+            ChessPieceKind::Rook,
+            ChessPieceKind::Queen,
+            ChessPieceKind::King,
+        ] {
+            for line in sprite(kind) {
+                assert_eq!(line.len(), SPRITE_SIZE as usize, "{kind:?}: {line}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_arrows() {
+        let mut game = ChessGame::default();
+        for mv in ["e2e4", "e7e5"] {
+            game.make_move(&Move::from_uci(mv).unwrap()).unwrap();
+        }
+        let theme = Theme::CLASSIC;
+        let knight = Move::from_uci("g1f3").unwrap();
+        let arrow = Annotation::Arrow(knight.from, knight.to, theme.arrow);
+        let image = position_image(&game, &[arrow], &RenderOptions::default());
+        // just short of the arrow's tip on the empty, light f3
+        let mut expected = rgba(theme.light_square);
+        expected.blend(&translucent(theme.arrow));
+        assert_eq!(*image.get_pixel(268, 272), expected);
+        // beside the arrow, f3 is untouched
+        assert_eq!(
+            *image.get_pixel(5 * SQUARE_SIZE, 5 * SQUARE_SIZE),
+            rgba(theme.light_square)
+        );
+    }
+
+    #[test]
+    fn test_save_game_images() {
+        let mut game = ChessGame::default();
+        for mv in ["e2e4", "e7e5"] {
+            game.make_move(&Move::from_uci(mv).unwrap()).unwrap();
+        }
+        let options = RenderOptions::default();
+        let dark = rgba(Theme::CLASSIC.dark_square);
+        let last_moves = render::last_moves(&game, &options.theme);
+        let image = position_image(&game, &last_moves, &options);
+        assert_eq!(image.dimensions(), (8 * SQUARE_SIZE, 8 * SQUARE_SIZE));
+        // a1 is dark and empty in its bottom left corner, e5 was just moved to
+        // and e4 the move before
+        assert_eq!(*image.get_pixel(0, 8 * SQUARE_SIZE - 1), dark);
+        assert_eq!(
+            *image.get_pixel(4 * SQUARE_SIZE, 3 * SQUARE_SIZE),
+            rgba(Theme::CLASSIC.highlight)
+        );
+        assert_eq!(
+            *image.get_pixel(4 * SQUARE_SIZE, 4 * SQUARE_SIZE),
+            rgba(Theme::CLASSIC.previous_move)
+        );
+        let flipped = RenderOptions {
+            flipped: true,
+            theme: Theme::COLOUR_BLIND,
+            ..RenderOptions::default()
+        };
+        let flipped = position_image(&game, &[], &flipped);
+        assert_eq!(
+            *flipped.get_pixel(8 * SQUARE_SIZE - 1, 0),
+            rgba(Theme::COLOUR_BLIND.dark_square)
+        );
+
+        let dir = std::env::temp_dir();
+        let png = dir.join(format!("position-{}.png", std::process::id()));
+        let gif = dir.join(format!("game-{}.gif", std::process::id()));
+        save_png(&game, &png, &options).unwrap();
+        save_gif(&game, &gif, &options, FRAME_DELAY).unwrap();
+        assert_eq!(image::open(&png).unwrap().to_rgba8(), image);
+        let frames = image::codecs::gif::GifDecoder::new(std::io::BufReader::new(
+            std::fs::File::open(&gif).unwrap(),
+        ))
+        .unwrap();
+        assert_eq!(image::AnimationDecoder::into_frames(frames).count(), 3);
+        std::fs::remove_file(&png).ok();
+        std::fs::remove_file(&gif).ok();
+    }
+}
@@ -0,0 +1,759 @@
+//! The command line: `chess play` and the other subcommands, with `play`'s
+//! options also accepted on their own, so `chess --opponent stockfish` starts
+//! a game.
+
+use crate::autosave::SavedGame;
+use crate::clock::TimeControl;
+use crate::config::{Config, EngineConfig, Opponent, Side};
+use crate::core::ChessColour;
+use crate::explorer::Database;
+use crate::game::ChessGame;
+use crate::import::Site;
+use crate::network;
+use crate::opening::Opening;
+use crate::render::{PieceStyle, RenderOptions};
+use crate::solver;
+use crate::variant::Variant;
+use anyhow::Context;
+use clap::{Args, Parser, Subcommand};
+use std::path::PathBuf;
+use std::time::Duration;
+
+#[derive(Debug, Parser)]
+#[command(
+    version,
+    about = "Play chess in the terminal",
+    args_conflicts_with_subcommands = true
+)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Commands>,
+    #[command(flatten)]
+    pub play: PlayArgs,
+    /// Read settings from this file instead of ~/.config/chess/config.toml.
+    #[arg(long, global = true)]
+    pub config: Option<PathBuf>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Commands {
+    /// Play a game, the default when no subcommand is given.
+    Play(PlayArgs),
+    /// Play a match between two engines, or an engine and the solver.
+    Match(MatchArgs),
+    /// Watch a game between two engines, or an engine and the solver.
+    Watch(WatchArgs),
+    /// Run the solver as a UCI engine, for chess GUIs.
+    Uci(SolverArgs),
+    /// Count the positions reachable in a number of moves.
+    Perft(PerftArgs),
+    /// Show the solver's best lines in a position.
+    Analyze(AnalyzeArgs),
+    /// Step through the moves of a game.
+    Replay(ReplayArgs),
+    /// Fetch games played on Lichess or chess.com and step through them.
+    Import(ImportArgs),
+    /// Solve puzzles, finding the winning line from each position.
+    Puzzle(PuzzleArgs),
+    /// Measure the solver or an engine on an EPD test suite.
+    Testsuite(TestsuiteArgs),
+    /// Serve games over HTTP, for web front ends.
+    Serve(ServeArgs),
+    /// Play in Discord channels, as the bot whose token is in DISCORD_TOKEN.
+    Discord(DiscordArgs),
+    /// Wait for someone on another computer to join a game with you.
+    Host(HostArgs),
+    /// Join a game hosted on another computer.
+    Join(JoinArgs),
+    /// Show your results against the computer and your estimated rating.
+    Profile,
+    /// Search the games you have finished.
+    Games(GamesArgs),
+    /// List the openings --opening can start from.
+    Openings,
+    /// List the options an engine supports.
+    EngineOptions(EngineArgs),
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct PlayArgs {
+    /// Who you play against, the solver unless the config file says
+    /// otherwise.
+    #[arg(long, value_enum)]
+    pub opponent: Option<Opponent>,
+    /// The side you play, asked for at the start if neither this nor the
+    /// config file says.
+    #[arg(long, value_enum)]
+    pub color: Option<Side>,
+    #[command(flatten)]
+    pub game: GameArgs,
+    #[command(flatten)]
+    pub solver: SolverArgs,
+    #[command(flatten)]
+    pub engine: EngineArgs,
+    /// Let the opponent think during your move.
+    #[arg(long)]
+    pub ponder: bool,
+    /// How long the opponent thinks about a hint, in milliseconds.
+    #[arg(long, default_value_t = 1000)]
+    pub hint_time: u64,
+    /// Turn the board to face whoever is to move, when two people play.
+    #[arg(long)]
+    pub auto_flip: bool,
+    /// Play in a full-screen terminal UI.
+    #[arg(long)]
+    pub tui: bool,
+    /// Save the final position as a PNG.
+    #[arg(long, value_name = "FILE")]
+    pub png: Option<PathBuf>,
+    /// Save the game as an animated GIF.
+    #[arg(long, value_name = "FILE")]
+    pub gif: Option<PathBuf>,
+    /// Where to save the game if you leave with Ctrl-C or Ctrl-D, by default
+    /// unfinished.pgn in the data directory.
+    #[arg(long, value_name = "FILE")]
+    pub save: Option<PathBuf>,
+    /// Don't save the game after every move, or offer to resume the last
+    /// unfinished one.
+    #[arg(long)]
+    pub no_autosave: bool,
+    /// Play a series of this many games, swapping colours after each, until
+    /// one side can't be caught.
+    #[arg(long, value_name = "GAMES", conflicts_with = "tui", value_parser = clap::value_parser!(u16).range(1..))]
+    pub series: Option<u16>,
+    /// Where to write the games of a series, series.pgn unless given.
+    #[arg(long, value_name = "FILE", requires = "series")]
+    pub archive: Option<PathBuf>,
+    /// Don't record the result in your profile, or pick the opponent's skill
+    /// level from it.
+    #[arg(long)]
+    pub no_profile: bool,
+    /// Don't keep the game in the game database when it ends.
+    #[arg(long)]
+    pub no_database: bool,
+    #[command(flatten)]
+    pub display: DisplayArgs,
+}
+
+impl PlayArgs {
+    /// The game these arguments start, with the user playing `colour` against
+    /// `opponent`, to autosave before any moves are played.
+    pub fn saved_game(&self, opponent: Opponent, colour: ChessColour) -> SavedGame {
+        SavedGame {
+            opponent,
+            colour: match colour {
+                ChessColour::White => Side::White,
+                ChessColour::Black => Side::Black,
+            },
+            engine: self.engine.engine.clone(),
+            elo: self.engine.elo,
+            skill: self.solver.skill,
+            chess960: self.game.chess960,
+            fen: self.game.fen.clone(),
+            time: self.game.time.map(|control| control.to_string()),
+            white_ms: None,
+            black_ms: None,
+            moves: Vec::new(),
+        }
+    }
+
+    /// Play on with `saved`'s settings rather than these.
+    pub fn resume(&mut self, saved: &SavedGame) -> anyhow::Result<()> {
+        self.opponent = Some(saved.opponent);
+        self.color = Some(saved.colour);
+        self.engine.engine = saved.engine.clone();
+        self.engine.elo = saved.elo;
+        self.solver.skill = saved.skill;
+        self.game.chess960 = saved.chess960;
+        self.game.fen = saved.fen.clone();
+        self.game.time = saved.time_control()?;
+        Ok(())
+    }
+}
+
+/// How the game starts and how long each side has.
+#[derive(Debug, Clone, Args)]
+pub struct GameArgs {
+    /// Start from this position instead of the usual one.
+    #[arg(long, value_name = "FEN")]
+    pub fen: Option<String>,
+    /// Play Chess960, from a random starting position unless --fen gives one.
+    #[arg(long)]
+    pub chess960: bool,
+    /// Start after the book moves of this opening, given by name or ECO
+    /// code, e.g. "Sicilian Najdorf" or B90.
+    #[arg(long, value_name = "NAME", conflicts_with_all = ["fen", "chess960"])]
+    pub opening: Option<Opening>,
+    /// The time control, e.g. 5+3 for five minutes plus three seconds a move.
+    #[arg(long, value_name = "CONTROL")]
+    pub time: Option<TimeControl>,
+}
+
+impl GameArgs {
+    pub fn variant(&self) -> Variant {
+        if self.chess960 {
+            Variant::Chess960
+        } else {
+            Variant::Standard
+        }
+    }
+
+    pub fn new_game(&self) -> anyhow::Result<ChessGame> {
+        let builder = match (&self.fen, self.variant()) {
+            (Some(fen), variant) => ChessGame::builder().fen(fen).variant(variant),
+            (None, Variant::Standard) => ChessGame::builder(),
+            (None, Variant::Chess960) => ChessGame::builder().random_chess960(),
+        };
+        let mut game = builder.build()?;
+        if let Some(opening) = &self.opening {
+            opening.play(&mut game)?;
+        }
+        // the clock is set after the opening, so its moves don't earn any
+        // increment
+        if let Some(time_control) = self.time {
+            game.set_time_control(time_control);
+        }
+        Ok(game)
+    }
+}
+
+/// How the built-in solver plays.
+#[derive(Debug, Clone, Args)]
+pub struct SolverArgs {
+    /// A Polyglot opening book to play from.
+    #[arg(long, value_name = "FILE")]
+    pub book: Option<PathBuf>,
+    /// An NNUE network to evaluate positions with.
+    #[arg(long, value_name = "FILE")]
+    pub nnue: Option<PathBuf>,
+    /// How much worse than an equal position a draw is, in centipawns.
+    #[arg(long, allow_negative_numbers = true)]
+    pub contempt: Option<i32>,
+    /// How well the opponent plays, from 0 to 20.
+    #[arg(long, value_parser = clap::value_parser!(u8).range(0..=20))]
+    pub skill: Option<u8>,
+}
+
+impl SolverArgs {
+    pub fn search_options(&self) -> anyhow::Result<solver::SearchOptions> {
+        let mut options = solver::SearchOptions::default();
+        if let Some(path) = &self.book {
+            options.book = solver::book::OpeningBook::Polyglot(
+                solver::polyglot::PolyglotBook::open(path)
+                    .with_context(|| format!("could not load opening book {}", path.display()))?,
+            );
+        }
+        if let Some(path) = &self.nnue {
+            options.evaluator = std::sync::Arc::new(
+                solver::nnue::NnueEvaluator::load(path)
+                    .with_context(|| format!("could not load network {}", path.display()))?,
+            );
+        }
+        if let Some(contempt) = self.contempt {
+            options.contempt = contempt;
+        }
+        if let Some(skill) = self.skill {
+            options = options.with_skill(skill);
+        }
+        Ok(options)
+    }
+}
+
+/// Which external engine to use.
+#[derive(Debug, Clone, Args)]
+pub struct EngineArgs {
+    /// The engine's name in the config file, or the path to run.
+    #[arg(long, default_value = "stockfish")]
+    pub engine: String,
+    /// Append everything sent to and read from the engine to this file.
+    #[arg(long, value_name = "FILE")]
+    pub engine_log: Option<PathBuf>,
+    /// Weaken the engine to play at about this rating.
+    #[arg(long)]
+    pub elo: Option<u32>,
+}
+
+impl EngineArgs {
+    /// How to start the engine called `name`.
+    pub fn engine_config(&self, config: &Config, name: &str) -> EngineConfig {
+        EngineConfig {
+            log: self.engine_log.clone(),
+            ..config.engine(name)
+        }
+    }
+}
+
+/// How to draw the board, on top of the config file's `[display]`.
+#[derive(Debug, Clone, Args)]
+pub struct DisplayArgs {
+    /// How to draw the pieces: letters or figurines.
+    #[arg(long)]
+    pub pieces: Option<PieceStyle>,
+    /// Don't colour the output (also set by NO_COLOR).
+    #[arg(long)]
+    pub no_color: bool,
+    /// Draw with ASCII only, and without colour.
+    #[arg(long)]
+    pub ascii: bool,
+    /// Shade the squares the opponent attacks.
+    #[arg(long)]
+    pub threats: bool,
+    /// Mark undefended pieces too, as well as the threats.
+    #[arg(long)]
+    pub hanging: bool,
+    /// Draw squares two lines tall.
+    #[arg(long)]
+    pub large: bool,
+    /// Show the position's FEN under the board.
+    #[arg(long)]
+    pub show_fen: bool,
+    /// Draw each board over the last one.
+    #[arg(long)]
+    pub redraw: bool,
+}
+
+impl DisplayArgs {
+    pub fn render_options(&self, config: &Config) -> RenderOptions {
+        let mut options = config.display.clone();
+        if let Some(pieces) = self.pieces {
+            options.pieces = pieces;
+        }
+        options.colour &= !self.no_color && !self.ascii;
+        options.ascii |= self.ascii;
+        options.threats |= self.threats || self.hanging;
+        options.hanging |= self.hanging;
+        options.large |= self.large;
+        options.fen |= self.show_fen;
+        options.redraw |= self.redraw;
+        let mut options = options.respect_environment();
+        options.pieces = options.pieces.supported();
+        options
+    }
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct MatchArgs {
+    /// The first player: an engine, or `solver`.
+    pub first: String,
+    /// The second player.
+    pub second: String,
+    /// How many games to play, swapping colours after each.
+    #[arg(long, default_value_t = 2)]
+    pub games: usize,
+    /// The time control for each game.
+    #[arg(long, value_name = "CONTROL")]
+    pub time: Option<TimeControl>,
+    #[arg(long)]
+    pub chess960: bool,
+    #[command(flatten)]
+    pub solver: SolverArgs,
+    /// Append everything sent to and read from the engines to this file.
+    #[arg(long, value_name = "FILE")]
+    pub engine_log: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct WatchArgs {
+    /// The player with White: an engine, or `solver`.
+    pub white: String,
+    /// The player with Black.
+    pub black: String,
+    /// How long to pause after each move, in milliseconds.
+    #[arg(long, default_value_t = 1000)]
+    pub delay: u64,
+    /// How long each move may take without a time control, in milliseconds.
+    #[arg(long, default_value_t = 500)]
+    pub movetime: u64,
+    /// How well White plays, from 0 to 20.
+    #[arg(long, value_parser = clap::value_parser!(u8).range(0..=20))]
+    pub white_skill: Option<u8>,
+    /// How well Black plays, from 0 to 20.
+    #[arg(long, value_parser = clap::value_parser!(u8).range(0..=20))]
+    pub black_skill: Option<u8>,
+    /// The time control for the game.
+    #[arg(long, value_name = "CONTROL")]
+    pub time: Option<TimeControl>,
+    #[arg(long)]
+    pub chess960: bool,
+    /// Append everything sent to and read from the engines to this file.
+    #[arg(long, value_name = "FILE")]
+    pub engine_log: Option<PathBuf>,
+    #[command(flatten)]
+    pub display: DisplayArgs,
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct PerftArgs {
+    /// How many half moves deep to count.
+    pub depth: u32,
+    /// Count from this position instead of the usual one.
+    #[arg(long, value_name = "FEN")]
+    pub fen: Option<String>,
+    /// Show the count after each first move.
+    #[arg(long)]
+    pub divide: bool,
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct AnalyzeArgs {
+    /// The position to analyse, the usual starting one if not given.
+    #[arg(long, value_name = "FEN")]
+    pub fen: Option<String>,
+    /// Moves to play from the position first, e.g. "1. e4 e5 2. Nf3".
+    #[arg(long)]
+    pub moves: Option<String>,
+    /// How many seconds to think for.
+    #[arg(long, default_value_t = 5)]
+    pub time: u64,
+    /// How many half moves deep to search at most.
+    #[arg(long)]
+    pub depth: Option<usize>,
+    /// How many of the best moves to show lines for.
+    #[arg(long, default_value_t = 3)]
+    pub lines: usize,
+    /// Also show what was played from the position in real games, from the
+    /// Lichess opening explorer.
+    #[arg(long, value_name = "DATABASE", num_args = 0..=1, default_missing_value = "masters")]
+    pub explorer: Option<Database>,
+    #[command(flatten)]
+    pub solver: SolverArgs,
+}
+
+impl AnalyzeArgs {
+    pub fn search_options(&self) -> anyhow::Result<solver::SearchOptions> {
+        let mut options = self.solver.search_options()?;
+        // a book move comes without a score or a line to show
+        if self.solver.book.is_none() {
+            options.book = solver::book::OpeningBook::Disabled;
+        }
+        options.max_time = Some(Duration::from_secs(self.time));
+        if let Some(depth) = self.depth {
+            options.max_depth = depth;
+        }
+        options.multi_pv = self.lines;
+        Ok(options)
+    }
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct ReplayArgs {
+    /// A PGN file, or a file of just the game's moves.
+    pub file: PathBuf,
+    #[command(flatten)]
+    pub review: ReviewArgs,
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct ImportArgs {
+    /// A username, or the address of a player's profile or a Lichess game.
+    pub source: String,
+    /// The site a username is on.
+    #[arg(long, value_enum, default_value_t)]
+    pub site: Site,
+    /// How many of the player's most recent games to fetch.
+    #[arg(long, default_value_t = 10, value_parser = clap::value_parser!(u16).range(1..))]
+    pub max: u16,
+    /// Save the games fetched to this file too.
+    #[arg(long, value_name = "FILE")]
+    pub save: Option<PathBuf>,
+    #[command(flatten)]
+    pub review: ReviewArgs,
+}
+
+/// Which of a set of games to step through, and what to show along the way.
+#[derive(Debug, Clone, Args)]
+pub struct ReviewArgs {
+    /// Which of the games to show, counting from 1.
+    #[arg(long, default_value_t = 1)]
+    pub game: usize,
+    /// Show an evaluation of each position, and the best move in it.
+    #[arg(long)]
+    pub eval: bool,
+    /// Evaluate with this engine instead of the solver.
+    #[arg(long, value_name = "ENGINE", requires = "eval")]
+    pub eval_engine: Option<String>,
+    /// How long to think about each position, in milliseconds.
+    #[arg(long, default_value_t = 500)]
+    pub eval_time: u64,
+    /// Show what was played from each position in real games, from the
+    /// Lichess opening explorer, until the game leaves them.
+    #[arg(long, value_name = "DATABASE", num_args = 0..=1, default_missing_value = "masters")]
+    pub explorer: Option<Database>,
+    #[command(flatten)]
+    pub display: DisplayArgs,
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct ServeArgs {
+    /// The address to listen on.
+    #[arg(default_value = "127.0.0.1:8080")]
+    pub address: String,
+    #[command(flatten)]
+    pub solver: SolverArgs,
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct DiscordArgs {
+    /// The IDs of the channels to play in.
+    #[arg(required = true)]
+    pub channels: Vec<String>,
+    /// How often to check for new messages, in seconds.
+    #[arg(long, default_value_t = 2, value_parser = clap::value_parser!(u64).range(1..))]
+    pub poll: u64,
+    /// How long the solver thinks about each move, in milliseconds.
+    #[arg(long, default_value_t = 2000)]
+    pub move_time: u64,
+    #[command(flatten)]
+    pub solver: SolverArgs,
+}
+
+/// Which of the games in the game database to show.
+#[derive(Debug, Clone, Args)]
+pub struct GamesArgs {
+    /// Only games against this player, on either side.
+    #[arg(long)]
+    pub opponent: Option<String>,
+    /// Only games in this opening, by ECO code or its first letters.
+    #[arg(long, value_name = "ECO")]
+    pub opening: Option<String>,
+    /// Only games with this result.
+    #[arg(long, value_parser = ["1-0", "0-1", "1/2-1/2"])]
+    pub result: Option<String>,
+    /// Only games played on or after this day, as YYYY-MM-DD.
+    #[arg(long, value_name = "DATE", value_parser = parse_date)]
+    pub from: Option<String>,
+    /// Only games played on or before this day, as YYYY-MM-DD.
+    #[arg(long, value_name = "DATE", value_parser = parse_date)]
+    pub to: Option<String>,
+    /// Print the games in PGN instead of listing them.
+    #[arg(long)]
+    pub pgn: bool,
+}
+
+fn parse_date(s: &str) -> Result<String, String> {
+    let digits = |part: &str, len| part.len() == len && part.bytes().all(|b| b.is_ascii_digit());
+    match s.split('-').collect::<Vec<_>>()[..] {
+        [year, month, day] if digits(year, 4) && digits(month, 2) && digits(day, 2) => {
+            Ok(s.to_string())
+        }
+        _ => Err(format!("{s} isn't a date like 2024-03-01")),
+    }
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct HostArgs {
+    /// The address to listen on for the other player.
+    #[arg(default_value_t = format!("0.0.0.0:{}", network::DEFAULT_PORT))]
+    pub address: String,
+    /// The side you play, White unless given.
+    #[arg(long, value_enum)]
+    pub color: Option<Side>,
+    #[command(flatten)]
+    pub game: GameArgs,
+    #[command(flatten)]
+    pub display: DisplayArgs,
+    /// Play in a full-screen terminal UI.
+    #[arg(long)]
+    pub tui: bool,
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct JoinArgs {
+    /// The host's address, e.g. 192.168.1.20, on the default port unless
+    /// one is given.
+    pub address: String,
+    #[command(flatten)]
+    pub display: DisplayArgs,
+    /// Play in a full-screen terminal UI.
+    #[arg(long)]
+    pub tui: bool,
+}
+
+impl JoinArgs {
+    /// The address with the default port added if it has none.
+    pub fn address(&self) -> String {
+        if self.address.contains(':') {
+            self.address.clone()
+        } else {
+            format!("{}:{}", self.address, network::DEFAULT_PORT)
+        }
+    }
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct PuzzleArgs {
+    /// A file of puzzles to solve instead of the built-in ones, one per line
+    /// as "FEN; solution in UCI; description".
+    pub file: Option<PathBuf>,
+    /// Take the puzzles in a random order.
+    #[arg(long)]
+    pub shuffle: bool,
+    #[command(flatten)]
+    pub display: DisplayArgs,
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct TestsuiteArgs {
+    /// An EPD file of positions with their best moves, or moves to avoid,
+    /// e.g. wac.epd.
+    pub file: PathBuf,
+    /// How long to think about each position, in milliseconds.
+    #[arg(long, default_value_t = 1000)]
+    pub movetime: u64,
+    /// Test this engine instead of the solver.
+    #[arg(long, value_name = "ENGINE")]
+    pub engine: Option<String>,
+    #[command(flatten)]
+    pub solver: SolverArgs,
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::autosave::{Autosave, SavedGame};
+    use clap::{CommandFactory, ValueEnum};
+
+    #[test]
+    fn test_parse_command_line() {
+        Cli::command().debug_assert();
+
+        let cli = Cli::try_parse_from(["chess"]).unwrap();
+        assert!(cli.command.is_none());
+        assert_eq!(cli.play.opponent, None);
+        assert_eq!(cli.play.color, None);
+
+        let cli = Cli::try_parse_from(["chess", "--opponent", "human", "--time", "5+3"]).unwrap();
+        assert_eq!(cli.play.opponent, Some(Opponent::Human));
+        assert!(cli.play.game.time.is_some());
+
+        let fen = "4k3/8/8/8/8/8/8/4K2R w K - 0 1";
+        let cli = Cli::try_parse_from([
+            "chess",
+            "play",
+            "--opponent",
+            "stockfish",
+            "--color",
+            "black",
+            "--skill",
+            "5",
+            "--fen",
+            fen,
+        ])
+        .unwrap();
+        let Some(Commands::Play(play)) = cli.command else {
+            panic!("expected the play subcommand");
+        };
+        assert_eq!(play.opponent, Some(Opponent::Stockfish));
+        assert_eq!(play.color.map(Side::colour), Some(ChessColour::Black));
+        assert!(Side::from_str("Black", true).is_ok());
+        assert!(Side::from_str("purple", true).is_err());
+        assert_eq!(play.solver.skill, Some(5));
+        assert_eq!(play.game.new_game().unwrap().fen(), fen);
+
+        let cli = Cli::try_parse_from(["chess", "perft", "3", "--divide"]).unwrap();
+        assert!(matches!(
+            cli.command,
+            Some(Commands::Perft(PerftArgs {
+                depth: 3,
+                divide: true,
+                ..
+            }))
+        ));
+        let cli =
+            Cli::try_parse_from(["chess", "import", "hikaru", "--site", "chess.com"]).unwrap();
+        assert!(matches!(
+            cli.command,
+            Some(Commands::Import(ImportArgs {
+                site: Site::ChessCom,
+                max: 10,
+                ..
+            }))
+        ));
+        let cli = Cli::try_parse_from(["chess", "analyze", "--explorer"]).unwrap();
+        let Some(Commands::Analyze(analyze)) = cli.command else {
+            panic!("expected the analyze subcommand");
+        };
+        assert_eq!(analyze.explorer, Some(Database::Masters));
+        let cli =
+            Cli::try_parse_from(["chess", "replay", "a.pgn", "--explorer", "lichess"]).unwrap();
+        let Some(Commands::Replay(replay)) = cli.command else {
+            panic!("expected the replay subcommand");
+        };
+        assert_eq!(replay.review.explorer, Some(Database::Lichess));
+        let cli =
+            Cli::try_parse_from(["chess", "games", "--result", "0-1", "--from", "2024-03-01"])
+                .unwrap();
+        let Some(Commands::Games(games)) = cli.command else {
+            panic!("expected the games subcommand");
+        };
+        assert_eq!(games.from.as_deref(), Some("2024-03-01"));
+        assert!(Cli::try_parse_from(["chess", "games", "--from", "March"]).is_err());
+        assert!(Cli::try_parse_from(["chess", "games", "--result", "win"]).is_err());
+        let cli =
+            Cli::try_parse_from(["chess", "testsuite", "wac.epd", "--movetime", "500"]).unwrap();
+        assert!(matches!(
+            cli.command,
+            Some(Commands::Testsuite(TestsuiteArgs {
+                movetime: 500,
+                engine: None,
+                ..
+            }))
+        ));
+        let cli = Cli::try_parse_from(["chess", "discord", "123", "456", "--skill", "8"]).unwrap();
+        let Some(Commands::Discord(discord)) = cli.command else {
+            panic!("expected the discord subcommand");
+        };
+        assert_eq!(discord.channels, ["123", "456"]);
+        assert!(Cli::try_parse_from(["chess", "discord"]).is_err());
+        let cli = Cli::try_parse_from(["chess", "serve", "0.0.0.0:80", "--skill", "3"]).unwrap();
+        assert!(matches!(
+            cli.command,
+            Some(Commands::Serve(ServeArgs {
+                solver: SolverArgs { skill: Some(3), .. },
+                ..
+            }))
+        ));
+        let cli =
+            Cli::try_parse_from(["chess", "host", "--color", "black", "--time", "5+3"]).unwrap();
+        let Some(Commands::Host(host)) = cli.command else {
+            panic!("expected the host subcommand");
+        };
+        assert_eq!(host.address, "0.0.0.0:7878");
+        assert_eq!(host.color.map(Side::colour), Some(ChessColour::Black));
+        let cli = Cli::try_parse_from(["chess", "join", "192.168.1.20"]).unwrap();
+        let Some(Commands::Join(join)) = cli.command else {
+            panic!("expected the join subcommand");
+        };
+        assert_eq!(join.address(), "192.168.1.20:7878");
+        assert!(Cli::try_parse_from(["chess", "--skill", "21"]).is_err());
+        assert!(Cli::try_parse_from(["chess", "--opponent", "nobody"]).is_err());
+        assert!(Cli::try_parse_from(["chess", "--time", "soon"]).is_err());
+    }
+
+    #[test]
+    fn test_save_and_resume() {
+        let cli =
+            Cli::try_parse_from(["chess", "--skill", "4", "--time", "5+3", "--chess960"]).unwrap();
+        let mut game = cli.play.game.new_game().unwrap();
+        let moves = crate::solver::legal_moves(&game);
+        game.make_move(&moves[0]).unwrap();
+
+        let path = std::env::temp_dir().join(format!("resume-{}.toml", std::process::id()));
+        let mut autosave = Autosave::new(
+            path.clone(),
+            cli.play.saved_game(Opponent::Solver, ChessColour::Black),
+        );
+        autosave.record(&game);
+        let saved = SavedGame::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let mut args = Cli::try_parse_from(["chess"]).unwrap().play;
+        args.resume(&saved).unwrap();
+        assert_eq!(args.opponent, Some(Opponent::Solver));
+        assert_eq!(args.color, Some(Side::Black));
+        assert_eq!(args.solver.skill, Some(4));
+        assert!(args.game.chess960);
+        assert_eq!(args.game.time, cli.play.game.time);
+        assert_eq!(args.game.fen, game.start_fen().map(str::to_string));
+    }
+}
@@ -0,0 +1,283 @@
+//! Time controls and the chess clock that runs them.
+
+use crate::core::{ChessColour, ChessError, ParseKind, serde_as_string};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt::Display;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+/// Serialized as it is written on the command line, e.g. `"5+3"`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Copy)]
+pub enum TimeControl {
+    /// A fixed amount of time for the whole game.
+    SuddenDeath { initial: Duration },
+    /// Fischer increment: `increment` is added to the clock after every move.
+    Increment {
+        initial: Duration,
+        increment: Duration,
+    },
+    /// Simple delay: the clock only starts counting down after `delay` has
+    /// passed on each move.
+    Delay { initial: Duration, delay: Duration },
+}
+
+impl TimeControl {
+    pub fn initial(&self) -> Duration {
+        match self {
+            TimeControl::SuddenDeath { initial }
+            | TimeControl::Increment { initial, .. }
+            | TimeControl::Delay { initial, .. } => *initial,
+        }
+    }
+
+    pub fn increment(&self) -> Duration {
+        match self {
+            TimeControl::Increment { increment, .. } => *increment,
+            _ => Duration::ZERO,
+        }
+    }
+}
+
+impl FromStr for TimeControl {
+    type Err = ChessError;
+
+    /// Parse `minutes`, `minutes+increment` or `minutes d delay`, with the
+    /// increment and delay in seconds, e.g. `5`, `3+2` or `10d5`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || ChessError::parse(ParseKind::TimeControl, s);
+        let minutes = |m: &str| {
+            m.trim()
+                .parse::<f64>()
+                .ok()
+                .filter(|m| m.is_finite() && *m > 0.0)
+                .and_then(|m| Duration::try_from_secs_f64(m * 60.0).ok())
+                .ok_or_else(invalid)
+        };
+        let seconds = |s: &str| {
+            s.trim()
+                .parse::<u64>()
+                .map(Duration::from_secs)
+                .map_err(|_| invalid())
+        };
+        if let Some((initial, increment)) = s.split_once('+') {
+            Ok(TimeControl::Increment {
+                initial: minutes(initial)?,
+                increment: seconds(increment)?,
+            })
+        } else if let Some((initial, delay)) = s.split_once('d') {
+            Ok(TimeControl::Delay {
+                initial: minutes(initial)?,
+                delay: seconds(delay)?,
+            })
+        } else {
+            Ok(TimeControl::SuddenDeath {
+                initial: minutes(s)?,
+            })
+        }
+    }
+}
+
+impl Display for TimeControl {
+    /// The time control as [`TimeControl::from_str`] reads it.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let minutes = self.initial().as_secs_f64() / 60.0;
+        match self {
+            TimeControl::SuddenDeath { .. } => write!(f, "{minutes}"),
+            TimeControl::Increment { increment, .. } => {
+                write!(f, "{minutes}+{}", increment.as_secs())
+            }
+            TimeControl::Delay { delay, .. } => write!(f, "{minutes}d{}", delay.as_secs()),
+        }
+    }
+}
+
+serde_as_string!(TimeControl, TimeControl::to_string, TimeControl::from_str);
+
+/// How many more moves to budget time for when the time control doesn't say.
+pub const DEFAULT_MOVES_TO_GO: u32 = 30;
+
+/// How long a player with `remaining` on their clock should spend on a move,
+/// sharing the time between the next `moves_to_go` moves and spending most of
+/// the `increment` they get back.
+pub fn move_budget(remaining: Duration, increment: Duration, moves_to_go: u32) -> Duration {
+    (remaining / moves_to_go.max(1))
+        .saturating_add(increment.saturating_mul(3) / 4)
+        .min(remaining)
+}
+
+/// A chess clock tracking the remaining time for both players.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Clock {
+    control: TimeControl,
+    white: Duration,
+    black: Duration,
+    /// When the side to move started thinking, if the clock is running.
+    #[serde(skip)]
+    running_since: Option<Instant>,
+}
+
+impl Clock {
+    pub fn new(control: TimeControl) -> Self {
+        Self {
+            control,
+            white: control.initial(),
+            black: control.initial(),
+            running_since: None,
+        }
+    }
+
+    /// A clock part way through a game, with `white` and `black` left.
+    pub fn with_remaining(control: TimeControl, white: Duration, black: Duration) -> Self {
+        Self {
+            white,
+            black,
+            ..Self::new(control)
+        }
+    }
+
+    pub fn control(&self) -> TimeControl {
+        self.control
+    }
+
+    /// Start (or restart) timing the side to move.
+    pub fn start(&mut self) {
+        self.running_since = Some(Instant::now());
+    }
+
+    /// The time `colour` had left when it last finished a move.
+    pub fn remaining(&self, colour: ChessColour) -> Duration {
+        match colour {
+            ChessColour::White => self.white,
+            ChessColour::Black => self.black,
+        }
+    }
+
+    /// The time `colour` has left right now, assuming it is the side to move.
+    pub fn remaining_now(&self, colour: ChessColour) -> Duration {
+        let elapsed = self.running_since.map(|s| s.elapsed()).unwrap_or_default();
+        self.remaining(colour)
+            .saturating_sub(self.charged_time(elapsed))
+    }
+
+    /// Whether `colour`, as the side to move, has run out of time.
+    pub fn is_flagged(&self, colour: ChessColour) -> bool {
+        self.remaining_now(colour).is_zero()
+    }
+
+    /// Stop the clock for `colour` after it moved, charging it for the time
+    /// since the clock was started, and start the opponent's clock. Returns
+    /// `false` if the player's flag fell before the move was made.
+    pub fn press(&mut self, colour: ChessColour) -> bool {
+        let elapsed = self.running_since.map(|s| s.elapsed()).unwrap_or_default();
+        let in_time = self.record_move(colour, elapsed);
+        self.start();
+        in_time
+    }
+
+    /// Charge `colour` for a move that took `elapsed`, applying any increment or
+    /// delay. Returns `false` if the player ran out of time.
+    pub fn record_move(&mut self, colour: ChessColour, elapsed: Duration) -> bool {
+        let charged = self.charged_time(elapsed);
+        let increment = self.control.increment();
+        let remaining = match colour {
+            ChessColour::White => &mut self.white,
+            ChessColour::Black => &mut self.black,
+        };
+        if charged >= *remaining {
+            *remaining = Duration::ZERO;
+            return false;
+        }
+        // a huge increment leaves the clock full rather than overflowing it
+        *remaining = (*remaining - charged).saturating_add(increment);
+        true
+    }
+
+    fn charged_time(&self, elapsed: Duration) -> Duration {
+        match self.control {
+            TimeControl::Delay { delay, .. } => elapsed.saturating_sub(delay),
+            _ => elapsed,
+        }
+    }
+}
+
+/// Formats a remaining clock time as `m:ss`, or `h:mm:ss` for long games.
+pub struct ClockTime(pub Duration);
+
+impl Display for ClockTime {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let secs = self.0.as_secs();
+        let (hours, minutes, seconds) = (secs / 3600, (secs / 60) % 60, secs % 60);
+        if hours > 0 {
+            write!(f, "{hours}:{minutes:02}:{seconds:02}")
+        } else {
+            write!(f, "{minutes}:{seconds:02}")
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_time_controls() {
+        assert_eq!(
+            TimeControl::from_str("5").unwrap(),
+            TimeControl::SuddenDeath {
+                initial: Duration::from_secs(300)
+            }
+        );
+        assert_eq!(
+            TimeControl::from_str("3+2").unwrap(),
+            TimeControl::Increment {
+                initial: Duration::from_secs(180),
+                increment: Duration::from_secs(2)
+            }
+        );
+        assert_eq!(
+            TimeControl::from_str("10d5").unwrap(),
+            TimeControl::Delay {
+                initial: Duration::from_secs(600),
+                delay: Duration::from_secs(5)
+            }
+        );
+        assert!(TimeControl::from_str("fast").is_err());
+        for huge in ["inf", "1e300", "NaN", "inf+2"] {
+            assert!(TimeControl::from_str(huge).is_err(), "{huge}");
+        }
+        for control in ["5", "3+2", "10d5", "0.5+1"] {
+            assert_eq!(TimeControl::from_str(control).unwrap().to_string(), control);
+        }
+    }
+
+    #[test]
+    fn test_increment_and_delay() {
+        let mut clock = Clock::new(TimeControl::from_str("1+2").unwrap());
+        assert!(clock.record_move(ChessColour::White, Duration::from_secs(10)));
+        assert_eq!(clock.remaining(ChessColour::White), Duration::from_secs(52));
+
+        let mut clock = Clock::new(TimeControl::from_str("1d5").unwrap());
+        assert!(clock.record_move(ChessColour::Black, Duration::from_secs(3)));
+        assert_eq!(clock.remaining(ChessColour::Black), Duration::from_secs(60));
+        assert!(clock.record_move(ChessColour::Black, Duration::from_secs(15)));
+        assert_eq!(clock.remaining(ChessColour::Black), Duration::from_secs(50));
+
+        for control in ["1+18446744073709551615", "1d18446744073709551615"] {
+            let mut clock = Clock::new(TimeControl::from_str(control).unwrap());
+            for _ in 0..2 {
+                assert!(clock.record_move(ChessColour::White, Duration::from_secs(10)));
+            }
+            let control = clock.control();
+            move_budget(clock.remaining(ChessColour::White), control.increment(), 30);
+        }
+    }
+
+    #[test]
+    fn test_flag_fall() {
+        let mut clock = Clock::new(TimeControl::from_str("1").unwrap());
+        assert!(!clock.record_move(ChessColour::White, Duration::from_secs(61)));
+        assert_eq!(clock.remaining(ChessColour::White), Duration::ZERO);
+        assert_eq!(ClockTime(Duration::from_secs(3725)).to_string(), "1:02:05");
+        assert_eq!(ClockTime(Duration::from_secs(65)).to_string(), "1:05");
+    }
+}
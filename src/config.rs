@@ -0,0 +1,267 @@
+//! The settings file, `~/.config/chess/config.toml`: defaults for play,
+//! engines to play against and how to draw the board.
+
+use crate::core::ChessColour;
+use crate::render::RenderOptions;
+use anyhow::Context;
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt::Display;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+/// Settings from the TOML config file, e.g.
+///
+/// ```toml
+/// [play]
+/// opponent = "stockfish"
+/// color = "black"
+///
+/// [display]
+/// pieces = "figurines"
+/// theme = "colourblind"
+/// redraw = true
+///
+/// [engines.stockfish]
+/// path = "/usr/local/bin/stockfish"
+/// options = { Hash = 256, Threads = 4 }
+/// log = "/tmp/stockfish.log"
+///
+/// [engines.lc0]
+/// path = "lc0"
+/// args = ["--weights=t1-768x15x24h-swa-4000000.pb.gz"]
+///
+/// [engines.gnuchess]
+/// path = "gnuchess"
+/// protocol = "xboard"
+/// ```
+#[derive(Debug, Clone, PartialEq, Default, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    /// Who to play when the command line doesn't say.
+    pub play: PlayConfig,
+    /// Engines to play against, by the name given to `--engine`.
+    pub engines: BTreeMap<String, EngineConfig>,
+    /// How to draw the board.
+    pub display: RenderOptions,
+}
+
+/// Defaults for `chess play`, which its options override.
+#[derive(Debug, Clone, PartialEq, Default, Deserialize)]
+#[serde(default)]
+pub struct PlayConfig {
+    pub opponent: Option<Opponent>,
+    pub color: Option<Side>,
+}
+
+/// Who plays the other side.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Opponent {
+    /// The UCI engine given by --engine.
+    Stockfish,
+    /// The built-in solver.
+    #[default]
+    Solver,
+    /// Another person at the same keyboard.
+    Human,
+}
+
+/// A side to play, as written on the command line or at the prompt.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Side {
+    White,
+    Black,
+    /// Either, picked at random.
+    Random,
+}
+
+impl Side {
+    pub fn colour(self) -> ChessColour {
+        match self {
+            Side::White => ChessColour::White,
+            Side::Black => ChessColour::Black,
+            Side::Random if rand::random() => ChessColour::White,
+            Side::Random => ChessColour::Black,
+        }
+    }
+}
+
+/// How to start an engine.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct EngineConfig {
+    pub path: String,
+    #[serde(default)]
+    pub protocol: Protocol,
+    #[serde(default)]
+    pub args: Vec<String>,
+    /// Options to set once the engine has started, by name.
+    #[serde(default)]
+    pub options: BTreeMap<String, OptionValue>,
+    /// A file to append everything sent to and read from the engine to.
+    #[serde(default)]
+    pub log: Option<PathBuf>,
+}
+
+/// The protocol an engine speaks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Protocol {
+    #[default]
+    Uci,
+    /// XBoard, also known as CECP.
+    XBoard,
+}
+
+/// The value of an engine option, which can be written as whichever TOML type
+/// suits it.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(untagged)]
+pub enum OptionValue {
+    Bool(bool),
+    Integer(i64),
+    String(String),
+}
+
+impl Display for OptionValue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            OptionValue::Bool(value) => write!(f, "{value}"),
+            OptionValue::Integer(value) => write!(f, "{value}"),
+            OptionValue::String(value) => write!(f, "{value}"),
+        }
+    }
+}
+
+impl EngineConfig {
+    /// Run the executable at `path` with no arguments and its default options.
+    pub fn from_path(path: &str) -> Self {
+        Self {
+            path: path.to_string(),
+            protocol: Protocol::Uci,
+            args: Vec::new(),
+            options: BTreeMap::new(),
+            log: None,
+        }
+    }
+}
+
+impl Config {
+    /// `chess/config.toml` in `$XDG_CONFIG_HOME`, or in `~/.config` if that
+    /// isn't set.
+    pub fn default_path() -> Option<PathBuf> {
+        let config_home = std::env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".config")))?;
+        Some(config_home.join("chess").join("config.toml"))
+    }
+
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("could not read config file {}", path.display()))?;
+        text.parse()
+            .with_context(|| format!("invalid config file {}", path.display()))
+    }
+
+    /// Load the config from [`Config::default_path`], or use the defaults if
+    /// there is no file there.
+    pub fn load_default() -> anyhow::Result<Self> {
+        match Self::default_path() {
+            Some(path) if path.exists() => Self::load(&path),
+            _ => Ok(Self::default()),
+        }
+    }
+
+    /// The engine called `name`, or if none is configured with that name, the
+    /// executable `name` itself.
+    pub fn engine(&self, name: &str) -> EngineConfig {
+        self.engines
+            .get(name)
+            .cloned()
+            .unwrap_or_else(|| EngineConfig::from_path(name))
+    }
+}
+
+/// Where files the program keeps for itself go, such as the autosaved game:
+/// `chess` in `$XDG_DATA_HOME`, or in `~/.local/share` if that isn't set.
+pub fn data_dir() -> Option<PathBuf> {
+    let data_home = std::env::var_os("XDG_DATA_HOME")
+        .map(PathBuf::from)
+        .or_else(|| {
+            std::env::var_os("HOME").map(|home| Path::new(&home).join(".local").join("share"))
+        })?;
+    Some(data_home.join("chess"))
+}
+
+impl FromStr for Config {
+    type Err = toml::de::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        toml::from_str(s)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::render::PieceStyle;
+
+    #[test]
+    fn test_parse_config() {
+        let config: Config = r#"
+            [play]
+            opponent = "stockfish"
+
+            [display]
+            pieces = "figurines"
+            colour = false
+
+            [engines.stockfish]
+            path = "/usr/local/bin/stockfish"
+            options = { Hash = 256, "Skill Level" = 3, Ponder = false }
+
+            [engines.lc0]
+            path = "lc0"
+            args = ["--weights=net.pb.gz"]
+            options = { Backend = "eigen" }
+
+            [engines.gnuchess]
+            path = "gnuchess"
+            protocol = "xboard"
+        "#
+        .parse()
+        .unwrap();
+
+        let stockfish = config.engine("stockfish");
+        assert_eq!(stockfish.path, "/usr/local/bin/stockfish");
+        assert!(stockfish.args.is_empty());
+        let options: Vec<_> = stockfish
+            .options
+            .iter()
+            .map(|(name, value)| format!("{name}={value}"))
+            .collect();
+        assert_eq!(options, ["Hash=256", "Ponder=false", "Skill Level=3"]);
+        assert_eq!(config.engine("lc0").args, ["--weights=net.pb.gz"]);
+        assert_eq!(stockfish.protocol, Protocol::Uci);
+        assert_eq!(config.engine("gnuchess").protocol, Protocol::XBoard);
+        assert_eq!(
+            config.engine("ethereal"),
+            EngineConfig::from_path("ethereal")
+        );
+
+        assert_eq!(config.play.opponent, Some(Opponent::Stockfish));
+        assert_eq!(config.play.color, None);
+        assert!("[play]\ncolor = \"black\"".parse::<Config>().is_ok());
+        assert!("[play]\nopponent = \"nobody\"".parse::<Config>().is_err());
+
+        assert_eq!(config.display.pieces, PieceStyle::Figurines);
+        assert!(!config.display.colour);
+        assert!(!config.display.ascii);
+        assert!(!config.display.redraw);
+
+        assert_eq!("".parse::<Config>().unwrap(), Config::default());
+        assert!("[engines.broken]\nargs = []".parse::<Config>().is_err());
+    }
+}
@@ -0,0 +1,249 @@
+//! Runs a game between two [`Player`]s, whoever they are, asking each for its
+//! move in turn until the game ends or a player leaves.
+
+use crate::autosave::Autosave;
+use crate::clock::Clock;
+use crate::core::{ChessColour, Move};
+use crate::game::ChessGame;
+use crate::player::{Action, Player};
+use crate::solver::Score;
+use anyhow::Context;
+
+pub struct GameController {
+    game: ChessGame,
+    white: Box<dyn Player>,
+    black: Box<dyn Player>,
+    autosave: Option<Autosave>,
+}
+
+impl GameController {
+    pub fn new(game: ChessGame, white: Box<dyn Player>, black: Box<dyn Player>) -> Self {
+        Self {
+            game,
+            white,
+            black,
+            autosave: None,
+        }
+    }
+
+    /// Save the game after every move.
+    pub fn autosave(mut self, autosave: Autosave) -> Self {
+        self.autosave = Some(autosave);
+        self
+    }
+
+    pub fn game(&self) -> &ChessGame {
+        &self.game
+    }
+
+    fn player(&mut self, colour: ChessColour) -> &mut dyn Player {
+        match colour {
+            ChessColour::White => self.white.as_mut(),
+            ChessColour::Black => self.black.as_mut(),
+        }
+    }
+
+    /// Play until the game is over, returning false if a player left first.
+    pub fn play(&mut self) -> anyhow::Result<bool> {
+        while self.game.result().is_none() {
+            let turn = self.game.get_board().turn;
+            let game = &self.game;
+            let action = match turn {
+                ChessColour::White => self.white.choose_move(game)?,
+                ChessColour::Black => self.black.choose_move(game)?,
+            };
+            if self.game.check_timeout().is_some() {
+                break;
+            }
+            match action {
+                Action::Move(mv) => {
+                    let score = self.player(turn).score();
+                    if self.play_move(&mv, score.map(|score| score.for_white(turn)))? {
+                        self.set_clock(turn);
+                    }
+                }
+                Action::Moves(moves) => {
+                    for mv in &moves {
+                        if !self.play_move(mv, None)? || self.game.result().is_some() {
+                            break;
+                        }
+                    }
+                }
+                Action::Resign => {
+                    self.game.resign(turn)?;
+                }
+                Action::OfferDraw => {
+                    let game = &self.game;
+                    let accepted = match turn {
+                        ChessColour::White => self.black.accepts_draw(game)?,
+                        ChessColour::Black => self.white.accepts_draw(game)?,
+                    };
+                    if accepted {
+                        self.game.agree_draw()?;
+                    } else {
+                        self.player(turn)
+                            .notify("Your opponent declined the draw offer.");
+                    }
+                }
+                Action::Undo => self.undo(),
+                Action::Quit => {
+                    self.game_over()?;
+                    return Ok(false);
+                }
+            }
+            if let Some(autosave) = &mut self.autosave {
+                autosave.record(&self.game);
+            }
+        }
+        if let Some(autosave) = &mut self.autosave {
+            autosave.record(&self.game);
+        }
+        self.game_over()?;
+        Ok(true)
+    }
+
+    fn game_over(&mut self) -> anyhow::Result<()> {
+        self.white.game_over(&self.game)?;
+        self.black.game_over(&self.game)
+    }
+
+    /// Take the clock the player of `colour` keeps, if it keeps one, as the
+    /// game's.
+    fn set_clock(&mut self, colour: ChessColour) {
+        if let Some((white, black)) = self.player(colour).clock_after_move()
+            && let Some(control) = self.game.clock().map(Clock::control)
+        {
+            self.game
+                .set_clock(Clock::with_remaining(control, white, black));
+        }
+    }
+
+    /// Play `mv`, telling both players about it, or say why not if it can't
+    /// be played and return false.
+    fn play_move(&mut self, mv: &Move, evaluation: Option<Score>) -> anyhow::Result<bool> {
+        let san = self.game.san(mv)?;
+        let turn = self.game.get_board().turn;
+        if let Err(e) = self.game.make_move(mv) {
+            self.player(turn).notify(&format!("Error making move: {e}"));
+            return Ok(false);
+        }
+        self.white
+            .observe(&self.game, &san, evaluation)
+            .context("White could not follow the move")?;
+        self.black
+            .observe(&self.game, &san, evaluation)
+            .context("Black could not follow the move")?;
+        Ok(true)
+    }
+
+    /// Take back the last move, and the computer's moves before it, so that
+    /// it is a person's turn again: between two people only the last move is
+    /// taken back, which hands the turn to whoever made it.
+    fn undo(&mut self) {
+        if let Err(e) = self.game.undo() {
+            let turn = self.game.get_board().turn;
+            self.player(turn).notify(&format!("Can't undo: {e}"));
+            return;
+        }
+        while !self.player(self.game.get_board().turn).is_human() && self.game.undo().is_ok() {}
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::game::{GameResult, Termination};
+    use crate::input::parse_moves;
+    use crate::player::SolverPlayer;
+    use crate::solver::SearchOptions;
+    use crate::variant::Variant;
+    use std::collections::VecDeque;
+
+    /// Takes the actions it was given in turn, as if typed by a person, then
+    /// leaves.
+    struct Scripted {
+        actions: VecDeque<Action>,
+        accepts_draw: bool,
+    }
+
+    impl Scripted {
+        fn new(actions: impl IntoIterator<Item = Action>) -> Box<Self> {
+            Box::new(Self {
+                actions: actions.into_iter().collect(),
+                accepts_draw: false,
+            })
+        }
+    }
+
+    impl Player for Scripted {
+        fn choose_move(&mut self, _game: &ChessGame) -> anyhow::Result<Action> {
+            Ok(self.actions.pop_front().unwrap_or(Action::Quit))
+        }
+
+        fn accepts_draw(&mut self, _game: &ChessGame) -> anyhow::Result<bool> {
+            Ok(self.accepts_draw)
+        }
+
+        fn is_human(&self) -> bool {
+            true
+        }
+    }
+
+    fn solver() -> Box<SolverPlayer> {
+        let options = SearchOptions {
+            max_depth: 2,
+            ..SearchOptions::default()
+        };
+        Box::new(SolverPlayer::new(options, false))
+    }
+
+    #[test]
+    fn test_solver_against_solver() {
+        let game = ChessGame::from_fen("k7/8/1K6/8/8/8/7Q/8 w - - 0 1", Variant::Standard).unwrap();
+        let mut controller = GameController::new(game, solver(), solver());
+        assert!(controller.play().unwrap());
+        assert_eq!(
+            controller.game().result(),
+            Some(GameResult::WhiteWins(Termination::Checkmate))
+        );
+        assert_eq!(controller.game().played_moves().len(), 1);
+    }
+
+    #[test]
+    fn test_two_people() {
+        let game = ChessGame::default();
+        let opening = parse_moves("1. e4 e5 2. Nf3", &game).unwrap();
+        let mut after = game.clone();
+        for mv in &opening {
+            after.make_move(mv).unwrap();
+        }
+        let reply = parse_moves("Nc6", &after).unwrap()[0];
+        let white = Scripted::new([Action::Moves(opening), Action::Undo]);
+        let black = Scripted::new([Action::Move(reply), Action::OfferDraw, Action::Resign]);
+        let mut controller = GameController::new(game, white, black);
+        assert!(controller.play().unwrap());
+        // only Black's reply was taken back, and the draw was declined
+        assert_eq!(controller.game().played_moves().len(), 3);
+        assert_eq!(
+            controller.game().result(),
+            Some(GameResult::WhiteWins(Termination::Resignation))
+        );
+    }
+
+    #[test]
+    fn test_person_against_solver() {
+        let game = ChessGame::default();
+        let e4 = parse_moves("e4", &game).unwrap()[0];
+        let white = Scripted::new([Action::Move(e4), Action::Undo, Action::OfferDraw]);
+        let black = solver();
+        let mut controller = GameController::new(game, white, black);
+        // the solver's reply and the move before it are taken back, then the
+        // solver, not being ahead, accepts a draw
+        assert!(controller.play().unwrap());
+        assert!(controller.game().played_moves().is_empty());
+        assert_eq!(
+            controller.game().result(),
+            Some(GameResult::Draw(Termination::Agreement))
+        );
+    }
+}
@@ -1,31 +1,166 @@
-use std::error::Error;
-use std::fmt::Display;
-use std::hash::{Hash, Hasher};
-use std::str::FromStr;
+//! The board and what goes on it: squares, pieces, moves and castling rights.
 
-#[derive(Debug)]
+use crate::game::GameResult;
+use crate::variant::{CHESS960_POSITIONS, STANDARD_BACK_ROW};
+use ::core::fmt::Display;
+use ::core::hash::Hash;
+use ::core::str::FromStr;
+use alloc::boxed::Box;
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+#[cfg(feature = "std")]
+use std::path::PathBuf;
+use thiserror::Error;
+
+/// Everything that can go wrong playing by the rules or reading the names of
+/// squares, moves and positions.
+#[derive(Debug, Error)]
 pub enum ChessError {
-    InvalidPiece(String),
-    InvalidMove(String),
-    SolverError(String),
+    /// The piece on `from` is the side to move's, but can't go to `to`.
+    #[error("The piece on {from} can't move to {to}: {reason}")]
+    IllegalMove {
+        from: Position,
+        to: Position,
+        reason: IllegalMoveReason,
+    },
+    #[error("There is no piece on {0}")]
+    NoPieceAtSquare(Position),
+    #[error("The piece on {square} is {}'s, and it is {turn}'s move", turn.flip())]
+    WrongColourToMove { square: Position, turn: ChessColour },
+    /// Input that could mean more than one legal move, with each of them in SAN.
+    #[error("'{input}' is ambiguous, it could be {}", candidates.join(" or "))]
+    AmbiguousMove {
+        input: String,
+        candidates: Vec<String>,
+    },
+    #[error("No legal move matches '{0}'")]
+    NoMatchingMove(String),
+    /// A move in a pasted list that couldn't be played, numbered e.g. `3.`.
+    #[error("Could not play {number} {token}: {source}")]
+    MoveInList {
+        number: String,
+        token: String,
+        source: Box<ChessError>,
+    },
+    #[error("the game is already over: {0}")]
+    GameOver(GameResult),
+    #[error("there are no moves to undo")]
+    NothingToUndo,
+    #[error("there are no legal moves")]
+    NoLegalMoves,
+    #[error("Can't read '{input}' as a {kind}")]
+    Parse { kind: ParseKind, input: String },
+    #[error("Invalid FEN '{fen}': {reason}")]
+    InvalidFen { fen: String, reason: String },
+    #[error("Invalid position: {}", join(.0))]
+    InvalidPosition(Vec<PositionError>),
+    #[error("Chess960 position index must be below {CHESS960_POSITIONS}, got {0}")]
+    InvalidChess960Index(u16),
+    #[error("{command} needs an argument, e.g. {example}")]
+    MissingArgument {
+        command: &'static str,
+        example: &'static str,
+    },
+    #[cfg(feature = "std")]
+    #[error("could not read {}: {source}", path.display())]
+    Io {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    #[error("Opening book error: {0}")]
+    InvalidBook(String),
+    #[error("Invalid network: {0}")]
+    InvalidNetwork(String),
+    #[error("Invalid position encoding: {0}")]
+    InvalidEncoding(String),
+}
+
+/// Why a move by the side to move's piece isn't legal.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IllegalMoveReason {
+    /// The piece doesn't move that way, or something is in its way.
+    Unreachable,
+    LeavesKingInCheck,
 }
 
-impl std::fmt::Display for ChessError {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+impl Display for IllegalMoveReason {
+    fn fmt(&self, f: &mut ::core::fmt::Formatter<'_>) -> ::core::fmt::Result {
         match self {
-            ChessError::InvalidPiece(msg) => write!(f, "Invalid chess piece: {msg}"),
-            ChessError::InvalidMove(msg) => write!(f, "Invalid move: {msg}"),
-            ChessError::SolverError(msg) => write!(f, "Solver error: {msg}"),
+            IllegalMoveReason::Unreachable => write!(f, "it can't get there"),
+            IllegalMoveReason::LeavesKingInCheck => {
+                write!(f, "that would leave the king in check")
+            }
         }
     }
 }
 
-impl Error for ChessError {
-    fn source(&self) -> Option<&(dyn Error + 'static)> {
-        None
+/// What a string that couldn't be read was meant to be.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParseKind {
+    Row,
+    Column,
+    Square,
+    Move,
+    Piece,
+    Board,
+    TimeControl,
+}
+
+impl Display for ParseKind {
+    fn fmt(&self, f: &mut ::core::fmt::Formatter<'_>) -> ::core::fmt::Result {
+        let name = match self {
+            ParseKind::Row => "row",
+            ParseKind::Column => "column",
+            ParseKind::Square => "square",
+            ParseKind::Move => "move",
+            ParseKind::Piece => "piece",
+            ParseKind::Board => "board",
+            ParseKind::TimeControl => "time control",
+        };
+        write!(f, "{name}")
     }
 }
 
+impl ChessError {
+    pub(crate) fn parse(kind: ParseKind, input: impl ToString) -> Self {
+        ChessError::Parse {
+            kind,
+            input: input.to_string(),
+        }
+    }
+}
+
+fn join(errors: &[PositionError]) -> String {
+    errors
+        .iter()
+        .map(PositionError::to_string)
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// A reason a board can not have been reached in a legal game.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum PositionError {
+    #[error("{0} has no king")]
+    MissingKing(ChessColour),
+    #[error("{0} has more than one king")]
+    TooManyKings(ChessColour),
+    #[error("pawn on back row at {0}")]
+    PawnOnBackRow(Position),
+    /// The side that is not to move is in check, so the previous move was illegal.
+    #[error("{0} is in check but it is not their turn")]
+    OpponentInCheck(ChessColour),
+    #[error("{0} has {1} pawns")]
+    TooManyPawns(ChessColour, usize),
+    #[error("{0} has {1} pieces")]
+    TooManyPieces(ChessColour, usize),
+    /// More pieces than could have come from promoting the missing pawns.
+    #[error("{0} has more promoted pieces than missing pawns")]
+    TooManyPromotedPieces(ChessColour),
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Copy)]
 pub enum Row {
     One,
@@ -46,7 +181,7 @@ impl Row {
 }
 
 impl Display for Row {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    fn fmt(&self, f: &mut ::core::fmt::Formatter<'_>) -> ::core::fmt::Result {
         let c = match self {
             Row::One => '1',
             Row::Two => '2',
@@ -74,7 +209,7 @@ impl FromStr for Row {
             "6" => Ok(Row::Six),
             "7" => Ok(Row::Seven),
             "8" => Ok(Row::Eight),
-            _ => Err(ChessError::InvalidMove(format!("Invalid row: '{s}'"))),
+            _ => Err(ChessError::parse(ParseKind::Row, s)),
         }
     }
 }
@@ -107,11 +242,12 @@ impl TryFrom<isize> for Row {
             5 => Ok(Row::Six),
             6 => Ok(Row::Seven),
             7 => Ok(Row::Eight),
-            _ => Err(ChessError::InvalidMove(format!("Invalid row: '{value}'"))),
+            _ => Err(ChessError::parse(ParseKind::Row, value)),
         }
     }
 }
 
+/// Serialized as its letter, e.g. `"e"`.
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Copy)]
 pub enum Column {
     A,
@@ -144,7 +280,7 @@ impl FromStr for Column {
             "f" => Ok(Column::F),
             "g" => Ok(Column::G),
             "h" => Ok(Column::H),
-            _ => Err(ChessError::InvalidMove(format!("Invalid column: '{s}'"))),
+            _ => Err(ChessError::parse(ParseKind::Column, s)),
         }
     }
 }
@@ -162,15 +298,13 @@ impl TryFrom<isize> for Column {
             5 => Ok(Column::F),
             6 => Ok(Column::G),
             7 => Ok(Column::H),
-            _ => Err(ChessError::InvalidMove(format!(
-                "Invalid column: '{value}'"
-            ))),
+            _ => Err(ChessError::parse(ParseKind::Column, value)),
         }
     }
 }
 
 impl Display for Column {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    fn fmt(&self, f: &mut ::core::fmt::Formatter<'_>) -> ::core::fmt::Result {
         let c = match self {
             Column::A => 'a',
             Column::B => 'b',
@@ -200,6 +334,7 @@ impl From<Column> for usize {
     }
 }
 
+/// Serialized as the square's name, e.g. `"e4"`.
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Copy)]
 pub struct Position {
     pub row: Row,
@@ -207,10 +342,18 @@ pub struct Position {
 }
 
 impl Position {
+    /// The column and row, in that order, counted from 0.
+    #[deprecated(note = "easy to index the wrong way round; use `Square::file` and `Square::rank`")]
     pub fn board_position(&self) -> (usize, usize) {
         (self.column.into(), self.row.into())
     }
 
+    /// Like [`Position::add_offset`], but returning `None` off the board rather
+    /// than building an error, for hot loops.
+    pub fn offset(&self, row: isize, column: isize) -> Option<Self> {
+        Square::from(*self).offset(row, column).map(Self::from)
+    }
+
     pub fn add_offset(&self, row: isize, column: isize) -> Result<Self, ChessError> {
         Ok(Self {
             row: self.row.try_add(row)?,
@@ -220,7 +363,7 @@ impl Position {
 }
 
 impl Display for Position {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    fn fmt(&self, f: &mut ::core::fmt::Formatter<'_>) -> ::core::fmt::Result {
         write!(f, "{}{}", self.column, self.row)
     }
 }
@@ -230,22 +373,227 @@ impl FromStr for Position {
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         // parse the format a1, c6, ...
+        let invalid = || ChessError::parse(ParseKind::Square, s);
         if s.len() != 2 {
-            return Err(ChessError::InvalidMove(format!(
-                "Invalid position format: '{s}'"
-            )));
-        }
-        let column = Column::from_str(s.get(0..1).ok_or_else(|| {
-            ChessError::InvalidMove(format!("Invalid column in position: '{s}'"))
-        })?)?;
-        let row =
-            Row::from_str(s.get(1..2).ok_or_else(|| {
-                ChessError::InvalidMove(format!("Invalid row in position: '{s}'"))
-            })?)?;
+            return Err(invalid());
+        }
+        let column = Column::from_str(s.get(0..1).ok_or_else(invalid)?).map_err(|_| invalid())?;
+        let row = Row::from_str(s.get(1..2).ok_or_else(invalid)?).map_err(|_| invalid())?;
         Ok(Position { row, column })
     }
 }
 
+const ROWS: [Row; 8] = [
+    Row::One,
+    Row::Two,
+    Row::Three,
+    Row::Four,
+    Row::Five,
+    Row::Six,
+    Row::Seven,
+    Row::Eight,
+];
+
+const COLUMNS: [Column; 8] = [
+    Column::A,
+    Column::B,
+    Column::C,
+    Column::D,
+    Column::E,
+    Column::F,
+    Column::G,
+    Column::H,
+];
+
+/// A square as its number along the rows from a1 (0) to h8 (63): the same
+/// square as a [`Position`], in a form that is cheap to step around the board
+/// with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Square(u8);
+
+impl Square {
+    /// The square `index` along the rows from a1, if it is on the board.
+    pub const fn from_index(index: usize) -> Option<Self> {
+        if index < 64 {
+            Some(Self(index as u8))
+        } else {
+            None
+        }
+    }
+
+    /// The square on row `rank` and column `file`, both counted from 0, if it
+    /// is on the board.
+    pub const fn new(rank: usize, file: usize) -> Option<Self> {
+        if rank < 8 && file < 8 {
+            Some(Self((rank * 8 + file) as u8))
+        } else {
+            None
+        }
+    }
+
+    /// Every square, from a1 along the rows to h8.
+    pub fn all() -> impl DoubleEndedIterator<Item = Self> {
+        (0..64).map(Self)
+    }
+
+    pub const fn index(self) -> usize {
+        self.0 as usize
+    }
+
+    /// The row, counted from 0 for the first.
+    pub const fn rank(self) -> usize {
+        (self.0 / 8) as usize
+    }
+
+    /// The column, counted from 0 for a.
+    pub const fn file(self) -> usize {
+        (self.0 % 8) as usize
+    }
+
+    pub const fn row(self) -> Row {
+        ROWS[self.rank()]
+    }
+
+    pub const fn column(self) -> Column {
+        COLUMNS[self.file()]
+    }
+
+    /// The square `rank` rows and `file` columns away, or `None` off the board.
+    pub const fn offset(self, rank: isize, file: isize) -> Option<Self> {
+        let rank = self.rank() as isize + rank;
+        let file = self.file() as isize + file;
+        if rank < 0 || rank >= 8 || file < 0 || file >= 8 {
+            return None;
+        }
+        Some(Self((rank * 8 + file) as u8))
+    }
+}
+
+impl From<Position> for Square {
+    fn from(pos: Position) -> Self {
+        Self((usize::from(pos.row) * 8 + usize::from(pos.column)) as u8)
+    }
+}
+
+impl From<Square> for Position {
+    fn from(square: Square) -> Self {
+        Self {
+            row: square.row(),
+            column: square.column(),
+        }
+    }
+}
+
+impl Display for Square {
+    fn fmt(&self, f: &mut ::core::fmt::Formatter<'_>) -> ::core::fmt::Result {
+        Position::from(*self).fmt(f)
+    }
+}
+
+impl FromStr for Square {
+    type Err = ChessError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Position::from_str(s).map(Self::from)
+    }
+}
+
+pub(crate) const KNIGHT_OFFSETS: [(isize, isize); 8] = [
+    (1, 2),
+    (1, -2),
+    (-1, 2),
+    (-1, -2),
+    (2, 1),
+    (2, -1),
+    (-2, 1),
+    (-2, -1),
+];
+pub(crate) const STRAIGHT: [(isize, isize); 4] = [(1, 0), (0, 1), (-1, 0), (0, -1)];
+pub(crate) const DIAGONAL: [(isize, isize); 4] = [(1, 1), (-1, 1), (-1, -1), (1, -1)];
+/// The straight directions then the diagonal ones.
+pub(crate) const ALL_DIRECTIONS: [(isize, isize); 8] = [
+    STRAIGHT[0],
+    STRAIGHT[1],
+    STRAIGHT[2],
+    STRAIGHT[3],
+    DIAGONAL[0],
+    DIAGONAL[1],
+    DIAGONAL[2],
+    DIAGONAL[3],
+];
+
+/// Up to eight squares, in a form that fits in a const table.
+#[derive(Debug, Clone, Copy)]
+pub struct Squares {
+    squares: [Square; 8],
+    len: u8,
+}
+
+impl Squares {
+    const EMPTY: Self = Self {
+        squares: [Square(0); 8],
+        len: 0,
+    };
+
+    const fn push(&mut self, square: Square) {
+        self.squares[self.len as usize] = square;
+        self.len += 1;
+    }
+
+    pub fn as_slice(&self) -> &[Square] {
+        &self.squares[..self.len as usize]
+    }
+}
+
+/// The squares one step of each of `offsets` away from every square.
+const fn step_table(offsets: &[(isize, isize)]) -> [Squares; 64] {
+    let mut table = [Squares::EMPTY; 64];
+    let mut index = 0;
+    while index < 64 {
+        let mut i = 0;
+        while i < offsets.len() {
+            let (rank, file) = offsets[i];
+            if let Some(to) = Square(index as u8).offset(rank, file) {
+                table[index].push(to);
+            }
+            i += 1;
+        }
+        index += 1;
+    }
+    table
+}
+
+/// The squares from every square to the edge of the board in each of
+/// [`ALL_DIRECTIONS`], nearest first.
+const fn ray_table() -> [[Squares; 64]; 8] {
+    let mut table = [[Squares::EMPTY; 64]; 8];
+    let mut direction = 0;
+    while direction < 8 {
+        let (rank, file) = ALL_DIRECTIONS[direction];
+        let mut index = 0;
+        while index < 64 {
+            let mut square = Square(index as u8);
+            while let Some(next) = square.offset(rank, file) {
+                table[direction][index].push(next);
+                square = next;
+            }
+            index += 1;
+        }
+        direction += 1;
+    }
+    table
+}
+
+/// The squares a knight reaches from each square.
+pub const KNIGHT_MOVES: [Squares; 64] = step_table(&KNIGHT_OFFSETS);
+/// The squares a king reaches from each square, castling aside.
+pub const KING_MOVES: [Squares; 64] = step_table(&ALL_DIRECTIONS);
+/// The squares from each square to the edge of the board, nearest first, by
+/// the index of their direction in [`ALL_DIRECTIONS`]: the first four straight,
+/// the last four diagonal.
+pub(crate) const RAYS: [[Squares; 64]; 8] = ray_table();
+
+/// Serialized in UCI notation, e.g. `"e2e4"`.
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Copy)]
 pub struct Move {
     pub from: Position,
@@ -255,28 +603,29 @@ pub struct Move {
 impl Move {
     pub fn from_uci(s: &str) -> Result<Self, ChessError> {
         // parse the format e2e4, b1c3, ...
+        let invalid = || ChessError::parse(ParseKind::Move, s);
         if s.len() != 4 {
-            return Err(ChessError::InvalidMove(format!(
-                "Invalid move format: '{s}'"
-            )));
-        }
-        let from = Position::from_str(s.get(0..2).ok_or_else(|| {
-            ChessError::InvalidMove(format!("Invalid from position in move: '{s}'"))
-        })?)?;
-        let to = Position::from_str(s.get(2..4).ok_or_else(|| {
-            ChessError::InvalidMove(format!("Invalid to position in move: '{s}'"))
-        })?)?;
+            return Err(invalid());
+        }
+        let from = Position::from_str(s.get(0..2).ok_or_else(invalid)?).map_err(|_| invalid())?;
+        let to = Position::from_str(s.get(2..4).ok_or_else(invalid)?).map_err(|_| invalid())?;
         Ok(Move { from, to })
     }
+
+    /// The move in UCI's long algebraic notation, e.g. `e2e4`.
+    pub fn to_uci(self) -> String {
+        format!("{}{}", self.from, self.to)
+    }
 }
 
 impl Display for Move {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    fn fmt(&self, f: &mut ::core::fmt::Formatter<'_>) -> ::core::fmt::Result {
         write!(f, "{}->{}", self.from, self.to)
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Hash, Copy)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum ChessPieceKind {
     Pawn,
     Knight,
@@ -311,14 +660,15 @@ impl ChessPieceKind {
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Hash, Copy)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum ChessColour {
     White,
     Black,
 }
 
 impl Display for ChessColour {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    fn fmt(&self, f: &mut ::core::fmt::Formatter<'_>) -> ::core::fmt::Result {
         match self {
             ChessColour::White => write!(f, "White"),
             ChessColour::Black => write!(f, "Black"),
@@ -346,9 +696,17 @@ impl ChessColour {
             ChessColour::Black => ChessColour::White,
         }
     }
+
+    /// The row the pieces of this colour start on.
+    pub fn back_row(&self) -> Row {
+        match self {
+            ChessColour::White => Row::One,
+            ChessColour::Black => Row::Eight,
+        }
+    }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Hash, Copy)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Copy, Serialize, Deserialize)]
 pub struct ChessPiece {
     pub kind: ChessPieceKind,
     pub colour: ChessColour,
@@ -384,9 +742,7 @@ impl TryFrom<char> for ChessPiece {
             'r' => Ok(ChessPieceKind::Rook),
             'q' => Ok(ChessPieceKind::Queen),
             'k' => Ok(ChessPieceKind::King),
-            _ => Err(ChessError::InvalidPiece(format!(
-                "Invalid chess piece character: '{c}'"
-            ))),
+            _ => Err(ChessError::parse(ParseKind::Piece, c)),
         }?;
         let colour = if c.is_uppercase() {
             ChessColour::White
@@ -401,7 +757,98 @@ impl TryFrom<char> for ChessPiece {
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Hash, Copy)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CastlingSide {
+    King,
+    Queen,
+}
+
+impl CastlingSide {
+    /// The column the king ends up on after castling to this side.
+    pub fn king_destination(&self) -> Column {
+        match self {
+            CastlingSide::King => Column::G,
+            CastlingSide::Queen => Column::C,
+        }
+    }
+
+    /// The column the rook ends up on after castling to this side.
+    pub fn rook_destination(&self) -> Column {
+        match self {
+            CastlingSide::King => Column::F,
+            CastlingSide::Queen => Column::D,
+        }
+    }
+}
+
+/// The columns of the rooks each side is still allowed to castle with.
+///
+/// Tracking the rook's column rather than a flag means the same rules work for
+/// both standard chess and Chess960, where the rooks can start on any column.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Copy, Default, Serialize, Deserialize)]
+pub struct CastlingRights {
+    pub white_king_side: Option<Column>,
+    pub white_queen_side: Option<Column>,
+    pub black_king_side: Option<Column>,
+    pub black_queen_side: Option<Column>,
+}
+
+impl CastlingRights {
+    pub fn standard() -> Self {
+        Self {
+            white_king_side: Some(Column::H),
+            white_queen_side: Some(Column::A),
+            black_king_side: Some(Column::H),
+            black_queen_side: Some(Column::A),
+        }
+    }
+
+    pub fn get(&self, colour: ChessColour, side: CastlingSide) -> Option<Column> {
+        match (colour, side) {
+            (ChessColour::White, CastlingSide::King) => self.white_king_side,
+            (ChessColour::White, CastlingSide::Queen) => self.white_queen_side,
+            (ChessColour::Black, CastlingSide::King) => self.black_king_side,
+            (ChessColour::Black, CastlingSide::Queen) => self.black_queen_side,
+        }
+    }
+
+    pub fn set(&mut self, colour: ChessColour, side: CastlingSide, column: Option<Column>) {
+        match (colour, side) {
+            (ChessColour::White, CastlingSide::King) => self.white_king_side = column,
+            (ChessColour::White, CastlingSide::Queen) => self.white_queen_side = column,
+            (ChessColour::Black, CastlingSide::King) => self.black_king_side = column,
+            (ChessColour::Black, CastlingSide::Queen) => self.black_queen_side = column,
+        }
+    }
+
+    /// Remove both castling rights for the given colour, e.g. once the king has moved.
+    pub fn clear(&mut self, colour: ChessColour) {
+        self.set(colour, CastlingSide::King, None);
+        self.set(colour, CastlingSide::Queen, None);
+    }
+
+    /// Remove any castling right that relies on a rook standing at `pos`. Used when
+    /// the rook moves away or is captured.
+    pub fn remove_rook(&mut self, pos: &Position) {
+        for colour in [ChessColour::White, ChessColour::Black] {
+            if pos.row != colour.back_row() {
+                continue;
+            }
+            for side in [CastlingSide::King, CastlingSide::Queen] {
+                if self.get(colour, side) == Some(pos.column) {
+                    self.set(colour, side, None);
+                }
+            }
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Copy, Serialize, Deserialize)]
 pub struct Cell {
     pub piece: Option<ChessPiece>,
     pub colour: ChessColour,
@@ -414,7 +861,7 @@ impl Cell {
         } else {
             Some(ChessPiece::try_from(value)?)
         };
-        let colour = if (pos.1 + pos.0) % 2 == 0 {
+        let colour = if (pos.1 + pos.0).is_multiple_of(2) {
             ChessColour::White
         } else {
             ChessColour::Black
@@ -432,38 +879,41 @@ impl From<&Cell> for char {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct ChessBoard {
-    pub board: [[Cell; 8]; 8],
+    /// Indexed by row then column; reached through [`ChessBoard::get`] and
+    /// [`ChessBoard::set`].
+    board: [[Cell; 8]; 8],
     pub turn: ChessColour,
+    pub castling: CastlingRights,
 }
 
 impl ChessBoard {
-    pub(crate) fn get_piece_at(&self, pos: &Position) -> Option<&Cell> {
-        let x: usize = pos.row.into();
-        let y: usize = pos.column.into();
-        if x < 8 && y < 8 {
-            Some(&self.board[x][y])
-        } else {
-            None
-        }
+    /// The piece on `square`, if any.
+    pub fn get(&self, square: Square) -> Option<ChessPiece> {
+        self.cell(square).piece
+    }
+
+    /// Place `piece` on `square` (or clear it with `None`), keeping the colour
+    /// of the square itself.
+    pub fn set(&mut self, square: Square, piece: Option<ChessPiece>) {
+        self.board[square.rank()][square.file()].piece = piece;
+    }
+
+    /// The square itself, with its colour and whatever piece is on it.
+    pub fn cell(&self, square: Square) -> &Cell {
+        &self.board[square.rank()][square.file()]
+    }
+
+    /// The square at `pos`, with whatever piece is on it.
+    pub fn get_piece_at(&self, pos: &Position) -> Option<&Cell> {
+        Some(self.cell(Square::from(*pos)))
     }
 
     pub fn pieces(&self) -> impl Iterator<Item = (Position, &Cell)> {
-        self.board.iter().enumerate().flat_map(|(row_index, row)| {
-            row.iter().enumerate().filter_map(move |(col_index, cell)| {
-                if cell.piece.is_some() {
-                    Some((
-                        Position {
-                            row: Row::try_from(row_index as isize).unwrap(),
-                            column: Column::try_from(col_index as isize).unwrap(),
-                        },
-                        cell,
-                    ))
-                } else {
-                    None
-                }
-            })
+        Square::all().filter_map(|square| {
+            let cell = self.cell(square);
+            cell.piece.map(|_| (Position::from(square), cell))
         })
     }
 
@@ -471,15 +921,197 @@ impl ChessBoard {
         self.board.iter()
     }
 
+    /// Find the king of the given colour, if it is on the board.
+    pub fn king_position(&self, colour: ChessColour) -> Option<Position> {
+        self.king_square(colour).map(Position::from)
+    }
+
+    pub(crate) fn king_square(&self, colour: ChessColour) -> Option<Square> {
+        Square::all().find(|square| {
+            self.get(*square)
+                .is_some_and(|p| p.kind == ChessPieceKind::King && p.colour == colour)
+        })
+    }
+
+    /// Whether the king of the given colour is currently attacked.
+    pub fn is_in_check(&self, colour: ChessColour) -> bool {
+        self.king_square(colour)
+            .is_some_and(|square| self.is_attacked(square, colour.flip()))
+    }
+
+    /// Check that the position could be reached in a legal game, returning every
+    /// problem found rather than stopping at the first.
+    pub fn validate(&self) -> Result<(), Vec<PositionError>> {
+        let mut errors = Vec::new();
+        for colour in [ChessColour::White, ChessColour::Black] {
+            let count = |kind: ChessPieceKind| {
+                self.pieces()
+                    .filter(|(_, cell)| {
+                        cell.piece
+                            .is_some_and(|p| p.kind == kind && p.colour == colour)
+                    })
+                    .count()
+            };
+            match count(ChessPieceKind::King) {
+                0 => errors.push(PositionError::MissingKing(colour)),
+                1 => {}
+                _ => errors.push(PositionError::TooManyKings(colour)),
+            }
+
+            let pawns = count(ChessPieceKind::Pawn);
+            if pawns > 8 {
+                errors.push(PositionError::TooManyPawns(colour, pawns));
+            }
+            let pieces = self
+                .pieces()
+                .filter(|(_, cell)| cell.piece.is_some_and(|p| p.colour == colour))
+                .count();
+            if pieces > 16 {
+                errors.push(PositionError::TooManyPieces(colour, pieces));
+            }
+
+            // every piece beyond the starting set must have been a pawn once
+            let promoted: usize = [
+                (ChessPieceKind::Queen, 1),
+                (ChessPieceKind::Rook, 2),
+                (ChessPieceKind::Bishop, 2),
+                (ChessPieceKind::Knight, 2),
+            ]
+            .into_iter()
+            .map(|(kind, start)| count(kind).saturating_sub(start))
+            .sum();
+            if promoted > 8usize.saturating_sub(pawns) {
+                errors.push(PositionError::TooManyPromotedPieces(colour));
+            }
+        }
+
+        for (pos, cell) in self.pieces() {
+            if cell.piece.is_some_and(|p| p.kind == ChessPieceKind::Pawn)
+                && (pos.row == Row::One || pos.row == Row::Eight)
+            {
+                errors.push(PositionError::PawnOnBackRow(pos));
+            }
+        }
+
+        let waiting = self.turn.flip();
+        if self.is_in_check(waiting) {
+            errors.push(PositionError::OpponentInCheck(waiting));
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
+    /// The squares attacked by the piece at `pos`, regardless of what stands on
+    /// them. Sliding pieces stop at the first occupied square.
+    pub fn attacked_squares(&self, pos: &Position) -> Vec<Position> {
+        let Some(piece) = self.get_piece_at(pos).and_then(|cell| cell.piece) else {
+            return Vec::new();
+        };
+        let square = Square::from(*pos);
+        let steps = |table: &[Squares; 64]| -> Vec<Position> {
+            table[square.index()]
+                .as_slice()
+                .iter()
+                .map(|to| Position::from(*to))
+                .collect()
+        };
+        let slides = |directions: ::core::ops::Range<usize>| -> Vec<Position> {
+            let mut squares = Vec::new();
+            for rays in &RAYS[directions] {
+                for to in rays[square.index()].as_slice() {
+                    squares.push(Position::from(*to));
+                    if self.get(*to).is_some() {
+                        break;
+                    }
+                }
+            }
+            squares
+        };
+        match piece.kind {
+            ChessPieceKind::Pawn => {
+                let direction = piece.colour.direction();
+                [-1, 1]
+                    .into_iter()
+                    .filter_map(|i| square.offset(direction, i).map(Position::from))
+                    .collect()
+            }
+            ChessPieceKind::Knight => steps(&KNIGHT_MOVES),
+            ChessPieceKind::Bishop => slides(4..8),
+            ChessPieceKind::Rook => slides(0..4),
+            ChessPieceKind::Queen => slides(0..8),
+            ChessPieceKind::King => steps(&KING_MOVES),
+        }
+    }
+
+    /// Whether any piece of colour `by` attacks the square at `pos`. The square
+    /// itself may be empty or occupied by either colour.
+    pub fn is_square_attacked(&self, pos: &Position, by: ChessColour) -> bool {
+        self.is_attacked(Square::from(*pos), by)
+    }
+
+    /// [`ChessBoard::is_square_attacked`] for a [`Square`], looking outwards
+    /// from it along the const tables for each kind of piece.
+    pub(crate) fn is_attacked(&self, square: Square, by: ChessColour) -> bool {
+        let is_attacker = |square: Square, kinds: &[ChessPieceKind]| {
+            self.get(square)
+                .is_some_and(|p| p.colour == by && kinds.contains(&p.kind))
+        };
+
+        // pawns attack diagonally forwards, so look backwards from the target square
+        let pawn_row = -by.direction();
+        if [-1, 1].into_iter().any(|i| {
+            square
+                .offset(pawn_row, i)
+                .is_some_and(|from| is_attacker(from, &[ChessPieceKind::Pawn]))
+        }) {
+            return true;
+        }
+
+        if KNIGHT_MOVES[square.index()]
+            .as_slice()
+            .iter()
+            .any(|from| is_attacker(*from, &[ChessPieceKind::Knight]))
+        {
+            return true;
+        }
+
+        if KING_MOVES[square.index()]
+            .as_slice()
+            .iter()
+            .any(|from| is_attacker(*from, &[ChessPieceKind::King]))
+        {
+            return true;
+        }
+
+        for (direction, rays) in RAYS.iter().enumerate() {
+            let sliders: &[ChessPieceKind] = if direction < 4 {
+                &[ChessPieceKind::Rook, ChessPieceKind::Queen]
+            } else {
+                &[ChessPieceKind::Bishop, ChessPieceKind::Queen]
+            };
+            for from in rays[square.index()].as_slice() {
+                if let Some(piece) = self.get(*from) {
+                    if piece.colour == by && sliders.contains(&piece.kind) {
+                        return true;
+                    }
+                    break;
+                }
+            }
+        }
+
+        false
+    }
+
     pub fn hash(&self) -> u64 {
         use xxhash_rust::xxh3::xxh3_64;
         let mut arr = [0; 64];
-        for i in 0..=7 {
-            for j in 0..=7 {
-                let cell = self.board[i][j];
-                if let Some(piece) = cell.piece {
-                    arr[i * 8 + j] = piece.kind.hash_value() + 8 * cell.colour.hash_multiplier();
-                }
+        for square in Square::all() {
+            if let Some(piece) = self.get(square) {
+                arr[square.index()] = piece.kind.hash_value() + 8 * piece.colour.hash_multiplier();
             }
         }
         xxh3_64(&arr)
@@ -502,15 +1134,11 @@ impl FromStr for ChessBoard {
             .enumerate()
         {
             if i >= 8 {
-                return Err(ChessError::InvalidPiece(
-                    "too many rows on chess board".to_string(),
-                ));
+                return Err(ChessError::parse(ParseKind::Board, s));
             }
             for (j, c) in line.chars().enumerate() {
                 if j >= 8 {
-                    return Err(ChessError::InvalidPiece(
-                        "too many columns on chess board".to_string(),
-                    ));
+                    return Err(ChessError::parse(ParseKind::Board, line));
                 }
                 board[i][j] = Cell::parse(c, (i, j))?;
             }
@@ -519,26 +1147,209 @@ impl FromStr for ChessBoard {
         Ok(Self {
             board,
             turn: ChessColour::White,
+            castling: CastlingRights::default(),
         })
     }
 }
 
-impl Default for ChessBoard {
-    fn default() -> Self {
-        let board_str = r#"
-        rnbqkbnr
-        pppppppp
-        ........
-        ........
-        ........
-        ........
-        PPPPPPPP
-        RNBQKBRN
-    "#;
-        let board = ChessBoard::from_str(board_str).expect("Failed to parse chess board");
+impl ChessBoard {
+    /// A board with nothing on it, White to move and no castling rights.
+    pub fn empty() -> Self {
+        let mut board = [[Cell {
+            piece: None,
+            colour: ChessColour::White,
+        }; 8]; 8];
+        for (i, row) in board.iter_mut().enumerate() {
+            for (j, cell) in row.iter_mut().enumerate() {
+                if !(i + j).is_multiple_of(2) {
+                    cell.colour = ChessColour::Black;
+                }
+            }
+        }
         ChessBoard {
-            board: board.board,
+            board,
             turn: ChessColour::White,
+            castling: CastlingRights::default(),
         }
     }
+
+    /// The start of a game with `back_row` on each side's back row and a row
+    /// of pawns in front, both sides able to castle with their outermost rooks.
+    pub fn starting_position(back_row: &[ChessPieceKind; 8]) -> Self {
+        let mut board = Self::empty();
+        for colour in [ChessColour::White, ChessColour::Black] {
+            let row = colour.back_row();
+            let pawn_row = row
+                .try_add(colour.direction())
+                .expect("pawns fit on the board");
+            let mut rooks = Vec::with_capacity(2);
+            for (i, kind) in back_row.iter().enumerate() {
+                let column = Column::try_from(i as isize).expect("eight columns");
+                if *kind == ChessPieceKind::Rook {
+                    rooks.push(column);
+                }
+                for (row, kind) in [(row, *kind), (pawn_row, ChessPieceKind::Pawn)] {
+                    board.set(
+                        Square::from(Position { row, column }),
+                        Some(ChessPiece {
+                            kind,
+                            colour,
+                            moved: false,
+                        }),
+                    );
+                }
+            }
+            board
+                .castling
+                .set(colour, CastlingSide::Queen, rooks.first().copied());
+            board
+                .castling
+                .set(colour, CastlingSide::King, rooks.last().copied());
+        }
+        board
+    }
+}
+
+impl Default for ChessBoard {
+    fn default() -> Self {
+        Self::starting_position(&STANDARD_BACK_ROW)
+    }
+}
+
+/// Implement `Serialize` and `Deserialize` for a type written as a string,
+/// using `$write` to write it and `$read` to read it back.
+macro_rules! serde_as_string {
+    ($type:ty, $write:expr, $read:expr) => {
+        impl Serialize for $type {
+            fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                serializer.serialize_str(&$write(self))
+            }
+        }
+
+        impl<'de> Deserialize<'de> for $type {
+            fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                let s = ::alloc::string::String::deserialize(deserializer)?;
+                $read(s.as_str()).map_err(serde::de::Error::custom)
+            }
+        }
+    };
+}
+#[cfg(feature = "std")]
+pub(crate) use serde_as_string;
+
+serde_as_string!(Row, Row::to_string, Row::from_str);
+serde_as_string!(Column, Column::to_string, Column::from_str);
+serde_as_string!(Position, Position::to_string, Position::from_str);
+serde_as_string!(Move, |mv: &Move| mv.to_uci(), Move::from_uci);
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_default_board_is_valid() {
+        assert!(ChessBoard::default().validate().is_ok());
+    }
+
+    #[test]
+    fn test_validate_reports_every_problem() {
+        let board = ChessBoard::from_str(
+            r#"
+            P...k...
+            ........
+            ........
+            ........
+            ....r...
+            ........
+            ........
+            ........
+        "#,
+        )
+        .unwrap();
+        let errors = board.validate().unwrap_err();
+        assert_eq!(
+            errors,
+            vec![
+                PositionError::MissingKing(ChessColour::White),
+                PositionError::PawnOnBackRow(Position {
+                    row: Row::Eight,
+                    column: Column::A
+                }),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_side_not_to_move_in_check() {
+        let mut board = ChessBoard::from_str(
+            r#"
+            ....k...
+            ........
+            ........
+            ........
+            ....R...
+            ........
+            ........
+            ....K...
+        "#,
+        )
+        .unwrap();
+        assert!(board.validate().is_err_and(
+            |errors| errors == vec![PositionError::OpponentInCheck(ChessColour::Black)]
+        ));
+        board.turn = ChessColour::Black;
+        assert!(board.validate().is_ok());
+    }
+
+    #[test]
+    fn test_square() {
+        let e4 = Square::from_str("e4").unwrap();
+        assert_eq!((e4.rank(), e4.file(), e4.index()), (3, 4, 28));
+        assert_eq!(Position::from(e4), Position::from_str("e4").unwrap());
+        assert_eq!(Square::from(Position::from(e4)), e4);
+        assert_eq!(e4.offset(1, -1).map(|s| s.to_string()), Some("d5".into()));
+        assert_eq!(Square::from_str("h8").unwrap().offset(0, 1), None);
+        assert_eq!(Square::from_index(64), None);
+
+        let corner = Square::from_str("a1").unwrap();
+        let names = |squares: &Squares| -> Vec<String> {
+            squares.as_slice().iter().map(|s| s.to_string()).collect()
+        };
+        assert_eq!(names(&KNIGHT_MOVES[corner.index()]), ["c2", "b3"]);
+        assert_eq!(names(&KING_MOVES[corner.index()]), ["a2", "b1", "b2"]);
+        assert_eq!(names(&RAYS[4][e4.index()]), ["f5", "g6", "h7"]);
+    }
+
+    #[test]
+    fn test_get_and_set() {
+        let mut board = ChessBoard::default();
+        let e2 = Square::from_str("e2").unwrap();
+        let e4 = Square::from_str("e4").unwrap();
+        let pawn = board.get(e2).unwrap();
+        assert_eq!(pawn.kind, ChessPieceKind::Pawn);
+        board.set(e2, None);
+        board.set(e4, Some(pawn));
+        assert_eq!(board.get(e2), None);
+        assert_eq!(
+            board.get_piece_at(&Position::from(e4)).unwrap().piece,
+            Some(pawn)
+        );
+        // squares keep their colour whatever stands on them
+        let empty = ChessBoard::empty();
+        assert_eq!(board.cell(e4).colour, empty.cell(e4).colour);
+        assert_eq!(board.cell(e2).colour, empty.cell(e2).colour);
+    }
+
+    #[test]
+    fn test_serde_round_trip() {
+        let mv = Move::from_uci("g1f3").unwrap();
+        let json = serde_json::to_string(&mv).unwrap();
+        assert_eq!(json, r#""g1f3""#);
+        assert_eq!(serde_json::from_str::<Move>(&json).unwrap(), mv);
+        assert!(serde_json::from_str::<Position>(r#""i9""#).is_err());
+
+        let board = ChessBoard::default();
+        let json = serde_json::to_string(&board).unwrap();
+        assert_eq!(serde_json::from_str::<ChessBoard>(&json).unwrap(), board);
+    }
 }
@@ -0,0 +1,295 @@
+//! Every finished game, kept in a local SQLite database so they can be looked
+//! up again by opponent, opening, result or date, and counted up.
+
+use crate::game::ChessGame;
+use anyhow::Context;
+use rusqlite::{Connection, OptionalExtension, Row, params};
+use std::path::{Path, PathBuf};
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS games (
+        id INTEGER PRIMARY KEY,
+        white TEXT NOT NULL,
+        black TEXT NOT NULL,
+        result TEXT NOT NULL,
+        eco TEXT,
+        date TEXT NOT NULL,
+        pgn TEXT NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS games_date ON games (date);
+";
+
+/// The conditions [`GameQuery`]'s fields set, as numbered parameters so one
+/// statement serves every query.
+const MATCHING: &str = "
+    WHERE (?1 IS NULL OR white = ?1 COLLATE NOCASE OR black = ?1 COLLATE NOCASE)
+      AND (?2 IS NULL OR eco LIKE ?2 || '%')
+      AND (?3 IS NULL OR result = ?3)
+      AND (?4 IS NULL OR date >= ?4)
+      AND (?5 IS NULL OR date <= ?5)
+";
+
+/// A game as the database keeps it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StoredGame {
+    pub id: i64,
+    pub white: String,
+    pub black: String,
+    /// `1-0`, `0-1` or `1/2-1/2`.
+    pub result: String,
+    /// The opening's ECO code, if it started from the usual position in a
+    /// known opening.
+    pub eco: Option<String>,
+    /// The day it was played, as `YYYY-MM-DD`.
+    pub date: String,
+    pub pgn: String,
+}
+
+impl StoredGame {
+    fn from_row(row: &Row) -> rusqlite::Result<Self> {
+        Ok(Self {
+            id: row.get(0)?,
+            white: row.get(1)?,
+            black: row.get(2)?,
+            result: row.get(3)?,
+            eco: row.get(4)?,
+            date: row.get(5)?,
+            pgn: row.get(6)?,
+        })
+    }
+}
+
+/// Which games to find. Each field left as `None` matches every game.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct GameQuery {
+    /// A player on either side, ignoring case.
+    pub opponent: Option<String>,
+    /// An ECO code, or its first letters, e.g. `B` for the semi-open games.
+    pub eco: Option<String>,
+    /// `1-0`, `0-1` or `1/2-1/2`.
+    pub result: Option<String>,
+    /// The first and last days to include, as `YYYY-MM-DD`.
+    pub from: Option<String>,
+    pub to: Option<String>,
+}
+
+/// How the games found went.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Results {
+    pub white_wins: u64,
+    pub draws: u64,
+    pub black_wins: u64,
+}
+
+pub struct GameDatabase {
+    connection: Connection,
+}
+
+impl GameDatabase {
+    /// `chess/games.sqlite` in the data directory.
+    pub fn default_path() -> Option<PathBuf> {
+        Some(crate::config::data_dir()?.join("games.sqlite"))
+    }
+
+    /// The database at `path`, created if there isn't one yet.
+    pub fn open(path: &Path) -> anyhow::Result<Self> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)
+                .with_context(|| format!("could not create {}", dir.display()))?;
+        }
+        let connection = Connection::open(path)
+            .with_context(|| format!("could not open the game database {}", path.display()))?;
+        Self::new(connection)
+    }
+
+    /// An empty database that is gone when dropped, e.g. for testing.
+    pub fn in_memory() -> anyhow::Result<Self> {
+        Self::new(Connection::open_in_memory()?)
+    }
+
+    fn new(connection: Connection) -> anyhow::Result<Self> {
+        connection
+            .execute_batch(SCHEMA)
+            .context("could not set up the game database")?;
+        Ok(Self { connection })
+    }
+
+    /// Keep `game`, which has to be over, as played today by `white` and
+    /// `black`. Returns its ID.
+    pub fn record(&self, game: &ChessGame, white: &str, black: &str) -> anyhow::Result<i64> {
+        let today: String =
+            self.connection
+                .query_row("SELECT date('now', 'localtime')", [], |row| row.get(0))?;
+        self.record_on(game, white, black, &today)
+    }
+
+    /// Keep `game` as played on `date`, given as `YYYY-MM-DD`.
+    pub fn record_on(
+        &self,
+        game: &ChessGame,
+        white: &str,
+        black: &str,
+        date: &str,
+    ) -> anyhow::Result<i64> {
+        let result = game
+            .result()
+            .context("only finished games are kept")?
+            .score();
+        let eco = game.opening().map(|opening| opening.eco);
+        let pgn = game.pgn_with_tags(&[
+            ("Date", &date.replace('-', ".")),
+            ("White", white),
+            ("Black", black),
+        ])?;
+        self.connection
+            .execute(
+                "INSERT INTO games (white, black, result, eco, date, pgn)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                params![white, black, result, eco, date, pgn],
+            )
+            .context("could not save the game")?;
+        Ok(self.connection.last_insert_rowid())
+    }
+
+    /// The game with ID `id`.
+    pub fn get(&self, id: i64) -> anyhow::Result<Option<StoredGame>> {
+        Ok(self
+            .connection
+            .query_row(
+                "SELECT id, white, black, result, eco, date, pgn FROM games WHERE id = ?1",
+                [id],
+                StoredGame::from_row,
+            )
+            .optional()?)
+    }
+
+    /// The games `query` matches, oldest first.
+    pub fn find(&self, query: &GameQuery) -> anyhow::Result<Vec<StoredGame>> {
+        let mut statement = self.connection.prepare(&format!(
+            "SELECT id, white, black, result, eco, date, pgn FROM games {MATCHING}
+             ORDER BY date, id"
+        ))?;
+        let games = statement
+            .query_map(Self::params(query), StoredGame::from_row)?
+            .collect::<Result<_, _>>()?;
+        Ok(games)
+    }
+
+    /// How the games `query` matches went.
+    pub fn results(&self, query: &GameQuery) -> anyhow::Result<Results> {
+        let mut statement = self.connection.prepare(&format!(
+            "SELECT result, COUNT(*) FROM games {MATCHING} GROUP BY result"
+        ))?;
+        let mut rows = statement.query(Self::params(query))?;
+        let mut results = Results::default();
+        while let Some(row) = rows.next()? {
+            let count: u64 = row.get(1)?;
+            match row.get::<_, String>(0)?.as_str() {
+                "1-0" => results.white_wins += count,
+                "0-1" => results.black_wins += count,
+                _ => results.draws += count,
+            }
+        }
+        Ok(results)
+    }
+
+    /// How many of the games `query` matches were played in each opening,
+    /// the most played first.
+    pub fn openings(&self, query: &GameQuery) -> anyhow::Result<Vec<(String, u64)>> {
+        let mut statement = self.connection.prepare(&format!(
+            "SELECT eco, COUNT(*) AS games FROM games {MATCHING} AND eco IS NOT NULL
+             GROUP BY eco ORDER BY games DESC, eco"
+        ))?;
+        let openings = statement
+            .query_map(Self::params(query), |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<Result<_, _>>()?;
+        Ok(openings)
+    }
+
+    fn params(query: &GameQuery) -> [Option<&str>; 5] {
+        [
+            query.opponent.as_deref(),
+            query.eco.as_deref(),
+            query.result.as_deref(),
+            query.from.as_deref(),
+            query.to.as_deref(),
+        ]
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::core::{ChessColour, Move};
+
+    fn play(moves: &str) -> ChessGame {
+        let mut game = ChessGame::default();
+        for mv in moves.split_whitespace() {
+            game.make_move(&Move::from_uci(mv).unwrap()).unwrap();
+        }
+        game
+    }
+
+    #[test]
+    fn test_record_and_find() {
+        let database = GameDatabase::in_memory().unwrap();
+        let mut sicilian = play("e2e4 c7c5 g1f3");
+        sicilian.resign(ChessColour::Black).unwrap();
+        let id = database
+            .record_on(&sicilian, "you", "stockfish", "2024-03-01")
+            .unwrap();
+        let mate = play("f2f3 e7e5 g2g4 d8h4");
+        database
+            .record_on(&mate, "solver", "you", "2024-05-20")
+            .unwrap();
+        assert!(database.record(&play("e2e4"), "you", "solver").is_err());
+
+        let stored = database.get(id).unwrap().unwrap();
+        assert_eq!(stored.result, "1-0");
+        assert_eq!(stored.eco.as_deref(), Some("B27"));
+        assert!(stored.pgn.contains("[Date \"2024.03.01\"]"));
+        assert!(stored.pgn.contains("[Black \"stockfish\"]"));
+        assert_eq!(database.get(id + 10).unwrap(), None);
+
+        let find = |query: GameQuery| -> Vec<i64> {
+            let games = database.find(&query).unwrap();
+            games.iter().map(|game| game.id).collect()
+        };
+        assert_eq!(find(GameQuery::default()).len(), 2);
+        let stockfish = GameQuery {
+            opponent: Some("Stockfish".to_string()),
+            ..GameQuery::default()
+        };
+        assert_eq!(find(stockfish), [id]);
+        let semi_open = GameQuery {
+            eco: Some("B".to_string()),
+            ..GameQuery::default()
+        };
+        assert_eq!(find(semi_open), [id]);
+        let black_wins = GameQuery {
+            result: Some("0-1".to_string()),
+            ..GameQuery::default()
+        };
+        assert_eq!(find(black_wins), [id + 1]);
+        let spring = GameQuery {
+            from: Some("2024-04-01".to_string()),
+            to: Some("2024-05-31".to_string()),
+            ..GameQuery::default()
+        };
+        assert_eq!(find(spring), [id + 1]);
+
+        let everything = GameQuery::default();
+        assert_eq!(
+            database.results(&everything).unwrap(),
+            Results {
+                white_wins: 1,
+                draws: 0,
+                black_wins: 1
+            }
+        );
+        assert_eq!(
+            database.openings(&everything).unwrap(),
+            [("B27".to_string(), 1)]
+        );
+    }
+}
@@ -0,0 +1,569 @@
+//! A Discord bot that plays in the channels it is given: members start games
+//! against the solver or each other with `!chess play`, then post their moves
+//! in SAN, and the bot answers with a picture of the board.
+//!
+//! [`Bot`] holds the games and decides what to say, knowing nothing of
+//! Discord itself. [`Client`] talks to Discord's REST API with `curl`, as
+//! [`crate::import`] does, so no TLS library has to be built in, and
+//! [`run`] polls the channels for new messages rather than holding a gateway
+//! connection open.
+
+use crate::board_image;
+use crate::core::ChessColour;
+use crate::game::ChessGame;
+use crate::input;
+use crate::render::{self, RenderOptions};
+use crate::solver::tt::TranspositionTable;
+use crate::solver::{self, SearchOptions};
+use anyhow::Context;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+use std::time::Duration;
+
+/// What messages to the bot start with.
+pub const PREFIX: &str = "!chess";
+
+/// The environment variable the bot's token is read from.
+pub const TOKEN_VARIABLE: &str = "DISCORD_TOKEN";
+
+const API: &str = "https://discord.com/api/v10";
+
+const HELP: &str = "`!chess play` to play the solver as White, `!chess play black` as Black, \
+`!chess play @member` to play a member. Then post your moves, e.g. `e4` or `Nf3`. \
+`!chess board` shows the board again and `!chess resign` gives up.";
+
+/// Someone on a server.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct User {
+    pub id: String,
+    #[serde(rename = "username")]
+    pub name: String,
+    #[serde(default)]
+    pub bot: bool,
+}
+
+/// A message posted in a channel, as Discord sends it.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct Message {
+    pub id: String,
+    pub channel_id: String,
+    pub author: User,
+    pub content: String,
+    /// The members the message mentions.
+    #[serde(default)]
+    pub mentions: Vec<User>,
+}
+
+/// What the bot posts in answer.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Reply {
+    pub channel_id: String,
+    pub text: String,
+    /// A PNG of the board.
+    pub image: Option<Vec<u8>>,
+}
+
+/// Who plays a side.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Seat {
+    Member(User),
+    Solver,
+}
+
+impl Seat {
+    fn is(&self, user: &User) -> bool {
+        matches!(self, Seat::Member(member) if member.id == user.id)
+    }
+
+    fn name(&self) -> String {
+        match self {
+            Seat::Member(user) => format!("<@{}>", user.id),
+            Seat::Solver => "the solver".to_string(),
+        }
+    }
+}
+
+struct Table {
+    game: ChessGame,
+    white: Seat,
+    black: Seat,
+}
+
+impl Table {
+    fn seat(&self, colour: ChessColour) -> &Seat {
+        match colour {
+            ChessColour::White => &self.white,
+            ChessColour::Black => &self.black,
+        }
+    }
+
+    fn colour_of(&self, user: &User) -> Option<ChessColour> {
+        [ChessColour::White, ChessColour::Black]
+            .into_iter()
+            .find(|colour| self.seat(*colour).is(user))
+    }
+}
+
+/// The games being played, one a channel, and how the solver plays in them.
+pub struct Bot {
+    tables: HashMap<String, Table>,
+    search_options: SearchOptions,
+    render_options: RenderOptions,
+}
+
+impl Bot {
+    pub fn new(search_options: SearchOptions) -> Self {
+        Self {
+            tables: HashMap::new(),
+            search_options,
+            render_options: RenderOptions::default(),
+        }
+    }
+
+    /// What to say to `message`, if anything. Messages that aren't commands
+    /// are only read as moves from the member to move.
+    pub fn handle(&mut self, message: &Message) -> anyhow::Result<Option<Reply>> {
+        if message.author.bot {
+            return Ok(None);
+        }
+        let content = message.content.trim();
+        let command = content
+            .strip_prefix(PREFIX)
+            .filter(|rest| rest.is_empty() || rest.starts_with(' '))
+            .map(str::trim);
+        let reply = |text: String| Reply {
+            channel_id: message.channel_id.clone(),
+            text,
+            image: None,
+        };
+        let Some(command) = command else {
+            return self.play_move(message, content, false);
+        };
+        let mut words = command.split_whitespace();
+        match words.next() {
+            None | Some("help") => Ok(Some(reply(HELP.to_string()))),
+            Some("play") => self.start(message, words.next()).map(Some),
+            Some("board") => match self.tables.get(&message.channel_id) {
+                Some(table) => self.show(message, table, String::new()).map(Some),
+                None => Ok(Some(reply("No game is being played here.".to_string()))),
+            },
+            Some("resign") => self.resign(message).map(Some),
+            Some(_) => self.play_move(message, command, true),
+        }
+    }
+
+    fn start(&mut self, message: &Message, opponent: Option<&str>) -> anyhow::Result<Reply> {
+        let channel = &message.channel_id;
+        if let Some(table) = self.tables.get(channel) {
+            return Ok(Reply {
+                channel_id: channel.clone(),
+                text: format!(
+                    "{} and {} are already playing here.",
+                    table.white.name(),
+                    table.black.name()
+                ),
+                image: None,
+            });
+        }
+        let author = Seat::Member(message.author.clone());
+        let (white, black) = match (opponent, message.mentions.first()) {
+            (_, Some(member)) if member.id != message.author.id && !member.bot => {
+                (author, Seat::Member(member.clone()))
+            }
+            (Some("black"), _) => (Seat::Solver, author),
+            _ => (author, Seat::Solver),
+        };
+        let mut table = Table {
+            game: ChessGame::default(),
+            white,
+            black,
+        };
+        let mut text = format!(
+            "New game: {} (White) against {} (Black).",
+            table.white.name(),
+            table.black.name()
+        );
+        if table.white == Seat::Solver {
+            text.push_str(&self.solver_move(&mut table.game)?);
+        }
+        let reply = self.show(message, &table, text)?;
+        self.tables.insert(channel.clone(), table);
+        Ok(reply)
+    }
+
+    /// Play `input` as the author's move if it is one. Unless `explicit`, a
+    /// message that isn't a legal move is taken as chat and ignored.
+    fn play_move(
+        &mut self,
+        message: &Message,
+        input: &str,
+        explicit: bool,
+    ) -> anyhow::Result<Option<Reply>> {
+        let channel = &message.channel_id;
+        let reply = |text: &str| {
+            Ok(Some(Reply {
+                channel_id: channel.clone(),
+                text: text.to_string(),
+                image: None,
+            }))
+        };
+        let Some(table) = self.tables.get(channel) else {
+            return if explicit {
+                reply("No game is being played here, start one with `!chess play`.")
+            } else {
+                Ok(None)
+            };
+        };
+        let turn = table.game.get_board().turn;
+        if !table.seat(turn).is(&message.author) {
+            return if explicit {
+                reply("It isn't your move.")
+            } else {
+                Ok(None)
+            };
+        }
+        let mv = match input::parse_move(input, &table.game) {
+            Ok(mv) => mv,
+            Err(e) => {
+                return if explicit {
+                    reply(&e.to_string())
+                } else {
+                    Ok(None)
+                };
+            }
+        };
+        let solver_replies = *table.seat(turn.flip()) == Seat::Solver;
+        // played on a copy, so that the game is left as it was if the move or
+        // the solver's reply fails
+        let mut game = table.game.clone();
+        let mut text = format!("{} {}", move_number(&game), game.san(&mv)?);
+        game.make_move(&mv)?;
+        if game.result().is_none() && solver_replies {
+            text.push_str(&self.solver_move(&mut game)?);
+        }
+        self.finish(message, game, text).map(Some)
+    }
+
+    fn resign(&mut self, message: &Message) -> anyhow::Result<Reply> {
+        let channel = &message.channel_id;
+        let Some(table) = self.tables.get(channel) else {
+            return Ok(Reply {
+                channel_id: channel.clone(),
+                text: "No game is being played here.".to_string(),
+                image: None,
+            });
+        };
+        let Some(colour) = table.colour_of(&message.author) else {
+            return Ok(Reply {
+                channel_id: channel.clone(),
+                text: "Only the players can resign.".to_string(),
+                image: None,
+            });
+        };
+        let mut game = table.game.clone();
+        game.resign(colour)?;
+        self.finish(message, game, format!("{colour} resigns."))
+    }
+
+    /// The solver's move in `game`, played, as text to add to a reply.
+    fn solver_move(&self, game: &mut ChessGame) -> anyhow::Result<String> {
+        let number = move_number(game);
+        let mut tt = TranspositionTable::default();
+        let search = solver::solve_next_move_with_options(game, &self.search_options, &mut tt)?;
+        let san = game.san(&search.best_move)?;
+        game.make_move(&search.best_move)?;
+        Ok(format!(" The solver plays {number} {san}."))
+    }
+
+    /// Carry on the game in the message's channel as `game`, showing the
+    /// board, and end it if it is over.
+    fn finish(
+        &mut self,
+        message: &Message,
+        game: ChessGame,
+        mut text: String,
+    ) -> anyhow::Result<Reply> {
+        let channel = &message.channel_id;
+        let table = self
+            .tables
+            .get_mut(channel)
+            .expect("only games being played are finished");
+        table.game = game;
+        let table = &self.tables[channel];
+        match table.game.result() {
+            Some(result) => text.push_str(&format!(" Game over: {result}.")),
+            None => {
+                let turn = table.game.get_board().turn;
+                text.push_str(&format!(" {} to move.", table.seat(turn).name()));
+            }
+        }
+        let over = table.game.result().is_some();
+        let reply = self.show(message, table, text);
+        if over {
+            self.tables.remove(channel);
+        }
+        reply
+    }
+
+    /// `text` with a picture of the board from the side of the member to
+    /// move.
+    fn show(&self, message: &Message, table: &Table, text: String) -> anyhow::Result<Reply> {
+        let turn = table.game.get_board().turn;
+        let flipped = match (&table.white, &table.black) {
+            (Seat::Solver, _) => true,
+            (_, Seat::Member(_)) => turn == ChessColour::Black,
+            _ => false,
+        };
+        let options = RenderOptions {
+            flipped,
+            ..self.render_options.clone()
+        };
+        let annotations = render::last_moves(&table.game, &options.theme);
+        Ok(Reply {
+            channel_id: message.channel_id.clone(),
+            text,
+            image: Some(board_image::png(&table.game, &annotations, &options)?),
+        })
+    }
+}
+
+/// The number to write before the next move, e.g. `3.` or `3...`.
+fn move_number(game: &ChessGame) -> String {
+    match game.get_board().turn {
+        ChessColour::White => format!("{}.", game.full_move_count()),
+        ChessColour::Black => format!("{}...", game.full_move_count()),
+    }
+}
+
+/// Discord's REST API, as the bot whose token it has.
+pub struct Client {
+    token: String,
+}
+
+impl Client {
+    pub fn new(token: String) -> Self {
+        Self { token }
+    }
+
+    /// The client for the token in [`TOKEN_VARIABLE`].
+    pub fn from_env() -> anyhow::Result<Self> {
+        let token = std::env::var(TOKEN_VARIABLE)
+            .with_context(|| format!("set {TOKEN_VARIABLE} to the bot's token"))?;
+        Ok(Self::new(token))
+    }
+
+    /// The messages posted in `channel` after the one with ID `after`, oldest
+    /// first, or only the latest if `after` is `None`.
+    pub fn messages(&self, channel: &str, after: Option<&str>) -> anyhow::Result<Vec<Message>> {
+        let url = match after {
+            Some(after) => format!("{API}/channels/{channel}/messages?after={after}&limit=50"),
+            None => format!("{API}/channels/{channel}/messages?limit=1"),
+        };
+        let body = self.request(&[url])?;
+        let mut messages: Vec<Message> = serde_json::from_str(&body)
+            .with_context(|| format!("Discord sent unexpected messages for channel {channel}"))?;
+        messages.reverse();
+        Ok(messages)
+    }
+
+    pub fn send(&self, reply: &Reply) -> anyhow::Result<()> {
+        let url = format!("{API}/channels/{}/messages", reply.channel_id);
+        let payload = serde_json::json!({
+            "content": reply.text,
+            // say who is to move without pinging them every move
+            "allowed_mentions": { "parse": [] },
+        });
+        let Some(image) = &reply.image else {
+            self.request(&[
+                "--header".to_string(),
+                "Content-Type: application/json".to_string(),
+                "--data".to_string(),
+                payload.to_string(),
+                url,
+            ])?;
+            return Ok(());
+        };
+        let path = image_path();
+        std::fs::write(&path, image)
+            .with_context(|| format!("could not write {}", path.display()))?;
+        let sent = self.request(&[
+            "--form".to_string(),
+            format!("payload_json={payload}"),
+            "--form".to_string(),
+            format!("files[0]=@{};filename=board.png", path.display()),
+            url,
+        ]);
+        let _ = std::fs::remove_file(&path);
+        sent.map(|_| ())
+    }
+
+    /// Run `curl` with `args`, returning what it fetched. The token is passed
+    /// on standard input rather than the command line, where other users
+    /// could see it.
+    fn request(&self, args: &[String]) -> anyhow::Result<String> {
+        let mut curl = Command::new("curl")
+            .args(["--silent", "--show-error", "--fail-with-body"])
+            .args(["--header", "@-"])
+            .args(["--user-agent", concat!("chess/", env!("CARGO_PKG_VERSION"))])
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .context("could not run curl, which is needed to reach Discord")?;
+        let mut stdin = curl.stdin.take().expect("stdin is piped");
+        writeln!(stdin, "Authorization: Bot {}", self.token)?;
+        drop(stdin);
+        let output = curl.wait_with_output()?;
+        let body = String::from_utf8_lossy(&output.stdout).into_owned();
+        anyhow::ensure!(
+            output.status.success(),
+            "Discord refused the request: {} {}",
+            String::from_utf8_lossy(&output.stderr).trim(),
+            body.trim()
+        );
+        Ok(body)
+    }
+}
+
+/// Where an image is kept while it is uploaded.
+fn image_path() -> PathBuf {
+    std::env::temp_dir().join(format!("chess-discord-{}.png", std::process::id()))
+}
+
+/// Answer the messages posted in `channels` every `poll` until something
+/// fails, starting from the latest.
+pub fn run(
+    client: &Client,
+    channels: &[String],
+    bot: &mut Bot,
+    poll: Duration,
+) -> anyhow::Result<()> {
+    let mut latest: HashMap<&str, Option<String>> = HashMap::new();
+    for channel in channels {
+        let last = client
+            .messages(channel, None)?
+            .pop()
+            .map(|message| message.id);
+        latest.insert(channel.as_str(), last);
+    }
+    loop {
+        for channel in channels {
+            let after = latest[channel.as_str()].clone();
+            let messages = match after {
+                Some(after) => client.messages(channel, Some(&after))?,
+                // an empty channel, so everything in it is new
+                None => client.messages(channel, Some("0"))?,
+            };
+            for message in messages {
+                latest.insert(channel.as_str(), Some(message.id.clone()));
+                match bot.handle(&message) {
+                    Ok(Some(reply)) => client.send(&reply)?,
+                    Ok(None) => {}
+                    Err(e) => tracing::warn!("could not answer {:?}: {e:#}", message.content),
+                }
+            }
+        }
+        std::thread::sleep(poll);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn user(id: &str) -> User {
+        User {
+            id: id.to_string(),
+            name: format!("member {id}"),
+            bot: false,
+        }
+    }
+
+    fn message(author: &str, content: &str) -> Message {
+        Message {
+            id: "1".to_string(),
+            channel_id: "10".to_string(),
+            author: user(author),
+            content: content.to_string(),
+            mentions: Vec::new(),
+        }
+    }
+
+    fn bot() -> Bot {
+        Bot::new(SearchOptions::fixed_depth(1))
+    }
+
+    #[test]
+    fn test_play_the_solver() {
+        let mut bot = bot();
+        assert_eq!(bot.handle(&message("1", "e4")).unwrap(), None);
+        let started = bot.handle(&message("1", "!chess play")).unwrap().unwrap();
+        assert!(started.text.contains("<@1> (White) against the solver"));
+        assert!(started.image.is_some());
+
+        // chat, and moves out of turn, are ignored unless addressed to the bot
+        assert_eq!(bot.handle(&message("1", "good luck")).unwrap(), None);
+        assert_eq!(bot.handle(&message("2", "e4")).unwrap(), None);
+        let refused = bot.handle(&message("2", "!chess e4")).unwrap().unwrap();
+        assert_eq!(refused.text, "It isn't your move.");
+        let illegal = bot.handle(&message("1", "!chess Ke2")).unwrap().unwrap();
+        assert!(illegal.image.is_none());
+
+        let played = bot.handle(&message("1", "e4")).unwrap().unwrap();
+        assert!(played.text.starts_with("1. e4 The solver plays 1... "));
+        assert!(played.text.ends_with("<@1> to move."));
+        let resigned = bot.handle(&message("1", "!chess resign")).unwrap().unwrap();
+        assert!(resigned.text.contains("Game over: 0-1"));
+        assert!(bot.tables.is_empty());
+    }
+
+    #[test]
+    fn test_play_a_member() {
+        let mut bot = bot();
+        let mut challenge = message("1", "!chess play <@2>");
+        challenge.mentions = vec![user("2")];
+        let started = bot.handle(&challenge).unwrap().unwrap();
+        assert!(started.text.contains("<@1> (White) against <@2> (Black)"));
+        for (author, mv) in [("1", "f3"), ("2", "e5"), ("1", "g4")] {
+            bot.handle(&message(author, mv)).unwrap().unwrap();
+        }
+        let mate = bot.handle(&message("2", "Qh4#")).unwrap().unwrap();
+        assert_eq!(mate.text, "2... Qh4# Game over: 0-1 (checkmate).");
+        assert!(bot.tables.is_empty());
+    }
+
+    #[test]
+    fn test_failures_keep_the_game() {
+        // the solver can't find a move when it may only consider none
+        let mut bot = Bot::new(SearchOptions {
+            search_moves: Some(Vec::new()),
+            ..SearchOptions::fixed_depth(1)
+        });
+        bot.handle(&message("1", "!chess play")).unwrap();
+        assert_eq!(bot.handle(&message("1", "très bien, eé4")).unwrap(), None);
+        assert_eq!(bot.handle(&message("1", "eé4")).unwrap(), None);
+        assert!(bot.handle(&message("1", "e4")).is_err());
+        let table = &bot.tables["10"];
+        assert!(table.game.played_moves().is_empty());
+        let board = bot.handle(&message("1", "!chess board")).unwrap().unwrap();
+        assert!(board.image.is_some());
+    }
+
+    #[test]
+    fn test_read_messages() {
+        let json = r#"[{
+            "id": "1200", "channel_id": "10", "content": "!chess play <@7>",
+            "author": {"id": "5", "username": "alice", "global_name": "Alice"},
+            "mentions": [{"id": "7", "username": "bob"}],
+            "attachments": [], "timestamp": "2024-01-01T00:00:00+00:00"
+        }]"#;
+        let messages: Vec<Message> = serde_json::from_str(json).unwrap();
+        assert_eq!(messages[0].author.name, "alice");
+        assert!(!messages[0].author.bot);
+        assert_eq!(messages[0].mentions[0].id, "7");
+    }
+}
@@ -0,0 +1,248 @@
+//! A compact binary encoding of a position, at most 32 bytes, for storing
+//! and sending positions where a FEN string would be wasteful.
+//!
+//! Version 1 is laid out as:
+//!
+//! - the version byte,
+//! - the occupied squares as a little endian `u64`, bit `8 * row + column`,
+//! - a 4 bit code for the piece on each occupied square in the same order,
+//!   two to a byte with the first in the low bits, padded with zero,
+//! - a flags byte: bit 0 set when Black is to move, bit 1 for Chess960,
+//! - the castling rook columns as 4 bit codes, 8 for none, for White's king
+//!   and queen sides then Black's,
+//! - the half move clock and full move count as little endian `u16`s,
+//!   saturating.
+
+use crate::core::{
+    CastlingSide, ChessBoard, ChessColour, ChessError, ChessPiece, ChessPieceKind, Column, Square,
+};
+use crate::game::ChessGame;
+use crate::variant::Variant;
+use alloc::format;
+use alloc::string::ToString;
+use alloc::vec::Vec;
+
+/// The version [`encode`] writes, and the only one [`decode`] reads.
+pub const VERSION: u8 = 1;
+
+const KINDS: [ChessPieceKind; 6] = [
+    ChessPieceKind::Pawn,
+    ChessPieceKind::Knight,
+    ChessPieceKind::Bishop,
+    ChessPieceKind::Rook,
+    ChessPieceKind::Queen,
+    ChessPieceKind::King,
+];
+
+const CASTLING: [(ChessColour, CastlingSide); 4] = [
+    (ChessColour::White, CastlingSide::King),
+    (ChessColour::White, CastlingSide::Queen),
+    (ChessColour::Black, CastlingSide::King),
+    (ChessColour::Black, CastlingSide::Queen),
+];
+
+const NO_ROOK: u8 = 8;
+
+/// The position of `game`, without its history.
+///
+/// ```
+/// use chess::encoding;
+/// use chess::game::ChessGame;
+///
+/// let game = ChessGame::default();
+/// let bytes = encoding::encode(&game);
+/// assert_eq!(bytes.len(), 32);
+/// assert_eq!(encoding::decode(&bytes).unwrap().fen(), game.fen());
+/// ```
+pub fn encode(game: &ChessGame) -> Vec<u8> {
+    let board = game.get_board();
+    let mut occupied = 0u64;
+    let mut codes = Vec::new();
+    for square in Square::all() {
+        if let Some(piece) = board.get(square) {
+            occupied |= 1 << square.index();
+            codes.push(piece_code(&piece));
+        }
+    }
+
+    let mut bytes = Vec::with_capacity(32);
+    bytes.push(VERSION);
+    bytes.extend_from_slice(&occupied.to_le_bytes());
+    bytes.extend(
+        codes
+            .chunks(2)
+            .map(|pair| pair[0] | pair.get(1).map_or(0, |c| c << 4)),
+    );
+    let black_to_move = u8::from(board.turn == ChessColour::Black);
+    let chess960 = u8::from(game.variant() == Variant::Chess960);
+    bytes.push(black_to_move | chess960 << 1);
+    let rooks: Vec<u8> = CASTLING
+        .iter()
+        .map(|(colour, side)| {
+            board
+                .castling
+                .get(*colour, *side)
+                .map_or(NO_ROOK, |column| usize::from(column) as u8)
+        })
+        .collect();
+    bytes.push(rooks[0] | rooks[1] << 4);
+    bytes.push(rooks[2] | rooks[3] << 4);
+    let saturate = |n: u32| u16::try_from(n).unwrap_or(u16::MAX).to_le_bytes();
+    bytes.extend_from_slice(&saturate(game.half_move_clock()));
+    bytes.extend_from_slice(&saturate(game.full_move_count()));
+    bytes
+}
+
+/// The position [`encode`] wrote to `bytes`, failing on other versions and
+/// on positions [`ChessBoard::validate`] rejects.
+pub fn decode(bytes: &[u8]) -> Result<ChessGame, ChessError> {
+    let mut reader = Reader(bytes);
+    let version = reader.byte()?;
+    if version != VERSION {
+        return Err(invalid(&format!("unsupported version {version}")));
+    }
+    let occupied = u64::from_le_bytes(reader.array()?);
+    let pieces = occupied.count_ones() as usize;
+    let codes = reader.take(pieces.div_ceil(2))?;
+
+    let mut board = ChessBoard::empty();
+    let squares = Square::all().filter(|square| occupied & 1 << square.index() != 0);
+    for (i, square) in squares.enumerate() {
+        let code = codes[i / 2] >> (4 * (i % 2)) & 0xf;
+        board.set(square, Some(piece(code)?));
+    }
+    let flags = reader.byte()?;
+    if flags & !0b11 != 0 {
+        return Err(invalid("unknown flags"));
+    }
+    board.turn = if flags & 1 == 0 {
+        ChessColour::White
+    } else {
+        ChessColour::Black
+    };
+    let variant = if flags & 2 == 0 {
+        Variant::Standard
+    } else {
+        Variant::Chess960
+    };
+    let [white, black] = reader.array()?;
+    let rooks = [white & 0xf, white >> 4, black & 0xf, black >> 4];
+    for ((colour, side), rook) in CASTLING.into_iter().zip(rooks) {
+        let column = match rook {
+            NO_ROOK => None,
+            rook => Some(
+                Column::try_from(rook as isize).map_err(|_| invalid("invalid castling rook"))?,
+            ),
+        };
+        board.castling.set(colour, side, column);
+    }
+    let half_move_clock = u16::from_le_bytes(reader.array()?);
+    let full_move_count = u16::from_le_bytes(reader.array()?);
+    if !reader.0.is_empty() {
+        return Err(invalid("trailing bytes"));
+    }
+    ChessGame::builder()
+        .board(board, half_move_clock.into(), full_move_count.into())
+        .variant(variant)
+        .build()
+}
+
+fn piece_code(piece: &ChessPiece) -> u8 {
+    let kind = KINDS.iter().position(|kind| *kind == piece.kind).unwrap() as u8;
+    match piece.colour {
+        ChessColour::White => kind,
+        ChessColour::Black => kind + 6,
+    }
+}
+
+fn piece(code: u8) -> Result<ChessPiece, ChessError> {
+    let kind = *KINDS
+        .get(usize::from(code % 6))
+        .filter(|_| code < 12)
+        .ok_or_else(|| invalid(&format!("unknown piece code {code}")))?;
+    let colour = if code < 6 {
+        ChessColour::White
+    } else {
+        ChessColour::Black
+    };
+    Ok(ChessPiece {
+        kind,
+        colour,
+        moved: false,
+    })
+}
+
+fn invalid(reason: &str) -> ChessError {
+    ChessError::InvalidEncoding(reason.to_string())
+}
+
+/// The bytes still to be decoded.
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], ChessError> {
+        if self.0.len() < len {
+            return Err(invalid("too short"));
+        }
+        let (taken, rest) = self.0.split_at(len);
+        self.0 = rest;
+        Ok(taken)
+    }
+
+    fn byte(&mut self) -> Result<u8, ChessError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N], ChessError> {
+        Ok(self.take(N)?.try_into().unwrap())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        for (fen, variant) in [
+            (
+                "r3k2r/ppp2ppp/2n5/3qp3/8/2N5/PPP2PPP/R3K2R w KQkq - 0 1",
+                Variant::Standard,
+            ),
+            ("8/8/4k3/8/8/3K4/8/7R b - - 37 112", Variant::Standard),
+            (
+                "bqnb1rkr/pp3ppp/3ppn2/2p5/5P2/P2P4/NPP1P1PP/BQ1BNRKR w HFhf - 2 9",
+                Variant::Chess960,
+            ),
+        ] {
+            let game = ChessGame::from_fen(fen, variant).unwrap();
+            let bytes = encode(&game);
+            assert!(bytes.len() <= 32);
+            let decoded = decode(&bytes).unwrap();
+            assert_eq!(decoded.fen(), game.fen());
+            assert_eq!(decoded.variant(), variant);
+            assert_eq!(decoded.get_board(), game.get_board());
+        }
+    }
+
+    #[test]
+    fn test_invalid() {
+        let bytes = encode(&ChessGame::default());
+        assert!(decode(&bytes[..bytes.len() - 1]).is_err());
+        assert!(decode(&[bytes.as_slice(), &[0]].concat()).is_err());
+        let mut future = bytes.clone();
+        future[0] = VERSION + 1;
+        assert!(matches!(
+            decode(&future),
+            Err(ChessError::InvalidEncoding(reason)) if reason.contains("version")
+        ));
+        // no kings
+        let mut empty = vec![VERSION];
+        empty.extend_from_slice(&[0; 8]);
+        empty.extend_from_slice(&[0, 0x88, 0x88, 0, 0, 1, 0]);
+        assert!(matches!(
+            decode(&empty),
+            Err(ChessError::InvalidPosition(_))
+        ));
+    }
+}
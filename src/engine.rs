@@ -0,0 +1,289 @@
+//! What playing against an external engine needs, whichever protocol the
+//! engine speaks: [`crate::uci::UciEngine`] for UCI and
+//! [`crate::xboard::XBoardEngine`] for XBoard.
+
+use crate::config::{EngineConfig, Protocol};
+use crate::core::Move;
+use crate::game::ChessGame;
+use crate::solver::Score;
+use crate::uci::UciEngine;
+use crate::uci::go::GoParams;
+use crate::variant::Variant;
+use crate::xboard::XBoardEngine;
+use anyhow::Context;
+use std::fmt;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Write};
+use std::path::Path;
+use std::process::Child;
+use std::sync::mpsc::{Receiver, RecvTimeoutError};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use thiserror::Error;
+
+/// How long to wait for the engine to answer anything other than a search.
+pub(crate) const RESPONSE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How much longer than it was asked to think the engine may take to send its
+/// move before it is given up on.
+pub(crate) const SEARCH_GRACE: Duration = Duration::from_secs(5);
+
+/// The tracing target every line sent to or read from an engine is logged
+/// under, so that the traffic can be shown on its own with e.g.
+/// `CHESS=engine_traffic=debug`.
+pub const TRAFFIC_TARGET: &str = "engine_traffic";
+
+/// An engine to play games against.
+pub trait EngineClient {
+    /// Play `variant` from now on.
+    fn set_variant(&mut self, variant: Variant) -> anyhow::Result<()>;
+
+    /// Forget the previous game before starting another.
+    fn new_game(&mut self) -> anyhow::Result<()>;
+
+    /// Pick a move for the side to move in `game`, within `params`' limits.
+    /// Fails with [`EngineError::Resigned`] if the engine gives up instead.
+    fn play(&mut self, game: &ChessGame, params: &GoParams) -> anyhow::Result<EngineReply>;
+}
+
+/// An engine's move, and what it thinks of its position.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EngineReply {
+    pub best_move: Move,
+    /// How good the position is for the engine, if it said.
+    pub score: Option<Score>,
+}
+
+/// Start the engine `config` describes, talking to it over its protocol.
+pub fn start(config: &EngineConfig) -> anyhow::Result<Box<dyn EngineClient>> {
+    Ok(match config.protocol {
+        Protocol::Uci => Box::new(UciEngine::from_config(config)?),
+        Protocol::XBoard => Box::new(XBoardEngine::from_config(config)?),
+    })
+}
+
+/// A failure talking to the engine process, as opposed to the engine rejecting
+/// what it was asked to do. Returned inside the `anyhow::Error`s from the
+/// engine clients, so callers can downcast to tell a crash from a bad request.
+#[derive(Debug, Error)]
+pub enum EngineError {
+    /// The engine closed its input or output, usually because it crashed.
+    #[error("The engine exited unexpectedly")]
+    Exited,
+    /// The engine is still running but didn't send `waiting_for` in time.
+    #[error("The engine did not reply with {waiting_for} within {after:?}")]
+    Timeout {
+        waiting_for: String,
+        after: Duration,
+    },
+    /// The engine gave up the game rather than move.
+    #[error("The engine resigned")]
+    Resigned,
+    #[error("Could not talk to the engine: {0}")]
+    Io(#[source] std::io::Error),
+}
+
+impl From<std::io::Error> for EngineError {
+    fn from(error: std::io::Error) -> Self {
+        match error.kind() {
+            std::io::ErrorKind::BrokenPipe => EngineError::Exited,
+            _ => EngineError::Io(error),
+        }
+    }
+}
+
+/// Whether `error` came from the engine having exited.
+pub(crate) fn is_exit(error: &anyhow::Error) -> bool {
+    matches!(error.downcast_ref(), Some(EngineError::Exited))
+}
+
+/// Every line sent to and read from an engine, written to a file for
+/// debugging protocol problems. Each line is marked with the time since the
+/// engine started, the engine and `>` for sent or `<` for read.
+#[derive(Clone)]
+struct TrafficLog {
+    file: Arc<Mutex<File>>,
+    engine: String,
+    started: Instant,
+}
+
+impl TrafficLog {
+    fn open(path: &Path, engine: &str) -> anyhow::Result<Self> {
+        let file = File::options()
+            .create(true)
+            .append(true)
+            .open(path)
+            .with_context(|| format!("could not open engine log {}", path.display()))?;
+        let engine = Path::new(engine)
+            .file_name()
+            .map_or(engine.into(), |name| name.to_string_lossy());
+        Ok(Self {
+            file: Arc::new(Mutex::new(file)),
+            engine: engine.to_string(),
+            started: Instant::now(),
+        })
+    }
+
+    fn record(&self, direction: char, line: &str) {
+        let elapsed = self.started.elapsed().as_secs_f64();
+        if let Ok(mut file) = self.file.lock()
+            && let Err(e) = writeln!(file, "[{elapsed:9.3}] {} {direction} {line}", self.engine)
+        {
+            tracing::warn!("Could not write to the engine log: {e}");
+        }
+    }
+}
+
+/// A running engine, with its output read on a separate thread so that
+/// waiting for it can time out.
+pub(crate) struct EngineProcess {
+    child: Child,
+    lines: Receiver<String>,
+    log: Option<TrafficLog>,
+}
+
+impl EngineProcess {
+    pub(crate) fn spawn(config: &EngineConfig) -> anyhow::Result<Self> {
+        let log = config
+            .log
+            .as_deref()
+            .map(|path| TrafficLog::open(path, &config.path))
+            .transpose()?;
+        let mut child = std::process::Command::new(&config.path)
+            .args(&config.args)
+            .stdin(std::process::Stdio::piped())
+            .stdout(std::process::Stdio::piped())
+            .spawn()
+            .with_context(|| format!("Failed to start engine at {}", config.path))?;
+        let stdout = child.stdout.take().unwrap();
+        let (sender, lines) = std::sync::mpsc::channel();
+        let reader_log = log.clone();
+        std::thread::spawn(move || {
+            for line in BufReader::new(stdout).lines() {
+                let Ok(line) = line else { break };
+                // logged here rather than when the line is used, so the time
+                // is when the engine sent it
+                tracing::debug!(target: TRAFFIC_TARGET, "< {line}");
+                if let Some(log) = &reader_log {
+                    log.record('<', &line);
+                }
+                if sender.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+        Ok(Self { child, lines, log })
+    }
+
+    pub(crate) fn send(&mut self, command: fmt::Arguments) -> Result<(), EngineError> {
+        let command = command.to_string();
+        let stdin = self.child.stdin.as_mut().ok_or(EngineError::Exited)?;
+        stdin
+            .write_all(command.as_bytes())
+            .and_then(|_| stdin.flush())?;
+        tracing::debug!(target: TRAFFIC_TARGET, "> {}", command.trim_end());
+        if let Some(log) = &self.log {
+            log.record('>', command.trim_end());
+        }
+        Ok(())
+    }
+
+    /// The next line of output, failing if it hasn't arrived `timeout` after
+    /// `since`. `waiting_for` is what the caller expects, for the error.
+    pub(crate) fn next_line(
+        &self,
+        since: Instant,
+        timeout: Option<Duration>,
+        waiting_for: &str,
+    ) -> Result<String, EngineError> {
+        let received = match timeout {
+            Some(timeout) => self
+                .lines
+                .recv_timeout((since + timeout).saturating_duration_since(Instant::now())),
+            None => self
+                .lines
+                .recv()
+                .map_err(|_| RecvTimeoutError::Disconnected),
+        };
+        match received {
+            Ok(line) => Ok(line),
+            Err(RecvTimeoutError::Timeout) => Err(EngineError::Timeout {
+                waiting_for: waiting_for.to_string(),
+                after: timeout.unwrap_or_default(),
+            }),
+            Err(RecvTimeoutError::Disconnected) => Err(EngineError::Exited),
+        }
+    }
+
+    /// Send `command` to ask the engine to exit, and wait until it has.
+    pub(crate) fn quit(&mut self, command: &str) {
+        if self.send(format_args!("{command}\n")).is_ok() {
+            self.child.wait().ok();
+        }
+    }
+
+    /// Make sure the engine has exited, e.g. before starting another.
+    pub(crate) fn kill(&mut self) {
+        self.child.kill().ok();
+        self.child.wait().ok();
+    }
+}
+
+/// Write `script` to an executable shell script to use as an engine in tests.
+#[cfg(all(test, unix))]
+pub(crate) fn fake_engine(name: &str, script: &str) -> std::path::PathBuf {
+    use std::os::unix::fs::PermissionsExt;
+
+    let path = std::env::temp_dir().join(format!("{name}-{}", std::process::id()));
+    std::fs::write(&path, format!("#!/bin/sh\n{script}")).unwrap();
+    std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+    path
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[cfg(unix)]
+    #[test]
+    fn test_traffic_log() {
+        let path = fake_engine(
+            "logged-engine",
+            "while read command rest; do
+                case $command in
+                    uci) echo 'id name Logged'; echo uciok ;;
+                    isready) echo readyok ;;
+                    quit) exit ;;
+                esac
+            done
+            ",
+        );
+        let log = std::env::temp_dir().join(format!("engine-traffic-{}", std::process::id()));
+        let config = EngineConfig {
+            log: Some(log.clone()),
+            ..EngineConfig::from_path(path.to_str().unwrap())
+        };
+        let mut engine = UciEngine::from_config(&config).unwrap();
+        engine.is_ready().unwrap();
+        drop(engine);
+
+        let traffic = std::fs::read_to_string(&log).unwrap();
+        let traffic: Vec<_> = traffic
+            .lines()
+            .map(|line| line.split_once("] ").unwrap().1)
+            .collect();
+        let engine = path.file_name().unwrap().to_str().unwrap();
+        let expected = [
+            "> uci",
+            "< id name Logged",
+            "< uciok",
+            "> isready",
+            "< readyok",
+            "> quit",
+        ]
+        .map(|line| format!("{engine} {line}"));
+        assert_eq!(traffic, expected);
+        std::fs::remove_file(&path).ok();
+        std::fs::remove_file(&log).ok();
+    }
+}
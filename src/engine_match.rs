@@ -0,0 +1,510 @@
+//! Matches between two players, each a UCI engine or the solver, in the
+//! spirit of cutechess-cli: the players swap colours every game, and games
+//! whose outcome is clear can be adjudicated rather than played out.
+
+use crate::clock::{self, DEFAULT_MOVES_TO_GO, TimeControl};
+use crate::config::EngineConfig;
+use crate::core::{ChessColour, ChessPieceKind, Move};
+use crate::engine::{self, EngineClient, EngineError};
+use crate::game::{ChessGame, GameResult, Termination};
+use crate::solver::mate::solve_mate;
+use crate::solver::tt::TranspositionTable;
+use crate::solver::{self, Score, SearchOptions};
+use crate::uci::go::GoParams;
+use crate::variant::{Variant, random_chess960_index};
+use std::fmt::Display;
+use std::time::Duration;
+
+/// What a forced mate counts as when comparing scores against the
+/// adjudication thresholds, in centipawns.
+const MATE_CENTIPAWNS: i32 = 100_000;
+
+/// How many moves ahead tablebase adjudication looks for a forced mate.
+const TABLEBASE_MATE_MOVES: usize = 2;
+
+/// One side of a match.
+pub enum Player {
+    /// An external engine, with the name to report it by.
+    Engine { name: String, config: EngineConfig },
+    /// The crate's own solver.
+    Solver(SearchOptions),
+}
+
+impl Player {
+    pub fn name(&self) -> &str {
+        match self {
+            Player::Engine { name, .. } => name,
+            Player::Solver(_) => "solver",
+        }
+    }
+}
+
+/// When to stop a game early and decide its result, based on the scores the
+/// players report for their own positions.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Adjudication {
+    /// A player loses once it has rated its position at this many centipawns
+    /// or more behind for `resign_moves` of its moves in a row.
+    pub resign_score: Option<i32>,
+    pub resign_moves: usize,
+    /// The game is drawn once both players have rated the position within this
+    /// many centipawns of equal for `draw_moves` of their moves in a row, from
+    /// move `draw_after` on.
+    pub draw_score: Option<i32>,
+    pub draw_moves: usize,
+    pub draw_after: usize,
+    /// Decide the game as a tablebase would once this few pieces are left.
+    /// The crate can't probe real tablebases, so this only catches forced
+    /// mates found within a couple of moves, and endings without enough
+    /// material to win.
+    pub tablebase_pieces: Option<usize>,
+    /// Draw the game once each side has made this many moves.
+    pub max_moves: Option<usize>,
+}
+
+impl Default for Adjudication {
+    fn default() -> Self {
+        Self {
+            resign_score: Some(1000),
+            resign_moves: 3,
+            draw_score: Some(10),
+            draw_moves: 8,
+            draw_after: 40,
+            tablebase_pieces: Some(5),
+            max_moves: None,
+        }
+    }
+}
+
+impl Adjudication {
+    /// The result to give `game` now, if it should be adjudicated. `scores`
+    /// holds what each player thought of its position after each move so far,
+    /// in centipawns, or `None` where it didn't say.
+    pub fn check(&self, game: &ChessGame, scores: &[Option<i32>]) -> Option<GameResult> {
+        let adjudicated = |winner| match winner {
+            Some(colour) => GameResult::win_for(colour, Termination::Adjudication),
+            None => GameResult::Draw(Termination::Adjudication),
+        };
+        if let Some(pieces) = self.tablebase_pieces
+            && game
+                .get_board()
+                .pieces()
+                .filter(|(_, cell)| cell.piece.is_some())
+                .count()
+                <= pieces
+        {
+            if solve_mate(game, TABLEBASE_MATE_MOVES).is_some() {
+                return Some(adjudicated(Some(game.get_board().turn)));
+            }
+            if cannot_win(game) {
+                return Some(adjudicated(None));
+            }
+        }
+
+        // the latest score is from the player who just moved
+        let mover = game.get_board().turn.flip();
+        if let Some(threshold) = self.resign_score
+            && self.resign_moves > 0
+            && scores.len() >= 2 * self.resign_moves - 1
+            && scores
+                .iter()
+                .rev()
+                .step_by(2)
+                .take(self.resign_moves)
+                .all(|score| score.is_some_and(|score| score <= -threshold))
+        {
+            return Some(adjudicated(Some(mover.flip())));
+        }
+
+        let full_moves = game.played_moves().len() / 2 + 1;
+        if let Some(threshold) = self.draw_score
+            && full_moves >= self.draw_after
+            && scores.len() >= 2 * self.draw_moves
+            && scores
+                .iter()
+                .rev()
+                .take(2 * self.draw_moves)
+                .all(|score| score.is_some_and(|score| score.abs() <= threshold))
+        {
+            return Some(adjudicated(None));
+        }
+
+        if self
+            .max_moves
+            .is_some_and(|max_moves| game.played_moves().len() >= 2 * max_moves)
+        {
+            return Some(adjudicated(None));
+        }
+        None
+    }
+}
+
+/// Whether neither side has the material to force a win: no pawns, rooks or
+/// queens, and at most one minor piece each.
+fn cannot_win(game: &ChessGame) -> bool {
+    let mut minor_pieces = [0; 2];
+    for (_, cell) in game.get_board().pieces() {
+        let Some(piece) = cell.piece else { continue };
+        match piece.kind {
+            ChessPieceKind::King => {}
+            ChessPieceKind::Bishop | ChessPieceKind::Knight => {
+                minor_pieces[usize::from(piece.colour == ChessColour::Black)] += 1;
+            }
+            _ => return false,
+        }
+    }
+    minor_pieces.iter().all(|count| *count <= 1)
+}
+
+/// The settings for a whole match.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MatchOptions {
+    pub games: usize,
+    /// The time control every game is played with, or `None` to give each move
+    /// `movetime`.
+    pub time_control: Option<TimeControl>,
+    pub movetime: Duration,
+    pub variant: Variant,
+    pub adjudication: Adjudication,
+}
+
+impl Default for MatchOptions {
+    fn default() -> Self {
+        Self {
+            games: 2,
+            time_control: None,
+            movetime: Duration::from_millis(500),
+            variant: Variant::Standard,
+            adjudication: Adjudication::default(),
+        }
+    }
+}
+
+/// A finished game of a match.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GameRecord {
+    /// Which player had white, 0 for the first.
+    pub white: usize,
+    pub result: GameResult,
+    pub moves: Vec<Move>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MatchReport {
+    pub names: [String; 2],
+    pub games: Vec<GameRecord>,
+}
+
+impl MatchReport {
+    /// The first player's wins, losses and draws.
+    pub fn score(&self) -> (usize, usize, usize) {
+        let mut score = (0, 0, 0);
+        for game in &self.games {
+            match game.result.winner() {
+                None => score.2 += 1,
+                Some(ChessColour::White) if game.white == 0 => score.0 += 1,
+                Some(ChessColour::Black) if game.white == 1 => score.0 += 1,
+                Some(_) => score.1 += 1,
+            }
+        }
+        score
+    }
+
+    /// The first player's share of the points, from 0 to 1.
+    pub fn points(&self) -> f64 {
+        let (wins, _, draws) = self.score();
+        (wins as f64 + draws as f64 / 2.0) / self.games.len().max(1) as f64
+    }
+}
+
+impl Display for MatchReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (i, game) in self.games.iter().enumerate() {
+            writeln!(
+                f,
+                "Game {}: {} vs {}: {}",
+                i + 1,
+                self.names[game.white],
+                self.names[1 - game.white],
+                game.result
+            )?;
+        }
+        let (wins, losses, draws) = self.score();
+        write!(
+            f,
+            "Score of {} vs {}: {wins} - {losses} - {draws} [{:.3}] {}",
+            self.names[0],
+            self.names[1],
+            self.points(),
+            self.games.len()
+        )
+    }
+}
+
+/// A player once its engine is running.
+enum Seat {
+    Engine(Box<dyn EngineClient>),
+    Solver {
+        options: SearchOptions,
+        tt: TranspositionTable,
+    },
+}
+
+impl Seat {
+    fn start(player: &Player, variant: Variant) -> anyhow::Result<Self> {
+        match player {
+            Player::Engine { config, .. } => {
+                let mut engine = engine::start(config)?;
+                engine.set_variant(variant)?;
+                Ok(Seat::Engine(engine))
+            }
+            Player::Solver(options) => Ok(Seat::Solver {
+                options: options.clone(),
+                tt: TranspositionTable::default(),
+            }),
+        }
+    }
+
+    fn new_game(&mut self) -> anyhow::Result<()> {
+        match self {
+            Seat::Engine(engine) => engine.new_game(),
+            Seat::Solver { tt, .. } => {
+                tt.clear();
+                Ok(())
+            }
+        }
+    }
+
+    /// Pick a move in `game`, along with what the player thinks of it.
+    fn play(
+        &mut self,
+        game: &ChessGame,
+        movetime: Duration,
+    ) -> anyhow::Result<(Move, Option<Score>)> {
+        match self {
+            Seat::Engine(engine) => {
+                let params = match game.clock() {
+                    Some(clock) => GoParams::new().clock(clock),
+                    None => GoParams::new().movetime(movetime),
+                };
+                let reply = engine.play(game, &params)?;
+                Ok((reply.best_move, reply.score))
+            }
+            Seat::Solver { options, tt } => {
+                let mut options = options.clone();
+                options.max_time = Some(match game.clock() {
+                    Some(clock) => clock::move_budget(
+                        clock.remaining_now(game.get_board().turn),
+                        clock.control().increment(),
+                        DEFAULT_MOVES_TO_GO,
+                    ),
+                    None => movetime,
+                });
+                let search = solver::solve_next_move_with_options(game, &options, tt)?;
+                Ok((search.best_move, Some(search.score)))
+            }
+        }
+    }
+}
+
+fn centipawns(score: Score) -> i32 {
+    match score {
+        Score::Centipawns(cp) => cp,
+        Score::Mate(moves) if moves > 0 => MATE_CENTIPAWNS,
+        Score::Mate(_) => -MATE_CENTIPAWNS,
+    }
+}
+
+/// Play `options.games` games between `players`, the first player taking
+/// white in the first game.
+pub fn run_match(players: &[Player; 2], options: &MatchOptions) -> anyhow::Result<MatchReport> {
+    let mut seats = [
+        Seat::start(&players[0], options.variant)?,
+        Seat::start(&players[1], options.variant)?,
+    ];
+    let mut games = Vec::with_capacity(options.games);
+    let mut chess960_index = 0;
+    for round in 0..options.games {
+        let white = round % 2;
+        // each Chess960 position is played twice, with the players swapped
+        if white == 0 {
+            chess960_index = random_chess960_index();
+        }
+        let mut game = new_game(options, chess960_index)?;
+        for seat in &mut seats {
+            seat.new_game()?;
+        }
+        let result = play_game(&mut game, &mut seats, white, options, &mut |_, _| {})?;
+        tracing::info!(
+            "Game {}: {} vs {}: {result}",
+            round + 1,
+            players[white].name(),
+            players[1 - white].name()
+        );
+        games.push(GameRecord {
+            white,
+            result,
+            moves: game.played_moves().to_vec(),
+        });
+    }
+    Ok(MatchReport {
+        names: [players[0].name().to_string(), players[1].name().to_string()],
+        games,
+    })
+}
+
+/// Play one game between `players`, the first taking white, calling `on_move`
+/// after each move with the game and what the player who moved thinks of its
+/// position, so that the game can be watched as it goes. Returns the finished
+/// game.
+pub fn watch_game(
+    players: &[Player; 2],
+    options: &MatchOptions,
+    mut on_move: impl FnMut(&ChessGame, Option<Score>),
+) -> anyhow::Result<ChessGame> {
+    let mut seats = [
+        Seat::start(&players[0], options.variant)?,
+        Seat::start(&players[1], options.variant)?,
+    ];
+    let mut game = new_game(options, random_chess960_index())?;
+    for seat in &mut seats {
+        seat.new_game()?;
+    }
+    play_game(&mut game, &mut seats, 0, options, &mut on_move)?;
+    Ok(game)
+}
+
+/// A game to start a match with `options`, from Chess960 position
+/// `chess960_index` when playing that.
+fn new_game(options: &MatchOptions, chess960_index: u16) -> anyhow::Result<ChessGame> {
+    let mut builder = match options.variant {
+        Variant::Standard => ChessGame::builder(),
+        Variant::Chess960 => ChessGame::builder().chess960(chess960_index),
+    };
+    if let Some(control) = options.time_control {
+        builder = builder.time_control(control);
+    }
+    Ok(builder.build()?)
+}
+
+fn play_game(
+    game: &mut ChessGame,
+    seats: &mut [Seat; 2],
+    white: usize,
+    options: &MatchOptions,
+    on_move: &mut dyn FnMut(&ChessGame, Option<Score>),
+) -> anyhow::Result<GameResult> {
+    let mut scores = Vec::new();
+    loop {
+        if let Some(result) = game.result() {
+            return Ok(result);
+        }
+        let turn = game.get_board().turn;
+        let seat = match turn {
+            ChessColour::White => white,
+            ChessColour::Black => 1 - white,
+        };
+        let (mv, score) = match seats[seat].play(game, options.movetime) {
+            Err(e) if matches!(e.downcast_ref(), Some(EngineError::Resigned)) => {
+                return Ok(game.resign(turn)?);
+            }
+            played => played?,
+        };
+        if let Err(e) = game.make_move(&mv) {
+            tracing::warn!("{turn} forfeits with the illegal move {mv}: {e}");
+            return Ok(game.adjudicate(Some(turn.flip()))?);
+        }
+        scores.push(score.map(centipawns));
+        if game.result().is_none()
+            && let Some(result) = options.adjudication.check(game, &scores)
+        {
+            game.adjudicate(result.winner())?;
+        }
+        on_move(game, score);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_adjudication() {
+        let adjudication = Adjudication {
+            draw_after: 0,
+            draw_moves: 2,
+            ..Adjudication::default()
+        };
+        let game = ChessGame::default();
+        // black, who moved last, has been losing for three moves
+        let losing = [-1200, 0, -1500, 50, -1100].map(Some);
+        assert_eq!(
+            adjudication.check(&game, &losing),
+            Some(GameResult::WhiteWins(Termination::Adjudication))
+        );
+        assert_eq!(adjudication.check(&game, &losing[1..]), None);
+        let level = [0, 5, -3, 8].map(Some);
+        assert_eq!(
+            adjudication.check(&game, &level),
+            Some(GameResult::Draw(Termination::Adjudication))
+        );
+        assert_eq!(
+            adjudication.check(&game, &[Some(0), None, Some(0), Some(0)]),
+            None
+        );
+
+        let mate = ChessGame::from_fen("k7/8/1K6/8/8/8/7Q/8 w - - 0 1", Variant::Standard).unwrap();
+        assert_eq!(
+            adjudication.check(&mate, &[]),
+            Some(GameResult::WhiteWins(Termination::Adjudication))
+        );
+        let minor_pieces =
+            ChessGame::from_fen("k7/8/1K6/8/8/8/2b5/6N1 w - - 0 1", Variant::Standard).unwrap();
+        assert_eq!(
+            adjudication.check(&minor_pieces, &[]),
+            Some(GameResult::Draw(Termination::Adjudication))
+        );
+    }
+
+    #[test]
+    fn test_solver_match() {
+        let solver = || {
+            Player::Solver(SearchOptions {
+                max_depth: 1,
+                ..SearchOptions::default()
+            })
+        };
+        let options = MatchOptions {
+            adjudication: Adjudication {
+                max_moves: Some(4),
+                ..Adjudication::default()
+            },
+            ..MatchOptions::default()
+        };
+        let report = run_match(&[solver(), solver()], &options).unwrap();
+        assert_eq!(report.games.len(), 2);
+        assert_eq!(report.games[0].white, 0);
+        assert_eq!(report.games[1].white, 1);
+        for game in &report.games {
+            assert_eq!(game.result, GameResult::Draw(Termination::Adjudication));
+            assert_eq!(game.moves.len(), 8);
+        }
+        assert_eq!(report.score(), (0, 0, 2));
+        assert!(
+            report
+                .to_string()
+                .ends_with("Score of solver vs solver: 0 - 0 - 2 [0.500] 2")
+        );
+
+        let mut watched = Vec::new();
+        let game = watch_game(&[solver(), solver()], &options, |game, score| {
+            watched.push((game.played_moves().len(), score.is_some()));
+        })
+        .unwrap();
+        assert_eq!(game.played_moves().len(), 8);
+        assert_eq!(
+            watched,
+            (1..=8).map(|moves| (moves, true)).collect::<Vec<_>>()
+        );
+    }
+}
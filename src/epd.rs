@@ -0,0 +1,163 @@
+//! Test suites in EPD, the format engine test positions such as Win At Chess
+//! come in: a FEN without the move counters, then operations such as
+//! `bm Qg6; id "WAC.001";` naming the best move, or a move to avoid.
+
+use crate::core::Move;
+use crate::game::ChessGame;
+use crate::input;
+use crate::variant::Variant;
+use anyhow::Context;
+
+/// A test position and the moves that solve it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EpdPosition {
+    /// The position, with the move counters added.
+    pub fen: String,
+    /// The moves that solve it, from `bm`. Any one will do.
+    pub best_moves: Vec<Move>,
+    /// The moves that fail, from `am`.
+    pub avoid_moves: Vec<Move>,
+    /// What the suite calls it, from `id`.
+    pub id: Option<String>,
+}
+
+impl EpdPosition {
+    pub fn game(&self) -> anyhow::Result<ChessGame> {
+        Ok(ChessGame::from_fen(&self.fen, Variant::Standard)?)
+    }
+
+    /// Whether playing `mv` solves the position: it is one of the best moves
+    /// if any are given, and not one to avoid.
+    pub fn solved_by(&self, mv: &Move) -> bool {
+        (self.best_moves.is_empty() || self.best_moves.contains(mv))
+            && !self.avoid_moves.contains(mv)
+    }
+}
+
+/// The positions in `text`, one a line. Blank lines and lines starting with
+/// `#` are skipped, as are operations other than `bm`, `am` and `id`.
+pub fn parse(text: &str) -> anyhow::Result<Vec<EpdPosition>> {
+    text.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty() && !line.trim_start().starts_with('#'))
+        .map(|(number, line)| parse_line(line).with_context(|| format!("on line {}", number + 1)))
+        .collect()
+}
+
+fn parse_line(line: &str) -> anyhow::Result<EpdPosition> {
+    let mut fields = line.trim().splitn(5, ' ');
+    let position: Vec<&str> = fields.by_ref().take(4).collect();
+    anyhow::ensure!(position.len() == 4, "a position needs four fields");
+    let fen = format!("{} 0 1", position.join(" "));
+    let game = ChessGame::from_fen(&fen, Variant::Standard)
+        .with_context(|| format!("invalid position {}", position.join(" ")))?;
+    let mut epd = EpdPosition {
+        fen,
+        best_moves: Vec::new(),
+        avoid_moves: Vec::new(),
+        id: None,
+    };
+    let moves = |operands: &[String]| -> Result<Vec<Move>, _> {
+        operands
+            .iter()
+            .map(|san| input::parse_move(san, &game))
+            .collect()
+    };
+    for operation in operations(fields.next().unwrap_or("")) {
+        let Some((opcode, operands)) = operation.split_first() else {
+            continue;
+        };
+        match opcode.as_str() {
+            "bm" => epd.best_moves = moves(operands)?,
+            "am" => epd.avoid_moves = moves(operands)?,
+            "id" => epd.id = operands.first().cloned(),
+            _ => {}
+        }
+    }
+    anyhow::ensure!(
+        !epd.best_moves.is_empty() || !epd.avoid_moves.is_empty(),
+        "no bm or am to test"
+    );
+    Ok(epd)
+}
+
+/// The operations in `text`, each an opcode followed by its operands, with
+/// the quotes taken off quoted ones. The last one's semicolon, which some
+/// suites leave out, is optional.
+fn operations(text: &str) -> Vec<Vec<String>> {
+    let mut operations = Vec::new();
+    let mut operation = Vec::new();
+    let mut chars = text.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        match c {
+            ';' => {
+                chars.next();
+                operations.push(std::mem::take(&mut operation));
+            }
+            '"' => {
+                chars.next();
+                let quoted: String = chars.by_ref().take_while(|&c| c != '"').collect();
+                operation.push(quoted);
+            }
+            c if c.is_whitespace() => {
+                chars.next();
+            }
+            _ => {
+                let mut word = String::new();
+                while let Some(&c) = chars.peek() {
+                    if c.is_whitespace() || c == ';' {
+                        break;
+                    }
+                    word.push(c);
+                    chars.next();
+                }
+                operation.push(word);
+            }
+        }
+    }
+    if !operation.is_empty() {
+        operations.push(operation);
+    }
+    operations
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let text = r#"
+# Win At Chess
+2rr3k/pp3pp1/1nnqbN1p/3pN3/2pP4/2P3Q1/PPB4P/R4RK1 w - - bm Qg6; id "WAC.001";
+r1b1k2r/ppppnppp/2n2q2/2b5/3NP3/2P1B3/PP3PPP/RN1QKB1R w KQkq - am Nxc6; id "no; not that";
+"#;
+        let positions = parse(text).unwrap();
+        assert_eq!(positions.len(), 2);
+        let wac = &positions[0];
+        assert_eq!(wac.id.as_deref(), Some("WAC.001"));
+        assert_eq!(
+            wac.fen,
+            "2rr3k/pp3pp1/1nnqbN1p/3pN3/2pP4/2P3Q1/PPB4P/R4RK1 w - - 0 1"
+        );
+        let qg6 = Move::from_uci("g3g6").unwrap();
+        assert_eq!(wac.best_moves, [qg6]);
+        assert!(wac.solved_by(&qg6));
+        assert!(!wac.solved_by(&Move::from_uci("g3h4").unwrap()));
+
+        let avoid = &positions[1];
+        assert_eq!(avoid.id.as_deref(), Some("no; not that"));
+        assert_eq!(avoid.avoid_moves.len(), 1);
+        assert!(!avoid.solved_by(&Move::from_uci("d4c6").unwrap()));
+        assert!(avoid.solved_by(&Move::from_uci("f1e2").unwrap()));
+        assert_eq!(avoid.game().unwrap().fen(), avoid.fen);
+
+        let error = parse("\n8/8/8/8/8/8/8/K1k5 w - - bm Kb1;").unwrap_err();
+        assert_eq!(
+            format!("{error:#}"),
+            "on line 2: No legal move matches 'Kb1'"
+        );
+        assert!(parse("8/8/8/8/8/8/8/K6k w - - id \"x\";").is_err());
+        assert!(parse("8/8/8/8/8/8/8/K6k w - - bm Kb1").is_ok());
+    }
+}
@@ -0,0 +1,52 @@
+//! Events a game announces as it is played, so that displays, logs and the
+//! like can follow it without checking the board after every move.
+
+use crate::core::{ChessColour, ChessPiece, Move, Position};
+use crate::game::GameResult;
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+
+/// Something that happened in a [`crate::game::ChessGame`], passed to the
+/// handlers subscribed with [`crate::game::ChessGame::subscribe`].
+///
+/// There is no promotion event yet: moves don't carry a promotion piece and
+/// a pawn reaching the last row stays a pawn, so there is no promotion to
+/// announce. It is to be added along with promotions themselves.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GameEvent {
+    /// `colour` played `mv`. Always the first event for a move.
+    MovePlayed { mv: Move, colour: ChessColour },
+    /// The move took `piece` on `square`.
+    PieceCaptured { piece: ChessPiece, square: Position },
+    /// The move put `colour`'s king in check, including checkmate.
+    Check(ChessColour),
+    /// The game finished, by a move, running out of time, resigning or
+    /// agreement.
+    GameEnded(GameResult),
+}
+
+type EventHandler = Box<dyn FnMut(&GameEvent) + Send>;
+
+/// The handlers subscribed to a game. They belong to the game they were
+/// subscribed to, so a clone, like those the solver searches with, starts
+/// without any and plays its moves quietly.
+#[derive(Default)]
+pub(crate) struct Observers(Vec<EventHandler>);
+
+impl Observers {
+    pub(crate) fn push(&mut self, handler: EventHandler) {
+        self.0.push(handler);
+    }
+
+    pub(crate) fn emit(&mut self, event: GameEvent) {
+        for handler in &mut self.0 {
+            handler(&event);
+        }
+    }
+}
+
+impl Clone for Observers {
+    fn clone(&self) -> Self {
+        Self::default()
+    }
+}
@@ -0,0 +1,225 @@
+//! What was played from a position in real games, from the Lichess opening
+//! explorer: how often each move was chosen and how the games went, to show
+//! beside the solver's opinion of it.
+
+use crate::game::ChessGame;
+use crate::import;
+use crate::variant::Variant;
+use anyhow::Context;
+use clap::ValueEnum;
+use serde::Deserialize;
+use std::fmt::Display;
+
+/// How many of the moves played from a position to show.
+pub const MAX_MOVES: usize = 8;
+
+/// Which games to count.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum Database {
+    /// Over-the-board games between masters.
+    #[default]
+    Masters,
+    /// Rated games played on Lichess by players rated 2000 and up.
+    Lichess,
+}
+
+impl Display for Database {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Database::Masters => write!(f, "Masters"),
+            Database::Lichess => write!(f, "Lichess"),
+        }
+    }
+}
+
+/// The games that reached a position.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct Explorer {
+    #[serde(skip)]
+    pub database: Database,
+    /// How many were won by White, drawn and won by Black.
+    pub white: u64,
+    pub draws: u64,
+    pub black: u64,
+    /// The moves played next, the most popular first.
+    pub moves: Vec<ExplorerMove>,
+    /// The opening the position is named as.
+    #[serde(default)]
+    pub opening: Option<ExplorerOpening>,
+}
+
+/// A move played in the games that reached a position, and how they went.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct ExplorerMove {
+    pub uci: String,
+    pub san: String,
+    pub white: u64,
+    pub draws: u64,
+    pub black: u64,
+    #[serde(rename = "averageRating", default)]
+    pub average_rating: Option<u32>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct ExplorerOpening {
+    pub eco: String,
+    pub name: String,
+}
+
+impl Explorer {
+    /// The games in `database` that reached `game`'s position, with the
+    /// [`MAX_MOVES`] moves most played from it.
+    pub fn fetch(game: &ChessGame, database: Database) -> anyhow::Result<Self> {
+        anyhow::ensure!(
+            game.variant() == Variant::Standard,
+            "the opening explorer only has standard chess games"
+        );
+        Self::parse(&import::get(&url(game, database))?, database)
+    }
+
+    /// Read the explorer's answer.
+    pub fn parse(json: &str, database: Database) -> anyhow::Result<Self> {
+        let explorer: Self =
+            serde_json::from_str(json).context("the opening explorer sent an unexpected answer")?;
+        Ok(Self {
+            database,
+            ..explorer
+        })
+    }
+
+    pub fn games(&self) -> u64 {
+        self.white + self.draws + self.black
+    }
+}
+
+impl ExplorerMove {
+    pub fn games(&self) -> u64 {
+        self.white + self.draws + self.black
+    }
+}
+
+/// The address to ask about `game`'s position.
+fn url(game: &ChessGame, database: Database) -> String {
+    let fen = game.fen().replace(' ', "%20");
+    match database {
+        Database::Masters => {
+            format!("https://explorer.lichess.ovh/masters?fen={fen}&moves={MAX_MOVES}")
+        }
+        Database::Lichess => format!(
+            "https://explorer.lichess.ovh/lichess?variant=standard&speeds=blitz,rapid,classical\
+             &ratings=2000,2200,2500&fen={fen}&moves={MAX_MOVES}"
+        ),
+    }
+}
+
+/// White's wins, draws and Black's wins, as whole percentages.
+fn percentages(white: u64, draws: u64, black: u64) -> String {
+    let total = (white + draws + black).max(1) as f64;
+    let percent = |n: u64| (n as f64 * 100.0 / total).round();
+    format!(
+        "{:>3}% {:>3}% {:>3}%",
+        percent(white),
+        percent(draws),
+        percent(black)
+    )
+}
+
+/// `n` with commas between the thousands.
+fn thousands(n: u64) -> String {
+    let digits = n.to_string();
+    let mut grouped = String::new();
+    for (i, digit) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            grouped.push(',');
+        }
+        grouped.push(digit);
+    }
+    grouped
+}
+
+impl Display for Explorer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(opening) = &self.opening {
+            writeln!(f, "{} {}", opening.eco, opening.name)?;
+        }
+        if self.games() == 0 {
+            return write!(f, "No {} games reached this position.", self.database);
+        }
+        write!(
+            f,
+            "{} {} games, won by White, drawn, won by Black:\n  {:<8} {:>11}  {}",
+            thousands(self.games()),
+            self.database,
+            "",
+            "",
+            percentages(self.white, self.draws, self.black)
+        )?;
+        for mv in &self.moves {
+            write!(
+                f,
+                "\n  {:<8} {:>11}  {}",
+                mv.san,
+                thousands(mv.games()),
+                percentages(mv.white, mv.draws, mv.black)
+            )?;
+            if let Some(rating) = mv.average_rating {
+                write!(f, "  rated {rating} on average")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::core::Move;
+
+    const ANSWER: &str = r#"{
+        "white": 1212, "draws": 1740, "black": 848,
+        "moves": [
+            {"uci": "g1f3", "san": "Nf3", "averageRating": 2581, "white": 802, "draws": 1191,
+             "black": 561, "game": null},
+            {"uci": "b1c3", "san": "Nc3", "averageRating": 2536, "white": 410, "draws": 549,
+             "black": 287, "game": null}
+        ],
+        "topGames": [],
+        "opening": {"eco": "C20", "name": "King's Pawn Game"}
+    }"#;
+
+    #[test]
+    fn test_parse() {
+        let explorer = Explorer::parse(ANSWER, Database::Masters).unwrap();
+        assert_eq!(explorer.games(), 3800);
+        assert_eq!(explorer.moves[0].san, "Nf3");
+        assert_eq!(explorer.moves[1].average_rating, Some(2536));
+        assert_eq!(
+            explorer.to_string(),
+            "C20 King's Pawn Game\n\
+             3,800 Masters games, won by White, drawn, won by Black:\n\
+             \x20                        32%  46%  22%\n\
+             \x20 Nf3            2,554   31%  47%  22%  rated 2581 on average\n\
+             \x20 Nc3            1,246   33%  44%  23%  rated 2536 on average"
+        );
+        let empty = r#"{"white": 0, "draws": 0, "black": 0, "moves": []}"#;
+        assert_eq!(
+            Explorer::parse(empty, Database::Lichess)
+                .unwrap()
+                .to_string(),
+            "No Lichess games reached this position."
+        );
+        assert!(Explorer::parse("<html>", Database::Masters).is_err());
+    }
+
+    #[test]
+    fn test_url() {
+        let mut game = ChessGame::default();
+        game.make_move(&Move::from_uci("e2e4").unwrap()).unwrap();
+        assert_eq!(
+            url(&game, Database::Masters),
+            "https://explorer.lichess.ovh/masters?fen=rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR%20b%20KQkq%20-%200%201&moves=8"
+        );
+        assert_eq!(thousands(1234567), "1,234,567");
+        assert_eq!(thousands(999), "999");
+    }
+}
@@ -1,45 +1,364 @@
-use crate::core::{Cell, ChessColour, ChessPiece, ChessPieceKind, Move, Position};
+//! A game from start to finish: making moves by the rules, the clock, and how
+//! the game ended.
+
+#[cfg(feature = "std")]
+use crate::clock::{Clock, TimeControl};
+use crate::core::{
+    CastlingRights, CastlingSide, ChessColour, ChessPiece, ChessPieceKind, Column,
+    IllegalMoveReason, Move, Position, Row, Square,
+};
+use crate::event::{GameEvent, Observers};
+use crate::solver::Score;
+use crate::tree::{GameTree, MoveNode};
+#[cfg(feature = "std")]
+use crate::variant::random_chess960_index;
+use crate::variant::{Variant, chess960_back_row};
+use crate::zobrist;
 use crate::{ChessBoard, ChessError};
+use ::core::fmt::Display;
+use ::core::str::FromStr;
+use alloc::boxed::Box;
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "std")]
+use std::sync::mpsc::Receiver;
 
-#[derive(Debug, Clone, PartialEq, Eq, Hash, Copy)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Copy, Serialize, Deserialize)]
 pub enum GameStatus {
     Ongoing,
+    Finished(GameResult),
+}
+
+/// Why a game ended.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Copy, Serialize, Deserialize)]
+pub enum Termination {
     Checkmate,
+    Resignation,
+    Timeout,
     Stalemate,
+    Agreement,
+    Repetition,
+    FiftyMoveRule,
+    InsufficientMaterial,
+    /// Stopped early and scored by whoever was running the game, such as a
+    /// match between engines.
+    Adjudication,
+}
+
+impl Display for Termination {
+    fn fmt(&self, f: &mut ::core::fmt::Formatter<'_>) -> ::core::fmt::Result {
+        let reason = match self {
+            Termination::Checkmate => "checkmate",
+            Termination::Resignation => "resignation",
+            Termination::Timeout => "timeout",
+            Termination::Stalemate => "stalemate",
+            Termination::Agreement => "agreement",
+            Termination::Repetition => "threefold repetition",
+            Termination::FiftyMoveRule => "fifty move rule",
+            Termination::InsufficientMaterial => "insufficient material",
+            Termination::Adjudication => "adjudication",
+        };
+        write!(f, "{reason}")
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Copy, Serialize, Deserialize)]
+pub enum GameResult {
+    WhiteWins(Termination),
+    BlackWins(Termination),
+    Draw(Termination),
+}
+
+impl GameResult {
+    pub fn win_for(colour: ChessColour, termination: Termination) -> Self {
+        match colour {
+            ChessColour::White => GameResult::WhiteWins(termination),
+            ChessColour::Black => GameResult::BlackWins(termination),
+        }
+    }
+
+    pub fn winner(&self) -> Option<ChessColour> {
+        match self {
+            GameResult::WhiteWins(_) => Some(ChessColour::White),
+            GameResult::BlackWins(_) => Some(ChessColour::Black),
+            GameResult::Draw(_) => None,
+        }
+    }
+
+    pub fn termination(&self) -> Termination {
+        match self {
+            GameResult::WhiteWins(t) | GameResult::BlackWins(t) | GameResult::Draw(t) => *t,
+        }
+    }
+
+    /// The result in PGN notation, e.g. `1-0`.
+    pub fn score(&self) -> &'static str {
+        match self {
+            GameResult::WhiteWins(_) => "1-0",
+            GameResult::BlackWins(_) => "0-1",
+            GameResult::Draw(_) => "1/2-1/2",
+        }
+    }
+}
+
+impl Display for GameResult {
+    fn fmt(&self, f: &mut ::core::fmt::Formatter<'_>) -> ::core::fmt::Result {
+        write!(f, "{} ({})", self.score(), self.termination())
+    }
 }
 
+/// Identifies a position for repetition detection: the Zobrist hash, which
+/// covers the pieces, the side to move and the castling rights.
+type PositionKey = u64;
+
+/// How the game stands after a move, and the board it was played on.
 pub struct GameState<'a> {
     pub status: GameStatus,
-    board: &'a ChessBoard,
+    pub board: &'a ChessBoard,
 }
 
 /// A simple chess game engine that manages the chess board and handles moves.
-#[derive(Clone)]
+/// It serializes with everything needed to carry on playing, though a running
+/// clock is stopped and subscriptions to its events are left behind.
+#[derive(Clone, Serialize, Deserialize)]
 pub struct ChessGame {
     chess_board: ChessBoard,
+    variant: Variant,
+    /// The FEN the game started from, if not the standard starting position.
+    start_fen: Option<String>,
+    /// The line from the start to the current position.
     moves: Vec<Move>,
+    /// Every line played or added, including variations left by going back.
+    tree: GameTree,
     taken_pieces: Vec<ChessPiece>,
     full_move_count: u32,
     /// The number of half-moves since the last capture or pawn advance.
     half_move_clock: u32,
+    position_history: Vec<PositionKey>,
+    result: Option<GameResult>,
+    #[cfg(feature = "std")]
+    clock: Option<Clock>,
+    #[serde(skip)]
+    observers: Observers,
 }
 
 impl Default for ChessGame {
     fn default() -> Self {
+        let chess_board = ChessBoard::default();
         Self {
-            chess_board: ChessBoard::default(),
+            chess_board: chess_board.clone(),
+            variant: Variant::Standard,
+            start_fen: None,
             moves: Vec::new(),
+            tree: GameTree::default(),
             taken_pieces: Vec::new(),
             full_move_count: 1,
             half_move_clock: 0,
+            position_history: vec![zobrist::hash(&chess_board)],
+            result: None,
+            #[cfg(feature = "std")]
+            clock: None,
+            observers: Observers::default(),
         }
     }
 }
 
+/// Where a [`ChessGameBuilder`] sets up the pieces from.
+#[derive(Debug, Clone, Default)]
+enum StartingPosition {
+    #[default]
+    Standard,
+    Fen(String),
+    /// The Chess960 starting position with this index.
+    Chess960(u16),
+    /// A board, with its half move clock and full move count.
+    Board(Box<ChessBoard>, u32, u32),
+}
+
+/// How to set up a [`ChessGame`], built up from [`ChessGame::builder`]:
+///
+/// ```
+/// use chess::clock::TimeControl;
+/// use chess::core::ChessColour;
+/// use chess::game::ChessGame;
+/// use std::str::FromStr;
+///
+/// let game = ChessGame::builder()
+///     .fen("4k3/8/8/8/8/8/4P3/4K3 w - - 0 1")
+///     .turn(ChessColour::Black)
+///     .time_control(TimeControl::from_str("5+3").unwrap())
+///     .build()
+///     .unwrap();
+/// assert_eq!(game.fen(), "4k3/8/8/8/8/8/4P3/4K3 b - - 0 1");
+/// ```
+///
+/// With nothing set it builds a standard game from the starting position.
+#[derive(Debug, Clone, Default)]
+pub struct ChessGameBuilder {
+    start: StartingPosition,
+    variant: Variant,
+    turn: Option<ChessColour>,
+    castling: Option<CastlingRights>,
+    #[cfg(feature = "std")]
+    clock: Option<Clock>,
+}
+
+impl ChessGameBuilder {
+    /// Start from a FEN string, as [`ChessGame::from_fen`] reads it.
+    pub fn fen(self, fen: &str) -> Self {
+        Self {
+            start: StartingPosition::Fen(fen.to_string()),
+            ..self
+        }
+    }
+
+    /// Start from `board`, with `half_move_clock` half moves since the last
+    /// capture or pawn advance and `full_move_count` the number of the next
+    /// full move.
+    pub fn board(self, board: ChessBoard, half_move_clock: u32, full_move_count: u32) -> Self {
+        Self {
+            start: StartingPosition::Board(Box::new(board), half_move_clock, full_move_count),
+            ..self
+        }
+    }
+
+    /// Play Chess960 from the starting position with this index.
+    pub fn chess960(self, index: u16) -> Self {
+        Self {
+            start: StartingPosition::Chess960(index),
+            variant: Variant::Chess960,
+            ..self
+        }
+    }
+
+    /// Play Chess960 from a randomly chosen starting position.
+    #[cfg(feature = "std")]
+    pub fn random_chess960(self) -> Self {
+        self.chess960(random_chess960_index())
+    }
+
+    /// Play by the castling rules of `variant`, e.g. Chess960 from a FEN.
+    pub fn variant(self, variant: Variant) -> Self {
+        Self { variant, ..self }
+    }
+
+    /// Give `turn` the first move, whoever the starting position says.
+    pub fn turn(self, turn: ChessColour) -> Self {
+        Self {
+            turn: Some(turn),
+            ..self
+        }
+    }
+
+    /// Replace the castling rights of the starting position.
+    pub fn castling(self, castling: CastlingRights) -> Self {
+        Self {
+            castling: Some(castling),
+            ..self
+        }
+    }
+
+    /// Play with a fresh clock for `control`.
+    #[cfg(feature = "std")]
+    pub fn time_control(self, control: TimeControl) -> Self {
+        self.clock(Clock::new(control))
+    }
+
+    /// Play on `clock`, e.g. one saved part way through a game.
+    #[cfg(feature = "std")]
+    pub fn clock(self, clock: Clock) -> Self {
+        Self {
+            clock: Some(clock),
+            ..self
+        }
+    }
+
+    /// The game, failing if the position is not one [`ChessBoard::validate`]
+    /// accepts. Its clock, if it has one, starts for the side to move.
+    pub fn build(self) -> Result<ChessGame, ChessError> {
+        let changed = self.turn.is_some() || self.castling.is_some();
+        let (mut board, half_move_clock, full_move_count) = match &self.start {
+            StartingPosition::Standard => (ChessBoard::default(), 0, 1),
+            StartingPosition::Fen(fen) => parse_fen(fen)?,
+            StartingPosition::Chess960(index) => {
+                let back_row = chess960_back_row(*index)?;
+                (ChessBoard::starting_position(&back_row), 0, 1)
+            }
+            StartingPosition::Board(board, half_move_clock, full_move_count) => {
+                (*board.clone(), *half_move_clock, *full_move_count)
+            }
+        };
+        if let Some(turn) = self.turn {
+            board.turn = turn;
+        }
+        if let Some(castling) = self.castling {
+            board.castling = castling;
+        }
+        board.validate().map_err(ChessError::InvalidPosition)?;
+
+        let mut game = ChessGame {
+            chess_board: board,
+            variant: self.variant,
+            half_move_clock,
+            full_move_count,
+            ..ChessGame::default()
+        };
+        game.position_history = vec![game.position_key()];
+        game.result = game.detect_result();
+        game.start_fen = match self.start {
+            StartingPosition::Standard if !changed => None,
+            // kept as given, as it may say more than the game keeps track of,
+            // like the en passant square
+            StartingPosition::Fen(fen) if !changed => Some(fen),
+            _ => Some(game.fen()),
+        };
+        #[cfg(feature = "std")]
+        if let Some(clock) = self.clock {
+            game.set_clock(clock);
+        }
+        Ok(game)
+    }
+}
+
 impl ChessGame {
+    /// Set up a game a step at a time, e.g. from a FEN with a clock.
+    pub fn builder() -> ChessGameBuilder {
+        ChessGameBuilder::default()
+    }
+
+    /// Start a Chess960 game from the starting position with the given index.
+    pub fn new_chess960(index: u16) -> Result<Self, ChessError> {
+        Self::builder().chess960(index).build()
+    }
+
+    /// Start a Chess960 game from a randomly chosen starting position.
+    #[cfg(feature = "std")]
+    pub fn random_chess960() -> Self {
+        Self::builder()
+            .random_chess960()
+            .build()
+            .expect("every Chess960 starting position is valid")
+    }
+
+    /// Load a game from a FEN string. Castling rights may be given either as
+    /// `KQkq` or as rook columns (X-FEN / Shredder-FEN), which is required for
+    /// some Chess960 positions. Positions that fail [`ChessBoard::validate`]
+    /// are rejected.
+    pub fn from_fen(fen: &str, variant: Variant) -> Result<Self, ChessError> {
+        Self::builder().fen(fen).variant(variant).build()
+    }
+
+    /// Serialise the current position as FEN. Castling rights use `KQkq` where
+    /// that is unambiguous and fall back to X-FEN rook columns otherwise.
     pub fn fen(&self) -> String {
         let mut fen = String::new();
-        for row in self.chess_board.board.iter() {
+        for (i, row) in self.chess_board.rows().rev().enumerate() {
+            if i != 0 {
+                fen.push('/');
+            }
             let mut empty_count = 0;
             for cell in row.iter() {
                 if let Some(piece) = cell.piece {
@@ -52,15 +371,29 @@ impl ChessGame {
                     empty_count += 1;
                 }
             }
+            if empty_count != 0 {
+                fen.push(char::from_digit(empty_count, 10).unwrap());
+            }
         }
 
         let move_char = match self.chess_board.turn {
             ChessColour::White => 'w',
             ChessColour::Black => 'b',
         };
+        let castling = format_castling_rights(&self.chess_board);
         let half_move_clock = self.half_move_clock;
         let full_move_count = self.full_move_count;
-        format!("{fen} {move_char} - - {half_move_clock} {full_move_count}")
+        format!("{fen} {move_char} {castling} - {half_move_clock} {full_move_count}")
+    }
+
+    /// The number of the current full move, starting at 1 and going up after
+    /// each of Black's moves.
+    pub fn full_move_count(&self) -> u32 {
+        self.full_move_count
+    }
+
+    pub fn variant(&self) -> Variant {
+        self.variant
     }
 
     /// Get the current chess board.
@@ -68,70 +401,420 @@ impl ChessGame {
         &self.chess_board
     }
 
+    /// The FEN of the position the game started from, or `None` if it started
+    /// from the standard starting position.
+    pub fn start_fen(&self) -> Option<&str> {
+        self.start_fen.as_deref()
+    }
+
+    /// The named opening, with its ECO code, that the game has reached by the
+    /// current position, if it started from the usual one.
+    #[cfg(feature = "std")]
+    pub fn opening(&self) -> Option<crate::opening::Opening> {
+        crate::opening::classify(self)
+    }
+
+    /// The moves from the start to the current position.
     pub fn played_moves(&self) -> &[Move] {
         &self.moves
     }
 
+    /// Every line of the game, with its variations and comments.
+    pub fn tree(&self) -> &GameTree {
+        &self.tree
+    }
+
+    /// The moves played from the current position, in the main line and any
+    /// variations, the main continuation first.
+    pub fn continuations(&self) -> Vec<Move> {
+        self.tree.continuations(&self.moves)
+    }
+
+    /// The comment on the current position.
+    pub fn comment(&self) -> Option<&str> {
+        self.tree.comment_at(&self.moves)
+    }
+
+    /// Comment on the current position, or remove its comment with `None`.
+    pub fn set_comment(&mut self, comment: Option<String>) {
+        self.tree.set_comment(&self.moves, comment);
+    }
+
+    /// The NAGs on the move that reached the current position.
+    pub fn nags(&self) -> &[u8] {
+        self.tree
+            .node_at(&self.moves)
+            .map_or(&[], |node| node.nags.as_slice())
+    }
+
+    /// Judge the move that reached the current position with `nag`, e.g. 2
+    /// for a mistake. The starting position has no move to judge.
+    pub fn add_nag(&mut self, nag: u8) {
+        if let Some(node) = self.tree.node_mut(&self.moves)
+            && !node.nags.contains(&nag)
+        {
+            node.nags.push(nag);
+        }
+    }
+
+    /// How good the current position was judged to be for White.
+    pub fn evaluation(&self) -> Option<Score> {
+        self.tree.node_at(&self.moves)?.evaluation
+    }
+
+    /// Record how good the current position is for White, or forget it with
+    /// `None`. The starting position can't be given one.
+    pub fn set_evaluation(&mut self, evaluation: Option<Score>) {
+        if let Some(node) = self.tree.node_mut(&self.moves) {
+            node.evaluation = evaluation;
+        }
+    }
+
+    /// The pieces captured so far, of both colours, in the order they were
+    /// taken.
+    pub fn taken_pieces(&self) -> &[ChessPiece] {
+        &self.taken_pieces
+    }
+
+    /// The number of half moves since the last capture or pawn advance.
+    pub fn half_move_clock(&self) -> u32 {
+        self.half_move_clock
+    }
+
+    /// `mv` in standard algebraic notation, e.g. `Nbd7`, `exd5` or `O-O+`, for
+    /// the side to move. Fails if the move isn't legal.
+    pub fn san(&self, mv: &Move) -> Result<String, ChessError> {
+        self.check_legal(mv)?;
+        let piece = self
+            .chess_board
+            .get(Square::from(mv.from))
+            .ok_or(ChessError::NoPieceAtSquare(mv.from))?;
+        let mut san = match self.castling_side(&mv.from, &mv.to) {
+            Some(CastlingSide::King) => "O-O".to_string(),
+            Some(CastlingSide::Queen) => "O-O-O".to_string(),
+            None => {
+                let captures = self
+                    .chess_board
+                    .get_piece_at(&mv.to)
+                    .is_some_and(|cell| cell.piece.is_some());
+                let mut san = String::new();
+                if piece.kind == ChessPieceKind::Pawn {
+                    if captures {
+                        san.push_str(&mv.from.column.to_string());
+                    }
+                } else {
+                    san.push(char::from(&piece).to_ascii_uppercase());
+                    san.push_str(&self.disambiguation(mv, &piece));
+                }
+                if captures {
+                    san.push('x');
+                }
+                san.push_str(&mv.to.to_string());
+                san
+            }
+        };
+        let mut after = self.clone();
+        after.make_move(mv)?;
+        if after
+            .result
+            .is_some_and(|result| result.termination() == Termination::Checkmate)
+        {
+            san.push('#');
+        } else if after.chess_board.is_in_check(after.chess_board.turn) {
+            san.push('+');
+        }
+        Ok(san)
+    }
+
+    /// What SAN needs after the piece letter to tell `mv` apart from moves of
+    /// the same kind of piece to the same square: its column if that is
+    /// enough, else its row, else both.
+    fn disambiguation(&self, mv: &Move, piece: &ChessPiece) -> String {
+        let others: Vec<Position> = self
+            .chess_board
+            .pieces()
+            .filter(|(pos, cell)| {
+                *pos != mv.from
+                    && cell
+                        .piece
+                        .is_some_and(|p| p.kind == piece.kind && p.colour == piece.colour)
+            })
+            .map(|(pos, _)| pos)
+            .filter(|pos| {
+                self.get_available_moves(*pos)
+                    .is_ok_and(|moves| moves.contains(&mv.to))
+            })
+            .collect();
+        if others.is_empty() {
+            String::new()
+        } else if others.iter().all(|pos| pos.column != mv.from.column) {
+            mv.from.column.to_string()
+        } else if others.iter().all(|pos| pos.row != mv.from.row) {
+            mv.from.row.to_string()
+        } else {
+            mv.from.to_string()
+        }
+    }
+
+    /// A new game from the position this one started from, to replay its
+    /// moves.
+    pub fn start_position(&self) -> Result<Self, ChessError> {
+        match &self.start_fen {
+            Some(fen) => Self::from_fen(fen, self.variant),
+            None => Ok(Self::default()),
+        }
+    }
+
+    /// The moves played so far in SAN, numbered a full move at a time, e.g.
+    /// `["1. e4 e5", "2. Nf3"]`. A game set up with Black to move starts
+    /// with e.g. `"1... e5"`.
+    pub fn numbered_moves(&self) -> Result<Vec<String>, ChessError> {
+        let mut replay = self.start_position()?;
+        let mut numbered = Vec::new();
+        for mv in &self.moves {
+            let san = replay.san(mv)?;
+            match replay.chess_board.turn {
+                ChessColour::White => numbered.push(format!("{}. {san}", replay.full_move_count)),
+                ChessColour::Black => match numbered.last_mut() {
+                    Some(full_move) => *full_move = format!("{full_move} {san}"),
+                    None => numbered.push(format!("{}... {san}", replay.full_move_count)),
+                },
+            }
+            replay.make_move(mv)?;
+        }
+        Ok(numbered)
+    }
+
+    /// `moves` played from here in SAN, as far as they are legal, e.g. a line
+    /// the solver expects.
+    pub fn san_line(&self, moves: &[Move]) -> String {
+        let mut game = self.clone();
+        let mut line = Vec::new();
+        for mv in moves {
+            let Ok(san) = game.san(mv) else { break };
+            if game.make_move(mv).is_err() {
+                break;
+            }
+            line.push(san);
+        }
+        line.join(" ")
+    }
+
+    /// Take back the last move, returning it, by replaying the game without
+    /// it. It is deleted from the tree along with what followed it, with its
+    /// first variation taking its place. The clock isn't wound back.
+    pub fn undo(&mut self) -> Result<Move, ChessError> {
+        let last = self.go_back()?;
+        let mut path = self.moves.clone();
+        path.push(last);
+        self.tree.remove(&path);
+        Ok(last)
+    }
+
+    /// Step back a move, returning it, keeping it in the tree so that playing
+    /// a different move starts a variation.
+    pub fn go_back(&mut self) -> Result<Move, ChessError> {
+        let mut earlier = self.moves.clone();
+        let last = earlier.pop().ok_or(ChessError::NothingToUndo)?;
+        self.go_to(&earlier)?;
+        Ok(last)
+    }
+
+    /// Play the main continuation from the current position, returning it, or
+    /// `None` at the end of a line.
+    pub fn go_forward(&mut self) -> Result<Option<Move>, ChessError> {
+        let Some(&mv) = self.continuations().first() else {
+            return Ok(None);
+        };
+        self.make_move(&mv)?;
+        Ok(Some(mv))
+    }
+
+    /// Go to the position after `line`, played from the start, by replaying
+    /// the game. Moves of `line` that aren't in the tree yet are added to it.
+    pub fn go_to(&mut self, line: &[Move]) -> Result<(), ChessError> {
+        let mut replay = self.start_position()?;
+        for mv in line {
+            replay.make_move(mv)?;
+        }
+        replay.tree = ::core::mem::take(&mut self.tree);
+        replay.tree.insert(line);
+        #[cfg(feature = "std")]
+        {
+            replay.clock = self.clock.take();
+        }
+        replay.observers = ::core::mem::take(&mut self.observers);
+        *self = replay;
+        Ok(())
+    }
+
+    /// Add every line of `tree`, with its annotations, from the current
+    /// position, and go to the end of its main line.
+    pub fn play_tree(&mut self, tree: &GameTree) -> Result<(), ChessError> {
+        if tree.comment.is_some() {
+            self.set_comment(tree.comment.clone());
+        }
+        self.play_line(&tree.main_line)
+    }
+
+    fn play_line(&mut self, line: &[MoveNode]) -> Result<(), ChessError> {
+        for node in line {
+            let before = self.moves.clone();
+            self.make_move(&node.mv)?;
+            if node.comment.is_some() {
+                self.set_comment(node.comment.clone());
+            }
+            for nag in &node.nags {
+                self.add_nag(*nag);
+            }
+            if node.evaluation.is_some() {
+                self.set_evaluation(node.evaluation);
+            }
+            if !node.variations.is_empty() {
+                let after = self.moves.clone();
+                for variation in &node.variations {
+                    self.go_to(&before)?;
+                    self.play_line(variation)?;
+                }
+                self.go_to(&after)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// The game in PGN, with its variations and annotations, the tags that aren't
+    /// known left as `?` and the movetext wrapped to 80 columns.
+    pub fn pgn(&self) -> Result<String, ChessError> {
+        self.pgn_with_tags(&[])
+    }
+
+    /// The game in PGN, with `tags` in place of the usual values of the
+    /// seven standard tags, e.g. `("White", "Carlsen")`.
+    pub fn pgn_with_tags(&self, tags: &[(&str, &str)]) -> Result<String, ChessError> {
+        let result = self.result.map_or("*", |result| result.score());
+        let mut pgn = String::new();
+        for (tag, default) in [
+            ("Event", "Casual game"),
+            ("Site", "?"),
+            ("Date", "????.??.??"),
+            ("Round", "-"),
+            ("White", "?"),
+            ("Black", "?"),
+            ("Result", result),
+        ] {
+            let value = tags
+                .iter()
+                .find(|(name, _)| *name == tag && tag != "Result")
+                .map_or(default, |(_, value)| value);
+            pgn.push_str(&format!("[{tag} \"{}\"]\n", escape_tag(value)));
+        }
+        #[cfg(feature = "std")]
+        if let Some(opening) = self.opening() {
+            pgn.push_str(&format!(
+                "[ECO \"{}\"]\n[Opening \"{}\"]\n",
+                opening.eco,
+                escape_tag(opening.name)
+            ));
+        }
+        if self.variant == Variant::Chess960 {
+            pgn.push_str("[Variant \"Chess960\"]\n");
+        }
+        if let Some(fen) = &self.start_fen {
+            pgn.push_str(&format!("[SetUp \"1\"]\n[FEN \"{fen}\"]\n"));
+        }
+        pgn.push('\n');
+        let mut movetext = Vec::new();
+        if let Some(comment) = &self.tree.comment {
+            movetext.push(format!("{{{comment}}}"));
+        }
+        write_line(
+            &self.start_position()?,
+            &self.tree.main_line,
+            true,
+            &mut movetext,
+        )?;
+        let mut line = String::new();
+        for word in movetext
+            .iter()
+            .flat_map(|chunk| chunk.split(' '))
+            .chain([result])
+        {
+            if !line.is_empty() && line.len() + 1 + word.len() > 80 {
+                pgn.push_str(&line);
+                pgn.push('\n');
+                line.clear();
+            }
+            if !line.is_empty() {
+                line.push(' ');
+            }
+            line.push_str(word);
+        }
+        pgn.push_str(&line);
+        pgn.push('\n');
+        Ok(pgn)
+    }
+
+    /// The key of every position reached so far, oldest first.
+    pub(crate) fn position_history(&self) -> &[PositionKey] {
+        &self.position_history
+    }
+
     /// Try and make a move on the chess board.
     pub fn make_move(&mut self, move_: &Move) -> Result<GameState<'_>, ChessError> {
         let starting_turn = self.chess_board.turn;
         let from = move_.from;
         let to = move_.to;
-        let from_cell = self
-            .chess_board
-            .get_piece_at(&from)
-            .ok_or(ChessError::InvalidMove(
-                "from position does not exist".to_string(),
-            ))?;
-        if from_cell.piece.is_none() {
-            return Err(ChessError::InvalidMove(
-                "no piece at from position".to_string(),
-            ));
+        if let Some(result) = self.result {
+            return Err(ChessError::GameOver(result));
         }
-        if from_cell.piece.as_ref().unwrap().colour != self.chess_board.turn {
-            return Err(ChessError::InvalidMove(
-                "cannot move opponent's piece".to_string(),
-            ));
+        self.check_legal(move_)?;
+        #[cfg(feature = "std")]
+        if let Some(clock) = &mut self.clock
+            && !clock.press(starting_turn)
+        {
+            // the flag fell before the move was made, so it does not count
+            let result = GameResult::win_for(starting_turn.flip(), Termination::Timeout);
+            self.result = Some(result);
+            self.observers.emit(GameEvent::GameEnded(result));
+            return Ok(GameState {
+                status: self.status(),
+                board: &self.chess_board,
+            });
         }
+        // in Chess960 castling moves the king onto its own rook
+        let captured = self
+            .chess_board
+            .get(Square::from(to))
+            .filter(|piece| piece.colour != starting_turn);
 
-        let (res, reset_half_clock) = if !self.get_available_moves(from)?.contains(&to) {
-            Err(ChessError::InvalidMove("".to_string()))
+        let reset_half_clock = if let Some(side) = self.castling_side(&from, &to) {
+            self.moves.push(Move { from, to });
+            self.castle(starting_turn, side);
+            self.chess_board.turn = self.chess_board.turn.flip();
+
+            false
         } else {
             self.moves.push(Move { from, to });
 
             // Move the piece on the board
-            let (old_x, old_y) = from.board_position();
-            let (new_x, new_y) = to.board_position();
-            let taken_cell = self.chess_board.board[new_y][new_x];
-            let old_cell = self.chess_board.board[old_y][old_x];
-            self.chess_board.board[new_y][new_x] = Cell {
-                piece: old_cell.piece,
-                colour: taken_cell.colour,
-            };
-
-            self.chess_board.board[old_y][old_x] = Cell {
-                piece: None,
-                colour: old_cell.colour,
-            };
-            if let Some(taken_piece) = taken_cell.piece {
+            let (old, new) = (Square::from(from), Square::from(to));
+            let taken_piece = self.chess_board.get(new);
+            let moved_piece = self.chess_board.get(old);
+            self.chess_board
+                .set(new, moved_piece.map(|p| ChessPiece { moved: true, ..p }));
+            self.chess_board.set(old, None);
+            if let Some(taken_piece) = taken_piece {
                 self.taken_pieces.push(taken_piece);
             }
+            if moved_piece.is_some_and(|p| p.kind == ChessPieceKind::King) {
+                self.chess_board.castling.clear(starting_turn);
+            }
+            self.chess_board.castling.remove_rook(&from);
+            self.chess_board.castling.remove_rook(&to);
             self.chess_board.turn = self.chess_board.turn.flip();
 
-            let reset_half_clock = old_cell
-                .piece
-                .map(|p| p.kind == ChessPieceKind::Pawn)
-                .unwrap_or(false)
-                || taken_cell.piece.is_some();
-
-            Ok((
-                GameState {
-                    status: GameStatus::Ongoing,
-                    board: &self.chess_board,
-                },
-                reset_half_clock,
-            ))
-        }?;
+            moved_piece.is_some_and(|p| p.kind == ChessPieceKind::Pawn) || taken_piece.is_some()
+        };
         if starting_turn == ChessColour::Black {
             self.full_move_count += 1;
         }
@@ -140,8 +823,185 @@ impl ChessGame {
         } else {
             self.half_move_clock += 1;
         }
+        self.tree.insert(&self.moves);
+        self.position_history.push(self.position_key());
+        self.result = self.detect_result();
+
+        self.observers.emit(GameEvent::MovePlayed {
+            mv: *move_,
+            colour: starting_turn,
+        });
+        if let Some(piece) = captured {
+            self.observers
+                .emit(GameEvent::PieceCaptured { piece, square: to });
+        }
+        let turn = self.chess_board.turn;
+        if self.chess_board.is_in_check(turn) {
+            self.observers.emit(GameEvent::Check(turn));
+        }
+        if let Some(result) = self.result {
+            self.observers.emit(GameEvent::GameEnded(result));
+        }
+
+        Ok(GameState {
+            status: self.status(),
+            board: &self.chess_board,
+        })
+    }
 
-        Ok(res)
+    /// The result of the game, if it has finished.
+    pub fn result(&self) -> Option<GameResult> {
+        self.result
+    }
+
+    pub fn status(&self) -> GameStatus {
+        match self.result {
+            Some(result) => GameStatus::Finished(result),
+            None => GameStatus::Ongoing,
+        }
+    }
+
+    /// Play the rest of the game with the given time control, starting the clock
+    /// for the side to move.
+    #[cfg(feature = "std")]
+    pub fn set_time_control(&mut self, control: TimeControl) {
+        self.set_clock(Clock::new(control));
+    }
+
+    /// Play the rest of the game on `clock`, e.g. one saved part way through a
+    /// game, starting it for the side to move.
+    #[cfg(feature = "std")]
+    pub fn set_clock(&mut self, mut clock: Clock) {
+        clock.start();
+        self.clock = Some(clock);
+    }
+
+    #[cfg(feature = "std")]
+    pub fn clock(&self) -> Option<&Clock> {
+        self.clock.as_ref()
+    }
+
+    /// Check whether the side to move has run out of time while thinking, ending
+    /// the game if so.
+    #[cfg(feature = "std")]
+    pub fn check_timeout(&mut self) -> Option<GameResult> {
+        let turn = self.chess_board.turn;
+        if self.result.is_none() && self.clock.as_ref().is_some_and(|c| c.is_flagged(turn)) {
+            let result = GameResult::win_for(turn.flip(), Termination::Timeout);
+            self.result = Some(result);
+            self.observers.emit(GameEvent::GameEnded(result));
+        }
+        self.result
+    }
+
+    /// Resign the game on behalf of `colour`.
+    pub fn resign(&mut self, colour: ChessColour) -> Result<GameResult, ChessError> {
+        self.finish(GameResult::win_for(colour.flip(), Termination::Resignation))
+    }
+
+    /// End the game as a draw agreed between both players.
+    pub fn agree_draw(&mut self) -> Result<GameResult, ChessError> {
+        self.finish(GameResult::Draw(Termination::Agreement))
+    }
+
+    /// End the game early as a win for `winner`, or a draw if `None`.
+    pub fn adjudicate(&mut self, winner: Option<ChessColour>) -> Result<GameResult, ChessError> {
+        self.finish(match winner {
+            Some(colour) => GameResult::win_for(colour, Termination::Adjudication),
+            None => GameResult::Draw(Termination::Adjudication),
+        })
+    }
+
+    /// End the game with `result`, as recorded elsewhere, e.g. by a PGN
+    /// file's `Result` tag.
+    pub fn record_result(&mut self, result: GameResult) -> Result<GameResult, ChessError> {
+        self.finish(result)
+    }
+
+    fn finish(&mut self, result: GameResult) -> Result<GameResult, ChessError> {
+        if let Some(existing) = self.result {
+            return Err(ChessError::GameOver(existing));
+        }
+        self.result = Some(result);
+        self.observers.emit(GameEvent::GameEnded(result));
+        Ok(result)
+    }
+
+    /// Call `handler` with each [`GameEvent`] from now on, e.g. to play a sound
+    /// on a capture. Handlers aren't copied to clones of the game.
+    pub fn subscribe(&mut self, handler: impl FnMut(&GameEvent) + Send + 'static) {
+        self.observers.push(Box::new(handler));
+    }
+
+    /// Receive each [`GameEvent`] from now on, e.g. on another thread to send
+    /// the game over the network. The events stop once the receiver is
+    /// dropped.
+    #[cfg(feature = "std")]
+    pub fn event_channel(&mut self) -> Receiver<GameEvent> {
+        let (sender, receiver) = std::sync::mpsc::channel();
+        self.subscribe(move |event| {
+            sender.send(event.clone()).ok();
+        });
+        receiver
+    }
+
+    /// Whether the side to move has any legal move.
+    pub fn has_legal_moves(&self) -> bool {
+        let turn = self.chess_board.turn;
+        self.chess_board
+            .pieces()
+            .filter(|(_, cell)| cell.piece.is_some_and(|p| p.colour == turn))
+            .any(|(pos, _)| {
+                self.get_available_moves(pos)
+                    .is_ok_and(|moves| !moves.is_empty())
+            })
+    }
+
+    fn position_key(&self) -> PositionKey {
+        zobrist::hash(&self.chess_board)
+    }
+
+    /// Work out whether the position after the last move ends the game.
+    fn detect_result(&self) -> Option<GameResult> {
+        let turn = self.chess_board.turn;
+        if !self.has_legal_moves() {
+            return Some(if self.chess_board.is_in_check(turn) {
+                GameResult::win_for(turn.flip(), Termination::Checkmate)
+            } else {
+                GameResult::Draw(Termination::Stalemate)
+            });
+        }
+        if self.half_move_clock >= 100 {
+            return Some(GameResult::Draw(Termination::FiftyMoveRule));
+        }
+        let key = self.position_key();
+        if self.position_history.iter().filter(|k| **k == key).count() >= 3 {
+            return Some(GameResult::Draw(Termination::Repetition));
+        }
+        if self.is_insufficient_material() {
+            return Some(GameResult::Draw(Termination::InsufficientMaterial));
+        }
+        None
+    }
+
+    /// Whether neither side can possibly deliver mate: bare kings, a single
+    /// minor piece against a bare king, or nothing but bishops besides the
+    /// kings, all on squares of one colour.
+    fn is_insufficient_material(&self) -> bool {
+        let others: Vec<(Square, ChessPieceKind)> = self
+            .chess_board
+            .pieces()
+            .filter_map(|(position, cell)| Some((Square::from(position), cell.piece?.kind)))
+            .filter(|(_, kind)| *kind != ChessPieceKind::King)
+            .collect();
+        let dark = |square: Square| (square.rank() + square.file()).is_multiple_of(2);
+        match others.as_slice() {
+            [] => true,
+            [(_, kind)] => matches!(kind, ChessPieceKind::Bishop | ChessPieceKind::Knight),
+            [(first, _), ..] => others.iter().all(|&(square, kind)| {
+                kind == ChessPieceKind::Bishop && dark(square) == dark(*first)
+            }),
+        }
     }
 
     /// Get the available moves for a piece at the given position.
@@ -149,30 +1009,22 @@ impl ChessGame {
         let cell = self
             .chess_board
             .get_piece_at(&pos)
-            .ok_or(ChessError::InvalidMove("cell does not exist".to_string()))?;
+            .ok_or(ChessError::NoPieceAtSquare(pos))?;
         if let Some(piece) = cell.piece {
-            let raw_moves = match piece.kind {
-                ChessPieceKind::Pawn => self.available_move_for_pawn(&pos, &piece),
-                ChessPieceKind::Knight => self.available_move_for_knight(&pos, &piece),
-                ChessPieceKind::Bishop => Ok(self.available_move_for_bishop(&pos, &piece)),
-                ChessPieceKind::Rook => Ok(self.available_move_for_rook(&pos, &piece)),
-                ChessPieceKind::Queen => Ok(self.available_move_for_queen(&pos, &piece)),
-                ChessPieceKind::King => Ok(self.available_move_for_king(&pos, &piece)),
-            }?
-            .into_iter()
-            .filter(|m| {
-                // filter out moves that are not valid because of other pieces
-                if let Some(cell) = self.chess_board.get_piece_at(m) {
-                    cell.piece.is_none()
-                        || cell
-                            .piece
-                            .map(|p| p.colour != piece.colour)
-                            .unwrap_or(false)
-                } else {
-                    false
-                }
-            })
-            .collect();
+            let raw_moves = self
+                .reachable_squares(&pos, &piece)?
+                .into_iter()
+                .filter(|m| !self.leaves_king_in_check(&pos, m, &piece))
+                .collect::<Vec<_>>();
+
+            if piece.kind == ChessPieceKind::King {
+                // castling moves are added after filtering as in Chess960 they are
+                // encoded as the king moving onto its own rook
+                return Ok(raw_moves
+                    .into_iter()
+                    .chain(self.castling_moves(&pos, &piece))
+                    .collect());
+            }
 
             Ok(raw_moves)
         } else {
@@ -180,6 +1032,73 @@ impl ChessGame {
         }
     }
 
+    /// The squares `piece` on `pos` could move to if it didn't matter whether
+    /// that left its king in check, not counting castling.
+    fn reachable_squares(
+        &self,
+        pos: &Position,
+        piece: &ChessPiece,
+    ) -> Result<Vec<Position>, ChessError> {
+        Ok(match piece.kind {
+            ChessPieceKind::Pawn => self.available_move_for_pawn(pos, piece),
+            ChessPieceKind::Knight => self.available_move_for_knight(pos),
+            ChessPieceKind::Bishop => Ok(self.available_move_for_bishop(pos)),
+            ChessPieceKind::Rook => Ok(self.available_move_for_rook(pos)),
+            ChessPieceKind::Queen => Ok(self.available_move_for_queen(pos)),
+            ChessPieceKind::King => Ok(self.available_move_for_king(pos)),
+        }?
+        .into_iter()
+        .filter(|m| {
+            // filter out moves that are not valid because of other pieces
+            if let Some(cell) = self.chess_board.get_piece_at(m) {
+                cell.piece.is_none()
+                    || cell
+                        .piece
+                        .map(|p| p.colour != piece.colour)
+                        .unwrap_or(false)
+            } else {
+                false
+            }
+        })
+        .collect())
+    }
+
+    /// Whether `mv` is legal for the side to move, and if not, why not.
+    pub fn check_legal(&self, mv: &Move) -> Result<(), ChessError> {
+        let turn = self.chess_board.turn;
+        let piece = self
+            .chess_board
+            .get(Square::from(mv.from))
+            .ok_or(ChessError::NoPieceAtSquare(mv.from))?;
+        if piece.colour != turn {
+            return Err(ChessError::WrongColourToMove {
+                square: mv.from,
+                turn,
+            });
+        }
+        if self.get_available_moves(mv.from)?.contains(&mv.to) {
+            return Ok(());
+        }
+        let reason = if self.reachable_squares(&mv.from, &piece)?.contains(&mv.to) {
+            IllegalMoveReason::LeavesKingInCheck
+        } else {
+            IllegalMoveReason::Unreachable
+        };
+        Err(ChessError::IllegalMove {
+            from: mv.from,
+            to: mv.to,
+            reason,
+        })
+    }
+
+    /// Whether moving `piece` from `from` to `to` would leave its own king attacked.
+    fn leaves_king_in_check(&self, from: &Position, to: &Position, piece: &ChessPiece) -> bool {
+        let mut board = self.chess_board.clone();
+        board.set(Square::from(*from), None);
+        board.set(Square::from(*to), Some(*piece));
+        board.is_in_check(piece.colour)
+    }
+
     fn available_move_for_pawn(
         &self,
         pos: &Position,
@@ -187,52 +1106,165 @@ impl ChessGame {
     ) -> Result<Vec<Position>, ChessError> {
         let direction = piece.colour.direction();
         let mut available_moves = Vec::new();
-        if let Ok(next_row) = pos.row.try_add(direction) {
-            available_moves.push(Position {
-                row: next_row,
-                column: pos.column,
-            })
-        }
-        if !piece.moved {
-            let double_move_row = pos.row.try_add(direction)?.try_add(direction)?;
-            available_moves.push(Position {
-                row: double_move_row,
-                column: pos.column,
-            });
+        let is_empty = |pos: &Position| self.chess_board.get(Square::from(*pos)).is_none();
+        if let Ok(next) = pos.add_offset(direction, 0)
+            && is_empty(&next)
+        {
+            available_moves.push(next);
+            // pawns on their starting row may advance two squares
+            let starting_row = piece.colour.back_row().try_add(direction)?;
+            if pos.row == starting_row
+                && let Ok(double_move) = next.add_offset(direction, 0)
+                && is_empty(&double_move)
+            {
+                available_moves.push(double_move);
+            }
         }
 
         // handle diagonal capture for the pawn
         for i in [-1, 1] {
-            if let Ok(diag) = pos.add_offset(direction, i) {
-                if let Some(cell) = self.chess_board.get_piece_at(&diag) {
-                    if cell
-                        .piece
-                        .map(|p| p.colour != piece.colour)
-                        .unwrap_or(false)
-                    {
-                        available_moves.push(diag)
-                    }
-                }
+            if let Ok(diag) = pos.add_offset(direction, i)
+                && let Some(cell) = self.chess_board.get_piece_at(&diag)
+                && cell.piece.is_some_and(|p| p.colour != piece.colour)
+            {
+                available_moves.push(diag)
             }
         }
 
         Ok(available_moves)
     }
 
-    fn available_move_for_king(&self, pos: &Position, piece: &ChessPiece) -> Vec<Position> {
-        // TODO: this does not prevent the king moving to be in check
-        // TODO: this does not handle castling
-        vec![(1, 0), (-1, 0), (0, 1), (0, -1)]
+    fn available_move_for_king(&self, pos: &Position) -> Vec<Position> {
+        vec![
+            (1, 0),
+            (-1, 0),
+            (0, 1),
+            (0, -1),
+            (1, 1),
+            (-1, 1),
+            (-1, -1),
+            (1, -1),
+        ]
+        .into_iter()
+        .flat_map(|(j, i)| pos.add_offset(j, i))
+        .collect()
+    }
+
+    /// The castling moves available to the king at `pos`. In standard chess these
+    /// are the squares the king lands on, in Chess960 they are the rook's squares.
+    fn castling_moves(&self, pos: &Position, piece: &ChessPiece) -> Vec<Position> {
+        [CastlingSide::King, CastlingSide::Queen]
             .into_iter()
-            .flat_map(|(j, i)| pos.add_offset(j, i))
+            .filter(|side| self.can_castle(pos, piece.colour, *side))
+            .filter_map(|side| self.castling_target(piece.colour, side))
             .collect()
     }
 
-    fn available_move_for_knight(
-        &self,
-        pos: &Position,
-        piece: &ChessPiece,
-    ) -> Result<Vec<Position>, ChessError> {
+    /// The square the king is moved to in order to castle to the given side.
+    fn castling_target(&self, colour: ChessColour, side: CastlingSide) -> Option<Position> {
+        let rook_column = self.chess_board.castling.get(colour, side)?;
+        let column = match self.variant {
+            Variant::Standard => side.king_destination(),
+            Variant::Chess960 => rook_column,
+        };
+        Some(Position {
+            row: colour.back_row(),
+            column,
+        })
+    }
+
+    /// If moving the king from `from` to `to` is a castling move, the side being castled to.
+    fn castling_side(&self, from: &Position, to: &Position) -> Option<CastlingSide> {
+        let piece = self.chess_board.get_piece_at(from)?.piece?;
+        if piece.kind != ChessPieceKind::King {
+            return None;
+        }
+        [CastlingSide::King, CastlingSide::Queen]
+            .into_iter()
+            .find(|side| {
+                self.castling_target(piece.colour, *side) == Some(*to)
+                    && self.can_castle(from, piece.colour, *side)
+            })
+    }
+
+    fn can_castle(&self, king: &Position, colour: ChessColour, side: CastlingSide) -> bool {
+        let Some(rook_column) = self.chess_board.castling.get(colour, side) else {
+            return false;
+        };
+        let row = colour.back_row();
+        if king.row != row {
+            return false;
+        }
+        let rook = Position {
+            row,
+            column: rook_column,
+        };
+        let is_own_rook = self
+            .chess_board
+            .get(Square::from(rook))
+            .is_some_and(|p| p.kind == ChessPieceKind::Rook && p.colour == colour);
+        if !is_own_rook || self.chess_board.is_in_check(colour) {
+            return false;
+        }
+
+        // evaluate the path with the king and rook lifted off the board, so
+        // neither blocks the other and attacks through the king's square count
+        let mut board = self.chess_board.clone();
+        board.set(Square::from(*king), None);
+        board.set(Square::from(rook), None);
+
+        let span = |a: Column, b: Column| {
+            let (a, b) = (usize::from(a), usize::from(b));
+            (a.min(b)..=a.max(b)).map(|c| Column::try_from(c as isize).unwrap())
+        };
+        let king_path = span(king.column, side.king_destination());
+        let rook_path = span(rook_column, side.rook_destination());
+        let path_is_clear = king_path
+            .clone()
+            .chain(rook_path)
+            .all(|column| board.get(Square::from(Position { row, column })).is_none());
+        path_is_clear
+            && king_path
+                .into_iter()
+                .all(|column| !board.is_square_attacked(&Position { row, column }, colour.flip()))
+    }
+
+    fn castle(&mut self, colour: ChessColour, side: CastlingSide) {
+        let row = colour.back_row();
+        let king = self
+            .chess_board
+            .king_position(colour)
+            .expect("castling requires a king");
+        let rook = Position {
+            row,
+            column: self
+                .chess_board
+                .castling
+                .get(colour, side)
+                .expect("castling requires the right to castle"),
+        };
+        let king_piece = self.chess_board.get(Square::from(king));
+        let rook_piece = self.chess_board.get(Square::from(rook));
+        self.chess_board.set(Square::from(king), None);
+        self.chess_board.set(Square::from(rook), None);
+        self.chess_board.set(
+            Square::from(Position {
+                row,
+                column: side.king_destination(),
+            }),
+            king_piece.map(|p| ChessPiece { moved: true, ..p }),
+        );
+        self.chess_board.set(
+            Square::from(Position {
+                row,
+                column: side.rook_destination(),
+            }),
+            rook_piece.map(|p| ChessPiece { moved: true, ..p }),
+        );
+        self.chess_board.castling.clear(colour);
+    }
+
+    fn available_move_for_knight(&self, pos: &Position) -> Result<Vec<Position>, ChessError> {
         Ok([
             (1, 2),
             (1, -2),
@@ -248,21 +1280,21 @@ impl ChessGame {
         .collect())
     }
 
-    fn available_move_for_bishop(&self, pos: &Position, piece: &ChessPiece) -> Vec<Position> {
+    fn available_move_for_bishop(&self, pos: &Position) -> Vec<Position> {
         vec![(1, 1), (-1, 1), (-1, -1), (1, -1)]
             .into_iter()
             .flat_map(|(i, j)| self.moves_with_offset(pos, i, j))
             .collect()
     }
 
-    fn available_move_for_rook(&self, pos: &Position, piece: &ChessPiece) -> Vec<Position> {
+    fn available_move_for_rook(&self, pos: &Position) -> Vec<Position> {
         vec![(1, 0), (0, 1), (-1, 0), (0, -1)]
             .into_iter()
             .flat_map(|(i, j)| self.moves_with_offset(pos, i, j))
             .collect()
     }
 
-    fn available_move_for_queen(&self, pos: &Position, piece: &ChessPiece) -> Vec<Position> {
+    fn available_move_for_queen(&self, pos: &Position) -> Vec<Position> {
         vec![
             (1, 0),
             (0, 1),
@@ -281,15 +1313,670 @@ impl ChessGame {
     fn moves_with_offset(&self, pos: &Position, i: isize, j: isize) -> Vec<Position> {
         let mut moves = Vec::new();
         for n in 1..8 {
-            if let Ok(npos) = pos.add_offset(i * n, j * n) {
-                if let Some(cell) = self.chess_board.get_piece_at(&npos) {
-                    moves.push(npos);
-                    if cell.piece.is_some() {
-                        return moves;
-                    }
+            if let Ok(npos) = pos.add_offset(i * n, j * n)
+                && let Some(cell) = self.chess_board.get_piece_at(&npos)
+            {
+                moves.push(npos);
+                if cell.piece.is_some() {
+                    return moves;
                 }
             }
         }
         moves
     }
 }
+
+/// `value` with its quotes and backslashes escaped, to go inside a PGN tag.
+fn escape_tag(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+/// Append the movetext of `line`, played from `game`, to `chunks`, with each
+/// move's NAGs and comment after it and each variation in brackets after the
+/// move it replaces. Black's moves are
+/// numbered at the start of a line and after a comment or variation.
+fn write_line(
+    game: &ChessGame,
+    line: &[MoveNode],
+    mut numbered: bool,
+    chunks: &mut Vec<String>,
+) -> Result<(), ChessError> {
+    let mut game = game.clone();
+    for node in line {
+        let number = game.full_move_count;
+        match game.chess_board.turn {
+            ChessColour::White => chunks.push(format!("{number}.")),
+            ChessColour::Black if numbered => chunks.push(format!("{number}...")),
+            ChessColour::Black => {}
+        }
+        chunks.push(game.san(&node.mv)?);
+        chunks.extend(node.nags.iter().map(|nag| format!("${nag}")));
+        let comment = node.pgn_comment();
+        if let Some(comment) = &comment {
+            chunks.push(format!("{{{comment}}}"));
+        }
+        for variation in &node.variations {
+            let mut inner = Vec::new();
+            write_line(&game, variation, true, &mut inner)?;
+            chunks.push(format!("({})", inner.join(" ")));
+        }
+        numbered = comment.is_some() || !node.variations.is_empty();
+        game.make_move(&node.mv)?;
+    }
+    Ok(())
+}
+
+/// The board, half move clock and full move count in a FEN string, which
+/// may not be a legal position.
+fn parse_fen(fen: &str) -> Result<(ChessBoard, u32, u32), ChessError> {
+    let invalid = |reason: &str| ChessError::InvalidFen {
+        fen: fen.to_string(),
+        reason: reason.to_string(),
+    };
+    let mut fields = fen.split_whitespace();
+    let placement = fields
+        .next()
+        .ok_or_else(|| invalid("missing piece placement"))?;
+    let turn = fields.next().unwrap_or("w");
+    let castling = fields.next().unwrap_or("-");
+    let _en_passant = fields.next().unwrap_or("-");
+    let half_move_clock = fields
+        .next()
+        .map(u32::from_str)
+        .transpose()
+        .map_err(|_| invalid("half move clock is not a number"))?
+        .unwrap_or(0);
+    let full_move_count = fields
+        .next()
+        .map(u32::from_str)
+        .transpose()
+        .map_err(|_| invalid("full move count is not a number"))?
+        .unwrap_or(1);
+
+    let ranks: Vec<&str> = placement.split('/').collect();
+    if ranks.len() != 8 {
+        return Err(invalid("expected 8 ranks"));
+    }
+    let mut chess_board = ChessBoard::empty();
+    for (i, rank) in ranks.iter().enumerate() {
+        let row = Row::try_from(7 - i as isize)?;
+        let mut column = 0;
+        for c in rank.chars() {
+            let (piece, count) = match c.to_digit(10) {
+                Some(empty) => (None, empty as isize),
+                None => (Some(ChessPiece::try_from(c)?), 1),
+            };
+            for _ in 0..count {
+                let position = Position {
+                    row,
+                    column: Column::try_from(column)
+                        .map_err(|_| invalid("too many columns in rank"))?,
+                };
+                chess_board.set(Square::from(position), piece);
+                column += 1;
+            }
+        }
+        if column != 8 {
+            return Err(invalid("rank does not have 8 columns"));
+        }
+    }
+
+    chess_board.turn = match turn {
+        "w" => ChessColour::White,
+        "b" => ChessColour::Black,
+        _ => return Err(invalid("side to move must be 'w' or 'b'")),
+    };
+    chess_board.castling =
+        parse_castling_rights(&chess_board, castling).map_err(|e| invalid(&e))?;
+    Ok((chess_board, half_move_clock, full_move_count))
+}
+
+/// Parse the castling field of a (X-)FEN string against the given board,
+/// failing with what is wrong with it.
+fn parse_castling_rights(board: &ChessBoard, field: &str) -> Result<CastlingRights, String> {
+    let mut rights = CastlingRights::default();
+    if field == "-" {
+        return Ok(rights);
+    }
+    for c in field.chars() {
+        let colour = if c.is_ascii_uppercase() {
+            ChessColour::White
+        } else {
+            ChessColour::Black
+        };
+        let row = colour.back_row();
+        let king = board
+            .king_position(colour)
+            .filter(|k| k.row == row)
+            .ok_or_else(|| format!("castling right '{c}' without a king on its back row"))?;
+        let rooks = rook_columns(board, colour);
+        let (side, column) = match c.to_ascii_lowercase() {
+            'k' => (
+                CastlingSide::King,
+                rooks
+                    .iter()
+                    .rev()
+                    .find(|r| **r > usize::from(king.column))
+                    .copied(),
+            ),
+            'q' => (
+                CastlingSide::Queen,
+                rooks
+                    .iter()
+                    .find(|r| **r < usize::from(king.column))
+                    .copied(),
+            ),
+            _ => {
+                let column = Column::from_str(&c.to_string())
+                    .map_err(|_| format!("invalid castling right '{c}'"))?;
+                let side = if usize::from(column) > usize::from(king.column) {
+                    CastlingSide::King
+                } else {
+                    CastlingSide::Queen
+                };
+                (
+                    side,
+                    rooks.iter().find(|r| **r == usize::from(column)).copied(),
+                )
+            }
+        };
+        let column = column.ok_or_else(|| format!("no rook for castling right '{c}'"))?;
+        rights.set(
+            colour,
+            side,
+            Some(Column::try_from(column as isize).map_err(|e| e.to_string())?),
+        );
+    }
+    Ok(rights)
+}
+
+fn format_castling_rights(board: &ChessBoard) -> String {
+    let mut field = String::new();
+    for colour in [ChessColour::White, ChessColour::Black] {
+        let rooks = rook_columns(board, colour);
+        for side in [CastlingSide::King, CastlingSide::Queen] {
+            let Some(column) = board.castling.get(colour, side) else {
+                continue;
+            };
+            // KQkq refers to the outermost rook, anything else needs the column
+            let outermost = match side {
+                CastlingSide::King => rooks.last(),
+                CastlingSide::Queen => rooks.first(),
+            };
+            let c = if outermost == Some(&usize::from(column)) {
+                match side {
+                    CastlingSide::King => 'k',
+                    CastlingSide::Queen => 'q',
+                }
+            } else {
+                column.to_string().chars().next().unwrap()
+            };
+            field.push(if colour == ChessColour::White {
+                c.to_ascii_uppercase()
+            } else {
+                c
+            });
+        }
+    }
+    if field.is_empty() {
+        field.push('-');
+    }
+    field
+}
+
+/// The columns of the rooks of the given colour on its back row, in ascending order.
+fn rook_columns(board: &ChessBoard, colour: ChessColour) -> Vec<usize> {
+    let row = colour.back_row();
+    board
+        .pieces()
+        .filter(|(pos, cell)| {
+            pos.row == row
+                && cell
+                    .piece
+                    .is_some_and(|p| p.kind == ChessPieceKind::Rook && p.colour == colour)
+        })
+        .map(|(pos, _)| usize::from(pos.column))
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const START_FEN: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+
+    #[test]
+    fn test_serde_round_trip() {
+        let mut game = ChessGame::default();
+        game.set_time_control(TimeControl::from_str("5+3").unwrap());
+        for mv in ["e2e4", "e7e5", "g1f3"] {
+            game.make_move(&Move::from_uci(mv).unwrap()).unwrap();
+        }
+        let json = serde_json::to_string(&game).unwrap();
+        let mut restored: ChessGame = serde_json::from_str(&json).unwrap();
+        assert_eq!(restored.fen(), game.fen());
+        assert_eq!(restored.played_moves(), game.played_moves());
+        assert_eq!(
+            restored.clock().map(Clock::control),
+            game.clock().map(Clock::control)
+        );
+        restored
+            .make_move(&Move::from_uci("b8c6").unwrap())
+            .unwrap();
+        assert_eq!(restored.undo().unwrap(), Move::from_uci("b8c6").unwrap());
+    }
+
+    #[test]
+    fn test_illegal_move_errors() {
+        let mut game = ChessGame::default();
+        let play = |game: &mut ChessGame, mv: &str| {
+            game.make_move(&Move::from_uci(mv).unwrap()).map(|_| ())
+        };
+        assert!(matches!(
+            play(&mut game, "e3e4"),
+            Err(ChessError::NoPieceAtSquare(_))
+        ));
+        assert!(matches!(
+            play(&mut game, "e7e5"),
+            Err(ChessError::WrongColourToMove {
+                turn: ChessColour::White,
+                ..
+            })
+        ));
+        assert!(matches!(
+            play(&mut game, "e2e5"),
+            Err(ChessError::IllegalMove {
+                reason: IllegalMoveReason::Unreachable,
+                ..
+            })
+        ));
+        // the knight on c6 is pinned to the king by the bishop on b5
+        for mv in ["e2e4", "d7d5", "f1b5", "b8c6", "a2a3"] {
+            play(&mut game, mv).unwrap();
+        }
+        assert!(matches!(
+            play(&mut game, "c6d4"),
+            Err(ChessError::IllegalMove {
+                reason: IllegalMoveReason::LeavesKingInCheck,
+                ..
+            })
+        ));
+        assert!(matches!(
+            ChessGame::default().undo(),
+            Err(ChessError::NothingToUndo)
+        ));
+    }
+
+    #[test]
+    fn test_events() {
+        let mut game = ChessGame::default();
+        let events = game.event_channel();
+        for mv in ["e2e4", "d7d5", "e4d5"] {
+            game.make_move(&Move::from_uci(mv).unwrap()).unwrap();
+        }
+        // a clone plays its moves without telling the original's subscribers
+        let mut search = game.clone();
+        search.make_move(&Move::from_uci("d8d5").unwrap()).unwrap();
+        game.resign(ChessColour::Black).unwrap();
+
+        let pawn = ChessPiece {
+            kind: ChessPieceKind::Pawn,
+            colour: ChessColour::Black,
+            moved: true,
+        };
+        let played = |mv: &str, colour| GameEvent::MovePlayed {
+            mv: Move::from_uci(mv).unwrap(),
+            colour,
+        };
+        assert_eq!(
+            events.try_iter().collect::<Vec<_>>(),
+            [
+                played("e2e4", ChessColour::White),
+                played("d7d5", ChessColour::Black),
+                played("e4d5", ChessColour::White),
+                GameEvent::PieceCaptured {
+                    piece: pawn,
+                    square: Position::from_str("d5").unwrap()
+                },
+                GameEvent::GameEnded(GameResult::WhiteWins(Termination::Resignation)),
+            ]
+        );
+
+        let mut game = ChessGame::default();
+        let events = game.event_channel();
+        for mv in ["f2f3", "e7e5", "g2g4", "d8h4"] {
+            game.make_move(&Move::from_uci(mv).unwrap()).unwrap();
+        }
+        assert_eq!(
+            events.try_iter().skip(4).collect::<Vec<_>>(),
+            [
+                GameEvent::Check(ChessColour::White),
+                GameEvent::GameEnded(GameResult::BlackWins(Termination::Checkmate)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_builder() {
+        let game = ChessGame::builder().build().unwrap();
+        assert_eq!(game.fen(), START_FEN);
+        assert_eq!(game.start_fen(), None);
+
+        let game = ChessGame::builder()
+            .turn(ChessColour::Black)
+            .castling(CastlingRights::default())
+            .build()
+            .unwrap();
+        assert_eq!(
+            game.start_fen(),
+            Some("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR b - - 0 1")
+        );
+
+        let game = ChessGame::builder().chess960(0).build().unwrap();
+        assert_eq!(game.variant(), Variant::Chess960);
+        assert_eq!(
+            game.fen(),
+            "bbqnnrkr/pppppppp/8/8/8/8/PPPPPPPP/BBQNNRKR w KQkq - 0 1"
+        );
+        assert!(ChessGame::builder().chess960(960).build().is_err());
+
+        // Black is in check, so it can't be White's move
+        let checked = "4k3/8/8/8/8/8/4R3/4K3 b - - 0 1";
+        assert!(ChessGame::builder().fen(checked).build().is_ok());
+        assert!(matches!(
+            ChessGame::builder()
+                .fen(checked)
+                .turn(ChessColour::White)
+                .build(),
+            Err(ChessError::InvalidPosition(_))
+        ));
+    }
+
+    #[test]
+    fn test_fen_round_trip() {
+        assert_eq!(ChessGame::default().fen(), START_FEN);
+        let shredder = "bqnb1rkr/pp3ppp/3ppn2/2p5/5P2/P2P4/NPP1P1PP/BQ1BNRKR w HFhf - 2 9";
+        assert_eq!(
+            ChessGame::from_fen(shredder, Variant::Chess960)
+                .unwrap()
+                .fen(),
+            shredder.replace("HFhf", "KQkq")
+        );
+        for fen in [
+            START_FEN,
+            "r3k2r/8/8/8/8/8/8/R3K2R b Kq - 4 20",
+            "bqnb1rkr/pp3ppp/3ppn2/2p5/5P2/P2P4/NPP1P1PP/BQ1BNRKR w KQkq - 2 9",
+            "1r2k1r1/8/8/8/8/8/8/RR2K2R w KBkq - 0 1",
+        ] {
+            let game = ChessGame::from_fen(fen, Variant::Chess960).unwrap();
+            assert_eq!(game.fen(), fen);
+        }
+    }
+
+    #[test]
+    fn test_from_fen_rejects_invalid_positions() {
+        let res = ChessGame::from_fen("4k3/8/8/8/8/8/8/8 w - - 0 1", Variant::Standard);
+        assert!(matches!(res, Err(ChessError::InvalidPosition(_))));
+    }
+
+    #[test]
+    fn test_checkmate_ends_the_game() {
+        let mut game = ChessGame::default();
+        for mv in ["f2f3", "e7e5", "g2g4", "d8h4"] {
+            game.make_move(&Move::from_uci(mv).unwrap()).unwrap();
+        }
+        assert_eq!(
+            game.status(),
+            GameStatus::Finished(GameResult::BlackWins(Termination::Checkmate))
+        );
+        assert!(game.make_move(&Move::from_uci("a2a3").unwrap()).is_err());
+    }
+
+    #[test]
+    fn test_insufficient_material() {
+        let take_knight = |fen: &str| {
+            let mut game = ChessGame::from_fen(fen, Variant::Standard).unwrap();
+            game.make_move(&Move::from_uci("b2e5").unwrap()).unwrap();
+            game.result()
+        };
+        // bishops on dark squares only
+        assert_eq!(
+            take_knight("4k3/8/8/4n3/8/8/1B5b/4K3 w - - 0 1"),
+            Some(GameResult::Draw(Termination::InsufficientMaterial))
+        );
+        // bishops on both colours could still help mate
+        assert_eq!(take_knight("4k3/8/8/4n3/8/8/1B4b1/4K3 w - - 0 1"), None);
+        assert_eq!(take_knight("4k2r/8/8/4n3/8/8/1B6/4K3 w - - 0 1"), None);
+        assert_eq!(
+            take_knight("4k3/8/8/4n3/8/8/1B6/4K3 w - - 0 1"),
+            Some(GameResult::Draw(Termination::InsufficientMaterial))
+        );
+    }
+
+    #[test]
+    fn test_undo_and_pgn() {
+        let mut game = ChessGame::default();
+        assert!(game.undo().is_err());
+        for mv in ["f2f3", "e7e5", "g2g4", "d8h4"] {
+            game.make_move(&Move::from_uci(mv).unwrap()).unwrap();
+        }
+        assert!(game.result().is_some());
+        assert_eq!(
+            game.pgn().unwrap(),
+            "[Event \"Casual game\"]
+[Site \"?\"]
+[Date \"????.??.??\"]
+[Round \"-\"]
+[White \"?\"]
+[Black \"?\"]
+[Result \"0-1\"]
+
+1. f3 e5 2. g4 Qh4# 0-1
+"
+        );
+
+        assert_eq!(game.undo().unwrap().to_uci(), "d8h4");
+        assert_eq!(game.result(), None);
+        assert_eq!(game.played_moves().len(), 3);
+        assert_eq!(game.get_board().turn, ChessColour::Black);
+        assert!(game.pgn().unwrap().ends_with("\n1. f3 e5 2. g4 *\n"));
+        let pgn = game
+            .pgn_with_tags(&[("White", "Fool"), ("Round", "2"), ("Result", "1-0")])
+            .unwrap();
+        assert!(pgn.contains("[Round \"2\"]\n[White \"Fool\"]\n[Black \"?\"]\n[Result \"*\"]"));
+    }
+
+    #[test]
+    fn test_variations() {
+        let uci = |mv: &str| Move::from_uci(mv).unwrap();
+        let mut game = ChessGame::default();
+        for mv in ["e2e4", "e7e5", "g1f3"] {
+            game.make_move(&uci(mv)).unwrap();
+        }
+        game.go_back().unwrap();
+        game.go_back().unwrap();
+        assert_eq!(game.continuations(), [uci("e7e5")]);
+        game.make_move(&uci("c7c5")).unwrap();
+        game.set_comment(Some("the Sicilian".to_string()));
+        assert_eq!(game.played_moves(), [uci("e2e4"), uci("c7c5")]);
+        assert_eq!(
+            game.tree().main_moves(),
+            [uci("e2e4"), uci("e7e5"), uci("g1f3")]
+        );
+        assert!(
+            game.pgn()
+                .unwrap()
+                .ends_with("\n1. e4 e5 (1... c5 {the Sicilian}) 2. Nf3 *\n")
+        );
+
+        game.go_to(&[uci("e2e4")]).unwrap();
+        assert_eq!(game.continuations(), [uci("e7e5"), uci("c7c5")]);
+        assert_eq!(game.go_forward().unwrap(), Some(uci("e7e5")));
+        assert_eq!(game.go_forward().unwrap(), Some(uci("g1f3")));
+        assert_eq!(game.go_forward().unwrap(), None);
+
+        // undoing deletes the move, and the variation takes the place of the
+        // line it was in
+        game.go_back().unwrap();
+        game.undo().unwrap();
+        assert_eq!(game.tree().main_moves(), [uci("e2e4"), uci("c7c5")]);
+        assert!(
+            game.pgn()
+                .unwrap()
+                .ends_with("\n1. e4 c5 {the Sicilian} *\n")
+        );
+
+        game.add_nag(5);
+        game.add_nag(5);
+        game.set_evaluation(Some(Score::Centipawns(30)));
+        assert_eq!(game.nags(), [5]);
+        assert_eq!(game.evaluation(), Some(Score::Centipawns(30)));
+        assert!(
+            game.pgn()
+                .unwrap()
+                .ends_with("\n1. e4 $5 {[%eval 0.30]} 1... c5 {the Sicilian} *\n")
+        );
+        game.go_to(&[]).unwrap();
+        game.add_nag(1);
+        assert!(game.nags().is_empty() && game.evaluation().is_none());
+    }
+
+    #[test]
+    fn test_san() {
+        let mut game = ChessGame::default();
+        for mv in [
+            "e2e4", "e7e5", "g1f3", "b8c6", "f1c4", "g8f6", "b1c3", "f8c5", "e1g1", "d7d6", "d2d3",
+            "c8g4", "c1e3", "c5e3", "f2e3", "d8d7", "c3d5", "f6d5", "c4d5", "e8c8",
+        ] {
+            game.make_move(&Move::from_uci(mv).unwrap()).unwrap();
+        }
+        assert_eq!(
+            game.numbered_moves().unwrap(),
+            [
+                "1. e4 e5",
+                "2. Nf3 Nc6",
+                "3. Bc4 Nf6",
+                "4. Nc3 Bc5",
+                "5. O-O d6",
+                "6. d3 Bg4",
+                "7. Be3 Bxe3",
+                "8. fxe3 Qd7",
+                "9. Nd5 Nxd5",
+                "10. Bxd5 O-O-O",
+            ]
+        );
+
+        // both rooks and both knights can reach the same squares
+        let game =
+            ChessGame::from_fen("k7/8/8/8/1N3N2/8/4K3/R6R b - - 3 40", Variant::Standard).unwrap();
+        let mut white = game.clone();
+        white.make_move(&Move::from_uci("a8b8").unwrap()).unwrap();
+        let san = |mv| white.san(&Move::from_uci(mv).unwrap()).unwrap();
+        assert_eq!(san("a1d1"), "Rad1");
+        assert_eq!(san("b4d5"), "Nbd5");
+        assert_eq!(san("h1h8"), "Rh8+");
+        assert!(white.san(&Move::from_uci("a1b2").unwrap()).is_err());
+        white.make_move(&Move::from_uci("h1h7").unwrap()).unwrap();
+        white.make_move(&Move::from_uci("b8c8").unwrap()).unwrap();
+        assert_eq!(white.san(&Move::from_uci("a1a8").unwrap()).unwrap(), "Ra8#");
+        assert_eq!(
+            white.numbered_moves().unwrap(),
+            ["40... Kb8", "41. Rh7 Kc8"]
+        );
+
+        let game =
+            ChessGame::from_fen("8/8/8/8/8/2k5/8/N1K1N3 w - - 0 1", Variant::Standard).unwrap();
+        assert_eq!(game.san(&Move::from_uci("e1c2").unwrap()).unwrap(), "Nec2");
+        let game =
+            ChessGame::from_fen("8/8/8/8/R7/2k5/8/R1K5 w - - 0 1", Variant::Standard).unwrap();
+        assert_eq!(game.san(&Move::from_uci("a1a2").unwrap()).unwrap(), "R1a2");
+    }
+
+    #[test]
+    fn test_cannot_leave_king_in_check() {
+        let game =
+            ChessGame::from_fen("4k3/8/8/8/8/8/4r3/4K3 w - - 0 1", Variant::Standard).unwrap();
+        let moves = game
+            .get_available_moves(Position::from_str("e1").unwrap())
+            .unwrap();
+        assert!(!moves.contains(&Position::from_str("f2").unwrap()));
+        assert!(moves.contains(&Position::from_str("e2").unwrap()));
+    }
+
+    #[test]
+    fn test_repetition_and_resignation() {
+        let mut game = ChessGame::default();
+        for mv in [
+            "g1f3", "g8f6", "f3g1", "f6g8", "g1f3", "g8f6", "f3g1", "f6g8",
+        ] {
+            game.make_move(&Move::from_uci(mv).unwrap()).unwrap();
+        }
+        assert_eq!(
+            game.result(),
+            Some(GameResult::Draw(Termination::Repetition))
+        );
+
+        let mut game = ChessGame::default();
+        assert_eq!(
+            game.resign(ChessColour::White).unwrap(),
+            GameResult::BlackWins(Termination::Resignation)
+        );
+        assert!(game.agree_draw().is_err());
+    }
+
+    #[test]
+    fn test_flag_fall_ends_the_game() {
+        let mut game = ChessGame::default();
+        game.set_time_control(TimeControl::SuddenDeath {
+            initial: std::time::Duration::ZERO,
+        });
+        let state = game.make_move(&Move::from_uci("e2e4").unwrap()).unwrap();
+        assert_eq!(
+            state.status,
+            GameStatus::Finished(GameResult::BlackWins(Termination::Timeout))
+        );
+        assert!(game.played_moves().is_empty());
+    }
+
+    #[test]
+    fn test_standard_castling() {
+        let mut game =
+            ChessGame::from_fen("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1", Variant::Standard).unwrap();
+        let e1 = Position::from_str("e1").unwrap();
+        let moves = game.get_available_moves(e1).unwrap();
+        assert!(moves.contains(&Position::from_str("g1").unwrap()));
+        assert!(moves.contains(&Position::from_str("c1").unwrap()));
+
+        game.make_move(&Move::from_uci("e1g1").unwrap()).unwrap();
+        assert_eq!(game.fen(), "r3k2r/8/8/8/8/8/8/R4RK1 b kq - 1 1");
+    }
+
+    #[test]
+    fn test_cannot_castle_through_check() {
+        let game = ChessGame::from_fen("r3k2r/8/8/8/8/8/5r2/R3K2R w KQkq - 0 1", Variant::Standard)
+            .unwrap();
+        let moves = game
+            .get_available_moves(Position::from_str("e1").unwrap())
+            .unwrap();
+        assert!(!moves.contains(&Position::from_str("g1").unwrap()));
+        assert!(moves.contains(&Position::from_str("c1").unwrap()));
+    }
+
+    #[test]
+    fn test_chess960_castling_onto_rook_square() {
+        // king on b1 with the queen side rook on a1 and king side rook on g1
+        let mut game =
+            ChessGame::from_fen("rk4r1/8/8/8/8/8/8/RK4R1 w GAga - 0 1", Variant::Chess960).unwrap();
+        game.make_move(&Move::from_uci("b1a1").unwrap()).unwrap();
+        assert_eq!(game.fen(), "rk4r1/8/8/8/8/8/8/2KR2R1 b kq - 1 1");
+        game.make_move(&Move::from_uci("b8a8").unwrap()).unwrap();
+        assert_eq!(game.fen(), "2kr2r1/8/8/8/8/8/8/2KR2R1 w - - 2 2");
+    }
+
+    #[test]
+    fn test_new_chess960_matches_index() {
+        let game = ChessGame::new_chess960(518).unwrap();
+        assert_eq!(game.fen(), START_FEN);
+        assert_eq!(game.variant(), Variant::Chess960);
+    }
+}
@@ -0,0 +1,231 @@
+//! Fetching games played online, on Lichess or chess.com, as PGN to review
+//! them here. Requests are made with `curl`, so that no TLS library has to be
+//! built in.
+
+use anyhow::Context;
+use clap::ValueEnum;
+use serde::Deserialize;
+use std::fmt::Display;
+use std::process::Command;
+
+/// A site games can be fetched from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum Site {
+    #[default]
+    Lichess,
+    #[value(name = "chess.com")]
+    ChessCom,
+}
+
+impl Display for Site {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Site::Lichess => write!(f, "Lichess"),
+            Site::ChessCom => write!(f, "chess.com"),
+        }
+    }
+}
+
+/// What to fetch.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Source {
+    /// The most recent games of a player.
+    Player { site: Site, name: String },
+    /// One Lichess game, by its eight character ID.
+    LichessGame(String),
+}
+
+impl Source {
+    /// Read `source` as the address of a game or a player's profile, or
+    /// otherwise as the name of a player on `site`.
+    pub fn parse(source: &str, site: Site) -> anyhow::Result<Self> {
+        let source = source.trim();
+        let path = source
+            .trim_start_matches("https://")
+            .trim_start_matches("http://")
+            .trim_start_matches("www.");
+        let path = path.split(['?', '#']).next().unwrap_or_default();
+        let player = |site: Site, name: &str| -> anyhow::Result<Self> {
+            let valid = !name.is_empty()
+                && name
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+            anyhow::ensure!(valid, "{name:?} isn't a {site} username");
+            Ok(Source::Player {
+                site,
+                name: name.to_string(),
+            })
+        };
+        if let Some(rest) = path.strip_prefix("lichess.org/") {
+            let mut parts = rest.split('/');
+            return match parts.next().unwrap_or_default() {
+                "@" => player(Site::Lichess, parts.next().unwrap_or_default()),
+                // /abcdefgh, or /abcdefghijkl from a player's side of the board
+                id if [8, 12].contains(&id.len())
+                    && id.chars().all(|c| c.is_ascii_alphanumeric()) =>
+                {
+                    Ok(Source::LichessGame(id[..8].to_string()))
+                }
+                _ => anyhow::bail!("{source} isn't the address of a Lichess game or player"),
+            };
+        }
+        if let Some(rest) = path.strip_prefix("chess.com/") {
+            let mut parts = rest.split('/');
+            return match parts.next().unwrap_or_default() {
+                "member" => player(Site::ChessCom, parts.next().unwrap_or_default()),
+                "game" | "analysis" => anyhow::bail!(
+                    "chess.com doesn't offer single games for download, import the player's recent games by their username instead"
+                ),
+                _ => anyhow::bail!("{source} isn't the address of a chess.com player"),
+            };
+        }
+        player(site, source)
+    }
+
+    /// The games as PGN, the most recent first, at most `max` of a player's.
+    pub fn fetch(&self, max: usize) -> anyhow::Result<String> {
+        match self {
+            Source::LichessGame(id) => get(&format!("https://lichess.org/game/export/{id}")),
+            Source::Player {
+                site: Site::Lichess,
+                name,
+            } => get(&format!(
+                "https://lichess.org/api/games/user/{name}?max={max}"
+            )),
+            Source::Player {
+                site: Site::ChessCom,
+                name,
+            } => fetch_chess_com(name, max),
+        }
+    }
+}
+
+impl Display for Source {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Source::Player { site, name } => write!(f, "{name} on {site}"),
+            Source::LichessGame(id) => write!(f, "https://lichess.org/{id}"),
+        }
+    }
+}
+
+/// The list of a chess.com player's monthly archives.
+#[derive(Deserialize)]
+struct Archives {
+    archives: Vec<String>,
+}
+
+/// chess.com keeps a player's games in monthly archives, oldest first, so
+/// read back from the latest month until there are enough.
+fn fetch_chess_com(name: &str, max: usize) -> anyhow::Result<String> {
+    let name = name.to_lowercase();
+    let list = get(&format!(
+        "https://api.chess.com/pub/player/{name}/games/archives"
+    ))?;
+    let list: Archives =
+        serde_json::from_str(&list).context("chess.com sent an unexpected list of archives")?;
+    let mut games = Vec::new();
+    for archive in list.archives.iter().rev() {
+        if games.len() >= max {
+            break;
+        }
+        let month = get(&format!("{archive}/pgn"))?;
+        games.extend(split_games(&month).into_iter().rev());
+    }
+    games.truncate(max);
+    Ok(games.join("\n\n"))
+}
+
+/// The games in `pgn`, each from its first tag.
+fn split_games(pgn: &str) -> Vec<String> {
+    let mut games: Vec<String> = Vec::new();
+    let mut in_movetext = false;
+    for line in pgn.lines() {
+        let is_tag = line.trim_start().starts_with('[');
+        // a tag after some moves starts the next game
+        if games.is_empty() || (is_tag && in_movetext) {
+            games.push(String::new());
+            in_movetext = false;
+        }
+        if !is_tag && !line.trim().is_empty() {
+            in_movetext = true;
+        }
+        let game = games.last_mut().expect("a game was just started");
+        game.push_str(line);
+        game.push('\n');
+    }
+    games.retain(|game| !game.trim().is_empty());
+    games
+}
+
+/// The body of a GET request to `url`.
+pub(crate) fn get(url: &str) -> anyhow::Result<String> {
+    let output = Command::new("curl")
+        .args(["--silent", "--show-error", "--fail", "--location"])
+        .args([
+            "--header",
+            "Accept: application/x-chess-pgn, application/json",
+        ])
+        .args(["--user-agent", concat!("chess/", env!("CARGO_PKG_VERSION"))])
+        .arg(url)
+        .output()
+        .context("could not run curl, which is needed to fetch games")?;
+    anyhow::ensure!(
+        output.status.success(),
+        "could not fetch {url}: {}",
+        String::from_utf8_lossy(&output.stderr).trim()
+    );
+    String::from_utf8(output.stdout).with_context(|| format!("{url} didn't send text"))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_source() {
+        let player = |site, name: &str| Source::Player {
+            site,
+            name: name.to_string(),
+        };
+        assert_eq!(
+            Source::parse("DrNykterstein", Site::Lichess).unwrap(),
+            player(Site::Lichess, "DrNykterstein")
+        );
+        assert_eq!(
+            Source::parse("hikaru", Site::ChessCom).unwrap(),
+            player(Site::ChessCom, "hikaru")
+        );
+        assert_eq!(
+            Source::parse("https://lichess.org/@/thibault/all", Site::ChessCom).unwrap(),
+            player(Site::Lichess, "thibault")
+        );
+        assert_eq!(
+            Source::parse("https://www.chess.com/member/Hikaru", Site::Lichess).unwrap(),
+            player(Site::ChessCom, "Hikaru")
+        );
+        for url in [
+            "https://lichess.org/q7ZvsdUF",
+            "lichess.org/q7ZvsdUFb2xe#12",
+            "http://lichess.org/q7ZvsdUF/black",
+        ] {
+            assert_eq!(
+                Source::parse(url, Site::Lichess).unwrap(),
+                Source::LichessGame("q7ZvsdUF".to_string())
+            );
+        }
+        assert!(Source::parse("https://www.chess.com/game/live/123", Site::Lichess).is_err());
+        assert!(Source::parse("https://lichess.org/tv", Site::Lichess).is_err());
+        assert!(Source::parse("no spaces", Site::Lichess).is_err());
+    }
+
+    #[test]
+    fn test_split_games() {
+        let pgn =
+            "[Event \"One\"]\n[Site \"?\"]\n\n1. e4 e5 1-0\n\n[Event \"Two\"]\n\n1. d4\nd5 0-1\n";
+        let games = split_games(pgn);
+        assert_eq!(games.len(), 2);
+        assert!(games[0].starts_with("[Event \"One\"]") && games[0].contains("1-0"));
+        assert!(games[1].starts_with("[Event \"Two\"]") && games[1].contains("d5 0-1"));
+    }
+}
@@ -1,29 +1,463 @@
-use crate::core::{ChessError, Column, Move, Position, Row};
+//! Reading moves and commands as people type them, in SAN, as squares or in
+//! words, with the line editor that prompts for them.
+
+use crate::core::{
+    ChessColour, ChessError, ChessPieceKind, Column, Move, ParseKind, Position, Row, Square,
+};
+use crate::game::ChessGame;
+use crate::solver;
+use rustyline::completion::Completer;
+use rustyline::error::ReadlineError;
+use rustyline::highlight::Highlighter;
+use rustyline::hint::Hinter;
+use rustyline::history::DefaultHistory;
+use rustyline::validate::Validator;
+use std::path::PathBuf;
 use std::str::FromStr;
 
+/// Something other than a move the player can type during a game.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Command {
+    /// Take back the last move, and the opponent's reply if it has made one.
+    Undo,
+    /// Ask the solver for a move.
+    Hint,
+    /// List the legal moves, or only those of the piece on the square.
+    Moves(Option<Position>),
+    /// Show the position's FEN.
+    Fen,
+    /// Turn the board around.
+    Flip,
+    /// Toggle the threat overlay.
+    Threats,
+    Resign,
+    /// Offer a draw.
+    Draw,
+    /// Write the game so far to the file as PGN.
+    Save(PathBuf),
+    Help,
+    Quit,
+}
+
+/// What each command does, for `help`.
+pub const COMMANDS_HELP: &str = "\
+Enter a move as e.g. e2e4, e2 e4, Nf3, 5254 or knight to f3, a square to see where its piece
+can go, or:
+  undo         take back your last move
+  hint         ask the solver for a move
+  moves [e2]   list the legal moves, or those of the piece on e2
+  fen          show the position's FEN
+  flip         turn the board around
+  threats      show the squares your opponent attacks
+  resign       give up the game
+  draw         offer a draw
+  save <file>  write the game so far to a PGN file
+  help         show this list
+  quit         leave the game";
+
+/// The name of each command, for completion.
+pub const COMMAND_NAMES: [&str; 11] = [
+    "undo", "hint", "moves", "fen", "flip", "threats", "resign", "draw", "save", "help", "quit",
+];
+
+impl Command {
+    /// The command `input` names, `None` if it doesn't start with the name of
+    /// one, so is a move, or an error if the command's argument is wrong.
+    pub fn parse(input: &str) -> Option<Result<Self, ChessError>> {
+        let mut words = input.split_whitespace();
+        let name = words.next()?;
+        let argument = words.next();
+        let command = match name {
+            "undo" | "takeback" => Command::Undo,
+            "hint" => Command::Hint,
+            "moves" => match argument.map(Position::from_str).transpose() {
+                Ok(square) => Command::Moves(square),
+                Err(e) => return Some(Err(e)),
+            },
+            "fen" => Command::Fen,
+            "flip" => Command::Flip,
+            "threats" => Command::Threats,
+            "resign" => Command::Resign,
+            "draw" => Command::Draw,
+            "save" => match argument {
+                Some(path) => Command::Save(PathBuf::from(path)),
+                None => {
+                    return Some(Err(ChessError::MissingArgument {
+                        command: "save",
+                        example: "save game.pgn",
+                    }));
+                }
+            },
+            "help" | "?" => Command::Help,
+            "quit" | "exit" => Command::Quit,
+            _ => return None,
+        };
+        Some(Ok(command))
+    }
+}
+
+/// Where the player types during a game, with line editing and the earlier
+/// lines a press of the up arrow away.
+pub struct Prompt {
+    editor: rustyline::Editor<Completions, DefaultHistory>,
+}
+
+impl Prompt {
+    pub fn new() -> anyhow::Result<Self> {
+        Ok(Self {
+            editor: rustyline::Editor::new()?,
+        })
+    }
+
+    /// The next line the player types after `prompt`, with Tab completing
+    /// the word being typed to one of `completions`, or `None` if they
+    /// pressed Ctrl-C or Ctrl-D to leave.
+    pub fn read(
+        &mut self,
+        prompt: &str,
+        completions: Vec<String>,
+    ) -> anyhow::Result<Option<String>> {
+        self.editor.set_helper(Some(Completions(completions)));
+        match self.editor.readline(prompt) {
+            Ok(line) => {
+                if !line.trim().is_empty() {
+                    self.editor.add_history_entry(line.as_str())?;
+                }
+                Ok(Some(line))
+            }
+            Err(ReadlineError::Interrupted | ReadlineError::Eof) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+}
+
+/// Everything the player might type at `game`'s move prompt: the command
+/// names, and each legal move in both SAN and UCI.
+pub fn move_completions(game: &ChessGame) -> Vec<String> {
+    let moves = solver::legal_moves(game);
+    let mut completions: Vec<String> = COMMAND_NAMES.iter().map(|name| name.to_string()).collect();
+    completions.extend(moves.iter().filter_map(|mv| game.san(mv).ok()));
+    completions.extend(moves.iter().map(|mv| mv.to_uci()));
+    completions
+}
+
+/// Completes the word before the cursor to any of its words starting with it.
+pub struct Completions(Vec<String>);
+
+impl Completions {
+    fn matching(&self, start: &str) -> Vec<String> {
+        let mut matching: Vec<String> = self
+            .0
+            .iter()
+            .filter(|word| word.starts_with(start))
+            .cloned()
+            .collect();
+        matching.sort();
+        matching.dedup();
+        matching
+    }
+}
+
+impl Completer for Completions {
+    type Candidate = String;
+
+    fn complete(
+        &self,
+        line: &str,
+        pos: usize,
+        _ctx: &rustyline::Context<'_>,
+    ) -> rustyline::Result<(usize, Vec<String>)> {
+        let start = line[..pos].rfind(' ').map_or(0, |space| space + 1);
+        Ok((start, self.matching(&line[start..pos])))
+    }
+}
+
+impl Hinter for Completions {
+    type Hint = String;
+}
+
+impl Highlighter for Completions {}
+
+impl Validator for Completions {}
+
+impl rustyline::Helper for Completions {}
+
+/// Read a legal move in `game` from what the player typed: a pair of squares
+/// (`e2 e4`, `e2e4`, `a23`), UCI's long algebraic notation (`e7e8q`), SAN
+/// (`Nf3`, `exd5`, `O-O`), ICCF numeric notation (`5254`), or a few plain
+/// words (`knight to f3`, `pawn takes d5`, `castle kingside`). The error says
+/// why if nothing legal matches.
+///
+/// Moves don't carry a promotion piece, so one given in UCI, SAN or ICCF is
+/// read but otherwise ignored.
+pub fn parse_move(input: &str, game: &ChessGame) -> Result<Move, ChessError> {
+    let candidates = parse_move_candidates(input, game)?;
+    match candidates[..] {
+        [mv] => Ok(mv),
+        _ => Err(ChessError::AmbiguousMove {
+            input: input.trim().to_string(),
+            candidates: candidate_names(&candidates, game),
+        }),
+    }
+}
+
+/// Every legal move `input` could mean, read as [`parse_move`] does, so that
+/// the player can be asked which they meant when there is more than one. Fails
+/// if there are none.
+pub fn parse_move_candidates(input: &str, game: &ChessGame) -> Result<Vec<Move>, ChessError> {
+    let input = input.trim();
+    let coordinates = input
+        .strip_suffix(['q', 'r', 'b', 'n'])
+        .filter(|uci| uci.len() == 4)
+        .unwrap_or(input);
+    if let Ok(mv) = parse_input_to_move(coordinates) {
+        return game.check_legal(&mv).map(|()| vec![mv]);
+    }
+    if let Some(mv) = parse_iccf(input) {
+        return game.check_legal(&mv).map(|()| vec![mv]);
+    }
+    let candidates = match words_to_san(input) {
+        Some(san) => parse_san(&san, game)?,
+        None => parse_san(input, game)?,
+    };
+    if candidates.is_empty() {
+        return Err(ChessError::NoMatchingMove(input.to_string()));
+    }
+    Ok(candidates)
+}
+
+/// Each of `candidates` in SAN, to ask the player which they meant.
+pub fn candidate_names(candidates: &[Move], game: &ChessGame) -> Vec<String> {
+    candidates
+        .iter()
+        .map(|mv| game.san(mv).unwrap_or_else(|_| mv.to_uci()))
+        .collect()
+}
+
+/// Which of `candidates` the player picked with `answer`, either its number
+/// counting from 1 or the move itself, or `None` if it isn't one of them.
+pub fn choose_candidate(answer: &str, candidates: &[Move], game: &ChessGame) -> Option<Move> {
+    match answer.trim().parse::<usize>() {
+        Ok(number) => number
+            .checked_sub(1)
+            .and_then(|i| candidates.get(i))
+            .copied(),
+        Err(_) => parse_move(answer, game)
+            .ok()
+            .filter(|mv| candidates.contains(mv)),
+    }
+}
+
+/// A move in ICCF numeric notation, where each square is its file then its
+/// rank as digits from 1 to 8, so `5254` is e2e4. A fifth digit for the
+/// promotion piece is ignored.
+fn parse_iccf(input: &str) -> Option<Move> {
+    let digits: Vec<u8> = input
+        .bytes()
+        .map(|c| c.is_ascii_digit().then(|| c - b'0'))
+        .collect::<Option<_>>()?;
+    let square = |file: u8, rank: u8| -> Option<Position> {
+        if !(1..=8).contains(&file) || !(1..=8).contains(&rank) {
+            return None;
+        }
+        format!("{}{rank}", (b'a' + file - 1) as char).parse().ok()
+    };
+    match digits[..] {
+        [from_file, from_rank, to_file, to_rank]
+        | [from_file, from_rank, to_file, to_rank, 1..=4] => Some(Move {
+            from: square(from_file, from_rank)?,
+            to: square(to_file, to_rank)?,
+        }),
+        _ => None,
+    }
+}
+
+/// The SAN for a move described in words, e.g. `knight to f3` is `Nf3`,
+/// `bishop from f1 takes b5` is `Bf1b5` and `castle queenside` is `O-O-O`, or
+/// `None` if `input` isn't made of the words this knows.
+fn words_to_san(input: &str) -> Option<String> {
+    let input = input.to_lowercase();
+    let words: Vec<&str> = input
+        .split_whitespace()
+        .filter(|word| {
+            ![
+                "to",
+                "takes",
+                "captures",
+                "x",
+                "on",
+                "from",
+                "moves",
+                "the",
+                "check",
+                "checkmate",
+                "mate",
+            ]
+            .contains(word)
+        })
+        .collect();
+    if words.first().is_some_and(|word| word.starts_with("castle")) {
+        return match words[1..] {
+            ["kingside" | "short"] | ["king", "side"] => Some("O-O".to_string()),
+            ["queenside" | "long"] | ["queen", "side"] => Some("O-O-O".to_string()),
+            _ => None,
+        };
+    }
+    let (piece, squares) = words.split_first()?;
+    let piece = match *piece {
+        "pawn" => "",
+        "knight" => "N",
+        "bishop" => "B",
+        "rook" => "R",
+        "queen" => "Q",
+        "king" => "K",
+        _ => return None,
+    };
+    let squares = squares
+        .iter()
+        .map(|square| Position::from_str(square).ok().map(|_| *square))
+        .collect::<Option<Vec<_>>>()?;
+    match squares[..] {
+        [to] => Some(format!("{piece}{to}")),
+        [from, to] => Some(format!("{piece}{from}{to}")),
+        _ => None,
+    }
+}
+
+/// Read one move as [`parse_move`] does, or a whole line of them as pasted
+/// from a game's movetext (`1. e4 e5 2. Nf3`) or a list of UCI moves
+/// (`e2e4 e7e5`), each of which must be legal after the ones before it. Move
+/// numbers and a result at the end are skipped. The error names the first
+/// move that couldn't be played.
+pub fn parse_moves(input: &str, game: &ChessGame) -> Result<Vec<Move>, ChessError> {
+    let single = parse_move(input, game);
+    // "e2 e4" is one move given as two squares, not two moves
+    if single.is_ok()
+        || input.split_whitespace().count() < 2
+        || parse_input_to_move(input).is_ok()
+        || words_to_san(input).is_some()
+    {
+        return single.map(|mv| vec![mv]);
+    }
+    let mut game = game.clone();
+    let mut moves = Vec::new();
+    for token in input.split_whitespace() {
+        let token = match token.split_once('.') {
+            Some((number, mv)) if number.chars().all(|c| c.is_ascii_digit()) => {
+                mv.trim_start_matches('.')
+            }
+            _ => token,
+        };
+        if token.is_empty() || ["1-0", "0-1", "1/2-1/2", "*"].contains(&token) {
+            continue;
+        }
+        let number = match game.get_board().turn {
+            ChessColour::White => format!("{}.", game.full_move_count()),
+            ChessColour::Black => format!("{}...", game.full_move_count()),
+        };
+        let mv = parse_move(token, &game)
+            .and_then(|mv| game.make_move(&mv).map(|_| mv))
+            .map_err(|e| ChessError::MoveInList {
+                number,
+                token: token.to_string(),
+                source: Box::new(e),
+            })?;
+        moves.push(mv);
+    }
+    Ok(moves)
+}
+
+/// The legal moves in `game` a move in SAN matches, more than one if it leaves
+/// out which piece moves when that needs saying. The capture sign, check and mate signs and annotations like `!?` are optional,
+/// as is using the piece letters in lower case, except for the bishop's.
+fn parse_san(input: &str, game: &ChessGame) -> Result<Vec<Move>, ChessError> {
+    let unreadable = || ChessError::parse(ParseKind::Move, input);
+    let san = input.trim_end_matches(['+', '#', '!', '?']);
+    let legal = solver::legal_moves(game);
+    if let castling @ ("O-O" | "O-O-O") = san.replace('0', "O").as_str() {
+        let candidates = legal
+            .into_iter()
+            .filter(|mv| {
+                game.san(mv)
+                    .is_ok_and(|san| san.trim_end_matches(['+', '#']) == castling)
+            })
+            .collect();
+        return Ok(candidates);
+    }
+
+    let mut chars: Vec<char> = san.chars().filter(|c| !"x:=-".contains(*c)).collect();
+    let kind = match chars.first() {
+        Some('N' | 'n') => ChessPieceKind::Knight,
+        Some('B') => ChessPieceKind::Bishop,
+        Some('R' | 'r') => ChessPieceKind::Rook,
+        Some('Q' | 'q') => ChessPieceKind::Queen,
+        Some('K' | 'k') => ChessPieceKind::King,
+        _ => ChessPieceKind::Pawn,
+    };
+    if kind != ChessPieceKind::Pawn {
+        chars.remove(0);
+    }
+    // a promotion piece after the destination
+    if kind == ChessPieceKind::Pawn
+        && chars.len() >= 3
+        && chars[chars.len() - 1].is_ascii_alphabetic()
+        && chars[chars.len() - 2].is_ascii_digit()
+    {
+        chars.pop();
+    }
+    if chars.len() < 2 {
+        return Err(unreadable());
+    }
+    let (hints, to) = chars.split_at(chars.len() - 2);
+    let to: Position = to
+        .iter()
+        .collect::<String>()
+        .parse()
+        .map_err(|_| unreadable())?;
+    // the file or rank, or both, the piece moves from when it needs saying
+    let mut from_column = None;
+    let mut from_row = None;
+    for hint in hints {
+        match hint {
+            'a'..='h' => from_column = Some(Column::from_str(&hint.to_string())?),
+            '1'..='8' => from_row = Some(Row::from_str(&hint.to_string())?),
+            _ => return Err(unreadable()),
+        }
+    }
+
+    let candidates = legal
+        .into_iter()
+        .filter(|mv| {
+            mv.to == to
+                && from_column.is_none_or(|column| mv.from.column == column)
+                && from_row.is_none_or(|row| mv.from.row == row)
+                && game
+                    .get_board()
+                    .get(Square::from(mv.from))
+                    .is_some_and(|piece| piece.kind == kind)
+        })
+        .collect();
+    Ok(candidates)
+}
+
 pub fn parse_input_to_move(input: &str) -> Result<Move, ChessError> {
     let input = input.trim();
+    let invalid = || ChessError::parse(ParseKind::Move, input);
     let (first, second) = if input.contains(' ') {
         let mut parts = input.split_whitespace();
         (
-            parts.next().ok_or(ChessError::InvalidMove(
-                "No 'from' position provided".to_string(),
-            ))?,
-            parts.next().ok_or(ChessError::InvalidMove(
-                "No 'to' position provided".to_string(),
-            ))?,
+            parts.next().ok_or_else(invalid)?,
+            parts.next().ok_or_else(invalid)?,
         )
     } else {
-        if input.len() < 3 {
-            return Err(ChessError::InvalidMove(
-                "Input must be at least 3 characters long".to_string(),
-            ));
+        // a square is two bytes, so anything else there isn't one
+        match (input.get(0..2), input.get(2..)) {
+            (Some(first), Some(second)) if !second.is_empty() => (first, second),
+            _ => return Err(invalid()),
         }
-        (&input[0..2], &input[2..])
     };
 
-    let from = Position::from_str(first)
-        .map_err(|_| ChessError::InvalidMove(format!("Invalid 'from' position: '{first}'")))?;
+    let from = Position::from_str(first)?;
     let to = if second.len() == 1 {
         let c = second.chars().next().unwrap();
         if c.is_ascii_digit() {
@@ -38,8 +472,7 @@ pub fn parse_input_to_move(input: &str) -> Result<Move, ChessError> {
             }
         }
     } else {
-        Position::from_str(second)
-            .map_err(|_| ChessError::InvalidMove(format!("Invalid 'to' position: '{first}'")))?
+        Position::from_str(second)?
     };
 
     Ok(Move { from, to })
@@ -50,6 +483,152 @@ mod test {
     use super::*;
     use crate::core::{Column, Row};
 
+    #[test]
+    fn test_parse_commands() {
+        assert_eq!(Command::parse("undo").unwrap().unwrap(), Command::Undo);
+        assert_eq!(Command::parse(" help ").unwrap().unwrap(), Command::Help);
+        assert_eq!(
+            Command::parse("moves").unwrap().unwrap(),
+            Command::Moves(None)
+        );
+        assert_eq!(
+            Command::parse("moves e2").unwrap().unwrap(),
+            Command::Moves(Some("e2".parse().unwrap()))
+        );
+        assert!(Command::parse("moves z9").unwrap().is_err());
+        assert_eq!(
+            Command::parse("save games/today.pgn").unwrap().unwrap(),
+            Command::Save(PathBuf::from("games/today.pgn"))
+        );
+        assert!(Command::parse("save").unwrap().is_err());
+        // moves aren't commands
+        assert!(Command::parse("e2e4").is_none());
+        assert!(Command::parse("Nf3").is_none());
+        assert!(Command::parse("").is_none());
+    }
+
+    #[test]
+    fn test_completions() {
+        let game = ChessGame::default();
+        let completions = Completions(move_completions(&game));
+        assert_eq!(completions.matching("N"), ["Na3", "Nc3", "Nf3", "Nh3"]);
+        assert_eq!(completions.matching("Nf"), ["Nf3"]);
+        assert_eq!(completions.matching("e2"), ["e2e3", "e2e4"]);
+        assert_eq!(completions.matching("e"), ["e2e3", "e2e4", "e3", "e4"]);
+        assert_eq!(completions.matching("s"), ["save"]);
+        assert!(completions.matching("Q").is_empty());
+    }
+
+    #[test]
+    fn test_parse_iccf_and_words() {
+        let mut game = ChessGame::default();
+        let uci = |input: &str, game: &ChessGame| parse_move(input, game).unwrap().to_uci();
+        assert_eq!(uci("5254", &game), "e2e4");
+        assert_eq!(uci("knight to f3", &game), "g1f3");
+        assert_eq!(uci("Knight from b1 to c3", &game), "b1c3");
+        assert!(parse_move("5255", &game).is_err());
+        assert!(parse_move("9254", &game).is_err());
+        assert!(parse_move("knight to f4", &game).is_err());
+        for mv in ["e4", "d5"] {
+            game.make_move(&parse_move(mv, &game).unwrap()).unwrap();
+        }
+        assert_eq!(uci("pawn takes d5", &game), "e4d5");
+        assert_eq!(uci("54453", &game), "e4d5");
+        for mv in ["Nf3", "Nf6", "Be2", "e6"] {
+            game.make_move(&parse_move(mv, &game).unwrap()).unwrap();
+        }
+        assert_eq!(uci("castle kingside", &game), "e1g1");
+        assert_eq!(uci("castles king side", &game), "e1g1");
+        assert!(parse_move("castle queenside", &game).is_err());
+    }
+
+    #[test]
+    fn test_ambiguous_moves() {
+        let mut game = ChessGame::default();
+        for mv in [
+            "e2e4", "a7a6", "b1c3", "a6a5", "c3e2", "a5a4", "g1f3", "h7h6",
+        ] {
+            game.make_move(&parse_move(mv, &game).unwrap()).unwrap();
+        }
+        let candidates = parse_move_candidates("Nd4", &game).unwrap();
+        assert_eq!(candidate_names(&candidates, &game), ["Ned4", "Nfd4"]);
+        let chosen = |answer: &str| choose_candidate(answer, &candidates, &game);
+        assert_eq!(chosen("2"), Some(candidates[1]));
+        assert_eq!(chosen("Ned4"), Some(candidates[0]));
+        assert_eq!(chosen("e2d4"), Some(candidates[0]));
+        assert_eq!(chosen("3"), None);
+        assert_eq!(chosen("0"), None);
+        assert_eq!(chosen("Nc3"), None);
+        assert_eq!(parse_move_candidates("Nf4", &game).unwrap().len(), 1);
+        assert!(parse_move_candidates("Nb5", &game).is_err());
+    }
+
+    #[test]
+    fn test_parse_moves() {
+        let game = ChessGame::default();
+        let uci = |input: &str| -> Vec<String> {
+            parse_moves(input, &game)
+                .unwrap()
+                .into_iter()
+                .map(Move::to_uci)
+                .collect()
+        };
+        assert_eq!(uci("e2 e4"), ["e2e4"]);
+        assert_eq!(uci("1. e4"), ["e2e4"]);
+        assert_eq!(
+            uci("1. e4 e5 2.Nf3 Nc6 3. Bb5 *"),
+            ["e2e4", "e7e5", "g1f3", "b8c6", "f1b5"]
+        );
+        assert_eq!(uci("e2e4 e7e5 g1f3"), ["e2e4", "e7e5", "g1f3"]);
+        let error = |input: &str| parse_moves(input, &game).unwrap_err().to_string();
+        assert!(error("1. e4 e5 2. Nf3 Nf6 3. Ke3").contains("Could not play 3. Ke3"));
+        assert!(error("e2e4 e7e5 e1e2 e5e4").contains("Could not play 2... e5e4"));
+        // still about the single move when given as two squares
+        assert!(error("e2 e5").contains("can't move to e5"));
+    }
+
+    #[test]
+    fn test_parse_move() {
+        let mut game = ChessGame::default();
+        let uci = |mv: Result<Move, ChessError>| mv.unwrap().to_uci();
+        for input in ["e2e4", "e2 e4", "e24", "e4", "e4!"] {
+            assert_eq!(uci(parse_move(input, &game)), "e2e4", "{input}");
+        }
+        assert_eq!(uci(parse_move("Nf3", &game)), "g1f3");
+        assert_eq!(uci(parse_move("nf3", &game)), "g1f3");
+        let error =
+            |input: &str, game: &ChessGame| parse_move(input, game).unwrap_err().to_string();
+        assert!(error("e3e4", &game).contains("There is no piece on e3"));
+        assert!(error("e7e5", &game).contains("it is White's move"));
+        assert!(error("e2e5", &game).contains("can't move to e5"));
+        assert!(error("Nf4", &game).contains("No legal move matches"));
+        assert!(error("hello", &game).contains("Can't read"));
+
+        for mv in ["e2e4", "d7d5", "g1f3", "b8c6", "f1b5", "g8f6"] {
+            game.make_move(&Move::from_uci(mv).unwrap()).unwrap();
+        }
+        assert_eq!(uci(parse_move("exd5", &game)), "e4d5");
+        assert_eq!(uci(parse_move("Bxc6+", &game)), "b5c6");
+        assert_eq!(uci(parse_move("O-O", &game)), "e1g1");
+        assert_eq!(uci(parse_move("0-0", &game)), "e1g1");
+
+        // two knights can reach d4
+        for mv in ["b1c3", "a7a6", "c3e2", "a6a5"] {
+            game.make_move(&Move::from_uci(mv).unwrap()).unwrap();
+        }
+        assert!(error("Nd4", &game).contains("ambiguous, it could be Ned4 or Nfd4"));
+        assert_eq!(uci(parse_move("Nfd4", &game)), "f3d4");
+        assert_eq!(uci(parse_move("Nf3d4", &game)), "f3d4");
+
+        let game = ChessGame::from_fen(
+            "8/4P1k1/8/8/8/8/8/4K3 w - - 0 1",
+            crate::variant::Variant::Standard,
+        )
+        .unwrap();
+        assert_eq!(uci(parse_move("e7e8q", &game)), "e7e8");
+        assert_eq!(uci(parse_move("e8=Q", &game)), "e7e8");
+    }
+
     #[test]
     fn test_parse_simple_inputs() {
         let expected = Move {
@@ -72,4 +651,13 @@ mod test {
             );
         }
     }
+
+    #[test]
+    fn test_parse_non_ascii() {
+        let game = ChessGame::default();
+        for input in ["eé4", "é", "e2é", "ée2e4", "Né3", "knight to é3"] {
+            assert!(parse_input_to_move(input).is_err(), "{input}");
+            assert!(parse_move(input, &game).is_err(), "{input}");
+        }
+    }
 }
@@ -0,0 +1,90 @@
+//! Chess rules, a solver to play against and clients for external engines,
+//! as used by the `chess` command line program.
+//!
+//! A game is a [`game::ChessGame`], which checks moves, keeps the clock and
+//! notices when the game is over:
+//!
+//! ```
+//! use chess::core::Move;
+//! use chess::game::ChessGame;
+//!
+//! let mut game = ChessGame::default();
+//! for mv in ["f2f3", "e7e5", "g2g4", "d8h4"] {
+//!     game.make_move(&Move::from_uci(mv).unwrap()).unwrap();
+//! }
+//! assert!(game.result().is_some());
+//! ```
+//!
+//! [`solver::solve_next_move_with_options`] searches a position for the best
+//! move, [`uci::UciEngine`] and [`xboard::XBoardEngine`] talk to engines such
+//! as Stockfish, and [`input`] and [`render`] read moves as people type them
+//! and draw the board in a terminal. [`controller::GameController`] runs a
+//! game between two [`player::Player`]s, each a person, the solver, an engine
+//! or someone over the network, for any front end to drive.
+//!
+//! Those all need the default `std` feature. Without it the crate is `no_std`,
+//! needing only an allocator, and keeps the rules in [`core`] and [`game`],
+//! move generation and fixed depth searches with
+//! [`solver::SearchOptions::fixed_depth`]. [`web::WebGame`] wraps a game in
+//! strings and numbers for JavaScript to drive from WebAssembly, with the
+//! bindings exported by the `wasm` feature.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+#[cfg(feature = "std")]
+pub mod autosave;
+#[cfg(feature = "images")]
+pub mod board_image;
+#[cfg(feature = "std")]
+pub mod clock;
+#[cfg(feature = "std")]
+pub mod config;
+#[cfg(feature = "std")]
+pub mod controller;
+pub mod core;
+#[cfg(feature = "database")]
+pub mod database;
+#[cfg(feature = "discord")]
+pub mod discord;
+pub mod encoding;
+#[cfg(feature = "std")]
+pub mod engine;
+#[cfg(feature = "std")]
+pub mod engine_match;
+#[cfg(feature = "std")]
+pub mod epd;
+pub mod event;
+#[cfg(feature = "std")]
+pub mod explorer;
+pub mod game;
+#[cfg(feature = "std")]
+pub mod import;
+#[cfg(feature = "std")]
+pub mod input;
+#[cfg(feature = "std")]
+pub mod network;
+#[cfg(feature = "std")]
+pub mod opening;
+#[cfg(feature = "std")]
+pub mod pgn;
+#[cfg(feature = "std")]
+pub mod player;
+#[cfg(feature = "std")]
+pub mod puzzle;
+#[cfg(feature = "std")]
+pub mod render;
+#[cfg(feature = "std")]
+pub mod server;
+pub mod solver;
+pub mod tree;
+#[cfg(feature = "std")]
+pub mod uci;
+pub mod variant;
+pub mod web;
+#[cfg(feature = "std")]
+pub mod xboard;
+mod zobrist;
+
+pub use core::{ChessBoard, ChessColour, ChessError};
@@ -1,92 +1,1103 @@
-mod core;
-mod game;
-mod input;
-mod render;
-mod solver;
-mod uci;
-
-use crate::game::GameStatus;
-use crate::input::parse_input_to_move;
+mod cli;
+mod profile;
+mod series;
+#[cfg(feature = "tui")]
+mod tui;
+
+use crate::cli::{
+    AnalyzeArgs, Cli, Commands, DiscordArgs, GamesArgs, HostArgs, ImportArgs, JoinArgs, MatchArgs,
+    PerftArgs, PlayArgs, PuzzleArgs, ReplayArgs, ReviewArgs, ServeArgs, TestsuiteArgs, WatchArgs,
+};
+use crate::config::Opponent;
+use crate::controller::GameController;
+use crate::input::{Prompt, parse_moves};
+use crate::player::human::Hints;
+use crate::player::{HumanPlayer, Player, RemotePlayer, SolverPlayer, UciPlayer};
 use anyhow::Context;
-use core::{ChessBoard, ChessColour, ChessError, ChessPiece};
+#[cfg(feature = "images")]
+use chess::board_image;
+use chess::core::{self, ChessColour};
+use chess::{
+    autosave, clock, config, controller, engine_match, epd, explorer, game, import, input, network,
+    opening, pgn, player, puzzle, render, server, solver, tree, uci, variant,
+};
+use clap::{Parser, ValueEnum};
+use std::io::IsTerminal;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
 use tracing_subscriber::EnvFilter;
 use tracing_subscriber::layer::SubscriberExt;
 use tracing_subscriber::util::SubscriberInitExt;
 
-#[derive(PartialEq)]
-enum Mode {
-    User,
-    Stockfish,
+/// Where the game is saved when the player leaves with Ctrl-C or Ctrl-D,
+/// unless `--save` says otherwise: `chess/unfinished.pgn` in the data
+/// directory, or the current directory if there is no data directory.
+fn unfinished_game_path() -> PathBuf {
+    let file = "unfinished.pgn";
+    config::data_dir().map_or_else(|| PathBuf::from(file), |dir| dir.join(file))
 }
 
+/// Where the games of a series go when --archive doesn't say.
+const SERIES_ARCHIVE: &str = "series.pgn";
+
 fn main() -> anyhow::Result<()> {
     tracing_subscriber::registry()
-        .with(tracing_subscriber::fmt::layer())
+        // stdout is for the game, or for the GUI in UCI mode
+        .with(tracing_subscriber::fmt::layer().with_writer(std::io::stderr))
         .with(EnvFilter::from_env("CHESS"))
         .init();
 
-    let mut game = game::ChessGame::default();
-
-    let mode = Mode::Stockfish;
-    tracing::info!("Initializing game");
-    let mut stockfish = uci::UciEngine::new("stockfish")?;
-    tracing::info!("starting new game in stockfish");
-    stockfish
-        .new_game()
-        .context("could not start new game in stockfish")?;
-    tracing::info!("setting skill level to 1");
-    stockfish
-        .skill_level(1)
-        .context("could not set skill level")?;
-    stockfish.is_ready().context("could not isready")?;
-
-    let mut status = GameStatus::Ongoing;
-    let users_chess_colour = ChessColour::White;
-
-    while status == GameStatus::Ongoing {
-        render::display_board(&game, game.get_board().turn == users_chess_colour);
-        if mode == Mode::User {
-            while game.get_board().turn == users_chess_colour {
-                let mut input = String::new();
-                println!("Please enter the move you want to play:");
-                std::io::stdin()
-                    .read_line(&mut input)
-                    .expect("Failed to read line");
-                let user_move = parse_input_to_move(&input);
-
-                if let Ok(user_move) = user_move {
-                    match game.make_move(&user_move) {
-                        Ok(game_state) => {
-                            status = game_state.status;
-                        }
-                        Err(e) => {
-                            println!("Error making move: {e}");
-                        }
+    let cli = Cli::parse();
+    let config = match &cli.config {
+        Some(path) => config::Config::load(path)?,
+        None => config::Config::load_default()?,
+    };
+    match cli.command.unwrap_or(Commands::Play(cli.play)) {
+        Commands::Play(args) => play(&args, &config),
+        Commands::Match(args) => play_match(&args, &config),
+        Commands::Watch(args) => watch(&args, &config),
+        Commands::Uci(args) => uci::server::run(
+            std::io::stdin().lock(),
+            std::io::stdout(),
+            args.search_options()?,
+        ),
+        Commands::Perft(args) => perft(&args),
+        Commands::Analyze(args) => analyze(&args),
+        Commands::Replay(args) => replay(&args, &config),
+        Commands::Import(args) => import_games(&args, &config),
+        Commands::Puzzle(args) => solve_puzzles(&args, &config),
+        Commands::Testsuite(args) => run_testsuite(&args, &config),
+        Commands::Serve(args) => serve(&args),
+        Commands::Discord(args) => play_discord(&args),
+        Commands::Host(args) => host(&args, &config),
+        Commands::Join(args) => join(&args, &config),
+        Commands::Profile => show_profile(),
+        Commands::Games(args) => show_games(&args),
+        Commands::Openings => {
+            for opening in opening::all() {
+                let mut game = game::ChessGame::default();
+                opening.play(&mut game)?;
+                println!("{opening}: {}", game.numbered_moves()?.join(" "));
+            }
+            Ok(())
+        }
+        Commands::EngineOptions(args) => {
+            let engine = uci::UciEngine::from_config(&args.engine_config(&config, &args.engine))?;
+            for option in engine.options() {
+                println!("{option}");
+            }
+            Ok(())
+        }
+    }
+}
+
+/// Play a game against the opponent `args` or the config file picks, at the
+/// keyboard.
+fn play(args: &PlayArgs, config: &config::Config) -> anyhow::Result<()> {
+    let mut prompt = Prompt::new()?;
+    // a saved game is resumed on its own, so games in a series aren't saved
+    let autosave_path =
+        autosave::default_path().filter(|_| !args.no_autosave && args.series.is_none());
+    let resumed = match &autosave_path {
+        Some(path) => offer_resume(path, &mut prompt)?,
+        None => None,
+    };
+    let mut args = args.clone();
+    if let Some(saved) = &resumed {
+        args.resume(saved)?;
+    }
+    let opponent = args.opponent.or(config.play.opponent).unwrap_or_default();
+    let profile_path = profile::Profile::default_path()
+        .filter(|_| !args.no_profile && opponent != Opponent::Human);
+    let mut profile = match &profile_path {
+        Some(path) => Some(profile::Profile::load(path)?),
+        None => None,
+    };
+    // match the opponent to the user unless they said how strong it should be
+    if let Some(profile) = &profile
+        && !profile.games.is_empty()
+        && args.solver.skill.is_none()
+        && args.engine.elo.is_none()
+    {
+        let skill = profile.suggested_skill();
+        println!(
+            "Playing at skill level {skill} to match your rating of {:.0}.",
+            profile.rating()
+        );
+        args.solver.skill = Some(skill);
+    }
+    let args = &args;
+    let game = match &resumed {
+        Some(saved) => saved.game()?,
+        None => args.game.new_game()?,
+    };
+    if resumed.is_none()
+        && let Some(opening) = &args.game.opening
+    {
+        println!("Starting from the {} ({}).", opening.name, opening.eco);
+    }
+    let mut search_options = args.solver.search_options()?;
+    let mut render_options = args.display.render_options(config);
+    if !cfg!(feature = "images") && (args.png.is_some() || args.gif.is_some()) {
+        return Err(anyhow::anyhow!(
+            "this build can't save images, rebuild with --features images"
+        ));
+    }
+    let users_chess_colour = match args.color.or(config.play.color) {
+        Some(side) => side.colour(),
+        // both sides are the user's when playing another person
+        None if opponent == Opponent::Human => ChessColour::White,
+        None => match choose_colour(&mut prompt)? {
+            Some(colour) => colour,
+            None => return Ok(()),
+        },
+    };
+    render_options.flipped = users_chess_colour == ChessColour::Black;
+    if !cfg!(feature = "tui") && args.tui {
+        return Err(anyhow::anyhow!(
+            "this build has no TUI, rebuild with --features tui"
+        ));
+    }
+    // the skill is the engine's rather than the solver's when playing it
+    if opponent == Opponent::Stockfish {
+        search_options = cli::SolverArgs {
+            skill: None,
+            ..args.solver.clone()
+        }
+        .search_options()?;
+    }
+    let hotseat = opponent == Opponent::Human;
+    let save_path = args.save.clone().unwrap_or_else(unfinished_game_path);
+    // hints come from whichever of the engine and the solver is played
+    let hints = || {
+        let hint_time = Duration::from_millis(args.hint_time);
+        match opponent {
+            Opponent::Stockfish => Hints::engine(
+                args.engine.engine_config(config, &args.engine.engine),
+                hint_time,
+            ),
+            Opponent::Solver | Opponent::Human => Hints::solver(search_options.clone(), hint_time),
+        }
+    };
+    let human = |colour: ChessColour| -> anyhow::Result<Box<dyn Player>> {
+        let render_options = render::RenderOptions {
+            // between two people the board stays the same way up unless
+            // it is to face whoever is to move
+            flipped: colour == ChessColour::Black && (!hotseat || args.auto_flip),
+            ..render_options.clone()
+        };
+        Ok(Box::new(HumanPlayer::new(
+            colour,
+            render_options,
+            hints(),
+            hotseat,
+            save_path.clone(),
+        )?))
+    };
+    // an engine plays at skill 1 unless given a level or an Elo
+    let (opponent_name, skill, elo) = match opponent {
+        Opponent::Stockfish => match args.engine.elo {
+            Some(elo) => (args.engine.engine.as_str(), None, Some(elo)),
+            None => (
+                args.engine.engine.as_str(),
+                Some(args.solver.skill.unwrap_or(1)),
+                None,
+            ),
+        },
+        Opponent::Solver => ("solver", args.solver.skill, None),
+        Opponent::Human => ("opponent", None, None),
+    };
+    let mut series = series::Series::new(args.series.map_or(1, usize::from));
+    let mut archive = String::new();
+    let mut first_game = Some(game);
+    while !series.is_decided() {
+        let colour = series.next_colour(users_chess_colour);
+        let game = match first_game.take() {
+            Some(game) => game,
+            None => args.game.new_game()?,
+        };
+        if args.series.is_some() {
+            println!(
+                "Game {} of {}, you play {colour}.",
+                series.results.len() + 1,
+                series.length
+            );
+        }
+        // both of the user's sides are played on the one screen
+        #[cfg(feature = "tui")]
+        let screen = args
+            .tui
+            .then(|| tui::Screen::start(&game, render_options.clone(), hotseat && args.auto_flip));
+        #[cfg(feature = "tui")]
+        let human = |colour: ChessColour| -> anyhow::Result<Box<dyn Player>> {
+            match &screen {
+                Some(screen) => Ok(Box::new(screen.player(hints()))),
+                None => human(colour),
+            }
+        };
+        let opponent_player: Box<dyn Player> = match opponent {
+            Opponent::Stockfish => Box::new(UciPlayer::start(
+                &args.engine.engine_config(config, &args.engine.engine),
+                args.engine.elo,
+                args.solver.skill.unwrap_or(1),
+                args.game.chess960,
+                args.ponder,
+            )?),
+            Opponent::Solver => Box::new(SolverPlayer::new(search_options.clone(), args.ponder)),
+            Opponent::Human => human(colour.flip())?,
+        };
+        let user = human(colour)?;
+        let (white, black) = match colour {
+            ChessColour::White => (user, opponent_player),
+            ChessColour::Black => (opponent_player, user),
+        };
+        let mut controller = GameController::new(game, white, black);
+        if let Some(path) = &autosave_path {
+            let saved = args.saved_game(opponent, colour);
+            controller = controller.autosave(autosave::Autosave::new(path.clone(), saved));
+        }
+        let finished = controller.play();
+        #[cfg(feature = "tui")]
+        if let Some(screen) = screen {
+            screen.close()?;
+        }
+        let finished = finished?;
+        let game = controller.game();
+        let Some(result) = game.result().filter(|_| finished) else {
+            break;
+        };
+        series.record(colour, result);
+        if let (Some(profile), Some(path)) = (&mut profile, &profile_path) {
+            profile.games.push(profile::GameRecord::new(
+                opponent_name,
+                skill,
+                elo,
+                colour,
+                result,
+            ));
+            if let Err(e) = profile.save(path) {
+                tracing::warn!("Could not save your profile: {e:#}");
+            }
+            println!("Your rating is now {:.0}.", profile.rating());
+        }
+        let render_options = render::RenderOptions {
+            flipped: colour == ChessColour::Black,
+            ..render_options.clone()
+        };
+        render::display_board(game, false, &render_options);
+        let (white, black) = match colour {
+            ChessColour::White => ("you", opponent_name),
+            ChessColour::Black => (opponent_name, "you"),
+        };
+        if !args.no_database {
+            record_game(game, white, black);
+        }
+        if args.series.is_some() {
+            let round = series.results.len().to_string();
+            archive.push_str(&game.pgn_with_tags(&[
+                ("Event", "Series"),
+                ("Round", &round),
+                ("White", white),
+                ("Black", black),
+            ])?);
+            archive.push('\n');
+            let (user, opponent) = series.score();
+            println!("Score: you {user} - {opponent} {opponent_name}");
+        }
+        #[cfg(feature = "images")]
+        {
+            if let Some(path) = &args.png {
+                board_image::save_png(game, path, &render_options)?;
+            }
+            if let Some(path) = &args.gif {
+                board_image::save_gif(game, path, &render_options, board_image::FRAME_DELAY)?;
+            }
+        }
+    }
+    if args.series.is_some() && !archive.is_empty() {
+        let path = args
+            .archive
+            .clone()
+            .unwrap_or_else(|| PathBuf::from(SERIES_ARCHIVE));
+        std::fs::write(&path, archive)
+            .with_context(|| format!("could not write the games to {}", path.display()))?;
+        println!("Wrote {} games to {}", series.results.len(), path.display());
+    }
+    Ok(())
+}
+
+/// Serve games over HTTP until stopped.
+fn serve(args: &ServeArgs) -> anyhow::Result<()> {
+    let listener = std::net::TcpListener::bind(&args.address)
+        .with_context(|| format!("could not listen on {}", args.address))?;
+    println!("Serving games on http://{}", listener.local_addr()?);
+    server::Server::new(args.solver.search_options()?).run(listener)
+}
+
+/// Play in Discord channels until stopped.
+fn play_discord(args: &DiscordArgs) -> anyhow::Result<()> {
+    #[cfg(feature = "discord")]
+    {
+        use chess::discord;
+
+        let client = discord::Client::from_env()?;
+        let search_options = solver::SearchOptions {
+            max_time: Some(Duration::from_millis(args.move_time)),
+            ..args.solver.search_options()?
+        };
+        let mut bot = discord::Bot::new(search_options);
+        println!(
+            "Playing in {} channels, stop with Ctrl-C.",
+            args.channels.len()
+        );
+        discord::run(
+            &client,
+            &args.channels,
+            &mut bot,
+            Duration::from_secs(args.poll),
+        )
+    }
+    #[cfg(not(feature = "discord"))]
+    {
+        let _ = args;
+        Err(anyhow::anyhow!(
+            "this build has no Discord bot, rebuild with --features discord"
+        ))
+    }
+}
+
+/// Wait for someone to join from another computer, then play them.
+fn host(args: &HostArgs, config: &config::Config) -> anyhow::Result<()> {
+    let game = args.game.new_game()?;
+    let colour = args.color.map_or(ChessColour::White, config::Side::colour);
+    let listener = std::net::TcpListener::bind(&args.address)
+        .with_context(|| format!("could not listen on {}", args.address))?;
+    println!(
+        "Waiting for the other player on {}...",
+        listener.local_addr()?
+    );
+    let (stream, address) = listener.accept()?;
+    println!("{address} joined, you play {colour}.");
+    let mut connection = network::Connection::new(stream)?;
+    connection.send(&network::Message::Hello {
+        version: network::PROTOCOL_VERSION,
+        colour: colour.flip(),
+        pgn: game.pgn()?,
+        time_control: game.clock().map(clock::Clock::control),
+    })?;
+    play_remote(game, colour, connection, &args.display, args.tui, config)
+}
+
+/// Join a game hosted on another computer.
+fn join(args: &JoinArgs, config: &config::Config) -> anyhow::Result<()> {
+    let address = args.address();
+    let stream = std::net::TcpStream::connect(&address)
+        .with_context(|| format!("could not connect to {address}"))?;
+    let mut connection = network::Connection::new(stream)?;
+    let network::Message::Hello {
+        version,
+        colour,
+        pgn,
+        time_control,
+    } = connection.receive()?
+    else {
+        anyhow::bail!("the host didn't start the game");
+    };
+    anyhow::ensure!(
+        version == network::PROTOCOL_VERSION,
+        "the host plays version {version} of the protocol, this is version {}",
+        network::PROTOCOL_VERSION
+    );
+    let mut game = pgn::parse(&pgn)?
+        .first()
+        .context("the host sent no game")?
+        .game()?;
+    if let Some(time_control) = time_control {
+        game.set_time_control(time_control);
+    }
+    println!("Joined the game, you play {colour}.");
+    play_remote(game, colour, connection, &args.display, args.tui, config)
+}
+
+/// Play `game` as `colour` at the keyboard, or on a full-screen UI if `tui`,, against whoever is at the other
+/// end of `connection`.
+fn play_remote(
+    game: game::ChessGame,
+    colour: ChessColour,
+    connection: network::Connection,
+    display: &cli::DisplayArgs,
+    tui: bool,
+    config: &config::Config,
+) -> anyhow::Result<()> {
+    let render_options = render::RenderOptions {
+        flipped: colour == ChessColour::Black,
+        ..display.render_options(config)
+    };
+    if !cfg!(feature = "tui") && tui {
+        return Err(anyhow::anyhow!(
+            "this build has no TUI, rebuild with --features tui"
+        ));
+    }
+    let hints = || Hints::solver(solver::SearchOptions::default(), Duration::from_secs(1));
+    let remote = RemotePlayer::new(connection, colour.flip(), &game);
+    // the screen shows what the other side is up to itself
+    let remote = if tui {
+        remote.without_announcements()
+    } else {
+        remote
+    };
+    #[cfg(feature = "tui")]
+    let screen = tui.then(|| tui::Screen::start(&game, render_options.clone(), false));
+    // neither side can take back a move the other has seen
+    let user = || -> anyhow::Result<Box<dyn Player>> {
+        Ok(Box::new(
+            HumanPlayer::new(
+                colour,
+                render_options.clone(),
+                hints(),
+                false,
+                unfinished_game_path(),
+            )?
+            .without_takebacks(),
+        ))
+    };
+    #[cfg(feature = "tui")]
+    let user = || -> anyhow::Result<Box<dyn Player>> {
+        match &screen {
+            Some(screen) => Ok(Box::new(screen.player(hints()).without_takebacks())),
+            None => user(),
+        }
+    };
+    let user = user()?;
+    let (white, black): (Box<dyn Player>, Box<dyn Player>) = match colour {
+        ChessColour::White => (user, Box::new(remote)),
+        ChessColour::Black => (Box::new(remote), user),
+    };
+    let mut controller = GameController::new(game, white, black);
+    let finished = controller.play();
+    #[cfg(feature = "tui")]
+    if let Some(screen) = screen {
+        screen.close()?;
+    }
+    if finished? {
+        render::display_board(controller.game(), false, &render_options);
+    }
+    Ok(())
+}
+
+/// Offer to carry on with the unfinished game saved at `path`, returning it
+/// if the user wants to. When the input isn't a terminal there is no one to
+/// ask, so a new game is started.
+fn offer_resume(path: &Path, prompt: &mut Prompt) -> anyhow::Result<Option<autosave::SavedGame>> {
+    if !path.exists() || !std::io::stdin().is_terminal() {
+        return Ok(None);
+    }
+    let saved = match autosave::SavedGame::load(path) {
+        Ok(saved) => saved,
+        Err(e) => {
+            tracing::warn!("Not offering to resume the last game: {e:#}");
+            return Ok(None);
+        }
+    };
+    let question = format!("Resume your unfinished game, {}? [Y/n] ", saved.describe());
+    let answers = ["yes", "no"].map(String::from).to_vec();
+    let resume = prompt
+        .read(&question, answers)?
+        .is_some_and(|answer| !answer.trim().starts_with(['n', 'N']));
+    Ok(resume.then_some(saved))
+}
+
+/// Ask which side the user wants to play, or `None` if they leave instead.
+/// When the input isn't a terminal there is no one to ask, so the user plays
+/// White.
+fn choose_colour(prompt: &mut Prompt) -> anyhow::Result<Option<ChessColour>> {
+    if !std::io::stdin().is_terminal() {
+        return Ok(Some(ChessColour::White));
+    }
+    loop {
+        let sides = ["white", "black", "random"].map(String::from).to_vec();
+        let Some(answer) = prompt.read("Play white, black or random? [white] ", sides)? else {
+            return Ok(None);
+        };
+        let answer = answer.trim();
+        if answer.is_empty() {
+            return Ok(Some(ChessColour::White));
+        }
+        match config::Side::from_str(answer, true) {
+            Ok(side) => return Ok(Some(side.colour())),
+            Err(_) => println!("{answer} isn't a side, answer white, black or random."),
+        }
+    }
+}
+
+/// Play a match between the two players `args` names.
+fn play_match(args: &MatchArgs, config: &config::Config) -> anyhow::Result<()> {
+    let search_options = args.solver.search_options()?;
+    let player = |name: &str| {
+        match_player(
+            name,
+            &search_options,
+            None,
+            config,
+            args.engine_log.as_ref(),
+        )
+    };
+    let options = engine_match::MatchOptions {
+        games: args.games,
+        time_control: args.time,
+        variant: if args.chess960 {
+            variant::Variant::Chess960
+        } else {
+            variant::Variant::Standard
+        },
+        ..Default::default()
+    };
+    let report = engine_match::run_match(&[player(&args.first), player(&args.second)], &options)?;
+    println!("{report}");
+    Ok(())
+}
+
+/// Show a game between the two players `args` names, move by move, with the
+/// evaluation of whoever moved last.
+fn watch(args: &WatchArgs, config: &config::Config) -> anyhow::Result<()> {
+    let search_options = solver::SearchOptions::default();
+    let engine_log = args.engine_log.as_ref();
+    let players = [
+        match_player(
+            &args.white,
+            &search_options,
+            args.white_skill,
+            config,
+            engine_log,
+        ),
+        match_player(
+            &args.black,
+            &search_options,
+            args.black_skill,
+            config,
+            engine_log,
+        ),
+    ];
+    let options = engine_match::MatchOptions {
+        games: 1,
+        time_control: args.time,
+        movetime: Duration::from_millis(args.movetime),
+        variant: if args.chess960 {
+            variant::Variant::Chess960
+        } else {
+            variant::Variant::Standard
+        },
+        ..Default::default()
+    };
+    let mut render_options = args.display.render_options(config);
+    let delay = Duration::from_millis(args.delay);
+    engine_match::watch_game(&players, &options, |game, score| {
+        if let Some(score) = score {
+            // the score is from the side that just moved
+            render_options.evaluation = Some(score.for_white(game.get_board().turn.flip()));
+        }
+        render::display_board(game, true, &render_options);
+        if game.result().is_none() {
+            std::thread::sleep(delay);
+        }
+    })?;
+    Ok(())
+}
+
+/// The match player called `name`: the solver, or an engine from the config
+/// file or at that path, weakened to `skill` if given.
+fn match_player(
+    name: &str,
+    search_options: &solver::SearchOptions,
+    skill: Option<u8>,
+    config: &config::Config,
+    engine_log: Option<&PathBuf>,
+) -> engine_match::Player {
+    if name == "solver" {
+        let options = search_options.clone();
+        return engine_match::Player::Solver(match skill {
+            Some(skill) => options.with_skill(skill),
+            None => options,
+        });
+    }
+    let mut engine = config::EngineConfig {
+        log: engine_log.cloned(),
+        ..config.engine(name)
+    };
+    if let Some(skill) = skill {
+        engine.options.insert(
+            "Skill Level".to_string(),
+            config::OptionValue::Integer(skill.into()),
+        );
+    }
+    engine_match::Player::Engine {
+        name: name.to_string(),
+        config: engine,
+    }
+}
+
+/// Count the positions `args.depth` half moves ahead, to check move generation.
+fn perft(args: &PerftArgs) -> anyhow::Result<()> {
+    let game = match &args.fen {
+        Some(fen) => game::ChessGame::from_fen(fen, variant::Variant::Standard)?,
+        None => game::ChessGame::default(),
+    };
+    let started = Instant::now();
+    let nodes = if args.divide {
+        let divided = solver::perft::divide(&game, args.depth);
+        for (mv, count) in &divided {
+            println!("{}: {count}", mv.to_uci());
+        }
+        divided.iter().map(|(_, count)| count).sum()
+    } else {
+        solver::perft::perft(&game, args.depth)
+    };
+    let elapsed = started.elapsed().as_secs_f64();
+    println!("Nodes: {nodes}");
+    println!(
+        "Time: {elapsed:.3}s ({:.0} nodes per second)",
+        nodes as f64 / elapsed.max(f64::EPSILON)
+    );
+    Ok(())
+}
+
+/// Show the solver's best lines in the position `args` describes.
+fn analyze(args: &AnalyzeArgs) -> anyhow::Result<()> {
+    let mut game = match &args.fen {
+        Some(fen) => game::ChessGame::from_fen(fen, variant::Variant::Standard)?,
+        None => game::ChessGame::default(),
+    };
+    if let Some(moves) = &args.moves {
+        for mv in parse_moves(moves, &game)? {
+            game.make_move(&mv)?;
+        }
+    }
+    let turn = game.get_board().turn;
+    let mut tt = solver::tt::TranspositionTable::default();
+    let search = solver::solve_next_move_with_options(&game, &args.search_options()?, &mut tt)?;
+    println!("{}", game.fen());
+    println!(
+        "Depth {}, {} nodes, scores for White:",
+        search.depth, search.nodes
+    );
+    for (number, line) in search.lines.iter().enumerate() {
+        println!(
+            "{}. {} {}",
+            number + 1,
+            line.score.for_white(turn),
+            game.san_line(&line.principal_variation)
+        );
+    }
+    if let Some(database) = args.explorer {
+        println!("{}", explorer::Explorer::fetch(&game, database)?);
+    }
+    Ok(())
+}
+
+/// Step back and forth through a game from `args.file`, showing the board
+/// at each position, with an evaluation if `args.eval` asks for one.
+fn replay(args: &ReplayArgs, config: &config::Config) -> anyhow::Result<()> {
+    let text = std::fs::read_to_string(&args.file)
+        .with_context(|| format!("could not read {}", args.file.display()))?;
+    let games = pgn::parse(&text).with_context(|| format!("in {}", args.file.display()))?;
+    review(
+        &games,
+        &args.file.display().to_string(),
+        &args.review,
+        config,
+    )
+}
+
+/// Fetch the games `args` asks for, list them and step through the one picked.
+/// Games the engine can't play, such as those with promotions, are skipped.
+fn import_games(args: &ImportArgs, config: &config::Config) -> anyhow::Result<()> {
+    let source = import::Source::parse(&args.source, args.site)?;
+    println!("Fetching the games of {source}...");
+    let text = source.fetch(args.max.into())?;
+    if let Some(path) = &args.save {
+        std::fs::write(path, &text)
+            .with_context(|| format!("could not write {}", path.display()))?;
+    }
+    let mut games = Vec::new();
+    for game in pgn::parse_each(&text) {
+        match game {
+            Ok(game) => games.push(game),
+            Err(e) => println!("Skipping a game: {e:#}"),
+        }
+    }
+    anyhow::ensure!(!games.is_empty(), "there are no games to show");
+    for (number, game) in games.iter().enumerate() {
+        println!(
+            "{:>3}. {} {} {}",
+            number + 1,
+            game.tag("Date").unwrap_or("????.??.??"),
+            game.players(),
+            game.tag("Result").unwrap_or("*")
+        );
+    }
+    review(&games, &source.to_string(), &args.review, config)
+}
+
+/// Step through the game of `games` that `args` picks, back and forth at the
+/// user's command.
+fn review(
+    games: &[pgn::PgnGame],
+    source: &str,
+    args: &ReviewArgs,
+    config: &config::Config,
+) -> anyhow::Result<()> {
+    let pgn = args
+        .game
+        .checked_sub(1)
+        .and_then(|index| games.get(index))
+        .ok_or_else(|| {
+            anyhow::anyhow!(
+                "{source} has {} games, there is no game {}",
+                games.len(),
+                args.game
+            )
+        })?;
+    println!("Game {} of {}: {}", args.game, games.len(), pgn.players());
+    let mut evaluator = args.eval.then(|| {
+        let think_time = Duration::from_millis(args.eval_time);
+        match &args.eval_engine {
+            Some(engine) => Hints::engine(config.engine(engine), think_time),
+            None => Hints::solver(
+                solver::SearchOptions {
+                    // a book move comes without a score
+                    book: solver::book::OpeningBook::Disabled,
+                    ..solver::SearchOptions::default()
+                },
+                think_time,
+            ),
+        }
+    });
+    let mut evaluations = std::collections::HashMap::new();
+    // what was played in real games, None once there is nothing to show, after
+    // which the explorer is not asked about the positions that follow
+    let mut statistics = std::collections::HashMap::new();
+    let mut render_options = args.display.render_options(config);
+    // the whole game, so its comments and variations can be shown on the way
+    let mut game = pgn.game()?;
+    game.go_to(&[])?;
+    let mut prompt = Prompt::new()?;
+    loop {
+        let ply = game.played_moves().len();
+        if let Some(evaluator) = &mut evaluator
+            && game.result().is_none()
+            && !evaluations.contains_key(&ply)
+        {
+            match evaluator.suggest(&game) {
+                Ok((best, score)) => {
+                    let turn = game.get_board().turn;
+                    evaluations.insert(ply, (game.san(&best)?, score.for_white(turn)));
+                }
+                Err(e) => println!("Could not evaluate the position: {e:#}"),
+            }
+        }
+        if let Some(database) = args.explorer
+            && !statistics.contains_key(&ply)
+        {
+            let left = ply > 0 && matches!(statistics.get(&(ply - 1)), Some(None));
+            let explorer = if left {
+                None
+            } else {
+                match explorer::Explorer::fetch(&game, database) {
+                    Ok(explorer) => Some(explorer),
+                    Err(e) => {
+                        println!("Could not reach the opening explorer: {e:#}");
+                        None
                     }
-                } else {
-                    println!(
-                        "Invalid input. Please enter moves in the format 'from to' (e.g., 'e2 e4')."
-                    );
                 }
+            };
+            statistics.insert(ply, explorer.filter(|explorer| explorer.games() > 0));
+        }
+        render_options.evaluation = evaluations
+            .get(&ply)
+            .map(|(_, score)| *score)
+            .or(game.evaluation());
+        render::display_board(&game, ply > 0, &render_options);
+        if let Some((best, score)) = evaluations.get(&ply) {
+            println!("{score} for White, best is {best}");
+        }
+        let nags: Vec<String> = game
+            .nags()
+            .iter()
+            .map(|nag| tree::nag_symbol(*nag).map_or_else(|| format!("${nag}"), String::from))
+            .collect();
+        match (nags.is_empty(), game.evaluation()) {
+            (true, None) => {}
+            (false, None) => println!("Annotated {}", nags.join(" ")),
+            (true, Some(score)) => println!("Annotated {score} for White"),
+            (false, Some(score)) => println!("Annotated {}, {score} for White", nags.join(" ")),
+        }
+        if let Some(Some(explorer)) = statistics.get(&ply) {
+            println!("{explorer}");
+        }
+        if let Some(comment) = game.comment() {
+            println!("{{{comment}}}");
+        }
+        let alternatives: Vec<String> = game
+            .continuations()
+            .iter()
+            .skip(1)
+            .filter_map(|mv| game.san(mv).ok())
+            .collect();
+        if !alternatives.is_empty() {
+            println!("Also analysed here: {}", alternatives.join(", "));
+        }
+        let Some(answer) = prompt.read(
+            &format!(
+                "Move {ply} of {}: Enter or n for the next, p for the previous, s or e for the start or end, a number to go to that move, q to stop: ",
+                pgn.moves.len()
+            ),
+            Vec::new(),
+        )?
+        else {
+            break;
+        };
+        let target = match answer.trim() {
+            "" | "n" => ply + 1,
+            "p" => ply.saturating_sub(1),
+            "s" => 0,
+            "e" => pgn.moves.len(),
+            "q" => break,
+            number => match number.parse::<usize>() {
+                Ok(number) => number,
+                Err(_) => {
+                    println!("{number} isn't a move number or one of the keys.");
+                    continue;
+                }
+            },
+        }
+        .min(pgn.moves.len());
+        while game.played_moves().len() > target {
+            game.go_back()?;
+        }
+        for mv in &pgn.moves[game.played_moves().len()..target] {
+            game.make_move(mv)?;
+        }
+    }
+    Ok(())
+}
+
+/// Set the puzzles from `args.file`, or the built-in ones, one after another,
+/// checking each move of the user's answer and keeping count of how many they
+/// solve in a row.
+fn solve_puzzles(args: &PuzzleArgs, config: &config::Config) -> anyhow::Result<()> {
+    let mut puzzles = match &args.file {
+        Some(path) => {
+            let text = std::fs::read_to_string(path)
+                .with_context(|| format!("could not read {}", path.display()))?;
+            puzzle::parse_puzzles(&text)?
+        }
+        None => puzzle::embedded(),
+    };
+    if args.shuffle {
+        use rand::seq::SliceRandom;
+        puzzles.shuffle(&mut rand::rng());
+    }
+    let mut render_options = args.display.render_options(config);
+    let mut prompt = Prompt::new()?;
+    let mut streak = puzzle::Streak::default();
+    'puzzles: for (number, puzzle) in puzzles.iter().enumerate() {
+        let mut game = puzzle.start()?;
+        let turn = game.get_board().turn;
+        render_options.flipped = turn == ChessColour::Black;
+        render::display_board(&game, false, &render_options);
+        println!(
+            "Puzzle {} of {}: {}, {turn} to move.",
+            number + 1,
+            puzzles.len(),
+            puzzle.description
+        );
+        let mut solved = true;
+        for step in (0..puzzle.solution.len()).step_by(2) {
+            let mv = loop {
+                let Some(input) = prompt.read(
+                    "Your move, or 'skip' to see the answer: ",
+                    input::move_completions(&game),
+                )?
+                else {
+                    break 'puzzles;
+                };
+                if input.trim() == "skip" {
+                    break None;
+                }
+                match input::parse_move(&input, &game) {
+                    Ok(mv) => break Some(mv),
+                    Err(e) => println!("{e}"),
+                }
+            };
+            if !mv.is_some_and(|mv| puzzle.accepts(&game, step, &mv)) {
+                let rest = &puzzle.solution[step..];
+                println!("Not quite, the answer was {}.", game.san_line(rest));
+                solved = false;
+                break;
+            }
+            game.make_move(&mv.unwrap_or(puzzle.solution[step]))?;
+            if let Some(reply) = puzzle.solution.get(step + 1) {
+                println!("Correct! The reply is {}.", game.san(reply)?);
+                game.make_move(reply)?;
+                render::display_board(&game, true, &render_options);
             }
+        }
+        streak.record(solved);
+        if solved {
+            render::display_board(&game, true, &render_options);
+            println!("Solved! That's {} in a row.", streak.current);
+        }
+    }
+    println!(
+        "Solved {} of {} puzzles, with a best streak of {}.",
+        streak.solved, streak.attempted, streak.best
+    );
+    Ok(())
+}
+
+/// Show the user's results against the computer and the level suggested for
+/// their next game.
+/// Have the solver, or the engine `args` names, find a move in each of the
+/// positions in an EPD file, and count how many it gets right.
+fn run_testsuite(args: &TestsuiteArgs, config: &config::Config) -> anyhow::Result<()> {
+    let text = std::fs::read_to_string(&args.file)
+        .with_context(|| format!("could not read {}", args.file.display()))?;
+    let positions = epd::parse(&text).with_context(|| format!("in {}", args.file.display()))?;
+    let think_time = Duration::from_millis(args.movetime);
+    let mut hints = match &args.engine {
+        Some(engine) => Hints::engine(config.engine(engine), think_time),
+        None => Hints::solver(
+            solver::SearchOptions {
+                book: solver::book::OpeningBook::Disabled,
+                ..args.solver.search_options()?
+            },
+            think_time,
+        ),
+    };
+    let mut solved = 0;
+    for (number, position) in positions.iter().enumerate() {
+        let game = position.game()?;
+        let (mv, _) = hints.suggest(&game)?;
+        let name = match &position.id {
+            Some(id) => id.clone(),
+            None => format!("Position {}", number + 1),
+        };
+        let san = |moves: &[core::Move]| -> anyhow::Result<String> {
+            let moves: Result<Vec<_>, _> = moves.iter().map(|mv| game.san(mv)).collect();
+            Ok(moves?.join(" or "))
+        };
+        if position.solved_by(&mv) {
+            solved += 1;
+            println!("{name}: {}, solved", game.san(&mv)?);
+        } else if position.best_moves.is_empty() {
+            println!("{name}: {}, which was to be avoided", game.san(&mv)?);
         } else {
-            stockfish
-                .position(&game.fen())
-                .with_context(|| "could not set position in stockfish")?;
-            // stockfish.is_ready().context("not ready after sending position")?;
-            tracing::info!("waiting for stockfish to make a move");
-            let best_move = stockfish
-                .best_move()
-                .expect("could not get best move from stockfish");
-            tracing::info!("Best move: {best_move}");
-            game.make_move(&best_move)
-                .with_context(|| format!("could not make move: {best_move}"))?;
-        }
-        tracing::info!("waiting for solver to make a move");
-        let mv = solver::solve_next_move(&game).with_context(|| "could not solve next move")?;
-        let game_state = game.make_move(&mv)?;
-        status = game_state.status;
-        println!("Opponent played move: {} to {}", mv.from, mv.to);
+            let best = san(&position.best_moves)?;
+            println!("{name}: {}, but the best is {best}", game.san(&mv)?);
+        }
+    }
+    println!(
+        "Solved {solved} of {} ({:.0}%).",
+        positions.len(),
+        100.0 * solved as f64 / positions.len().max(1) as f64
+    );
+    Ok(())
+}
+
+/// Keep a finished game in the game database, if this build has one.
+fn record_game(game: &game::ChessGame, white: &str, black: &str) {
+    #[cfg(feature = "database")]
+    {
+        use chess::database::GameDatabase;
+
+        let Some(path) = GameDatabase::default_path() else {
+            return;
+        };
+        if let Err(e) = GameDatabase::open(&path).and_then(|db| db.record(game, white, black)) {
+            tracing::warn!("Could not keep the game in the game database: {e:#}");
+        }
+    }
+    #[cfg(not(feature = "database"))]
+    let _ = (game, white, black);
+}
+
+/// List the games in the game database that `args` picks, with how they went.
+fn show_games(args: &GamesArgs) -> anyhow::Result<()> {
+    #[cfg(feature = "database")]
+    {
+        use chess::database::{GameDatabase, GameQuery};
+
+        let Some(path) = GameDatabase::default_path() else {
+            return Err(anyhow::anyhow!(
+                "there is no home directory to keep games in"
+            ));
+        };
+        let database = GameDatabase::open(&path)?;
+        let query = GameQuery {
+            opponent: args.opponent.clone(),
+            eco: args.opening.clone(),
+            result: args.result.clone(),
+            from: args.from.clone(),
+            to: args.to.clone(),
+        };
+        let games = database.find(&query)?;
+        if args.pgn {
+            for game in &games {
+                println!("{}", game.pgn);
+            }
+            return Ok(());
+        }
+        if games.is_empty() {
+            println!("No games found.");
+            return Ok(());
+        }
+        for game in &games {
+            println!(
+                "{:>4}  {}  {} - {}  {}  {}",
+                game.id,
+                game.date,
+                game.white,
+                game.black,
+                game.result,
+                game.eco.as_deref().unwrap_or("")
+            );
+        }
+        let results = database.results(&query)?;
+        println!(
+            "{} games: {} won by White, {} drawn, {} won by Black",
+            games.len(),
+            results.white_wins,
+            results.draws,
+            results.black_wins
+        );
+        Ok(())
+    }
+    #[cfg(not(feature = "database"))]
+    {
+        let _ = args;
+        Err(anyhow::anyhow!(
+            "this build has no game database, rebuild with --features database"
+        ))
+    }
+}
+
+fn show_profile() -> anyhow::Result<()> {
+    let Some(path) = profile::Profile::default_path() else {
+        return Err(anyhow::anyhow!(
+            "there is no home directory to keep a profile in"
+        ));
+    };
+    let profile = profile::Profile::load(&path)?;
+    if profile.games.is_empty() {
+        println!("No games against the computer yet.");
+        return Ok(());
     }
+    let (wins, draws, losses) = profile.results();
+    println!(
+        "{} games: {wins} won, {draws} drawn, {losses} lost",
+        profile.games.len()
+    );
+    println!("Estimated rating: {:.0}", profile.rating());
+    println!("Suggested skill level: {}", profile.suggested_skill());
     Ok(())
 }
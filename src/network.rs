@@ -0,0 +1,166 @@
+//! Playing someone on another computer: the messages two instances exchange
+//! over TCP, one JSON object a line, and the connection that carries them.
+//!
+//! The host sends [`Message::Hello`] with the game and the colour the other
+//! side plays, then each side sends its own moves as they are made. Moves
+//! are numbered by the half move they are, so one that arrives out of turn
+//! is noticed, and [`Message::Resync`] asks for the whole game to catch up.
+
+use crate::clock::{Clock, TimeControl};
+use crate::core::{ChessColour, Move};
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpStream;
+
+/// The version of the messages below, which both sides have to agree on.
+pub const PROTOCOL_VERSION: u32 = 1;
+
+/// The port games are hosted on unless another is given.
+pub const DEFAULT_PORT: u16 = 7878;
+
+/// Something one side tells the other.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Message {
+    /// From the host on connecting: the game so far as PGN, the colour the
+    /// other side plays and the time control, if the game has a clock.
+    Hello {
+        version: u32,
+        colour: ChessColour,
+        pgn: String,
+        time_control: Option<TimeControl>,
+    },
+    /// The sender played `mv` as half move `seq`, counting from 1, leaving
+    /// its clock at `clock`.
+    Move {
+        seq: usize,
+        mv: Move,
+        clock: Option<ClockState>,
+    },
+    /// The sender resigned after `seq` half moves.
+    Resign { seq: usize },
+    /// The sender offers a draw after `seq` half moves.
+    OfferDraw { seq: usize },
+    /// The answer to [`Message::OfferDraw`].
+    DrawReply { accepted: bool },
+    /// The sender lost track of the game after `seq` half moves and wants
+    /// [`Message::Sync`].
+    Resync { seq: usize },
+    /// Every move of the game so far.
+    Sync { moves: Vec<Move> },
+    /// The sender left the game.
+    Leave,
+}
+
+/// The time each side had left, in milliseconds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ClockState {
+    pub white_ms: u64,
+    pub black_ms: u64,
+}
+
+impl From<&Clock> for ClockState {
+    fn from(clock: &Clock) -> Self {
+        Self {
+            white_ms: clock.remaining(ChessColour::White).as_millis() as u64,
+            black_ms: clock.remaining(ChessColour::Black).as_millis() as u64,
+        }
+    }
+}
+
+/// A connection to the other player's instance.
+pub struct Connection {
+    reader: BufReader<TcpStream>,
+    writer: TcpStream,
+}
+
+impl Connection {
+    pub fn new(stream: TcpStream) -> anyhow::Result<Self> {
+        // moves are small and should go at once
+        stream.set_nodelay(true)?;
+        Ok(Self {
+            reader: BufReader::new(stream.try_clone()?),
+            writer: stream,
+        })
+    }
+
+    pub fn send(&mut self, message: &Message) -> anyhow::Result<()> {
+        tracing::debug!("sending {message:?}");
+        let mut line = serde_json::to_string(message)?;
+        line.push('\n');
+        self.writer
+            .write_all(line.as_bytes())
+            .context("lost the connection to the other player")
+    }
+
+    /// Wait for the next message, failing if the other side hung up.
+    pub fn receive(&mut self) -> anyhow::Result<Message> {
+        let mut line = String::new();
+        let read = self
+            .reader
+            .read_line(&mut line)
+            .context("lost the connection to the other player")?;
+        anyhow::ensure!(read > 0, "the other player disconnected");
+        let message = serde_json::from_str(&line)
+            .with_context(|| format!("the other player sent {:?}", line.trim()))?;
+        tracing::debug!("received {message:?}");
+        Ok(message)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::net::TcpListener;
+
+    #[test]
+    fn test_messages_round_trip() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let messages = vec![
+            Message::Hello {
+                version: PROTOCOL_VERSION,
+                colour: ChessColour::Black,
+                pgn: "1. e4 *".to_string(),
+                time_control: Some("5+3".parse().unwrap()),
+            },
+            Message::Move {
+                seq: 2,
+                mv: Move::from_uci("e7e5").unwrap(),
+                clock: Some(ClockState {
+                    white_ms: 300_000,
+                    black_ms: 299_000,
+                }),
+            },
+            Message::Resync { seq: 1 },
+            Message::Leave,
+        ];
+        let sent = messages.clone();
+        let sender = std::thread::spawn(move || {
+            let mut connection = Connection::new(TcpStream::connect(address).unwrap()).unwrap();
+            for message in &sent {
+                connection.send(message).unwrap();
+            }
+        });
+        let mut connection = Connection::new(listener.accept().unwrap().0).unwrap();
+        for message in &messages {
+            assert_eq!(&connection.receive().unwrap(), message);
+        }
+        sender.join().unwrap();
+        assert!(connection.receive().is_err());
+    }
+
+    #[test]
+    fn test_message_format() {
+        let message = Message::Move {
+            seq: 1,
+            mv: Move::from_uci("e2e4").unwrap(),
+            clock: None,
+        };
+        assert_eq!(
+            serde_json::to_string(&message).unwrap(),
+            r#"{"type":"move","seq":1,"mv":"e2e4","clock":null}"#
+        );
+    }
+}
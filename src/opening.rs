@@ -0,0 +1,737 @@
+//! Named openings with their ECO codes, for starting a game a few moves in
+//! with `--opening`, and for telling which opening a game is in.
+
+use crate::core::Move;
+use crate::game::ChessGame;
+use crate::variant::Variant;
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::str::FromStr;
+use std::sync::OnceLock;
+
+/// The ECO code, name and moves in UCI notation of each opening.
+const OPENINGS: &[(&str, &str, &str)] = &[
+    ("A01", "Nimzo-Larsen Attack", "b2b3"),
+    ("A02", "Bird's Opening", "f2f4"),
+    ("A04", "Reti Opening", "g1f3"),
+    ("A10", "English Opening", "c2c4"),
+    ("A45", "Trompowsky Attack", "d2d4 g8f6 c1g5"),
+    ("A56", "Benoni Defence", "d2d4 g8f6 c2c4 c7c5"),
+    ("A57", "Benko Gambit", "d2d4 g8f6 c2c4 c7c5 d4d5 b7b5"),
+    ("A80", "Dutch Defence", "d2d4 f7f5"),
+    ("B00", "Nimzowitsch Defence", "e2e4 b8c6"),
+    ("B01", "Scandinavian Defence", "e2e4 d7d5"),
+    ("B02", "Alekhine's Defence", "e2e4 g8f6"),
+    ("B06", "Modern Defence", "e2e4 g7g6"),
+    ("B07", "Pirc Defence", "e2e4 d7d6 d2d4 g8f6 b1c3 g7g6"),
+    ("B10", "Caro-Kann Defence", "e2e4 c7c6"),
+    (
+        "B12",
+        "Caro-Kann Advance Variation",
+        "e2e4 c7c6 d2d4 d7d5 e4e5",
+    ),
+    ("B20", "Sicilian Defence", "e2e4 c7c5"),
+    (
+        "B21",
+        "Sicilian Smith-Morra Gambit",
+        "e2e4 c7c5 d2d4 c5d4 c2c3",
+    ),
+    ("B22", "Sicilian Alapin", "e2e4 c7c5 c2c3"),
+    ("B23", "Sicilian Closed", "e2e4 c7c5 b1c3"),
+    (
+        "B33",
+        "Sicilian Sveshnikov",
+        "e2e4 c7c5 g1f3 b8c6 d2d4 c5d4 f3d4 g8f6 b1c3 e7e5",
+    ),
+    (
+        "B34",
+        "Sicilian Accelerated Dragon",
+        "e2e4 c7c5 g1f3 b8c6 d2d4 c5d4 f3d4 g7g6",
+    ),
+    (
+        "B46",
+        "Sicilian Taimanov",
+        "e2e4 c7c5 g1f3 e7e6 d2d4 c5d4 f3d4 b8c6 b1c3 a7a6",
+    ),
+    (
+        "B70",
+        "Sicilian Dragon",
+        "e2e4 c7c5 g1f3 d7d6 d2d4 c5d4 f3d4 g8f6 b1c3 g7g6",
+    ),
+    (
+        "B90",
+        "Sicilian Najdorf",
+        "e2e4 c7c5 g1f3 d7d6 d2d4 c5d4 f3d4 g8f6 b1c3 a7a6",
+    ),
+    ("C00", "French Defence", "e2e4 e7e6"),
+    (
+        "C02",
+        "French Advance Variation",
+        "e2e4 e7e6 d2d4 d7d5 e4e5",
+    ),
+    (
+        "C03",
+        "French Tarrasch Variation",
+        "e2e4 e7e6 d2d4 d7d5 b1d2",
+    ),
+    (
+        "C15",
+        "French Winawer Variation",
+        "e2e4 e7e6 d2d4 d7d5 b1c3 f8b4",
+    ),
+    ("C21", "Danish Gambit", "e2e4 e7e5 d2d4 e5d4 c2c3"),
+    ("C23", "Bishop's Opening", "e2e4 e7e5 f1c4"),
+    ("C25", "Vienna Game", "e2e4 e7e5 b1c3"),
+    ("C30", "King's Gambit", "e2e4 e7e5 f2f4"),
+    ("C33", "King's Gambit Accepted", "e2e4 e7e5 f2f4 e5f4"),
+    ("C41", "Philidor Defence", "e2e4 e7e5 g1f3 d7d6"),
+    ("C42", "Petrov Defence", "e2e4 e7e5 g1f3 g8f6"),
+    ("C45", "Scotch Game", "e2e4 e7e5 g1f3 b8c6 d2d4 e5d4 f3d4"),
+    ("C47", "Four Knights Game", "e2e4 e7e5 g1f3 b8c6 b1c3 g8f6"),
+    ("C50", "Italian Game", "e2e4 e7e5 g1f3 b8c6 f1c4"),
+    ("C51", "Evans Gambit", "e2e4 e7e5 g1f3 b8c6 f1c4 f8c5 b2b4"),
+    (
+        "C55",
+        "Two Knights Defence",
+        "e2e4 e7e5 g1f3 b8c6 f1c4 g8f6",
+    ),
+    (
+        "C57",
+        "Fried Liver Attack",
+        "e2e4 e7e5 g1f3 b8c6 f1c4 g8f6 f3g5 d7d5 e4d5 f6d5 g5f7",
+    ),
+    ("C60", "Ruy Lopez", "e2e4 e7e5 g1f3 b8c6 f1b5"),
+    (
+        "C65",
+        "Ruy Lopez Berlin Defence",
+        "e2e4 e7e5 g1f3 b8c6 f1b5 g8f6",
+    ),
+    (
+        "C68",
+        "Ruy Lopez Exchange Variation",
+        "e2e4 e7e5 g1f3 b8c6 f1b5 a7a6 b5c6",
+    ),
+    (
+        "C84",
+        "Ruy Lopez Closed",
+        "e2e4 e7e5 g1f3 b8c6 f1b5 a7a6 b5a4 g8f6 e1g1 f8e7",
+    ),
+    (
+        "C89",
+        "Ruy Lopez Marshall Attack",
+        "e2e4 e7e5 g1f3 b8c6 f1b5 a7a6 b5a4 g8f6 e1g1 f8e7 f1e1 b7b5 a4b3 e8g8 c2c3 d7d5",
+    ),
+    ("D00", "Blackmar-Diemer Gambit", "d2d4 d7d5 e2e4"),
+    ("D02", "London System", "d2d4 d7d5 g1f3 g8f6 c1f4"),
+    ("D06", "Queen's Gambit", "d2d4 d7d5 c2c4"),
+    ("D08", "Albin Countergambit", "d2d4 d7d5 c2c4 e7e5"),
+    ("D10", "Slav Defence", "d2d4 d7d5 c2c4 c7c6"),
+    ("D20", "Queen's Gambit Accepted", "d2d4 d7d5 c2c4 d5c4"),
+    ("D30", "Queen's Gambit Declined", "d2d4 d7d5 c2c4 e7e6"),
+    (
+        "D43",
+        "Semi-Slav Defence",
+        "d2d4 d7d5 c2c4 c7c6 g1f3 g8f6 b1c3 e7e6",
+    ),
+    ("D80", "Grunfeld Defence", "d2d4 g8f6 c2c4 g7g6 b1c3 d7d5"),
+    (
+        "E01",
+        "Catalan Opening",
+        "d2d4 g8f6 c2c4 e7e6 g2g3 d7d5 f1g2",
+    ),
+    (
+        "E11",
+        "Bogo-Indian Defence",
+        "d2d4 g8f6 c2c4 e7e6 g1f3 f8b4",
+    ),
+    (
+        "E12",
+        "Queen's Indian Defence",
+        "d2d4 g8f6 c2c4 e7e6 g1f3 b7b6",
+    ),
+    (
+        "E20",
+        "Nimzo-Indian Defence",
+        "d2d4 g8f6 c2c4 e7e6 b1c3 f8b4",
+    ),
+    ("E60", "King's Indian Defence", "d2d4 g8f6 c2c4 g7g6"),
+];
+
+/// Further positions with names of their own, which games are placed in but
+/// `--opening` doesn't start from. [`OPENINGS`] names the positions both have.
+const CLASSIFICATION: &[(&str, &str, &str)] = &[
+    ("A00", "Polish Opening", "b2b4"),
+    ("A00", "Grob Opening", "g2g4"),
+    ("A00", "Van't Kruijs Opening", "e2e3"),
+    ("A00", "Mieses Opening", "d2d3"),
+    ("A00", "Saragossa Opening", "c2c3"),
+    ("A00", "Dunst Opening", "b1c3"),
+    ("A00", "Benko's Opening", "g2g3"),
+    ("A02", "Bird's Opening, From's Gambit", "f2f4 e7e5"),
+    ("A03", "Bird's Opening, Dutch Variation", "f2f4 d7d5"),
+    ("A06", "Reti Opening", "g1f3 d7d5"),
+    ("A07", "King's Indian Attack", "g1f3 d7d5 g2g3"),
+    ("A09", "Reti Opening", "g1f3 d7d5 c2c4"),
+    ("A13", "English Opening, Agincourt Defence", "c2c4 e7e6"),
+    ("A15", "English Opening, Anglo-Indian Defence", "c2c4 g8f6"),
+    (
+        "A20",
+        "English Opening, King's English Variation",
+        "c2c4 e7e5",
+    ),
+    ("A30", "English Opening, Symmetrical Variation", "c2c4 c7c5"),
+    ("A40", "Queen's Pawn Game", "d2d4"),
+    ("A40", "Englund Gambit", "d2d4 e7e5"),
+    ("A40", "Modern Defence", "d2d4 g7g6"),
+    ("A43", "Old Benoni Defence", "d2d4 c7c5"),
+    ("A45", "Indian Defence", "d2d4 g8f6"),
+    ("A50", "Indian Defence", "d2d4 g8f6 c2c4"),
+    ("A51", "Budapest Gambit", "d2d4 g8f6 c2c4 e7e5"),
+    ("A53", "Old Indian Defence", "d2d4 g8f6 c2c4 d7d6"),
+    ("A60", "Modern Benoni", "d2d4 g8f6 c2c4 c7c5 d4d5 e7e6"),
+    (
+        "A87",
+        "Dutch Defence, Leningrad Variation",
+        "d2d4 f7f5 c2c4 g8f6 g2g3 g7g6 f1g2 f8g7 g1f3",
+    ),
+    ("B00", "King's Pawn Game", "e2e4"),
+    ("B00", "Owen's Defence", "e2e4 b7b6"),
+    ("B00", "St. George Defence", "e2e4 a7a6"),
+    ("B03", "Alekhine's Defence", "e2e4 g8f6 e4e5 f6d5 d2d4"),
+    (
+        "B04",
+        "Alekhine's Defence, Modern Variation",
+        "e2e4 g8f6 e4e5 f6d5 d2d4 d7d6 g1f3",
+    ),
+    ("B07", "Pirc Defence", "e2e4 d7d6"),
+    (
+        "B09",
+        "Pirc Defence, Austrian Attack",
+        "e2e4 d7d6 d2d4 g8f6 b1c3 g7g6 f2f4",
+    ),
+    (
+        "B10",
+        "Caro-Kann Defence, Two Knights Attack",
+        "e2e4 c7c6 b1c3 d7d5 g1f3",
+    ),
+    (
+        "B13",
+        "Caro-Kann Defence, Exchange Variation",
+        "e2e4 c7c6 d2d4 d7d5 e4d5 c6d5",
+    ),
+    (
+        "B13",
+        "Caro-Kann Defence, Panov Attack",
+        "e2e4 c7c6 d2d4 d7d5 e4d5 c6d5 c2c4",
+    ),
+    ("B15", "Caro-Kann Defence", "e2e4 c7c6 d2d4 d7d5 b1c3"),
+    (
+        "B17",
+        "Caro-Kann Defence, Steinitz Variation",
+        "e2e4 c7c6 d2d4 d7d5 b1c3 d5e4 c3e4 b8d7",
+    ),
+    (
+        "B18",
+        "Caro-Kann Defence, Classical Variation",
+        "e2e4 c7c6 d2d4 d7d5 b1c3 d5e4 c3e4 c8f5",
+    ),
+    (
+        "B21",
+        "Sicilian Defence, Grand Prix Attack",
+        "e2e4 c7c5 f2f4",
+    ),
+    ("B27", "Sicilian Defence", "e2e4 c7c5 g1f3"),
+    (
+        "B30",
+        "Sicilian Defence, Old Sicilian",
+        "e2e4 c7c5 g1f3 b8c6",
+    ),
+    (
+        "B32",
+        "Sicilian Defence, Open",
+        "e2e4 c7c5 g1f3 b8c6 d2d4 c5d4 f3d4",
+    ),
+    (
+        "B40",
+        "Sicilian Defence, French Variation",
+        "e2e4 c7c5 g1f3 e7e6",
+    ),
+    (
+        "B41",
+        "Sicilian Defence, Kan Variation",
+        "e2e4 c7c5 g1f3 e7e6 d2d4 c5d4 f3d4 a7a6",
+    ),
+    (
+        "B44",
+        "Sicilian Defence, Taimanov Variation",
+        "e2e4 c7c5 g1f3 e7e6 d2d4 c5d4 f3d4 b8c6",
+    ),
+    (
+        "B45",
+        "Sicilian Defence, Four Knights Variation",
+        "e2e4 c7c5 g1f3 e7e6 d2d4 c5d4 f3d4 g8f6 b1c3 b8c6",
+    ),
+    (
+        "B50",
+        "Sicilian Defence, Modern Variations",
+        "e2e4 c7c5 g1f3 d7d6",
+    ),
+    (
+        "B51",
+        "Sicilian Defence, Moscow Variation",
+        "e2e4 c7c5 g1f3 d7d6 f1b5",
+    ),
+    (
+        "B54",
+        "Sicilian Defence, Open",
+        "e2e4 c7c5 g1f3 d7d6 d2d4 c5d4 f3d4",
+    ),
+    (
+        "B56",
+        "Sicilian Defence, Open",
+        "e2e4 c7c5 g1f3 d7d6 d2d4 c5d4 f3d4 g8f6 b1c3",
+    ),
+    (
+        "B58",
+        "Sicilian Defence, Classical Variation",
+        "e2e4 c7c5 g1f3 d7d6 d2d4 c5d4 f3d4 g8f6 b1c3 b8c6",
+    ),
+    (
+        "B60",
+        "Sicilian Defence, Richter-Rauzer Attack",
+        "e2e4 c7c5 g1f3 d7d6 d2d4 c5d4 f3d4 g8f6 b1c3 b8c6 c1g5",
+    ),
+    (
+        "B80",
+        "Sicilian Defence, Scheveningen Variation",
+        "e2e4 c7c5 g1f3 d7d6 d2d4 c5d4 f3d4 g8f6 b1c3 e7e6",
+    ),
+    (
+        "B92",
+        "Sicilian Najdorf, Opocensky Variation",
+        "e2e4 c7c5 g1f3 d7d6 d2d4 c5d4 f3d4 g8f6 b1c3 a7a6 f1e2",
+    ),
+    (
+        "C01",
+        "French Defence, Exchange Variation",
+        "e2e4 e7e6 d2d4 d7d5 e4d5",
+    ),
+    (
+        "C10",
+        "French Defence, Paulsen Variation",
+        "e2e4 e7e6 d2d4 d7d5 b1c3",
+    ),
+    (
+        "C10",
+        "French Defence, Rubinstein Variation",
+        "e2e4 e7e6 d2d4 d7d5 b1c3 d5e4",
+    ),
+    (
+        "C11",
+        "French Defence, Classical Variation",
+        "e2e4 e7e6 d2d4 d7d5 b1c3 g8f6",
+    ),
+    ("C20", "King's Pawn Game", "e2e4 e7e5"),
+    ("C20", "Wayward Queen Attack", "e2e4 e7e5 d1h5"),
+    ("C22", "Centre Game", "e2e4 e7e5 d2d4 e5d4 d1d4"),
+    (
+        "C24",
+        "Bishop's Opening, Berlin Defence",
+        "e2e4 e7e5 f1c4 g8f6",
+    ),
+    ("C26", "Vienna Game", "e2e4 e7e5 b1c3 g8f6"),
+    ("C29", "Vienna Gambit", "e2e4 e7e5 b1c3 g8f6 f2f4"),
+    (
+        "C30",
+        "King's Gambit Declined, Classical Variation",
+        "e2e4 e7e5 f2f4 f8c5",
+    ),
+    (
+        "C31",
+        "King's Gambit Declined, Falkbeer Countergambit",
+        "e2e4 e7e5 f2f4 d7d5",
+    ),
+    (
+        "C39",
+        "King's Gambit Accepted",
+        "e2e4 e7e5 f2f4 e5f4 g1f3 g7g5 h2h4",
+    ),
+    ("C40", "King's Knight Opening", "e2e4 e7e5 g1f3"),
+    ("C40", "Latvian Gambit", "e2e4 e7e5 g1f3 f7f5"),
+    ("C40", "Elephant Gambit", "e2e4 e7e5 g1f3 d7d5"),
+    (
+        "C43",
+        "Petrov Defence, Steinitz Attack",
+        "e2e4 e7e5 g1f3 g8f6 d2d4",
+    ),
+    ("C44", "King's Knight Opening", "e2e4 e7e5 g1f3 b8c6"),
+    ("C44", "Ponziani Opening", "e2e4 e7e5 g1f3 b8c6 c2c3"),
+    ("C44", "Scotch Gambit", "e2e4 e7e5 g1f3 b8c6 d2d4 e5d4 f1c4"),
+    ("C46", "Three Knights Game", "e2e4 e7e5 g1f3 b8c6 b1c3"),
+    (
+        "C47",
+        "Four Knights Game, Scotch Variation",
+        "e2e4 e7e5 g1f3 b8c6 b1c3 g8f6 d2d4",
+    ),
+    (
+        "C48",
+        "Four Knights Game, Spanish Variation",
+        "e2e4 e7e5 g1f3 b8c6 b1c3 g8f6 f1b5",
+    ),
+    ("C50", "Giuoco Piano", "e2e4 e7e5 g1f3 b8c6 f1c4 f8c5"),
+    ("C50", "Hungarian Defence", "e2e4 e7e5 g1f3 b8c6 f1c4 f8e7"),
+    (
+        "C53",
+        "Giuoco Piano, Main Line",
+        "e2e4 e7e5 g1f3 b8c6 f1c4 f8c5 c2c3",
+    ),
+    (
+        "C54",
+        "Giuoco Piano",
+        "e2e4 e7e5 g1f3 b8c6 f1c4 f8c5 c2c3 g8f6 d2d4",
+    ),
+    (
+        "C57",
+        "Two Knights Defence, Knight Attack",
+        "e2e4 e7e5 g1f3 b8c6 f1c4 g8f6 f3g5",
+    ),
+    (
+        "C58",
+        "Two Knights Defence, Polerio Defence",
+        "e2e4 e7e5 g1f3 b8c6 f1c4 g8f6 f3g5 d7d5 e4d5 c6a5",
+    ),
+    (
+        "C62",
+        "Ruy Lopez, Steinitz Defence",
+        "e2e4 e7e5 g1f3 b8c6 f1b5 d7d6",
+    ),
+    (
+        "C63",
+        "Ruy Lopez, Schliemann Defence",
+        "e2e4 e7e5 g1f3 b8c6 f1b5 f7f5",
+    ),
+    (
+        "C64",
+        "Ruy Lopez, Classical Variation",
+        "e2e4 e7e5 g1f3 b8c6 f1b5 f8c5",
+    ),
+    (
+        "C67",
+        "Ruy Lopez, Berlin Defence, Rio de Janeiro Variation",
+        "e2e4 e7e5 g1f3 b8c6 f1b5 g8f6 e1g1 f6e4",
+    ),
+    (
+        "C70",
+        "Ruy Lopez, Morphy Defence",
+        "e2e4 e7e5 g1f3 b8c6 f1b5 a7a6 b5a4",
+    ),
+    (
+        "C78",
+        "Ruy Lopez, Morphy Defence",
+        "e2e4 e7e5 g1f3 b8c6 f1b5 a7a6 b5a4 g8f6 e1g1",
+    ),
+    (
+        "C80",
+        "Ruy Lopez, Open Variation",
+        "e2e4 e7e5 g1f3 b8c6 f1b5 a7a6 b5a4 g8f6 e1g1 f6e4",
+    ),
+    (
+        "C88",
+        "Ruy Lopez, Closed",
+        "e2e4 e7e5 g1f3 b8c6 f1b5 a7a6 b5a4 g8f6 e1g1 f8e7 f1e1 b7b5 a4b3",
+    ),
+    ("D00", "Queen's Pawn Game", "d2d4 d7d5"),
+    ("D01", "Richter-Veresov Attack", "d2d4 d7d5 b1c3 g8f6 c1g5"),
+    ("D02", "Queen's Pawn Game", "d2d4 d7d5 g1f3"),
+    ("D04", "Colle System", "d2d4 d7d5 g1f3 g8f6 e2e3"),
+    (
+        "D07",
+        "Queen's Gambit Declined, Chigorin Defence",
+        "d2d4 d7d5 c2c4 b8c6",
+    ),
+    (
+        "D10",
+        "Slav Defence, Exchange Variation",
+        "d2d4 d7d5 c2c4 c7c6 c4d5 c6d5",
+    ),
+    ("D11", "Slav Defence", "d2d4 d7d5 c2c4 c7c6 g1f3"),
+    ("D15", "Slav Defence", "d2d4 d7d5 c2c4 c7c6 g1f3 g8f6 b1c3"),
+    ("D21", "Queen's Gambit Accepted", "d2d4 d7d5 c2c4 d5c4 g1f3"),
+    ("D31", "Queen's Gambit Declined", "d2d4 d7d5 c2c4 e7e6 b1c3"),
+    ("D32", "Tarrasch Defence", "d2d4 d7d5 c2c4 e7e6 b1c3 c7c5"),
+    (
+        "D35",
+        "Queen's Gambit Declined",
+        "d2d4 d7d5 c2c4 e7e6 b1c3 g8f6",
+    ),
+    (
+        "D35",
+        "Queen's Gambit Declined, Exchange Variation",
+        "d2d4 d7d5 c2c4 e7e6 b1c3 g8f6 c4d5",
+    ),
+    (
+        "D37",
+        "Queen's Gambit Declined",
+        "d2d4 d7d5 c2c4 e7e6 b1c3 g8f6 g1f3",
+    ),
+    (
+        "D38",
+        "Queen's Gambit Declined, Ragozin Defence",
+        "d2d4 d7d5 c2c4 e7e6 b1c3 g8f6 g1f3 f8b4",
+    ),
+    (
+        "D50",
+        "Queen's Gambit Declined",
+        "d2d4 d7d5 c2c4 e7e6 b1c3 g8f6 c1g5",
+    ),
+    (
+        "D85",
+        "Grunfeld Defence, Exchange Variation",
+        "d2d4 g8f6 c2c4 g7g6 b1c3 d7d5 c4d5 f6d5",
+    ),
+    (
+        "D90",
+        "Grunfeld Defence, Three Knights Variation",
+        "d2d4 g8f6 c2c4 g7g6 b1c3 d7d5 g1f3",
+    ),
+    ("E00", "Indian Defence", "d2d4 g8f6 c2c4 e7e6"),
+    ("E10", "Indian Defence", "d2d4 g8f6 c2c4 e7e6 g1f3"),
+    (
+        "E21",
+        "Nimzo-Indian Defence, Three Knights Variation",
+        "d2d4 g8f6 c2c4 e7e6 b1c3 f8b4 g1f3",
+    ),
+    (
+        "E32",
+        "Nimzo-Indian Defence, Classical Variation",
+        "d2d4 g8f6 c2c4 e7e6 b1c3 f8b4 d1c2",
+    ),
+    (
+        "E40",
+        "Nimzo-Indian Defence, Rubinstein Variation",
+        "d2d4 g8f6 c2c4 e7e6 b1c3 f8b4 e2e3",
+    ),
+    (
+        "E61",
+        "King's Indian Defence",
+        "d2d4 g8f6 c2c4 g7g6 b1c3 f8g7",
+    ),
+    (
+        "E62",
+        "King's Indian Defence, Fianchetto Variation",
+        "d2d4 g8f6 c2c4 g7g6 g1f3 f8g7 g2g3",
+    ),
+    (
+        "E70",
+        "King's Indian Defence, Normal Variation",
+        "d2d4 g8f6 c2c4 g7g6 b1c3 f8g7 e2e4 d7d6",
+    ),
+    (
+        "E76",
+        "King's Indian Defence, Four Pawns Attack",
+        "d2d4 g8f6 c2c4 g7g6 b1c3 f8g7 e2e4 d7d6 f2f4",
+    ),
+    (
+        "E80",
+        "King's Indian Defence, Samisch Variation",
+        "d2d4 g8f6 c2c4 g7g6 b1c3 f8g7 e2e4 d7d6 f2f3",
+    ),
+    (
+        "E90",
+        "King's Indian Defence, Normal Variation",
+        "d2d4 g8f6 c2c4 g7g6 b1c3 f8g7 e2e4 d7d6 g1f3",
+    ),
+    (
+        "E92",
+        "King's Indian Defence, Classical Variation",
+        "d2d4 g8f6 c2c4 g7g6 b1c3 f8g7 e2e4 d7d6 g1f3 e8g8 f1e2 e7e5",
+    ),
+    (
+        "E97",
+        "King's Indian Defence, Mar del Plata Variation",
+        "d2d4 g8f6 c2c4 g7g6 b1c3 f8g7 e2e4 d7d6 g1f3 e8g8 f1e2 e7e5 e1g1 b8c6 d4d5 c6e7",
+    ),
+];
+
+/// One of the openings `chess openings` lists.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Opening {
+    pub eco: &'static str,
+    pub name: &'static str,
+    /// The book moves, in UCI notation.
+    pub line: &'static str,
+}
+
+impl Opening {
+    pub fn moves(&self) -> Vec<Move> {
+        self.line
+            .split_whitespace()
+            .map(|mv| Move::from_uci(mv).expect("the embedded openings are valid"))
+            .collect()
+    }
+
+    /// Play the book moves in `game`, which has to be at the usual starting
+    /// position.
+    pub fn play(&self, game: &mut ChessGame) -> anyhow::Result<()> {
+        for mv in self.moves() {
+            game.make_move(&mv)?;
+        }
+        Ok(())
+    }
+}
+
+impl Display for Opening {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {}", self.eco, self.name)
+    }
+}
+
+/// Finds an opening by its ECO code or name, ignoring case and punctuation,
+/// so `B90`, `najdorf` and `kings gambit` all work. When the name is part of
+/// several openings' names, it picks the one the rest are variations of, so
+/// `sicilian` is the Sicilian Defence.
+impl FromStr for Opening {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let query = normalise(s);
+        if let Some(opening) = all().find(|opening| {
+            opening.eco.eq_ignore_ascii_case(s.trim()) || normalise(opening.name) == query
+        }) {
+            return Ok(opening);
+        }
+        let matches: Vec<_> = all()
+            .filter(|opening| !query.is_empty() && normalise(opening.name).contains(&query))
+            .collect();
+        let shortest = matches.iter().min_by_key(|opening| opening.line.len());
+        match shortest {
+            None => Err(anyhow::anyhow!(
+                "no opening called {s}, see `chess openings` for the list"
+            )),
+            Some(parent)
+                if matches
+                    .iter()
+                    .all(|opening| opening.line.starts_with(parent.line)) =>
+            {
+                Ok(*parent)
+            }
+            Some(_) => Err(anyhow::anyhow!(
+                "{s} could be any of {}",
+                matches
+                    .iter()
+                    .map(|opening| opening.name)
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )),
+        }
+    }
+}
+
+/// Every opening, in ECO order.
+pub fn all() -> impl Iterator<Item = Opening> {
+    OPENINGS
+        .iter()
+        .map(|&(eco, name, line)| Opening { eco, name, line })
+}
+
+/// The opening `game` is in: the last position it went through that has a
+/// name, so a game that reaches one by another order of moves is placed too.
+/// Only games from the usual starting position are.
+pub fn classify(game: &ChessGame) -> Option<Opening> {
+    if game.variant() != Variant::Standard || game.start_fen().is_some() {
+        return None;
+    }
+    let positions = positions();
+    game.position_history()
+        .iter()
+        .rev()
+        .find_map(|key| positions.get(key).copied())
+}
+
+/// Every named position by its key, worked out the first time it's needed.
+fn positions() -> &'static HashMap<u64, Opening> {
+    static POSITIONS: OnceLock<HashMap<u64, Opening>> = OnceLock::new();
+    POSITIONS.get_or_init(|| {
+        let further = CLASSIFICATION
+            .iter()
+            .map(|&(eco, name, line)| Opening { eco, name, line });
+        let mut positions = HashMap::new();
+        for opening in all().chain(further) {
+            let mut game = ChessGame::default();
+            opening
+                .play(&mut game)
+                .expect("the embedded openings are valid");
+            let key = *game.position_history().last().unwrap();
+            positions.entry(key).or_insert(opening);
+        }
+        positions
+    })
+}
+
+fn normalise(name: &str) -> String {
+    name.chars()
+        .filter(char::is_ascii_alphanumeric)
+        .map(|c| c.to_ascii_lowercase())
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_openings() {
+        for opening in all() {
+            let mut game = ChessGame::default();
+            assert!(opening.play(&mut game).is_ok(), "{opening}");
+        }
+
+        let najdorf: Opening = "Sicilian Najdorf".parse().unwrap();
+        assert_eq!(najdorf.eco, "B90");
+        assert_eq!("b90".parse::<Opening>().unwrap(), najdorf);
+        assert_eq!("najdorf".parse::<Opening>().unwrap(), najdorf);
+        assert_eq!("kings gambit".parse::<Opening>().unwrap().eco, "C30");
+        // the other Sicilians are variations of the Sicilian Defence
+        assert_eq!("sicilian".parse::<Opening>().unwrap().eco, "B20");
+        assert!("gambit".parse::<Opening>().is_err());
+        assert!("Hippopotamus".parse::<Opening>().is_err());
+
+        let mut game = ChessGame::default();
+        najdorf.play(&mut game).unwrap();
+        assert_eq!(
+            game.fen(),
+            "rnbqkb1r/1p2pppp/p2p1n2/8/3NP3/2N5/PPP2PPP/R1BQKB1R w KQkq - 0 6"
+        );
+
+        game.make_move(&Move::from_uci("c1g5").unwrap()).unwrap();
+        assert_eq!(classify(&game), Some(najdorf));
+        assert!(
+            game.pgn()
+                .unwrap()
+                .contains("[ECO \"B90\"]\n[Opening \"Sicilian Najdorf\"]\n")
+        );
+        let mut set_up = ChessGame::from_fen(&game.fen(), Variant::Standard).unwrap();
+        set_up.make_move(&Move::from_uci("d8b6").unwrap()).unwrap();
+        assert_eq!(classify(&set_up), None);
+
+        let play = |moves: &str| {
+            let mut game = ChessGame::default();
+            for mv in moves.split_whitespace() {
+                game.make_move(&Move::from_uci(mv).unwrap()).unwrap();
+            }
+            classify(&game).map(|opening| opening.to_string())
+        };
+        assert_eq!(play(""), None);
+        assert_eq!(play("f2f3 e7e5 g2g4"), None);
+        assert_eq!(play("e2e4 c7c5 g1f3").unwrap(), "B27 Sicilian Defence");
+        assert_eq!(
+            play("e2e4 c7c5 g1f3 a7a6 h2h3").unwrap(),
+            "B27 Sicilian Defence"
+        );
+        // the Queen's Gambit Declined by way of the Indian Defence
+        assert_eq!(
+            play("d2d4 g8f6 c2c4 e7e6 b1c3 d7d5").unwrap(),
+            "D35 Queen's Gambit Declined"
+        );
+    }
+}
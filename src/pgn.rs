@@ -0,0 +1,445 @@
+//! Reading games from PGN files: the tags, the moves of the main line, and
+//! the variations around them, with the comments, NAGs and `[%eval]`
+//! evaluations annotating the moves.
+
+use crate::core::Move;
+use crate::game::{ChessGame, GameResult, Termination};
+use crate::input::parse_move;
+use crate::tree::{self, GameTree};
+use crate::variant::Variant;
+use anyhow::Context;
+
+/// One game from a PGN file.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct PgnGame {
+    /// The tag pairs, in the order they were given.
+    pub tags: Vec<(String, String)>,
+    /// The moves of the main line.
+    pub moves: Vec<Move>,
+    /// The main line with its variations and comments.
+    pub tree: GameTree,
+}
+
+impl PgnGame {
+    pub fn tag(&self, name: &str) -> Option<&str> {
+        self.tags
+            .iter()
+            .find(|(tag, _)| tag == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// The position the game starts from, as its `FEN` and `Variant` tags
+    /// give it.
+    pub fn start(&self) -> anyhow::Result<ChessGame> {
+        let variant = match self.tag("Variant") {
+            Some(variant) if variant.eq_ignore_ascii_case("chess960") => Variant::Chess960,
+            _ => Variant::Standard,
+        };
+        Ok(match self.tag("FEN") {
+            Some(fen) => ChessGame::from_fen(fen, variant)?,
+            None => ChessGame::default(),
+        })
+    }
+
+    /// The game at the end of its main line, with the variations and comments
+    /// to explore, ended as its `Result` tag says if its moves don't end it.
+    pub fn game(&self) -> anyhow::Result<ChessGame> {
+        let mut game = self.start()?;
+        game.play_tree(&self.tree)?;
+        if game.result().is_none()
+            && let Some(result) = self.result()
+        {
+            game.record_result(result)?;
+        }
+        Ok(game)
+    }
+
+    /// The result the `Result` tag gives, if the game finished. PGN only
+    /// records the score, so unless the `Termination` tag says the game was
+    /// lost on time or adjudicated, a win is taken to be by resignation and a
+    /// draw by agreement.
+    pub fn result(&self) -> Option<GameResult> {
+        let termination = match self.tag("Termination") {
+            Some(t) if t.eq_ignore_ascii_case("time forfeit") => Some(Termination::Timeout),
+            Some(t) if t.eq_ignore_ascii_case("adjudication") => Some(Termination::Adjudication),
+            _ => None,
+        };
+        match self.tag("Result")? {
+            "1-0" => Some(GameResult::WhiteWins(
+                termination.unwrap_or(Termination::Resignation),
+            )),
+            "0-1" => Some(GameResult::BlackWins(
+                termination.unwrap_or(Termination::Resignation),
+            )),
+            "1/2-1/2" => Some(GameResult::Draw(
+                termination.unwrap_or(Termination::Agreement),
+            )),
+            _ => None,
+        }
+    }
+
+    /// Who played, e.g. "Carlsen - Nepomniachtchi".
+    pub fn players(&self) -> String {
+        format!(
+            "{} - {}",
+            self.tag("White").unwrap_or("?"),
+            self.tag("Black").unwrap_or("?")
+        )
+    }
+}
+
+/// Every game in `text`. A file of just movetext, without tags, is read as
+/// one game.
+pub fn parse(text: &str) -> anyhow::Result<Vec<PgnGame>> {
+    parse_each(text).into_iter().collect()
+}
+
+/// Like [`parse`], but reading on past the games that fail, for files from
+/// elsewhere that may hold a game this crate can't play.
+pub fn parse_each(text: &str) -> Vec<anyhow::Result<PgnGame>> {
+    let mut games = Vec::new();
+    let mut current = PgnGame::default();
+    let mut movetext = String::new();
+    let mut finish = |current: &mut PgnGame, movetext: &mut String| {
+        if current.tags.is_empty() && movetext.trim().is_empty() {
+            return;
+        }
+        let mut game = std::mem::take(current);
+        let played = game.start().and_then(|start| {
+            parse_movetext(movetext, &start)
+                .with_context(|| format!("in the game {}", game.players()))
+        });
+        movetext.clear();
+        games.push(played.map(|played| {
+            game.moves = played.played_moves().to_vec();
+            game.tree = played.tree().clone();
+            game
+        }));
+    };
+    for line in text.lines() {
+        let line = line.trim();
+        if line.starts_with('[') && !in_comment(&movetext) {
+            // a tag after some moves starts the next game
+            if !movetext.trim().is_empty() {
+                finish(&mut current, &mut movetext);
+            }
+            match parse_tag(line) {
+                Ok(tag) => current.tags.push(tag),
+                Err(e) => {
+                    games.push(Err(e));
+                    return games;
+                }
+            }
+        } else {
+            movetext.push_str(line);
+            movetext.push('\n');
+        }
+    }
+    finish(&mut current, &mut movetext);
+    games
+}
+
+/// Whether `movetext` stops inside a `{` comment, which can run over lines.
+fn in_comment(movetext: &str) -> bool {
+    movetext.matches('{').count() > movetext.matches('}').count()
+}
+
+/// A tag pair such as `[White "Morphy, Paul"]`.
+fn parse_tag(line: &str) -> anyhow::Result<(String, String)> {
+    let invalid = || anyhow::anyhow!("invalid PGN tag {line}");
+    let inner = line
+        .strip_prefix('[')
+        .and_then(|line| line.strip_suffix(']'))
+        .ok_or_else(invalid)?;
+    let (name, value) = inner.split_once(char::is_whitespace).ok_or_else(invalid)?;
+    let value = value
+        .trim()
+        .strip_prefix('"')
+        .and_then(|value| value.strip_suffix('"'))
+        .ok_or_else(invalid)?;
+    Ok((name.to_string(), unescape_tag(value)))
+}
+
+/// A tag's value with the backslashes escaping its quotes and backslashes
+/// taken off.
+fn unescape_tag(value: &str) -> String {
+    let mut unescaped = String::new();
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => unescaped.extend(chars.next()),
+            c => unescaped.push(c),
+        }
+    }
+    unescaped
+}
+
+/// `movetext` played from `start`, ending at the end of its main line.
+fn parse_movetext(movetext: &str, start: &ChessGame) -> anyhow::Result<ChessGame> {
+    let mut game = start.clone();
+    // where to return to at the end of each variation being read
+    let mut variations: Vec<Vec<Move>> = Vec::new();
+    let mut chars = movetext.chars();
+    let mut token = String::new();
+    while let Some(c) = chars.next() {
+        let ends_token = c.is_whitespace() || "{;()".contains(c);
+        if ends_token && !token.is_empty() {
+            if !play_token(&token, &mut game)? {
+                return Ok(game);
+            }
+            token.clear();
+        }
+        match c {
+            '{' => {
+                let comment: String = chars.by_ref().take_while(|c| *c != '}').collect();
+                add_comment(&mut game, &comment);
+            }
+            ';' => {
+                let comment: String = chars.by_ref().take_while(|c| *c != '\n').collect();
+                add_comment(&mut game, &comment);
+            }
+            '(' => {
+                // the variation replaces the move just played
+                variations.push(game.played_moves().to_vec());
+                game.go_back()
+                    .context("a variation before the first move")?;
+            }
+            ')' => {
+                let end = variations.pop().context("a ')' outside a variation")?;
+                game.go_to(&end)?;
+            }
+            c if c.is_whitespace() => {}
+            c => token.push(c),
+        }
+    }
+    if !token.is_empty() {
+        play_token(&token, &mut game)?;
+    }
+    anyhow::ensure!(variations.is_empty(), "a variation without its ')'");
+    Ok(game)
+}
+
+/// Add `comment` to the one on the current position, with the whitespace
+/// it was wrapped with tidied away and any `[%eval]` command taken out as
+/// the position's evaluation.
+fn add_comment(game: &mut ChessGame, comment: &str) {
+    let comment = take_evaluation(game, comment);
+    let words = comment.split_whitespace();
+    let comment = game
+        .comment()
+        .into_iter()
+        .flat_map(str::split_whitespace)
+        .chain(words)
+        .collect::<Vec<_>>()
+        .join(" ");
+    if !comment.is_empty() {
+        game.set_comment(Some(comment));
+    }
+}
+
+/// Set the evaluation of the current position from the `[%eval]` command
+/// in `comment`, if it has one, returning the rest of the comment.
+fn take_evaluation(game: &mut ChessGame, comment: &str) -> String {
+    const COMMAND: &str = "[%eval";
+    let Some(start) = comment.find(COMMAND) else {
+        return comment.to_string();
+    };
+    let Some(end) = comment[start..].find(']').map(|end| start + end) else {
+        return comment.to_string();
+    };
+    // some tools add the depth, as in [%eval 0.17,24]
+    let score = comment[start + COMMAND.len()..end]
+        .split(',')
+        .next()
+        .and_then(|score| tree::parse_evaluation(score.trim()));
+    match score {
+        Some(score) if !game.played_moves().is_empty() => {
+            game.set_evaluation(Some(score));
+            format!("{} {}", &comment[..start], &comment[end + 1..])
+        }
+        _ => comment.to_string(),
+    }
+}
+
+/// Play the move in `token`, skipping move numbers and noting NAGs, whether
+/// written as `$2` or as `?`, on the move before, and returning false at the
+/// result, which ends the game.
+fn play_token(token: &str, game: &mut ChessGame) -> anyhow::Result<bool> {
+    if matches!(token, "1-0" | "0-1" | "1/2-1/2" | "*") {
+        return Ok(false);
+    }
+    // "12." or "12..." before a move, possibly run together with it
+    let san = token.trim_start_matches(|c: char| c.is_ascii_digit() || c == '.');
+    if san.is_empty() {
+        return Ok(true);
+    }
+    if let Some(nag) = san.strip_prefix('$') {
+        let nag = nag.parse().with_context(|| format!("invalid NAG {san}"))?;
+        game.add_nag(nag);
+        return Ok(true);
+    }
+    let symbol = san.trim_start_matches(|c| c != '!' && c != '?');
+    if symbol.len() < san.len() {
+        let mv = parse_move(san, game)
+            .with_context(|| format!("could not play {san} at move {}", game.full_move_count()))?;
+        game.make_move(&mv)?;
+    }
+    if let Some(nag) = tree::nag_from_symbol(symbol) {
+        game.add_nag(nag);
+    }
+    Ok(true)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::solver::Score;
+
+    #[test]
+    fn test_parse_pgn() {
+        let text = r#"[Event "Casual game"]
+[White "Morphy, Paul"]
+[Black "Duke Karl / Count Isouard"]
+[Result "1-0"]
+
+1. e4 e5 2. Nf3 d6 3. d4 Bg4 {This is a weak move
+already.} 4. dxe5 Bxf3 5. Qxf3 dxe5 6. Bc4 Nf6 7. Qb3 $1 Qe7 (7... Qd7 8. Qxb7)
+8. Nc3 c6 9. Bg5 b5 ; the queen's knight is free now
+10. Nxb5 cxb5 11. Bxb5+ Nbd7 12. O-O-O Rd8 13. Rxd7 Rxd7 14. Rd1 Qe6
+15. Bxd7+ Nxd7 16. Qb8+ Nxb8 17. Rd8# 1-0
+
+[White "?"]
+[Black "?"]
+[SetUp "1"]
+[FEN "6k1/5ppp/8/8/8/8/5PPP/3R2K1 w - - 0 1"]
+
+1.Rd8# 1-0
+"#;
+        let games = parse(text).unwrap();
+        assert_eq!(games.len(), 2);
+        let opera = &games[0];
+        assert_eq!(opera.players(), "Morphy, Paul - Duke Karl / Count Isouard");
+        assert_eq!(opera.tag("Result"), Some("1-0"));
+        assert_eq!(opera.moves.len(), 33);
+        let mut game = opera.start().unwrap();
+        for mv in &opera.moves {
+            game.make_move(mv).unwrap();
+        }
+        assert!(game.result().is_some());
+        let line = &opera.tree.main_line;
+        assert_eq!(
+            line[5].comment.as_deref(),
+            Some("This is a weak move already.")
+        );
+        assert_eq!(
+            line[13].variations[0][0].mv,
+            Move::from_uci("d8d7").unwrap()
+        );
+        assert_eq!(line[13].variations[0].len(), 2);
+        assert_eq!(
+            line[17].comment.as_deref(),
+            Some("the queen's knight is free now")
+        );
+        assert_eq!(line[12].nags, [1]);
+        // written back out, the variations and annotations come back the same
+        let written = opera.game().unwrap().pgn().unwrap();
+        assert_eq!(parse(&written).unwrap()[0].tree, opera.tree);
+
+        assert_eq!(games[1].moves, [Move::from_uci("d1d8").unwrap()]);
+        assert_eq!(
+            games[1].start().unwrap().fen(),
+            "6k1/5ppp/8/8/8/8/5PPP/3R2K1 w - - 0 1"
+        );
+
+        // just the moves, without tags
+        let games = parse("1. d4 d5 2. c4").unwrap();
+        assert_eq!(games.len(), 1);
+        assert_eq!(games[0].moves.len(), 3);
+        assert!(parse("").unwrap().is_empty());
+
+        let error = parse("1. e4 e5 2. Ke3").unwrap_err();
+        assert_eq!(
+            format!("{error:#}").split(':').nth(1).map(str::trim),
+            Some("could not play Ke3 at move 2")
+        );
+        assert!(parse("1. e4 $x").is_err());
+        assert!(parse("[White Morphy]").is_err());
+        assert!(parse("1. e4 (1. d4").is_err());
+        assert!(parse("(1. d4) 1. e4").is_err());
+
+        // NAGs, as numbers or symbols, and evaluations
+        let annotated = "1. e4 {[%eval 0.3]} e5 $2 {[%eval 1.10,21] loses a pawn} \
+                         2. Qh5?! ! Nc6 3. Bc4 Nf6?? {[%eval #1]} 4. Qxf7# 1-0";
+        let game = parse(annotated).unwrap().remove(0).game().unwrap();
+        let line = &game.tree().main_line;
+        assert_eq!(line[0].evaluation, Some(Score::Centipawns(30)));
+        assert_eq!(line[0].comment, None);
+        assert_eq!(line[1].nags, [2]);
+        assert_eq!(line[1].evaluation, Some(Score::Centipawns(110)));
+        assert_eq!(line[1].comment.as_deref(), Some("loses a pawn"));
+        assert_eq!(line[2].nags, [6, 1]);
+        assert_eq!(line[5].nags, [4]);
+        assert_eq!(line[5].evaluation, Some(Score::Mate(1)));
+        let written = game.pgn().unwrap();
+        assert!(written.contains(
+            "1. e4 {[%eval 0.30]} 1... e5 $2 {[%eval 1.10] loses a pawn} 2. Qh5 $6 $1 Nc6"
+        ));
+        assert_eq!(parse(&written).unwrap()[0].tree, *game.tree());
+
+        // the games either side of one that can't be played are still read
+        let games =
+            parse_each("[Round \"1\"]\n1. e4 *\n[Round \"2\"]\n1. Ke3 *\n[Round \"3\"]\n1. d4 *");
+        assert_eq!(games.len(), 3);
+        assert!(games[0].is_ok() && games[1].is_err() && games[2].is_ok());
+    }
+
+    #[test]
+    fn test_tag_round_trip() {
+        let mut game = ChessGame::default();
+        for mv in ["e2e4", "e7e5", "g1f3", "b8c6", "f1b5"] {
+            game.make_move(&Move::from_uci(mv).unwrap()).unwrap();
+        }
+        let white = r#"Paul "the Pride" Morphy"#;
+        let black = r"C:\Users\duke";
+        let text = game
+            .pgn_with_tags(&[("White", white), ("Black", black)])
+            .unwrap();
+        assert!(text.contains(r#"[White "Paul \"the Pride\" Morphy"]"#));
+        assert!(text.contains(r#"[Black "C:\\Users\\duke"]"#));
+        let read = &parse(&text).unwrap()[0];
+        assert_eq!(read.tag("White"), Some(white));
+        assert_eq!(read.tag("Black"), Some(black));
+        assert_eq!(read.tag("ECO"), Some("C60"));
+        assert_eq!(read.tag("Opening"), Some("Ruy Lopez"));
+        assert_eq!(read.moves, game.played_moves());
+    }
+
+    #[test]
+    fn test_result_round_trip() {
+        let decisive = "[White \"Anderssen\"]\n[Black \"Kieseritzky\"]\n[Result \"1-0\"]\n\n\
+                        1. e4 e5 2. f4 exf4 1-0\n";
+        let game = parse(decisive).unwrap()[0].game().unwrap();
+        assert_eq!(
+            game.result(),
+            Some(GameResult::WhiteWins(Termination::Resignation))
+        );
+        let written = game.pgn().unwrap();
+        assert!(written.contains("[Result \"1-0\"]"));
+        assert!(written.trim_end().ends_with("exf4 1-0"));
+        let read = &parse(&written).unwrap()[0];
+        assert_eq!(read.game().unwrap().result(), game.result());
+
+        // a result the moves reach is kept as it is
+        let mate = "[Result \"0-1\"]\n\n1. f3 e5 2. g4 Qh4# 0-1\n";
+        let game = parse(mate).unwrap()[0].game().unwrap();
+        assert_eq!(
+            game.result(),
+            Some(GameResult::BlackWins(Termination::Checkmate))
+        );
+        let flagged = "[Result \"1/2-1/2\"]\n[Termination \"time forfeit\"]\n\n1. e4 1/2-1/2\n";
+        let game = parse(flagged).unwrap()[0].game().unwrap();
+        assert_eq!(game.result(), Some(GameResult::Draw(Termination::Timeout)));
+        let unfinished = "[Result \"*\"]\n\n1. e4 *\n";
+        assert_eq!(parse(unfinished).unwrap()[0].game().unwrap().result(), None);
+    }
+}
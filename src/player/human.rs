@@ -0,0 +1,356 @@
+use super::{Action, Player};
+use crate::config::EngineConfig;
+use crate::core::{ChessColour, Move, Position, Square};
+use crate::game::ChessGame;
+use crate::input::{
+    self, COMMANDS_HELP, Command, Prompt, move_completions, parse_move_candidates, parse_moves,
+};
+use crate::render::{self, RenderOptions};
+use crate::solver::movegen::MoveGen;
+use crate::solver::{self, Score, SearchOptions};
+use crate::uci::UciEngine;
+use crate::uci::go::GoParams;
+use crate::variant::Variant;
+use anyhow::Context;
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// Where hints come from: the solver, or an engine. The engine is a second
+/// copy of the one being played against, started the first time a hint is
+/// asked for, so that the hints are at full strength and don't disturb its
+/// pondering.
+pub struct Hints {
+    /// For the solver, and as it plays the variant.
+    search_options: SearchOptions,
+    /// How long to think about each hint.
+    think_time: Duration,
+    engine: Option<EngineConfig>,
+    running: Option<UciEngine>,
+}
+
+impl Hints {
+    pub fn solver(search_options: SearchOptions, think_time: Duration) -> Self {
+        Self {
+            search_options,
+            think_time,
+            engine: None,
+            running: None,
+        }
+    }
+
+    pub fn engine(config: EngineConfig, think_time: Duration) -> Self {
+        Self {
+            engine: Some(config),
+            ..Self::solver(SearchOptions::default(), think_time)
+        }
+    }
+
+    /// The best move for the side to move in `game`, and its score for them.
+    pub fn suggest(&mut self, game: &ChessGame) -> anyhow::Result<(Move, Score)> {
+        let Some(config) = &self.engine else {
+            let hint = solver::hint(game, &self.search_options, self.think_time)?;
+            return Ok((hint.best_move, hint.score));
+        };
+        if self.running.is_none() {
+            let mut engine = UciEngine::from_config(config)?;
+            if game.variant() == Variant::Chess960 {
+                engine.chess960(true)?;
+            }
+            engine.new_game()?;
+            self.running = Some(engine);
+        }
+        let engine = self.running.as_mut().expect("the engine was just started");
+        engine.game_position(game)?;
+        let lines = engine.analyse(&GoParams::new().movetime(self.think_time), 1)?;
+        lines
+            .into_iter()
+            .find_map(|line| Some((*line.principal_variation.first()?, line.score)))
+            .ok_or_else(|| anyhow::anyhow!("the engine didn't suggest a move"))
+    }
+}
+
+/// A person typing moves and commands at the keyboard.
+pub struct HumanPlayer {
+    colour: ChessColour,
+    prompt: Prompt,
+    render_options: RenderOptions,
+    hints: Hints,
+    /// Whether the other side is also played at this keyboard.
+    hotseat: bool,
+    /// Where the game is saved if they leave with Ctrl-C or Ctrl-D.
+    save_path: PathBuf,
+    /// Whether they may take moves back.
+    takebacks: bool,
+}
+
+impl HumanPlayer {
+    pub fn new(
+        colour: ChessColour,
+        render_options: RenderOptions,
+        hints: Hints,
+        hotseat: bool,
+        save_path: PathBuf,
+    ) -> anyhow::Result<Self> {
+        Ok(Self {
+            colour,
+            prompt: Prompt::new()?,
+            render_options,
+            hints,
+            hotseat,
+            save_path,
+            takebacks: true,
+        })
+    }
+
+    /// Refuse to take moves back, for a game the other side can't take back
+    /// moves in too.
+    pub fn without_takebacks(self) -> Self {
+        Self {
+            takebacks: false,
+            ..self
+        }
+    }
+
+    /// What `input` asks to do, or `None` if it was a command that doesn't
+    /// end the turn or the person has to try again.
+    fn read_action(&mut self, input: &str, game: &ChessGame) -> anyhow::Result<Option<Action>> {
+        match Command::parse(input) {
+            Some(Ok(Command::Quit)) => return Ok(Some(Action::Quit)),
+            Some(Ok(Command::Resign)) => return Ok(Some(Action::Resign)),
+            Some(Ok(Command::Draw)) => return Ok(Some(Action::OfferDraw)),
+            Some(Ok(Command::Undo)) if !self.takebacks => {
+                println!("Moves can't be taken back in this game.");
+                return Ok(None);
+            }
+            Some(Ok(Command::Undo)) => return Ok(Some(Action::Undo)),
+            Some(Ok(command)) => {
+                self.run_command(&command, game)?;
+                return Ok(None);
+            }
+            Some(Err(e)) => {
+                println!("{e}");
+                return Ok(None);
+            }
+            None => {}
+        }
+        // a square with one of the player's pieces on it picks the piece,
+        // then shows where it can go; any other square is a pawn move in SAN
+        if let Ok(from) = input.trim().parse::<Position>()
+            && game
+                .get_board()
+                .get(Square::from(from))
+                .is_some_and(|piece| piece.colour == self.colour)
+        {
+            return Ok(self.choose_destination(game, from)?.map(Action::Move));
+        }
+        if let Ok(candidates) = parse_move_candidates(input, game)
+            && candidates.len() > 1
+        {
+            return Ok(self
+                .choose_candidate(game, input, &candidates)?
+                .map(Action::Move));
+        }
+        match parse_moves(input, game) {
+            Ok(mut moves) if moves.len() == 1 => Ok(moves.pop().map(Action::Move)),
+            Ok(moves) => Ok(Some(Action::Moves(moves))),
+            Err(e) => {
+                println!("{e}");
+                Ok(None)
+            }
+        }
+    }
+
+    /// Carry out one of the commands that doesn't end the turn.
+    fn run_command(&mut self, command: &Command, game: &ChessGame) -> anyhow::Result<()> {
+        match command {
+            Command::Hint => match self.hints.suggest(game) {
+                Ok((mv, score)) => println!("Try {} ({score})", game.san(&mv)?),
+                Err(e) => println!("Could not find a hint: {e:#}"),
+            },
+            Command::Moves(square) => {
+                let mut legal = MoveGen::new(game);
+                if let Some(from) = square {
+                    legal = legal.from_square(*from);
+                }
+                let moves: Vec<_> = legal.filter_map(|mv| game.san(&mv).ok()).collect();
+                println!("{}", moves.join(" "));
+            }
+            Command::Fen => println!("{}", game.fen()),
+            Command::Flip => {
+                self.render_options.flipped = !self.render_options.flipped;
+                render::display_board(game, true, &self.render_options);
+            }
+            Command::Threats => {
+                self.render_options.threats = !self.render_options.threats;
+                render::display_board(game, true, &self.render_options);
+            }
+            Command::Save(path) => match std::fs::write(path, game.pgn()?) {
+                Ok(()) => println!("Saved the game to {}", path.display()),
+                Err(e) => println!("Could not save the game to {}: {e}", path.display()),
+            },
+            Command::Help => println!("{COMMANDS_HELP}"),
+            Command::Undo | Command::Resign | Command::Draw | Command::Quit => {}
+        }
+        Ok(())
+    }
+
+    /// Ask which of `candidates`, the moves `input` could mean, was meant,
+    /// or `None` if they don't pick one of them.
+    fn choose_candidate(
+        &mut self,
+        game: &ChessGame,
+        input: &str,
+        candidates: &[Move],
+    ) -> anyhow::Result<Option<Move>> {
+        let names = input::candidate_names(candidates, game);
+        println!("'{}' could be more than one move:", input.trim());
+        for (number, name) in names.iter().enumerate() {
+            println!("  {}. {name}", number + 1);
+        }
+        let Some(answer) = self.prompt.read("Which one? ", names)? else {
+            return Ok(None);
+        };
+        let chosen = input::choose_candidate(&answer, candidates, game);
+        if chosen.is_none() {
+            println!("{} isn't one of them.", answer.trim());
+        }
+        Ok(chosen)
+    }
+
+    /// Show where the piece on `from` can move and ask which square to move
+    /// it to, or `None` if it can't move or they pick somewhere it can't go.
+    fn choose_destination(
+        &mut self,
+        game: &ChessGame,
+        from: Position,
+    ) -> anyhow::Result<Option<Move>> {
+        let board = game.get_board();
+        let own_piece = board
+            .get(Square::from(from))
+            .is_some_and(|piece| piece.colour == board.turn);
+        let destinations = if own_piece {
+            game.get_available_moves(from)?
+        } else {
+            Vec::new()
+        };
+        if destinations.is_empty() {
+            println!("None of your pieces can move from {from}.");
+            return Ok(None);
+        }
+        render::display_moves(game, from, &destinations, &self.render_options);
+        // Ctrl-C or Ctrl-D here only puts the piece back
+        let Some(input) = self.prompt.read(
+            &format!("Move the piece on {from} to: "),
+            destinations.iter().map(ToString::to_string).collect(),
+        )?
+        else {
+            return Ok(None);
+        };
+        match input.trim().parse::<Position>() {
+            Ok(to) if destinations.contains(&to) => Ok(Some(Move { from, to })),
+            _ => {
+                println!("{} isn't one of the highlighted squares.", input.trim());
+                Ok(None)
+            }
+        }
+    }
+}
+
+impl Player for HumanPlayer {
+    /// Read moves and commands until the person decides what to do.
+    fn choose_move(&mut self, game: &ChessGame) -> anyhow::Result<Action> {
+        render::display_board(game, true, &self.render_options);
+        let question = if self.hotseat {
+            format!("{} to move, or 'help' for the commands: ", self.colour)
+        } else {
+            "Your move, or 'help' for the commands: ".to_string()
+        };
+        loop {
+            let Some(input) = self.prompt.read(&question, move_completions(game))? else {
+                // Ctrl-C or Ctrl-D: keep the game rather than lose it
+                if let Some(dir) = self.save_path.parent() {
+                    std::fs::create_dir_all(dir)
+                        .with_context(|| format!("could not create {}", dir.display()))?;
+                }
+                std::fs::write(&self.save_path, game.pgn()?).with_context(|| {
+                    format!("could not save the game to {}", self.save_path.display())
+                })?;
+                println!("Saved the game to {}", self.save_path.display());
+                return Ok(Action::Quit);
+            };
+            if let Some(action) = self.read_action(&input, game)? {
+                return Ok(action);
+            }
+        }
+    }
+
+    /// Ask whether to agree to the draw the other player offered.
+    fn accepts_draw(&mut self, _game: &ChessGame) -> anyhow::Result<bool> {
+        let question = format!("{}, do you accept a draw? ", self.colour);
+        Ok(self
+            .prompt
+            .read(&question, vec!["yes".to_string(), "no".to_string()])?
+            .is_some_and(|answer| answer.trim().starts_with(['y', 'Y'])))
+    }
+
+    /// Keep the evaluation bar up to date, and say what the computer played.
+    fn observe(
+        &mut self,
+        game: &ChessGame,
+        san: &str,
+        evaluation: Option<Score>,
+    ) -> anyhow::Result<()> {
+        if evaluation.is_some() {
+            self.render_options.evaluation = evaluation;
+        }
+        if !self.hotseat && game.get_board().turn == self.colour {
+            println!("Opponent played {san}");
+        }
+        Ok(())
+    }
+
+    fn notify(&mut self, message: &str) {
+        println!("{message}");
+    }
+
+    fn is_human(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    #[cfg(unix)]
+    use crate::engine::fake_engine;
+
+    #[cfg(unix)]
+    #[test]
+    fn test_engine_hints() {
+        let path = fake_engine(
+            "hinting-engine",
+            "while read command rest; do
+                case $command in
+                    uci) echo uciok ;;
+                    isready) echo readyok ;;
+                    go) echo 'info depth 5 score cp 35 pv g1f3 d7d5'; echo 'bestmove g1f3' ;;
+                    quit) exit ;;
+                esac
+            done
+            ",
+        );
+        let config = EngineConfig::from_path(path.to_str().unwrap());
+        let mut hints = Hints::engine(config, Duration::from_millis(10));
+        let game = ChessGame::default();
+        let (mv, score) = hints.suggest(&game).unwrap();
+        assert_eq!(game.san(&mv).unwrap(), "Nf3");
+        assert_eq!(score.to_string(), "+0.35");
+        // the engine keeps running for the next hint
+        assert!(hints.suggest(&game).is_ok());
+        std::fs::remove_file(&path).ok();
+
+        let mut hints = Hints::solver(SearchOptions::default(), Duration::from_millis(100));
+        let (mv, _) = hints.suggest(&game).unwrap();
+        assert!(solver::legal_moves(&game).contains(&mv));
+    }
+}
@@ -0,0 +1,88 @@
+//! Whoever plays one side of a game run by
+//! [`crate::controller::GameController`]: a person at the keyboard or on
+//! another computer, the solver or a UCI engine.
+
+pub mod human;
+pub mod remote;
+pub mod solver;
+pub mod uci;
+
+pub use human::HumanPlayer;
+pub use remote::RemotePlayer;
+pub use solver::SolverPlayer;
+pub use uci::UciPlayer;
+
+use crate::core::Move;
+use crate::game::ChessGame;
+use crate::solver::Score;
+use std::time::Duration;
+
+/// What a player does on its turn.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Action {
+    Move(Move),
+    /// Several moves in a row, for both sides, e.g. pasted from a game's
+    /// movetext.
+    Moves(Vec<Move>),
+    Resign,
+    /// Offer a draw, which the other player can accept or decline.
+    OfferDraw,
+    /// Take back moves until it is a person's turn again.
+    Undo,
+    /// Leave the game without finishing it.
+    Quit,
+}
+
+/// One side of a game. Any two players can be paired up by
+/// [`crate::controller::GameController`].
+pub trait Player {
+    /// Decide what to do in `game`, where it is this player's turn: usually
+    /// a move, but a person can also e.g. resign or take a move back.
+    fn choose_move(&mut self, game: &ChessGame) -> anyhow::Result<Action>;
+
+    /// Whether to agree to the draw the other player offered in `game`. By
+    /// default that's the solver's judgement of the position.
+    fn accepts_draw(&mut self, game: &ChessGame) -> anyhow::Result<bool> {
+        Ok(crate::solver::accepts_draw(
+            game,
+            game.get_board().turn.flip(),
+        ))
+    }
+
+    /// Hear about a move either side made, `san`, which left `game`, along
+    /// with what the mover made of it, for White, if it said.
+    fn observe(
+        &mut self,
+        _game: &ChessGame,
+        _san: &str,
+        _evaluation: Option<Score>,
+    ) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    /// How good the position was for this player when it last chose a move,
+    /// if it knows.
+    fn score(&self) -> Option<Score> {
+        None
+    }
+
+    /// The time White and Black had left after this player's last move, for
+    /// a player timed by a clock of its own rather than the game's.
+    fn clock_after_move(&self) -> Option<(Duration, Duration)> {
+        None
+    }
+
+    /// Hear something the game has to tell this player, such as that its
+    /// draw offer was declined.
+    fn notify(&mut self, _message: &str) {}
+
+    /// Hear that `game` is over, or that a player left it unfinished.
+    fn game_over(&mut self, _game: &ChessGame) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    /// Whether this is a person, whose turn [`Action::Undo`] goes back to.
+    fn is_human(&self) -> bool {
+        false
+    }
+}
@@ -0,0 +1,270 @@
+use super::{Action, Player};
+use crate::core::{ChessColour, Move};
+use crate::game::{ChessGame, GameResult, Termination};
+use crate::network::{ClockState, Connection, Message};
+use std::time::Duration;
+
+/// Someone playing from another computer, whose moves come over the network,
+/// and who is sent the moves played here.
+pub struct RemotePlayer {
+    connection: Connection,
+    colour: ChessColour,
+    /// How many half moves the other side knows have been played.
+    known: usize,
+    /// The clock the other side sent with its last move.
+    clock: Option<ClockState>,
+    /// Whether the other side's draw offer is waiting for an answer.
+    offered_draw: bool,
+    /// Whether the other side ended the game itself, by resigning or leaving,
+    /// so doesn't need telling.
+    ended: bool,
+    /// Whether to print what it is waiting for.
+    announce: bool,
+}
+
+impl RemotePlayer {
+    /// The player of `colour` at the other end of `connection`, once both
+    /// sides have the game as it stands in `game`.
+    pub fn new(connection: Connection, colour: ChessColour, game: &ChessGame) -> Self {
+        Self {
+            connection,
+            colour,
+            known: game.played_moves().len(),
+            clock: None,
+            offered_draw: false,
+            ended: false,
+            announce: true,
+        }
+    }
+
+    /// Don't print what it is waiting for, for a front end that shows that
+    /// itself.
+    pub fn without_announcements(self) -> Self {
+        Self {
+            announce: false,
+            ..self
+        }
+    }
+
+    fn announce(&self, message: &str) {
+        if self.announce {
+            println!("{message}");
+        }
+    }
+
+    /// The moves of `moves` after those already played in `game`, which they
+    /// have to start with.
+    fn catch_up(game: &ChessGame, moves: &[Move]) -> anyhow::Result<Vec<Move>> {
+        let played = game.played_moves();
+        anyhow::ensure!(
+            moves.starts_with(played),
+            "the other player's game has gone a different way"
+        );
+        Ok(moves[played.len()..].to_vec())
+    }
+
+    fn send_game(&mut self, game: &ChessGame) -> anyhow::Result<()> {
+        self.connection.send(&Message::Sync {
+            moves: game.played_moves().to_vec(),
+        })
+    }
+}
+
+impl Player for RemotePlayer {
+    /// Wait for the other side's move, answering its requests meanwhile.
+    fn choose_move(&mut self, game: &ChessGame) -> anyhow::Result<Action> {
+        let played = game.played_moves().len();
+        if std::mem::take(&mut self.offered_draw) {
+            // asked again, so the draw was declined
+            self.connection
+                .send(&Message::DrawReply { accepted: false })?;
+        }
+        self.announce(&format!("Waiting for {}'s move...", self.colour));
+        loop {
+            match self.connection.receive()? {
+                Message::Move { seq, mv, clock } if seq == played + 1 => {
+                    self.known = seq;
+                    self.clock = clock;
+                    return Ok(Action::Move(mv));
+                }
+                // already played
+                Message::Move { seq, .. } if seq <= played => {}
+                Message::Move { .. } => {
+                    self.connection.send(&Message::Resync { seq: played })?;
+                }
+                Message::Sync { moves } => {
+                    self.known = moves.len();
+                    let missing = Self::catch_up(game, &moves)?;
+                    if !missing.is_empty() {
+                        return Ok(Action::Moves(missing));
+                    }
+                }
+                Message::Resync { .. } => self.send_game(game)?,
+                Message::Resign { .. } => {
+                    self.ended = true;
+                    return Ok(Action::Resign);
+                }
+                Message::OfferDraw { .. } => {
+                    self.offered_draw = true;
+                    return Ok(Action::OfferDraw);
+                }
+                Message::Leave => {
+                    self.ended = true;
+                    self.announce(&format!("{} left the game.", self.colour));
+                    return Ok(Action::Quit);
+                }
+                message @ (Message::Hello { .. } | Message::DrawReply { .. }) => {
+                    tracing::warn!("ignoring {message:?} from the other player");
+                }
+            }
+        }
+    }
+
+    fn accepts_draw(&mut self, game: &ChessGame) -> anyhow::Result<bool> {
+        let seq = game.played_moves().len();
+        self.connection.send(&Message::OfferDraw { seq })?;
+        self.announce(&format!("Waiting for {} to answer...", self.colour));
+        loop {
+            match self.connection.receive()? {
+                Message::DrawReply { accepted } => return Ok(accepted),
+                Message::Resync { .. } => self.send_game(game)?,
+                Message::Leave => anyhow::bail!("{} left the game", self.colour),
+                message => tracing::warn!("ignoring {message:?} while waiting for an answer"),
+            }
+        }
+    }
+
+    /// Send the moves made here.
+    fn observe(
+        &mut self,
+        game: &ChessGame,
+        _san: &str,
+        _evaluation: Option<crate::solver::Score>,
+    ) -> anyhow::Result<()> {
+        let moves = game.played_moves();
+        if moves.len() <= self.known {
+            return Ok(());
+        }
+        let clock = game.clock().map(ClockState::from);
+        for (index, mv) in moves.iter().enumerate().skip(self.known) {
+            self.connection.send(&Message::Move {
+                seq: index + 1,
+                mv: *mv,
+                clock,
+            })?;
+        }
+        self.known = moves.len();
+        Ok(())
+    }
+
+    fn clock_after_move(&self) -> Option<(Duration, Duration)> {
+        self.clock.map(|clock| {
+            (
+                Duration::from_millis(clock.white_ms),
+                Duration::from_millis(clock.black_ms),
+            )
+        })
+    }
+
+    /// Tell the other side how the game ended here, unless it ended it.
+    fn game_over(&mut self, game: &ChessGame) -> anyhow::Result<()> {
+        if self.ended {
+            return Ok(());
+        }
+        let seq = game.played_moves().len();
+        let message = match game.result() {
+            None => Message::Leave,
+            Some(result) if result.termination() == Termination::Resignation => {
+                Message::Resign { seq }
+            }
+            Some(GameResult::Draw(Termination::Agreement)) if self.offered_draw => {
+                Message::DrawReply { accepted: true }
+            }
+            Some(_) => return Ok(()),
+        };
+        // they may already have gone
+        if let Err(e) = self.connection.send(&message) {
+            tracing::debug!("could not say goodbye: {e:#}");
+        }
+        Ok(())
+    }
+
+    fn is_human(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::net::{TcpListener, TcpStream};
+
+    fn connected() -> (Connection, Connection) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let stream = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let (accepted, _) = listener.accept().unwrap();
+        (
+            Connection::new(stream).unwrap(),
+            Connection::new(accepted).unwrap(),
+        )
+    }
+
+    fn uci(uci: &str) -> Move {
+        Move::from_uci(uci).unwrap()
+    }
+
+    #[test]
+    fn test_moves_in_sequence() {
+        let (here, mut there) = connected();
+        let mut game = ChessGame::default();
+        let mut remote = RemotePlayer::new(here, ChessColour::Black, &game);
+        game.make_move(&uci("e2e4")).unwrap();
+        remote.observe(&game, "e4", None).unwrap();
+        assert!(matches!(
+            there.receive().unwrap(),
+            Message::Move { seq: 1, .. }
+        ));
+
+        // a repeat is ignored, and the move after it played
+        for seq in [1, 2] {
+            let mv = if seq == 1 { "e2e4" } else { "e7e5" };
+            there
+                .send(&Message::Move {
+                    seq,
+                    mv: uci(mv),
+                    clock: None,
+                })
+                .unwrap();
+        }
+        let action = remote.choose_move(&game).unwrap();
+        assert_eq!(action, Action::Move(uci("e7e5")));
+        game.make_move(&uci("e7e5")).unwrap();
+        // the other side's own move isn't sent back
+        remote.observe(&game, "e5", None).unwrap();
+
+        // one too far ahead asks for the game, which fills the gap
+        there
+            .send(&Message::Move {
+                seq: 5,
+                mv: uci("f8c5"),
+                clock: None,
+            })
+            .unwrap();
+        game.make_move(&uci("g1f3")).unwrap();
+        remote.observe(&game, "Nf3", None).unwrap();
+        there.receive().unwrap();
+        let mut black = game.clone();
+        black.make_move(&uci("b8c6")).unwrap();
+        let waiting = std::thread::spawn(move || {
+            assert_eq!(there.receive().unwrap(), Message::Resync { seq: 3 });
+            there
+                .send(&Message::Sync {
+                    moves: black.played_moves().to_vec(),
+                })
+                .unwrap();
+        });
+        let action = remote.choose_move(&game).unwrap();
+        waiting.join().unwrap();
+        assert_eq!(action, Action::Moves(vec![uci("b8c6")]));
+    }
+}
@@ -0,0 +1,110 @@
+use super::{Action, Player};
+use crate::clock;
+use crate::game::ChessGame;
+use crate::solver::ponder::Ponderer;
+use crate::solver::tt::TranspositionTable;
+use crate::solver::{self, Score, SearchOptions};
+use anyhow::Context;
+
+/// The built-in solver, thinking within the clock if the game has one.
+pub struct SolverPlayer {
+    options: SearchOptions,
+    tt: TranspositionTable,
+    /// Whether to think about the expected reply during the opponent's turn.
+    ponder: bool,
+    ponderer: Option<Ponderer>,
+    score: Option<Score>,
+}
+
+impl SolverPlayer {
+    pub fn new(options: SearchOptions, ponder: bool) -> Self {
+        Self {
+            options,
+            tt: TranspositionTable::default(),
+            ponder,
+            ponderer: None,
+            score: None,
+        }
+    }
+}
+
+impl Player for SolverPlayer {
+    fn choose_move(&mut self, game: &ChessGame) -> anyhow::Result<Action> {
+        tracing::info!("waiting for solver to make a move");
+        let mut options = self.options.clone();
+        if let Some(clock) = game.clock() {
+            options.max_time = Some(clock::move_budget(
+                clock.remaining_now(game.get_board().turn),
+                clock.control().increment(),
+                clock::DEFAULT_MOVES_TO_GO,
+            ));
+        }
+        let pondered = self.ponderer.take().and_then(|p| p.finish(game));
+        let search = match pondered {
+            Some((Ok(search), table)) => {
+                tracing::info!("ponder hit");
+                self.tt = table;
+                Ok(search)
+            }
+            // no worse than a miss: search the position as usual
+            Some((Err(e), _)) => {
+                tracing::info!("ponder hit, but the search failed: {e}");
+                solver::solve_next_move_with_options(game, &options, &mut self.tt)
+            }
+            // however little time is left the search gives a move, if a
+            // shallow one
+            None => solver::solve_next_move_with_options(game, &options, &mut self.tt),
+        }
+        .context("could not solve next move")?;
+        self.score = Some(search.score);
+        tracing::info!(
+            "Solver score {} at depth {} ({} nodes), expecting {}",
+            search.score,
+            search.depth,
+            search.nodes,
+            game.san_line(&search.principal_variation)
+        );
+        // think about the reply we expect while the opponent decides on theirs
+        if self.ponder
+            && let Some(expected) = search.principal_variation.get(1)
+        {
+            let mut after = game.clone();
+            after.make_move(&search.best_move)?;
+            if after.result().is_none() {
+                self.ponderer = Some(Ponderer::start(&after, *expected, &options)?);
+            }
+        }
+        Ok(Action::Move(search.best_move))
+    }
+
+    fn score(&self) -> Option<Score> {
+        self.score
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::clock::{Clock, TimeControl};
+    use crate::solver::book::OpeningBook;
+    use std::time::Duration;
+
+    #[test]
+    fn test_moves_in_a_time_scramble() {
+        let control: TimeControl = "1+0".parse().unwrap();
+        let mut game = ChessGame::default();
+        let left = Duration::from_micros(50);
+        game.set_clock(Clock::with_remaining(control, left, left));
+        for ponder in [false, true] {
+            let options = SearchOptions {
+                book: OpeningBook::Disabled,
+                ..SearchOptions::default()
+            };
+            let mut player = SolverPlayer::new(options, ponder);
+            let Action::Move(mv) = player.choose_move(&game).unwrap() else {
+                panic!("expected a move");
+            };
+            assert!(solver::legal_moves(&game).contains(&mv));
+        }
+    }
+}
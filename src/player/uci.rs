@@ -0,0 +1,125 @@
+use super::{Action, Player};
+use crate::config::{EngineConfig, Protocol};
+use crate::game::ChessGame;
+use crate::solver::Score;
+use crate::uci::UciEngine;
+use crate::uci::go::GoParams;
+use anyhow::Context;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// How long the engine thinks about each move when the game has no clock.
+const MOVE_TIME: Duration = Duration::from_secs(1);
+
+/// A UCI engine such as Stockfish.
+pub struct UciPlayer {
+    engine: UciEngine,
+    /// The latest score the engine sent for its best line.
+    score: Arc<Mutex<Option<Score>>>,
+    /// Whether to let the engine think during the opponent's turn.
+    ponder: bool,
+    /// Whether the engine has moved since it last started pondering.
+    moved: bool,
+}
+
+impl UciPlayer {
+    /// Start the engine `config` describes, limited to `elo` if given or to
+    /// `skill` otherwise.
+    pub fn start(
+        config: &EngineConfig,
+        elo: Option<u32>,
+        skill: u8,
+        chess960: bool,
+        ponder: bool,
+    ) -> anyhow::Result<Self> {
+        if config.protocol != Protocol::Uci {
+            return Err(anyhow::anyhow!(
+                "{} isn't a UCI engine, which is all that can be played against here for now; use the match subcommand instead",
+                config.path
+            ));
+        }
+        let mut engine = UciEngine::from_config(config)?;
+        let score = Arc::new(Mutex::new(None));
+        let latest_score = Arc::clone(&score);
+        engine.set_info_handler(move |info| {
+            tracing::debug!("engine is thinking: {info}");
+            if info.score.is_some() && info.multipv.is_none_or(|line| line == 1) {
+                *latest_score.lock().unwrap() = info.score;
+            }
+        });
+        engine.set_auto_restart(true);
+        tracing::info!("starting new game in the engine");
+        engine
+            .new_game()
+            .context("could not start new game in the engine")?;
+        if let Some(elo) = elo {
+            tracing::info!("limiting the engine to {elo} Elo");
+            engine
+                .limit_elo(elo)
+                .context("could not limit the engine's strength")?;
+        } else {
+            tracing::info!("setting skill level to {skill}");
+            engine
+                .skill_level(skill)
+                .context("could not set skill level")?;
+        }
+        if chess960 {
+            engine.chess960(true).context("could not enable Chess960")?;
+        }
+        engine.is_ready().context("could not isready")?;
+        Ok(Self {
+            engine,
+            score,
+            ponder,
+            moved: false,
+        })
+    }
+}
+
+impl Player for UciPlayer {
+    fn choose_move(&mut self, game: &ChessGame) -> anyhow::Result<Action> {
+        let params = go_params(game);
+        tracing::info!("waiting for the engine to make a move");
+        let best_move = if self.engine.is_pondering() {
+            self.engine.finish_pondering(game, &params)
+        } else {
+            self.engine
+                .game_position(game)
+                .context("could not set position in the engine")?;
+            self.engine.go(&params)
+        }
+        .context("could not get best move from the engine")?;
+        tracing::info!("Best move: {best_move}");
+        self.moved = true;
+        Ok(Action::Move(best_move))
+    }
+
+    /// Once the engine's own move has been played, let it think about the
+    /// opponent's reply while they do.
+    fn observe(
+        &mut self,
+        game: &ChessGame,
+        _san: &str,
+        _evaluation: Option<Score>,
+    ) -> anyhow::Result<()> {
+        if self.ponder && self.moved && game.result().is_none() {
+            self.engine
+                .start_pondering(game, &go_params(game))
+                .context("could not start the engine pondering")?;
+        }
+        self.moved = false;
+        Ok(())
+    }
+
+    fn score(&self) -> Option<Score> {
+        *self.score.lock().unwrap()
+    }
+}
+
+/// Search limits for a move in `game`: the clock if it has one.
+fn go_params(game: &ChessGame) -> GoParams {
+    match game.clock() {
+        Some(clock) => GoParams::new().clock(clock),
+        None => GoParams::new().movetime(MOVE_TIME),
+    }
+}
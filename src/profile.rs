@@ -0,0 +1,175 @@
+//! The user's results against the solver and engines, kept in a local file
+//! to estimate their rating and pick an opponent level to match it.
+
+use crate::core::ChessColour;
+use crate::game::GameResult;
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// The rating a new player is assumed to have.
+pub const INITIAL_RATING: f64 = 1200.0;
+
+/// How far one game moves the rating.
+const K_FACTOR: f64 = 32.0;
+
+/// The highest skill level, which is full strength.
+pub const MAX_SKILL: u8 = 20;
+
+/// Every game played against the computer, e.g.
+///
+/// ```toml
+/// [[games]]
+/// opponent = "solver"
+/// skill = 5
+/// score = 1.0
+///
+/// [[games]]
+/// opponent = "stockfish"
+/// elo = 1500
+/// score = 0.5
+/// ```
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Profile {
+    pub games: Vec<GameRecord>,
+}
+
+/// How one game went.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GameRecord {
+    /// `solver`, or the engine's name.
+    pub opponent: String,
+    /// The opponent's skill level, if it was limited by level.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub skill: Option<u8>,
+    /// The opponent's rating, if it was limited by Elo.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub elo: Option<u32>,
+    /// 1 for a win, 0.5 for a draw and 0 for a loss.
+    pub score: f64,
+}
+
+impl GameRecord {
+    /// The game that ended with `result`, with the user playing `colour`.
+    pub fn new(
+        opponent: &str,
+        skill: Option<u8>,
+        elo: Option<u32>,
+        colour: ChessColour,
+        result: GameResult,
+    ) -> Self {
+        let score = match result.winner() {
+            Some(winner) if winner == colour => 1.0,
+            Some(_) => 0.0,
+            None => 0.5,
+        };
+        Self {
+            opponent: opponent.to_string(),
+            skill,
+            elo,
+            score,
+        }
+    }
+
+    /// Roughly how strong the opponent was: its Elo limit if it had one,
+    /// otherwise a guess from its skill level.
+    pub fn opponent_rating(&self) -> f64 {
+        match self.elo {
+            Some(elo) => elo.into(),
+            None => skill_rating(self.skill.unwrap_or(MAX_SKILL)),
+        }
+    }
+}
+
+/// A rough rating for an opponent at skill level `skill`, from 800 at level 0
+/// to 2800 at full strength.
+pub fn skill_rating(skill: u8) -> f64 {
+    800.0 + 100.0 * f64::from(skill.min(MAX_SKILL))
+}
+
+impl Profile {
+    /// `chess/profile.toml` in the data directory.
+    pub fn default_path() -> Option<PathBuf> {
+        Some(crate::config::data_dir()?.join("profile.toml"))
+    }
+
+    /// The profile at `path`, or an empty one if there isn't one yet.
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("could not read the profile {}", path.display()))?;
+        toml::from_str(&text).with_context(|| format!("invalid profile {}", path.display()))
+    }
+
+    pub fn save(&self, path: &Path) -> anyhow::Result<()> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)
+                .with_context(|| format!("could not create {}", dir.display()))?;
+        }
+        std::fs::write(path, toml::to_string(self)?)
+            .with_context(|| format!("could not save the profile to {}", path.display()))
+    }
+
+    /// The user's estimated rating, updating an Elo rating game by game from
+    /// [`INITIAL_RATING`].
+    pub fn rating(&self) -> f64 {
+        self.games.iter().fold(INITIAL_RATING, |rating, game| {
+            let expected = 1.0 / (1.0 + 10f64.powf((game.opponent_rating() - rating) / 400.0));
+            rating + K_FACTOR * (game.score - expected)
+        })
+    }
+
+    /// The skill level whose rough rating is closest to the user's.
+    pub fn suggested_skill(&self) -> u8 {
+        ((self.rating() - skill_rating(0)) / 100.0)
+            .round()
+            .clamp(0.0, MAX_SKILL.into()) as u8
+    }
+
+    /// Wins, draws and losses.
+    pub fn results(&self) -> (usize, usize, usize) {
+        let count = |score| self.games.iter().filter(|game| game.score == score).count();
+        (count(1.0), count(0.5), count(0.0))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::game::Termination;
+
+    #[test]
+    fn test_rating() {
+        let mut profile = Profile::default();
+        assert_eq!(profile.rating(), INITIAL_RATING);
+        assert_eq!(profile.suggested_skill(), 4);
+
+        // beating stronger opponents raises the rating and the level
+        for _ in 0..10 {
+            profile.games.push(GameRecord::new(
+                "solver",
+                Some(8),
+                None,
+                ChessColour::Black,
+                GameResult::BlackWins(Termination::Checkmate),
+            ));
+        }
+        assert!(profile.rating() > 1400.0);
+        assert!(profile.suggested_skill() > 6);
+        profile.games.push(GameRecord::new(
+            "stockfish",
+            None,
+            Some(1500),
+            ChessColour::White,
+            GameResult::Draw(Termination::Agreement),
+        ));
+        assert_eq!(profile.results(), (10, 1, 0));
+
+        let text = toml::to_string(&profile).unwrap();
+        assert_eq!(toml::from_str::<Profile>(&text).unwrap(), profile);
+        assert_eq!(toml::from_str::<Profile>("").unwrap(), Profile::default());
+    }
+}
@@ -0,0 +1,184 @@
+//! Puzzles: a position and the line that wins from it, for `chess puzzle`.
+//! Puzzle files have one puzzle per line, the FEN, the solution in UCI
+//! notation and a description, separated by semicolons, e.g.
+//!
+//! ```text
+//! # back-rank mates
+//! 6k1/5ppp/8/8/8/8/5PPP/3R2K1 w - - 0 1; d1d8; Mate in 1
+//! ```
+//!
+//! The solution alternates between the moves of whoever is solving the
+//! puzzle and the opponent's replies, starting and ending with theirs.
+
+use crate::core::Move;
+use crate::game::{ChessGame, Termination};
+use crate::variant::Variant;
+use anyhow::Context;
+use std::str::FromStr;
+
+/// The puzzles played when no file is given.
+const EMBEDDED: &str = "\
+6k1/5ppp/8/8/8/8/5PPP/3R2K1 w - - 0 1; d1d8; Back-rank mate in 1
+r1bqkb1r/pppp1ppp/2n2n2/4p2Q/2B1P3/8/PPPP1PPP/RNB1K1NR w KQkq - 4 4; h5f7; Scholar's mate in 1
+rnbqkbnr/pppp1ppp/8/4p3/6P1/5P2/PPPPP2P/RNBQKBNR b KQkq g3 0 2; d8h4; Fool's mate in 1
+7k/8/5N2/8/8/8/8/6RK w - - 0 1; g1g8; Arabian mate in 1
+7k/8/6K1/8/8/8/Q7/8 w - - 0 1; a2a8; Queen mate in 1
+6k1/8/8/8/8/8/R7/1R4K1 w - - 0 1; a2a7 g8h8 b1b8; Rook roller mate in 2
+";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Puzzle {
+    pub fen: String,
+    pub solution: Vec<Move>,
+    pub description: String,
+}
+
+impl FromStr for Puzzle {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut fields = s.split(';').map(str::trim);
+        let fen = fields.next().unwrap_or_default().to_string();
+        let solution = fields
+            .next()
+            .unwrap_or_default()
+            .split_whitespace()
+            .map(Move::from_uci)
+            .collect::<Result<Vec<_>, _>>()?;
+        if solution.len() % 2 == 0 {
+            return Err(anyhow::anyhow!(
+                "a solution has to end with the solving side's move"
+            ));
+        }
+        let puzzle = Self {
+            fen,
+            solution,
+            description: fields.next().unwrap_or("Find the best line").to_string(),
+        };
+        // make sure the solution can be played
+        let mut game = puzzle.start()?;
+        for mv in &puzzle.solution {
+            game.make_move(mv)
+                .with_context(|| format!("{mv} can't be played in the solution"))?;
+        }
+        Ok(puzzle)
+    }
+}
+
+impl Puzzle {
+    pub fn start(&self) -> anyhow::Result<ChessGame> {
+        Ok(ChessGame::from_fen(&self.fen, Variant::Standard)?)
+    }
+
+    /// Whether `mv` is a right answer at step `step` of the solution in
+    /// `game`: the solution's move, or on the last step any checkmate.
+    pub fn accepts(&self, game: &ChessGame, step: usize, mv: &Move) -> bool {
+        if self.solution.get(step) == Some(mv) {
+            return true;
+        }
+        let mut after = game.clone();
+        step + 1 == self.solution.len()
+            && after.make_move(mv).is_ok()
+            && after
+                .result()
+                .is_some_and(|result| result.termination() == Termination::Checkmate)
+    }
+}
+
+/// The puzzles in `text`, skipping blank lines and `#` comments.
+pub fn parse_puzzles(text: &str) -> anyhow::Result<Vec<Puzzle>> {
+    text.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty() && !line.trim_start().starts_with('#'))
+        .map(|(number, line)| {
+            line.parse()
+                .with_context(|| format!("invalid puzzle on line {}", number + 1))
+        })
+        .collect()
+}
+
+/// The puzzles that come with the program.
+pub fn embedded() -> Vec<Puzzle> {
+    parse_puzzles(EMBEDDED).expect("the embedded puzzles are valid")
+}
+
+/// How a run of puzzles is going.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Streak {
+    pub attempted: usize,
+    pub solved: usize,
+    /// Puzzles solved in a row, up to the last one.
+    pub current: usize,
+    pub best: usize,
+}
+
+impl Streak {
+    pub fn record(&mut self, solved: bool) {
+        self.attempted += 1;
+        if solved {
+            self.solved += 1;
+            self.current += 1;
+            self.best = self.best.max(self.current);
+        } else {
+            self.current = 0;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_embedded_puzzles() {
+        let puzzles = embedded();
+        assert_eq!(puzzles.len(), 6);
+        for puzzle in &puzzles {
+            let mut game = puzzle.start().unwrap();
+            for (step, mv) in puzzle.solution.iter().enumerate() {
+                assert!(puzzle.accepts(&game, step, mv));
+                game.make_move(mv).unwrap();
+            }
+            assert_eq!(
+                game.result().map(|result| result.termination()),
+                Some(Termination::Checkmate),
+                "{}",
+                puzzle.description
+            );
+        }
+    }
+
+    #[test]
+    fn test_puzzle_answers() {
+        let puzzles = parse_puzzles(
+            "# two ways to mate\n\n\
+             6k1/5ppp/8/8/8/8/5PPP/R2R2K1 w - - 0 1; d1d8\n",
+        )
+        .unwrap();
+        let puzzle = &puzzles[0];
+        assert_eq!(puzzle.description, "Find the best line");
+        let game = puzzle.start().unwrap();
+        // Ra8# is as good as the solution's Rd8#, Rd7 isn't mate
+        assert!(puzzle.accepts(&game, 0, &Move::from_uci("a1a8").unwrap()));
+        assert!(!puzzle.accepts(&game, 0, &Move::from_uci("d1d7").unwrap()));
+
+        assert!(parse_puzzles("8/8/8/8/8/8/8/8 w - - 0 1; e2e4").is_err());
+        assert!(parse_puzzles("k7/8/1K6/8/8/8/7Q/8 w - - 0 1; h2h8 a8b8").is_err());
+        let error = parse_puzzles("\nk7/8/1K6/8/8/8/7Q/8 w - - 0 1; h2h3 a8a7 h3h8").unwrap_err();
+        assert_eq!(error.to_string(), "invalid puzzle on line 2");
+
+        let mut streak = Streak::default();
+        for solved in [true, true, false, true] {
+            streak.record(solved);
+        }
+        assert_eq!(
+            streak,
+            Streak {
+                attempted: 4,
+                solved: 3,
+                current: 1,
+                best: 2
+            }
+        );
+    }
+}
@@ -1,44 +1,1035 @@
-use crate::game::ChessGame;
-use crate::{ChessBoard, ChessColour, ChessPiece};
-use colored::Colorize;
-
-pub fn display_board(game: &ChessGame, highlight_last_move: bool) {
-    let chess_board = game.get_board();
-    let last_move = game.played_moves().last();
-
-    println!("    a b c d e f g h");
-    println!("  ┌─────────────────┐");
-    for (yidx, row) in chess_board.rows().rev().enumerate() {
-        for (xidx, cell) in row.iter().enumerate() {
-            if xidx == 0 {
-                print!("{} │ ", 8 - yidx);
-            }
+//! Drawing the board in the terminal, with colour themes, piece styles and
+//! annotations such as the last move and threatened pieces.
+
+use crate::ChessColour;
+use crate::clock::ClockTime;
+use crate::core::{ChessPiece, ChessPieceKind, Column, Position, Row, Square};
+use crate::game::{ChessGame, GameResult, Termination};
+use crate::solver::Score;
+use crate::solver::eval::{PAWN_VALUE, material};
+use colored::{Color, Colorize};
+use crossterm::cursor::MoveTo;
+use crossterm::terminal::{Clear, ClearType};
+use serde::Deserialize;
+use std::fmt::{Display, Formatter};
+use std::io::IsTerminal;
+use std::str::FromStr;
+
+/// How to draw the board, from the `[display]` section of the config file.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default)]
+pub struct RenderOptions {
+    pub pieces: PieceStyle,
+    /// Whether to show the board from Black's side, with the eighth rank at
+    /// the bottom.
+    #[serde(skip)]
+    pub flipped: bool,
+    /// Whether to colour the pieces and highlights with terminal escape codes.
+    pub colour: bool,
+    /// Whether to stick to ASCII, for terminals and logs that can't show the
+    /// box drawing characters or figurines.
+    pub ascii: bool,
+    /// Whether to clear the terminal and draw each board over the last one,
+    /// rather than below it, so long games don't scroll away.
+    pub redraw: bool,
+    /// Whether to show the position's FEN below the board, for copying.
+    pub fen: bool,
+    /// Whether to draw each square two lines tall and four columns wide, for
+    /// large terminals.
+    pub large: bool,
+    /// Whether to pick out the squares the opponent attacks, to see danger
+    /// before moving.
+    pub threats: bool,
+    /// With `threats`, whether to also pick out the pieces that are attacked
+    /// and not defended.
+    pub hanging: bool,
+    pub theme: Theme,
+    /// White's score from the latest search, shown as a bar beside the board.
+    #[serde(skip)]
+    pub evaluation: Option<Score>,
+}
+
+impl Default for RenderOptions {
+    fn default() -> Self {
+        Self {
+            pieces: PieceStyle::default(),
+            flipped: false,
+            colour: true,
+            ascii: false,
+            redraw: false,
+            fen: false,
+            large: false,
+            threats: false,
+            hanging: false,
+            theme: Theme::default(),
+            evaluation: None,
+        }
+    }
+}
+
+impl RenderOptions {
+    /// Turn colour off if the environment asks for that, following
+    /// <https://no-color.org> and treating `TERM=dumb` the same way. A dumb
+    /// terminal can't be redrawn either.
+    pub fn respect_environment(self) -> Self {
+        let dumb = std::env::var("TERM").is_ok_and(|term| term == "dumb");
+        let no_colour = std::env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty());
+        Self {
+            colour: self.colour && !no_colour && !dumb,
+            redraw: self.redraw && !dumb,
+            ..self
+        }
+    }
+}
+
+/// A colour as red, green and blue, written `#rrggbb` in the config file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "String")]
+pub struct Rgb(pub u8, pub u8, pub u8);
+
+impl FromStr for Rgb {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || anyhow::anyhow!("invalid colour {s}, expected e.g. #f0d9b5");
+        let hex = s
+            .strip_prefix('#')
+            .filter(|hex| hex.len() == 6)
+            .ok_or_else(invalid)?;
+        let channel = |i: usize| {
+            hex.get(i..i + 2)
+                .and_then(|channel| u8::from_str_radix(channel, 16).ok())
+                .ok_or_else(invalid)
+        };
+        Ok(Rgb(channel(0)?, channel(2)?, channel(4)?))
+    }
+}
+
+impl TryFrom<String> for Rgb {
+    type Error = anyhow::Error;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl From<Rgb> for Color {
+    fn from(Rgb(r, g, b): Rgb) -> Self {
+        Color::TrueColor { r, g, b }
+    }
+}
+
+/// The colours to draw the board in. In the config file, either the name of
+/// a built-in theme:
+///
+/// ```toml
+/// [display]
+/// theme = "colourblind"
+/// ```
+///
+/// or a table of colours to change, on top of an optional built-in `base`:
+///
+/// ```toml
+/// [display.theme]
+/// base = "blue"
+/// highlight = "#ff8c00"
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "ThemeConfig")]
+pub struct Theme {
+    pub light_square: Rgb,
+    pub dark_square: Rgb,
+    /// For the last move and the squares a picked up piece can move to.
+    pub highlight: Rgb,
+    /// For the move before the last, the other player's.
+    pub previous_move: Rgb,
+    /// For arrows, e.g. the engine's best move.
+    pub arrow: Rgb,
+    /// For the square of a king in check, and with the threat overlay, the
+    /// pieces left hanging.
+    pub check: Rgb,
+    /// For the squares the opponent attacks, with the threat overlay.
+    pub threat: Rgb,
+    pub white_piece: Rgb,
+    pub black_piece: Rgb,
+}
+
+impl Theme {
+    /// Browns like a wooden board.
+    pub const CLASSIC: Theme = Theme {
+        light_square: Rgb(240, 217, 181),
+        dark_square: Rgb(181, 136, 99),
+        highlight: Rgb(205, 210, 106),
+        previous_move: Rgb(230, 232, 170),
+        arrow: Rgb(21, 120, 27),
+        check: Rgb(235, 97, 80),
+        threat: Rgb(226, 160, 130),
+        white_piece: Rgb(255, 255, 255),
+        black_piece: Rgb(0, 0, 0),
+    };
+
+    /// Greyish blues.
+    pub const BLUE: Theme = Theme {
+        light_square: Rgb(222, 227, 230),
+        dark_square: Rgb(140, 162, 173),
+        highlight: Rgb(155, 199, 0),
+        previous_move: Rgb(200, 222, 130),
+        arrow: Rgb(21, 120, 27),
+        check: Rgb(235, 97, 80),
+        threat: Rgb(232, 170, 160),
+        white_piece: Rgb(255, 255, 255),
+        black_piece: Rgb(0, 0, 0),
+    };
+
+    /// Blue and orange, from the Okabe-Ito palette, which stay distinct with
+    /// the common kinds of colour blindness.
+    pub const COLOUR_BLIND: Theme = Theme {
+        light_square: Rgb(232, 232, 232),
+        dark_square: Rgb(86, 180, 233),
+        highlight: Rgb(230, 159, 0),
+        previous_move: Rgb(240, 228, 66),
+        arrow: Rgb(204, 121, 167),
+        check: Rgb(213, 94, 0),
+        threat: Rgb(0, 158, 115),
+        white_piece: Rgb(255, 255, 255),
+        black_piece: Rgb(0, 0, 0),
+    };
+
+    /// The built-in theme called `name`.
+    pub fn named(name: &str) -> anyhow::Result<Self> {
+        match name {
+            "classic" => Ok(Theme::CLASSIC),
+            "blue" => Ok(Theme::BLUE),
+            "colourblind" | "colorblind" => Ok(Theme::COLOUR_BLIND),
+            _ => Err(anyhow::anyhow!(
+                "unknown theme {name}, expected classic, blue or colourblind"
+            )),
+        }
+    }
+
+    /// The colour of the square in `row` and `column`, counted from a1.
+    pub fn square(&self, row: usize, column: usize) -> Rgb {
+        if (row + column).is_multiple_of(2) {
+            self.dark_square
+        } else {
+            self.light_square
+        }
+    }
+
+    pub fn piece(&self, colour: ChessColour) -> Rgb {
+        match colour {
+            ChessColour::White => self.white_piece,
+            ChessColour::Black => self.black_piece,
+        }
+    }
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Theme::CLASSIC
+    }
+}
 
-            let mut c: char = cell.into();
-            if c == '.' {
-                c = '·'
+/// A theme as written in the config file, see [`Theme`].
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ThemeConfig {
+    Named(String),
+    Custom {
+        base: Option<String>,
+        light_square: Option<Rgb>,
+        dark_square: Option<Rgb>,
+        highlight: Option<Rgb>,
+        previous_move: Option<Rgb>,
+        arrow: Option<Rgb>,
+        check: Option<Rgb>,
+        threat: Option<Rgb>,
+        white_piece: Option<Rgb>,
+        black_piece: Option<Rgb>,
+    },
+}
+
+impl TryFrom<ThemeConfig> for Theme {
+    type Error = anyhow::Error;
+
+    fn try_from(config: ThemeConfig) -> Result<Self, Self::Error> {
+        match config {
+            ThemeConfig::Named(name) => Theme::named(&name),
+            ThemeConfig::Custom {
+                base,
+                light_square,
+                dark_square,
+                highlight,
+                previous_move,
+                arrow,
+                check,
+                threat,
+                white_piece,
+                black_piece,
+            } => {
+                let base = base.as_deref().map_or(Ok(Theme::default()), Theme::named)?;
+                Ok(Theme {
+                    light_square: light_square.unwrap_or(base.light_square),
+                    dark_square: dark_square.unwrap_or(base.dark_square),
+                    highlight: highlight.unwrap_or(base.highlight),
+                    previous_move: previous_move.unwrap_or(base.previous_move),
+                    arrow: arrow.unwrap_or(base.arrow),
+                    check: check.unwrap_or(base.check),
+                    threat: threat.unwrap_or(base.threat),
+                    white_piece: white_piece.unwrap_or(base.white_piece),
+                    black_piece: black_piece.unwrap_or(base.black_piece),
+                })
             }
-            let should_highlight = highlight_last_move
-                && last_move.map_or(false, |m| {
-                    (usize::from(m.from.column) == xidx && usize::from(m.from.row) == 7 - yidx)
-                        || (usize::from(m.to.column) == xidx && usize::from(m.to.row) == 7 - yidx)
-                });
-            let colour = if should_highlight {
-                c.to_string().red()
-            } else if cell.colour == ChessColour::White {
-                c.to_string().white()
+        }
+    }
+}
+
+/// How pieces are drawn.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PieceStyle {
+    /// FEN letters, upper case for White.
+    #[default]
+    Letters,
+    /// The Unicode chess symbols, e.g. ♔ and ♚.
+    Figurines,
+}
+
+impl PieceStyle {
+    /// The character to draw `piece` with.
+    pub fn glyph(self, piece: &ChessPiece) -> char {
+        if self == PieceStyle::Letters {
+            return piece.into();
+        }
+        let (white, black) = match piece.kind {
+            ChessPieceKind::King => ('♔', '♚'),
+            ChessPieceKind::Queen => ('♕', '♛'),
+            ChessPieceKind::Rook => ('♖', '♜'),
+            ChessPieceKind::Bishop => ('♗', '♝'),
+            ChessPieceKind::Knight => ('♘', '♞'),
+            ChessPieceKind::Pawn => ('♙', '♟'),
+        };
+        match piece.colour {
+            ChessColour::White => white,
+            ChessColour::Black => black,
+        }
+    }
+
+    /// This style, unless it is figurines and the terminal probably can't
+    /// show them, in which case letters.
+    pub fn supported(self) -> Self {
+        if self == PieceStyle::Figurines && !terminal_supports_unicode() {
+            tracing::warn!("The terminal doesn't look like it can show figurines, using letters");
+            return PieceStyle::Letters;
+        }
+        self
+    }
+}
+
+impl FromStr for PieceStyle {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "letters" => Ok(PieceStyle::Letters),
+            "figurines" => Ok(PieceStyle::Figurines),
+            _ => Err(anyhow::anyhow!(
+                "unknown piece style {s}, expected letters or figurines"
+            )),
+        }
+    }
+}
+
+/// Whether the terminal is set up for UTF-8, going by the locale. The Linux
+/// console's fonts lack the chess symbols even when it is.
+fn terminal_supports_unicode() -> bool {
+    if cfg!(windows) {
+        // the old console host can't, Windows Terminal can
+        return std::env::var_os("WT_SESSION").is_some();
+    }
+    if std::env::var("TERM").is_ok_and(|term| term == "linux") {
+        return false;
+    }
+    ["LC_ALL", "LC_CTYPE", "LANG"]
+        .iter()
+        .filter_map(|name| std::env::var(name).ok())
+        .find(|value| !value.is_empty())
+        .is_some_and(|locale| {
+            let locale = locale.to_lowercase();
+            locale.contains("utf-8") || locale.contains("utf8")
+        })
+}
+
+/// How many full moves of the move list to show on each line under the board.
+const MOVES_PER_LINE: usize = 5;
+
+/// The pieces `colour` has captured, least valuable first, followed by how
+/// many pawns of material it is ahead by, if any, e.g. `♟♟♞ +2`.
+fn captured_pieces(game: &ChessGame, colour: ChessColour, pieces: PieceStyle) -> String {
+    let mut taken: Vec<_> = game
+        .taken_pieces()
+        .iter()
+        .filter(|piece| piece.colour != colour)
+        .collect();
+    taken.sort_by_key(|piece| piece.kind.value());
+    let mut line: String = taken.iter().map(|piece| pieces.glyph(piece)).collect();
+    let board = game.get_board();
+    // from the board rather than the captures, to count promotions and
+    // positions set up without some pieces
+    let ahead = (material(board, colour) - material(board, colour.flip())) / PAWN_VALUE;
+    if ahead > 0 {
+        line.push_str(&format!(" +{ahead}"));
+    }
+    line.trim_start().to_string()
+}
+
+/// The square of the side to move's king if it is in check, or checkmated.
+pub fn checked_king(game: &ChessGame) -> Option<Position> {
+    let board = game.get_board();
+    let checkmated = game
+        .result()
+        .is_some_and(|result| result.termination() == Termination::Checkmate);
+    if game.result().is_some() && !checkmated {
+        return None;
+    }
+    board
+        .king_position(board.turn)
+        .filter(|_| board.is_in_check(board.turn))
+}
+
+/// How the game ended, e.g. `1-0 White wins by checkmate`, with the score
+/// of a draw written `1/2-1/2` if `ascii`, otherwise `½-½`.
+pub fn result_line(result: &GameResult, ascii: bool) -> String {
+    let score = match result {
+        GameResult::Draw(_) if !ascii => "½-½",
+        result => result.score(),
+    };
+    match result.winner() {
+        Some(winner) => format!("{score} {winner} wins by {}", result.termination()),
+        None => format!("{score} Draw by {}", result.termination()),
+    }
+}
+
+/// Whether `pos` is the square in `row` and `column`, counted from a1.
+fn is_at(pos: &Position, row: usize, column: usize) -> bool {
+    usize::from(pos.row) == row && usize::from(pos.column) == column
+}
+
+/// Where an unfinished game stands: the move number, the side to move,
+/// whether it is in check and the clocks, e.g.
+/// `Move 12, Black to move, in check | White 4:32 | Black 5:01`.
+pub fn status_line(game: &ChessGame) -> String {
+    let turn = game.get_board().turn;
+    let mut status = format!("Move {}, {turn} to move", game.full_move_count());
+    if checked_king(game).is_some() {
+        status.push_str(", in check");
+    }
+    if let Some(clock) = game.clock() {
+        for colour in [ChessColour::White, ChessColour::Black] {
+            // only the side to move's clock is running
+            let remaining = if colour == turn {
+                clock.remaining_now(colour)
             } else {
-                c.to_string().blue()
+                clock.remaining(colour)
             };
+            status.push_str(&format!(" | {colour} {}", ClockTime(remaining)));
+        }
+    }
+    status
+}
 
-            print!("{colour} ");
-            if xidx == 7 {
-                println!("│");
+/// How much of the evaluation bar is White's, from 0 to 1, for White's
+/// `score`. Centipawns are turned into an expected result the way Elo
+/// differences are, so the bar moves less the further ahead a side already is.
+pub fn white_share(score: Score) -> f64 {
+    match score {
+        Score::Mate(moves) if moves > 0 => 1.0,
+        Score::Mate(_) => 0.0,
+        Score::Centipawns(cp) => 1.0 / (1.0 + 10f64.powf(-f64::from(cp) / 400.0)),
+    }
+}
+
+/// The evaluation bar's cell beside the `line`th rank from the top, White's
+/// share filling it from White's side of the board.
+fn eval_bar_cell(score: Score, line: usize, options: &RenderOptions) -> String {
+    let white_cells = (white_share(score) * 8.0).round() as usize;
+    let white = if options.flipped {
+        line < white_cells
+    } else {
+        line >= 8 - white_cells
+    };
+    if options.colour {
+        let theme = &options.theme;
+        let fill = if white {
+            theme.white_piece
+        } else {
+            theme.black_piece
+        };
+        " ".on_color(fill).to_string()
+    } else {
+        match (white, options.ascii) {
+            (true, true) => "#",
+            (false, true) => ".",
+            (true, false) => "█",
+            (false, false) => "░",
+        }
+        .to_string()
+    }
+}
+
+/// Something drawn on the board besides the pieces, such as the last moves,
+/// the engine's best move or a threat.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Annotation {
+    /// Colour a square.
+    Square(Position, Rgb),
+    /// An arrow from the first square to the second. Text boards can only
+    /// colour its ends.
+    Arrow(Position, Position, Rgb),
+}
+
+impl Annotation {
+    pub fn colour(&self) -> Rgb {
+        match self {
+            Annotation::Square(_, colour) | Annotation::Arrow(_, _, colour) => *colour,
+        }
+    }
+
+    /// Whether this colours the square in `row` and `column` on a text board.
+    pub fn covers(&self, row: usize, column: usize) -> bool {
+        match self {
+            Annotation::Square(pos, _) => is_at(pos, row, column),
+            Annotation::Arrow(from, to, _) => is_at(from, row, column) || is_at(to, row, column),
+        }
+    }
+}
+
+/// Both players' last moves in `game`, the latest last so that it is drawn
+/// over the one before.
+pub fn last_moves(game: &ChessGame, theme: &Theme) -> Vec<Annotation> {
+    let latest_first = game
+        .played_moves()
+        .iter()
+        .rev()
+        .zip([theme.highlight, theme.previous_move]);
+    latest_first
+        .rev()
+        .flat_map(|(mv, colour)| {
+            [
+                Annotation::Square(mv.from, colour),
+                Annotation::Square(mv.to, colour),
+            ]
+        })
+        .collect()
+}
+
+/// The squares the side to move's opponent attacks, and if `hanging`, the
+/// side to move's pieces among them that nothing defends, drawn over the rest.
+pub fn threats(game: &ChessGame, theme: &Theme, hanging: bool) -> Vec<Annotation> {
+    let board = game.get_board();
+    let (us, them) = (board.turn, board.turn.flip());
+    let mut attacked = Vec::new();
+    let mut undefended = Vec::new();
+    for row in 0..8 {
+        for column in 0..8 {
+            let pos = Position {
+                row: Row::try_from(row).unwrap(),
+                column: Column::try_from(column).unwrap(),
+            };
+            if !board.is_square_attacked(&pos, them) {
+                continue;
+            }
+            attacked.push(Annotation::Square(pos, theme.threat));
+            // a king in check is shown as such already
+            let ours = board
+                .get(Square::from(pos))
+                .is_some_and(|piece| piece.colour == us && piece.kind != ChessPieceKind::King);
+            if hanging && ours && !board.is_square_attacked(&pos, us) {
+                undefended.push(Annotation::Square(pos, theme.check));
             }
         }
     }
-    println!("  └─────────────────┘");
-    if chess_board.turn == ChessColour::White {
-        println!("White's turn");
+    attacked.extend(undefended);
+    attacked
+}
+
+/// The annotations `options` asks for on every board, to draw under the rest.
+fn overlays(game: &ChessGame, options: &RenderOptions) -> Vec<Annotation> {
+    if options.threats {
+        threats(game, &options.theme, options.hanging)
+    } else {
+        Vec::new()
+    }
+}
+
+/// The board as text with both players' last moves picked out, over any
+/// overlays `options` asks for, ready to print or to show in another UI.
+pub fn board_to_string(game: &ChessGame, options: &RenderOptions) -> String {
+    let mut annotations = overlays(game, options);
+    annotations.extend(last_moves(game, &options.theme));
+    annotated_board_to_string(game, &annotations, options)
+}
+
+/// The board as text with `annotations`, later ones drawn over earlier ones.
+pub fn annotated_board_to_string(
+    game: &ChessGame,
+    annotations: &[Annotation],
+    options: &RenderOptions,
+) -> String {
+    BoardView {
+        game,
+        annotations,
+        options,
+    }
+    .to_string()
+}
+
+pub fn display_board(game: &ChessGame, highlight_last_move: bool, options: &RenderOptions) {
+    if highlight_last_move {
+        show(&board_to_string(game, options), options);
+    } else {
+        let annotations = overlays(game, options);
+        show(
+            &annotated_board_to_string(game, &annotations, options),
+            options,
+        );
+    }
+}
+
+/// Show the board with the piece on `from` picked out and the squares it can
+/// move to highlighted, for choosing where to move it.
+pub fn display_moves(game: &ChessGame, from: Position, to: &[Position], options: &RenderOptions) {
+    let mut annotations = overlays(game, options);
+    annotations.extend(
+        std::iter::once(&from)
+            .chain(to)
+            .map(|pos| Annotation::Square(*pos, options.theme.highlight)),
+    );
+    show(
+        &annotated_board_to_string(game, &annotations, options),
+        options,
+    );
+}
+
+/// Print `board`, over the last one if redrawing. Redrawing is skipped when
+/// the output isn't a terminal, e.g. when it is piped to a file.
+fn show(board: &str, options: &RenderOptions) {
+    let mut stdout = std::io::stdout();
+    if options.redraw
+        && stdout.is_terminal()
+        && let Err(e) = crossterm::execute!(stdout, MoveTo(0, 0), Clear(ClearType::All))
+    {
+        tracing::warn!("Could not clear the terminal: {e}");
+    }
+    print!("{board}");
+}
+
+/// The board with `annotations`, later ones drawn over earlier ones, and
+/// the moves and state of the game below it.
+struct BoardView<'a> {
+    game: &'a ChessGame,
+    annotations: &'a [Annotation],
+    options: &'a RenderOptions,
+}
+
+impl Display for BoardView<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let BoardView {
+            game,
+            annotations,
+            options,
+        } = *self;
+        let chess_board = game.get_board();
+        let checked_king = checked_king(game);
+        let pieces = if options.ascii {
+            PieceStyle::Letters
+        } else {
+            options.pieces
+        };
+        // a square's text with `c` in it, and how many lines tall it is
+        let square_text = |c: char| {
+            if options.large {
+                format!(" {c}  ")
+            } else {
+                format!("{c} ")
+            }
+        };
+        let square_lines = if options.large { 2 } else { 1 };
+        let rule = (if options.ascii { "-" } else { "─" }).repeat(square_text(' ').len() * 8 + 1);
+        let (top_border, bottom_border, side) = if options.ascii {
+            (format!("+{rule}+"), format!("+{rule}+"), '|')
+        } else {
+            (format!("┌{rule}┐"), format!("└{rule}┘"), '│')
+        };
+        // from the top left corner of the board as shown
+        let order = |i: usize| if options.flipped { i } else { 7 - i };
+        let files = |i: usize| if options.flipped { 7 - i } else { i };
+
+        let header: String = (0..8)
+            .map(|i| square_text(char::from(b'a' + files(i) as u8)))
+            .collect();
+        writeln!(f, "    {}", header.trim_end())?;
+        writeln!(f, "  {top_border}")?;
+        let top = if options.flipped {
+            ChessColour::White
+        } else {
+            ChessColour::Black
+        };
+        for (line, row) in (0..8).map(order).enumerate() {
+            for square_line in 0..square_lines {
+                if square_line == 0 {
+                    write!(f, "{} {side} ", row + 1)?;
+                } else {
+                    write!(f, "  {side} ")?;
+                }
+                for column in (0..8).map(files) {
+                    let cell = chess_board.cell(Square::new(row, column).expect("on the board"));
+                    let annotation = annotations.iter().rev().find(|a| a.covers(row, column));
+                    let highlighted = annotation.is_some();
+                    let c = match &cell.piece {
+                        Some(piece) => pieces.glyph(piece),
+                        // a bigger dot, so an empty square that is highlighted stands
+                        // out, even without colour
+                        None if highlighted && options.ascii => '*',
+                        None if highlighted => '•',
+                        // the squares' own colours show where they are
+                        None if options.colour => ' ',
+                        None if options.ascii => '.',
+                        None => '·',
+                    };
+                    // a large square has its piece on its first line
+                    let c = if square_line == 0 { c } else { ' ' };
+                    if options.colour {
+                        let theme = &options.theme;
+                        let in_check = checked_king.is_some_and(|king| is_at(&king, row, column));
+                        let background = if in_check {
+                            theme.check
+                        } else if let Some(annotation) = annotation {
+                            annotation.colour()
+                        } else {
+                            theme.square(row, column)
+                        };
+                        let foreground = cell
+                            .piece
+                            .map_or(theme.black_piece, |piece| theme.piece(piece.colour));
+                        write!(
+                            f,
+                            "{}",
+                            square_text(c).color(foreground).on_color(background)
+                        )?;
+                    } else {
+                        write!(f, "{}", square_text(c))?;
+                    }
+                }
+                write!(f, "{side}")?;
+                if let Some(score) = options.evaluation {
+                    write!(f, " {}", eval_bar_cell(score, line, options))?;
+                }
+                // each side's captures beside its end of the board
+                let captured = match (line, square_line) {
+                    (0, 0) => captured_pieces(game, top, pieces),
+                    (7, _) if square_line == square_lines - 1 => {
+                        captured_pieces(game, top.flip(), pieces)
+                    }
+                    _ => String::new(),
+                };
+                if captured.is_empty() {
+                    writeln!(f)?;
+                } else {
+                    writeln!(f, "  {captured}")?;
+                }
+            }
+        }
+        match options.evaluation {
+            Some(score) => writeln!(f, "  {bottom_border} {score}")?,
+            None => writeln!(f, "  {bottom_border}")?,
+        }
+        match game.numbered_moves() {
+            Ok(moves) => {
+                for line in moves.chunks(MOVES_PER_LINE) {
+                    writeln!(f, "  {}", line.join(" "))?;
+                }
+            }
+            Err(e) => tracing::warn!("Could not write the moves in SAN: {e}"),
+        }
+        if let Some(opening) = game.opening() {
+            writeln!(f, "  {opening}")?;
+        }
+        match game.result() {
+            Some(result) => writeln!(f, "{}", result_line(&result, options.ascii))?,
+            None => writeln!(f, "{}", status_line(game))?,
+        }
+        if options.fen {
+            writeln!(f, "FEN: {}", game.fen())?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_captured_pieces() {
+        let mut game = ChessGame::default();
+        for mv in [
+            "e2e4", "d7d5", "e4d5", "d8d5", "b1c3", "d5a2", "c3b5", "a2a1",
+        ] {
+            game.make_move(&crate::core::Move::from_uci(mv).unwrap())
+                .unwrap();
+        }
+        assert_eq!(
+            captured_pieces(&game, ChessColour::White, PieceStyle::Letters),
+            "p"
+        );
+        assert_eq!(
+            captured_pieces(&game, ChessColour::Black, PieceStyle::Letters),
+            "PPR +6"
+        );
+        assert_eq!(
+            captured_pieces(&game, ChessColour::Black, PieceStyle::Figurines),
+            "♙♙♖ +6"
+        );
+        let start = ChessGame::default();
+        assert_eq!(
+            captured_pieces(&start, ChessColour::White, PieceStyle::Letters),
+            ""
+        );
+    }
+
+    #[test]
+    fn test_board_to_string() {
+        let options = RenderOptions {
+            colour: false,
+            ascii: true,
+            ..RenderOptions::default()
+        };
+        let mut game = ChessGame::default();
+        game.make_move(&crate::core::Move::from_uci("e2e4").unwrap())
+            .unwrap();
+        let expected = "    a b c d e f g h
+  +-----------------+
+8 | r n b q k b n r |
+7 | p p p p p p p p |
+6 | . . . . . . . . |
+5 | . . . . . . . . |
+4 | . . . . P . . . |
+3 | . . . . . . . . |
+2 | P P P P * P P P |
+1 | R N B Q K B N R |
+  +-----------------+
+  1. e4
+  B00 King's Pawn Game
+Move 1, Black to move
+";
+        assert_eq!(board_to_string(&game, &options), expected);
+        assert!(annotated_board_to_string(&game, &[], &options).contains("2 | P P P P . P P P |"));
+    }
+
+    #[test]
+    fn test_large_board() {
+        let options = RenderOptions {
+            colour: false,
+            ascii: true,
+            large: true,
+            ..RenderOptions::default()
+        };
+        let board = board_to_string(&ChessGame::default(), &options);
+        let lines: Vec<_> = board.lines().collect();
+        assert_eq!(lines[0], "     a   b   c   d   e   f   g   h");
+        assert_eq!(lines[1], format!("  +{}+", "-".repeat(33)));
+        assert_eq!(lines[2], "8 |  r   n   b   q   k   b   n   r  |");
+        assert_eq!(lines[3], format!("  |{}|", " ".repeat(33)));
+        assert_eq!(lines[18], lines[1]);
+    }
+
+    #[test]
+    fn test_status_line() {
+        let mut game = ChessGame::default();
+        assert_eq!(status_line(&game), "Move 1, White to move");
+        game.set_time_control("5+3".parse().unwrap());
+        // White's clock is running, so may already be below 5:00
+        assert!(status_line(&game).ends_with(" | Black 5:00"));
+
+        let game = ChessGame::from_fen(
+            "4k3/8/8/8/8/8/4R3/4K3 b - - 0 30",
+            crate::variant::Variant::Standard,
+        )
+        .unwrap();
+        assert_eq!(status_line(&game), "Move 30, Black to move, in check");
+        let options = RenderOptions {
+            fen: true,
+            ..RenderOptions::default()
+        };
+        assert!(board_to_string(&game, &options).ends_with(
+            "Move 30, Black to move, in check\nFEN: 4k3/8/8/8/8/8/4R3/4K3 b - - 0 30\n"
+        ));
+    }
+
+    #[test]
+    fn test_last_moves() {
+        let theme = Theme::CLASSIC;
+        let mut game = ChessGame::default();
+        assert!(last_moves(&game, &theme).is_empty());
+        game.make_move(&crate::core::Move::from_uci("e2e4").unwrap())
+            .unwrap();
+        let squares = |game: &ChessGame| -> Vec<(String, Rgb)> {
+            last_moves(game, &theme)
+                .iter()
+                .map(|annotation| match annotation {
+                    Annotation::Square(pos, colour) => (pos.to_string(), *colour),
+                    Annotation::Arrow(..) => panic!("{annotation:?}"),
+                })
+                .collect()
+        };
+        assert_eq!(
+            squares(&game),
+            [
+                ("e2".to_string(), theme.highlight),
+                ("e4".to_string(), theme.highlight)
+            ]
+        );
+        for mv in ["e7e5", "g1f3"] {
+            game.make_move(&crate::core::Move::from_uci(mv).unwrap())
+                .unwrap();
+        }
+        // the latest move last, so it is drawn over the one before
+        let squares = squares(&game);
+        assert_eq!(squares[0], ("e7".to_string(), theme.previous_move));
+        assert_eq!(squares[3], ("f3".to_string(), theme.highlight));
+        assert_eq!(squares.len(), 4);
+
+        let arrow = Annotation::Arrow("g8".parse().unwrap(), "f6".parse().unwrap(), theme.arrow);
+        assert!(arrow.covers(7, 6) && arrow.covers(5, 5));
+        assert!(!arrow.covers(6, 6));
+    }
+
+    #[test]
+    fn test_threats() {
+        let theme = Theme::CLASSIC;
+        let game = ChessGame::from_fen(
+            "4k3/8/8/3r4/8/3N4/8/4K3 w - - 0 1",
+            crate::variant::Variant::Standard,
+        )
+        .unwrap();
+        let squares = |annotations: &[Annotation], colour: Rgb| -> Vec<String> {
+            annotations
+                .iter()
+                .filter(|annotation| annotation.colour() == colour)
+                .map(|annotation| match annotation {
+                    Annotation::Square(pos, _) => pos.to_string(),
+                    Annotation::Arrow(..) => panic!("{annotation:?}"),
+                })
+                .collect()
+        };
+        let overlay = threats(&game, &theme, false);
+        let attacked = squares(&overlay, theme.threat);
+        // the rook's file stops at the knight, which nothing defends
+        assert!(attacked.contains(&"d4".to_string()));
+        assert!(attacked.contains(&"d3".to_string()));
+        assert!(!attacked.contains(&"d2".to_string()));
+        assert!(attacked.contains(&"e7".to_string()));
+        assert!(squares(&overlay, theme.check).is_empty());
+
+        let overlay = threats(&game, &theme, true);
+        assert_eq!(squares(&overlay, theme.check), ["d3"]);
+        assert_eq!(
+            overlay.last(),
+            Some(&Annotation::Square("d3".parse().unwrap(), theme.check))
+        );
+    }
+
+    #[test]
+    fn test_eval_bar() {
+        assert_eq!(white_share(Score::Centipawns(0)), 0.5);
+        assert!(white_share(Score::Centipawns(150)) > 0.6);
+        assert!(white_share(Score::Centipawns(-150)) < 0.4);
+        assert_eq!(white_share(Score::Mate(3)), 1.0);
+        assert_eq!(white_share(Score::Mate(-1)), 0.0);
+        assert_eq!(
+            Score::Centipawns(80).for_white(ChessColour::Black),
+            Score::Centipawns(-80)
+        );
+
+        // a pawn and a half up fills six of the eight cells from White's side
+        let options = RenderOptions {
+            colour: false,
+            ascii: true,
+            ..RenderOptions::default()
+        };
+        let bar = |options: &RenderOptions| -> String {
+            (0..8)
+                .map(|line| eval_bar_cell(Score::Centipawns(150), line, options))
+                .collect()
+        };
+        assert_eq!(bar(&options), "..######");
+        let flipped = RenderOptions {
+            flipped: true,
+            ..options
+        };
+        assert_eq!(bar(&flipped), "######..");
+    }
+
+    #[test]
+    fn test_themes() {
+        assert_eq!("#f0d9b5".parse::<Rgb>().unwrap(), Rgb(240, 217, 181));
+        assert!("f0d9b5".parse::<Rgb>().is_err());
+        assert!("#f0d9".parse::<Rgb>().is_err());
+        assert!("#f0d9zz".parse::<Rgb>().is_err());
+
+        let options: RenderOptions = toml::from_str("theme = \"colourblind\"").unwrap();
+        assert_eq!(options.theme, Theme::COLOUR_BLIND);
+        let options: RenderOptions =
+            toml::from_str("[theme]\nbase = \"blue\"\nhighlight = \"#ff8c00\"").unwrap();
+        assert_eq!(
+            options.theme,
+            Theme {
+                highlight: Rgb(255, 140, 0),
+                ..Theme::BLUE
+            }
+        );
+        assert!(toml::from_str::<RenderOptions>("theme = \"neon\"").is_err());
+        // a1 is a dark square
+        assert_eq!(Theme::CLASSIC.square(0, 0), Theme::CLASSIC.dark_square);
+    }
+
+    #[test]
+    fn test_check_and_result() {
+        let mut game = ChessGame::default();
+        for mv in ["e2e4", "f7f6", "d2d4", "g7g5"] {
+            game.make_move(&crate::core::Move::from_uci(mv).unwrap())
+                .unwrap();
+        }
+        assert_eq!(checked_king(&game), None);
+        game.make_move(&crate::core::Move::from_uci("d1h5").unwrap())
+            .unwrap();
+        assert_eq!(checked_king(&game).unwrap().to_string(), "e8");
+        assert_eq!(
+            result_line(&game.result().unwrap(), false),
+            "1-0 White wins by checkmate"
+        );
+
+        let draw = GameResult::Draw(Termination::Stalemate);
+        assert_eq!(result_line(&draw, false), "½-½ Draw by stalemate");
+        assert_eq!(result_line(&draw, true), "1/2-1/2 Draw by stalemate");
+        let mut resigned = ChessGame::default();
+        resigned.resign(ChessColour::Black).unwrap();
+        assert_eq!(checked_king(&resigned), None);
+    }
+
+    #[test]
+    fn test_piece_styles() {
+        let piece = |c| ChessPiece::try_from(c).unwrap();
+        assert_eq!(PieceStyle::Letters.glyph(&piece('N')), 'N');
+        assert_eq!(PieceStyle::Letters.glyph(&piece('q')), 'q');
+        assert_eq!(PieceStyle::Figurines.glyph(&piece('N')), '♘');
+        assert_eq!(PieceStyle::Figurines.glyph(&piece('q')), '♛');
+        assert_eq!(PieceStyle::Letters.supported(), PieceStyle::Letters);
+        assert_eq!(
+            "figurines".parse::<PieceStyle>().unwrap(),
+            PieceStyle::Figurines
+        );
+        assert!("emoji".parse::<PieceStyle>().is_err());
     }
 }
@@ -0,0 +1,110 @@
+//! A series of games against the same opponent, swapping colours after each
+//! game, for `chess --series`.
+
+use crate::core::ChessColour;
+use crate::game::GameResult;
+
+/// The games of a series so far.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Series {
+    /// How many games the series is the best of.
+    pub length: usize,
+    /// The side the user played in each finished game, and how it ended.
+    pub results: Vec<(ChessColour, GameResult)>,
+}
+
+impl Series {
+    pub fn new(length: usize) -> Self {
+        Self {
+            length,
+            results: Vec::new(),
+        }
+    }
+
+    /// The side the user plays in the next game, when they played `first`
+    /// in the first.
+    pub fn next_colour(&self, first: ChessColour) -> ChessColour {
+        if self.results.len().is_multiple_of(2) {
+            first
+        } else {
+            first.flip()
+        }
+    }
+
+    pub fn record(&mut self, colour: ChessColour, result: GameResult) {
+        self.results.push((colour, result));
+    }
+
+    /// The user's points and their opponent's, a win counting 1 and a draw
+    /// a half.
+    pub fn score(&self) -> (f64, f64) {
+        self.results.iter().fold(
+            (0.0, 0.0),
+            |(user, opponent), (colour, result)| match result.winner() {
+                Some(winner) if winner == *colour => (user + 1.0, opponent),
+                Some(_) => (user, opponent + 1.0),
+                None => (user + 0.5, opponent + 0.5),
+            },
+        )
+    }
+
+    /// Whether every game has been played, or one side is so far ahead
+    /// that the rest couldn't change the winner.
+    pub fn is_decided(&self) -> bool {
+        let (user, opponent) = self.score();
+        let remaining = self.length.saturating_sub(self.results.len());
+        (user - opponent).abs() > remaining as f64 || remaining == 0
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::game::Termination;
+
+    #[test]
+    fn test_series() {
+        let mut series = Series::new(3);
+        assert_eq!(series.next_colour(ChessColour::White), ChessColour::White);
+        series.record(
+            ChessColour::White,
+            GameResult::WhiteWins(Termination::Checkmate),
+        );
+        assert_eq!(series.next_colour(ChessColour::White), ChessColour::Black);
+        assert!(!series.is_decided());
+        series.record(ChessColour::Black, GameResult::Draw(Termination::Stalemate));
+        // the last game could still level it
+        assert_eq!(series.score(), (1.5, 0.5));
+        assert!(!series.is_decided());
+
+        // three points of five can't be caught
+        let mut series = Series::new(5);
+        for colour in [ChessColour::White, ChessColour::Black] {
+            series.record(colour, GameResult::Draw(Termination::Repetition));
+        }
+        series.record(
+            ChessColour::White,
+            GameResult::WhiteWins(Termination::Checkmate),
+        );
+        assert!(!series.is_decided());
+        series.record(
+            ChessColour::Black,
+            GameResult::BlackWins(Termination::Timeout),
+        );
+        assert_eq!(series.score(), (3.0, 1.0));
+        assert!(series.is_decided());
+
+        let mut series = Series::new(2);
+        series.record(
+            ChessColour::White,
+            GameResult::BlackWins(Termination::Resignation),
+        );
+        assert!(!series.is_decided());
+        series.record(
+            ChessColour::Black,
+            GameResult::BlackWins(Termination::Checkmate),
+        );
+        assert_eq!(series.score(), (1.0, 1.0));
+        assert!(series.is_decided());
+    }
+}
@@ -0,0 +1,237 @@
+//! Just enough HTTP/1.1 for [`super::Server`]: one request a connection,
+//! bodies sized by `Content-Length`, and responses that close the
+//! connection after them.
+
+use anyhow::Context;
+use serde::Serialize;
+use std::fmt::Display;
+use std::io::{BufRead, Write};
+
+/// The largest body a request may have.
+const MAX_BODY: usize = 64 * 1024;
+
+/// The most headers a request may have.
+const MAX_HEADERS: usize = 100;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Request {
+    pub method: String,
+    /// The path, without the query string.
+    pub path: String,
+    /// The query string's parameters, in order, not percent-decoded.
+    pub query: Vec<(String, String)>,
+    /// The headers, with their names in lower case.
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl Request {
+    /// A request without headers or a body, e.g. for testing.
+    pub fn new(method: &str, target: &str, body: &str) -> Self {
+        let (path, query) = split_target(target);
+        Self {
+            method: method.to_string(),
+            path,
+            query,
+            headers: Vec::new(),
+            body: body.as_bytes().to_vec(),
+        }
+    }
+
+    /// Read the next request from `reader`, or `None` if the connection was
+    /// closed before one started.
+    pub fn read(reader: &mut impl BufRead) -> anyhow::Result<Option<Self>> {
+        let mut line = String::new();
+        if reader.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+        let mut parts = line.split_whitespace();
+        let (Some(method), Some(target), Some(version)) =
+            (parts.next(), parts.next(), parts.next())
+        else {
+            anyhow::bail!("malformed request line {:?}", line.trim());
+        };
+        anyhow::ensure!(version.starts_with("HTTP/1."), "unsupported {version}");
+        let (path, query) = split_target(target);
+        let mut request = Self {
+            method: method.to_string(),
+            path,
+            query,
+            headers: Vec::new(),
+            body: Vec::new(),
+        };
+        loop {
+            line.clear();
+            anyhow::ensure!(
+                reader.read_line(&mut line)? > 0,
+                "connection closed in the headers"
+            );
+            let header = line.trim_end();
+            if header.is_empty() {
+                break;
+            }
+            anyhow::ensure!(request.headers.len() < MAX_HEADERS, "too many headers");
+            let (name, value) = header
+                .split_once(':')
+                .with_context(|| format!("malformed header {header:?}"))?;
+            request
+                .headers
+                .push((name.trim().to_ascii_lowercase(), value.trim().to_string()));
+        }
+        if let Some(length) = request.header("content-length") {
+            let length: usize = length.parse().context("malformed Content-Length")?;
+            anyhow::ensure!(length <= MAX_BODY, "the body is too large");
+            request.body = vec![0; length];
+            reader.read_exact(&mut request.body)?;
+        }
+        Ok(Some(request))
+    }
+
+    /// The value of the header called `name`, given in lower case.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(header, _)| header == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// The value of the query parameter called `name`.
+    pub fn param(&self, name: &str) -> Option<&str> {
+        self.query
+            .iter()
+            .find(|(param, _)| param == name)
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+fn split_target(target: &str) -> (String, Vec<(String, String)>) {
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+    let query = query
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let (name, value) = pair.split_once('=').unwrap_or((pair, ""));
+            (name.to_string(), value.to_string())
+        })
+        .collect();
+    (path.to_string(), query)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Response {
+    pub status: u16,
+    pub content_type: &'static str,
+    pub body: Vec<u8>,
+}
+
+impl Response {
+    pub fn json(status: u16, value: &impl Serialize) -> Self {
+        Self {
+            status,
+            content_type: "application/json",
+            body: serde_json::to_vec(value).expect("responses serialize"),
+        }
+    }
+
+    pub fn text(status: u16, content_type: &'static str, body: String) -> Self {
+        Self {
+            status,
+            content_type,
+            body: body.into_bytes(),
+        }
+    }
+
+    /// A failure, with `message` to show as `{"error": message}`.
+    pub fn error(status: u16, message: impl Display) -> Self {
+        #[derive(Serialize)]
+        struct Error {
+            error: String,
+        }
+        Self::json(
+            status,
+            &Error {
+                error: message.to_string(),
+            },
+        )
+    }
+
+    /// An empty response.
+    pub fn empty(status: u16) -> Self {
+        Self::text(status, "text/plain", String::new())
+    }
+
+    pub fn write_to(&self, writer: &mut impl Write) -> std::io::Result<()> {
+        write!(
+            writer,
+            "HTTP/1.1 {} {}\r\n\
+             Content-Type: {}\r\n\
+             Content-Length: {}\r\n\
+             Access-Control-Allow-Origin: *\r\n\
+             Access-Control-Allow-Methods: GET, POST, DELETE, OPTIONS\r\n\
+             Access-Control-Allow-Headers: Content-Type\r\n\
+             Connection: close\r\n\r\n",
+            self.status,
+            reason(self.status),
+            self.content_type,
+            self.body.len()
+        )?;
+        writer.write_all(&self.body)?;
+        writer.flush()
+    }
+}
+
+fn reason(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        201 => "Created",
+        204 => "No Content",
+        400 => "Bad Request",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        409 => "Conflict",
+        422 => "Unprocessable Entity",
+        500 => "Internal Server Error",
+        _ => "",
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_read_request() {
+        let raw = "POST /games/1/moves?verbose=1&x HTTP/1.1\r\n\
+                   Host: localhost\r\n\
+                   Content-Type: application/json\r\n\
+                   Content-Length: 15\r\n\r\n\
+                   {\"move\":\"e4\"}\r\nleft over";
+        let request = Request::read(&mut raw.as_bytes()).unwrap().unwrap();
+        assert_eq!(request.method, "POST");
+        assert_eq!(request.path, "/games/1/moves");
+        assert_eq!(request.param("verbose"), Some("1"));
+        assert_eq!(request.param("x"), Some(""));
+        assert_eq!(request.header("content-type"), Some("application/json"));
+        assert_eq!(request.body, b"{\"move\":\"e4\"}\r\n");
+
+        assert_eq!(Request::read(&mut "".as_bytes()).unwrap(), None);
+        assert!(Request::read(&mut "GET /\r\n\r\n".as_bytes()).is_err());
+        let huge = format!(
+            "POST / HTTP/1.1\r\nContent-Length: {}\r\n\r\n",
+            MAX_BODY + 1
+        );
+        assert!(Request::read(&mut huge.as_bytes()).is_err());
+    }
+
+    #[test]
+    fn test_write_response() {
+        let mut written = Vec::new();
+        Response::error(404, "no game 7")
+            .write_to(&mut written)
+            .unwrap();
+        let written = String::from_utf8(written).unwrap();
+        assert!(written.starts_with("HTTP/1.1 404 Not Found\r\n"));
+        assert!(written.contains("Content-Length: 21\r\n"));
+        assert!(written.ends_with("\r\n\r\n{\"error\":\"no game 7\"}"));
+    }
+}
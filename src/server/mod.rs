@@ -0,0 +1,979 @@
+//! `chess serve`: games kept on a server and played over HTTP with JSON, so
+//! that a web front end can use the rules and the solver without its own
+//! copy of them.
+//!
+//! | Request                           | Does                                      |
+//! |-----------------------------------|-------------------------------------------|
+//! | `POST /games`                     | start a game, as [`NewGame`] describes    |
+//! | `GET /games`                      | list the games' IDs                       |
+//! | `GET /games/{id}`                 | the game's [`GameState`]                  |
+//! | `DELETE /games/{id}`              | forget the game                           |
+//! | `GET /games/{id}/moves`           | the legal moves, in UCI                   |
+//! | `POST /games/{id}/moves`          | play `{"move"}`, in SAN or UCI            |
+//! | `POST /games/{id}/undo`           | take back the last move                   |
+//! | `GET /games/{id}/fen`             | the position, as FEN                      |
+//! | `GET /games/{id}/pgn`             | the game, as PGN                          |
+//! | `GET /games/{id}/analysis`        | the solver's [`Analysis`]                 |
+//! | `GET /games/{id}/stream`          | a WebSocket of the game's [`Update`]s     |
+//!
+//! An analysis takes a second unless the `movetime` parameter gives the
+//! milliseconds to take, and can be limited by `depth` and widened to more
+//! `lines`, e.g. `/games/1/analysis?movetime=5000&lines=3`. Failures are
+//! answered with `{"error": message}` and a 4xx status.
+//!
+//! A stream starts with the game's state and is sent it again whenever the
+//! game changes, each iteration of a search of it, the solver's own
+//! included, and the time left every second while its clock runs. A game the
+//! solver plays both sides of is a match anyone can watch this way.
+
+pub mod http;
+pub mod websocket;
+
+use crate::clock::TimeControl;
+use crate::core::{ChessColour, Move};
+use crate::game::ChessGame;
+use crate::input;
+use crate::network::ClockState;
+use crate::solver::book::OpeningBook;
+use crate::solver::tt::TranspositionTable;
+use crate::solver::{self, Score, SearchOptions, SearchResult};
+use crate::variant::Variant;
+use http::{Request, Response};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::io::BufReader;
+use std::net::{Shutdown, TcpListener, TcpStream};
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::time::{Duration, Instant};
+use websocket::Frame;
+
+/// The longest an analysis may take, so that one request can't hold a
+/// thread for ever.
+const MAX_MOVETIME: Duration = Duration::from_secs(30);
+
+/// How long an analysis takes unless the request says.
+const DEFAULT_MOVETIME: Duration = Duration::from_secs(1);
+
+/// How often a stream is sent the time left.
+const TICK: Duration = Duration::from_secs(1);
+
+/// The games being played on a server, and the solver settings to analyse
+/// them and play in them with. Clones share the games.
+#[derive(Clone)]
+pub struct Server {
+    games: Arc<Mutex<Games>>,
+    search_options: SearchOptions,
+}
+
+#[derive(Default)]
+struct Games {
+    next_id: u64,
+    games: HashMap<u64, Entry>,
+}
+
+/// A game on the server, and who is following it.
+struct Entry {
+    game: ChessGame,
+    /// The sides the solver plays.
+    solver: Vec<ChessColour>,
+    /// Whether the solver is choosing a move.
+    thinking: bool,
+    /// The streams of the game.
+    watchers: Vec<Sender<StreamEvent>>,
+}
+
+impl Entry {
+    fn broadcast(&mut self, update: Update) {
+        self.watchers
+            .retain(|watcher| watcher.send(StreamEvent::Update(update.clone())).is_ok());
+    }
+}
+
+/// What a stream's connection has to send.
+enum StreamEvent {
+    Update(Update),
+    /// The answer to a ping.
+    Pong(Vec<u8>),
+    /// The client closed the stream.
+    Closed,
+}
+
+/// What a stream of a game is sent, as JSON tagged with its `type`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Update {
+    /// The game as it is now.
+    State(GameState),
+    /// An iteration of a search of the position.
+    Analysis(Analysis),
+    /// The time each side has left.
+    Clock(ClockState),
+}
+
+/// A game as `GET /games/{id}` and the requests that change it answer.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct GameState {
+    pub id: u64,
+    pub fen: String,
+    pub turn: ChessColour,
+    /// The moves played, in UCI.
+    pub moves: Vec<Move>,
+    /// The same moves in SAN.
+    pub san: Vec<String>,
+    pub legal_moves: Vec<Move>,
+    pub in_check: bool,
+    /// The result, e.g. `1-0`, once the game is over.
+    pub result: Option<String>,
+    /// Why the game ended, e.g. `checkmate`.
+    pub termination: Option<String>,
+    /// The time each side has left, if the game has a clock.
+    pub clock: Option<ClockState>,
+    /// The sides the solver plays.
+    pub solver: Vec<ChessColour>,
+}
+
+/// The solver's view of a position, with scores for White.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Analysis {
+    pub depth: usize,
+    pub nodes: u64,
+    pub best_move: Move,
+    pub lines: Vec<AnalysisLine>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct AnalysisLine {
+    pub score: ScoreJson,
+    /// The moves expected, in UCI.
+    pub pv: Vec<Move>,
+    /// The same line in SAN.
+    pub san: String,
+}
+
+/// A score as UCI reports it, in centipawns or moves to mate.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ScoreJson {
+    Cp(i32),
+    Mate(i32),
+}
+
+impl From<Score> for ScoreJson {
+    fn from(score: Score) -> Self {
+        match score {
+            Score::Centipawns(cp) => ScoreJson::Cp(cp),
+            Score::Mate(moves) => ScoreJson::Mate(moves),
+        }
+    }
+}
+
+/// The body of `POST /games`, every field optional.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct NewGame {
+    /// The position to start from instead of the usual one.
+    pub fen: Option<String>,
+    /// Play Chess960, from a random position unless `fen` gives one.
+    pub chess960: bool,
+    /// The time control, e.g. `"5+3"`.
+    pub time: Option<TimeControl>,
+    /// The sides the solver plays, e.g. `["black"]`.
+    pub solver: Vec<ChessColour>,
+}
+
+/// The body of `POST /games/{id}/moves`.
+#[derive(Debug, Deserialize)]
+struct PlayMove {
+    #[serde(rename = "move")]
+    mv: String,
+}
+
+impl Server {
+    pub fn new(search_options: SearchOptions) -> Self {
+        Self {
+            games: Arc::default(),
+            search_options,
+        }
+    }
+
+    /// Answer requests from `listener`, each connection on its own thread,
+    /// until it fails.
+    pub fn run(&self, listener: TcpListener) -> anyhow::Result<()> {
+        for stream in listener.incoming() {
+            let stream = stream?;
+            let server = self.clone();
+            std::thread::spawn(move || {
+                if let Err(e) = server.serve_connection(stream) {
+                    tracing::debug!("connection failed: {e:#}");
+                }
+            });
+        }
+        Ok(())
+    }
+
+    fn serve_connection(&self, stream: TcpStream) -> anyhow::Result<()> {
+        let mut reader = BufReader::new(stream.try_clone()?);
+        let response = match Request::read(&mut reader) {
+            Ok(Some(request)) => {
+                tracing::info!("{} {}", request.method, request.path);
+                if let Some(id) = stream_id(&request) {
+                    return self.stream(id, &request, stream, reader);
+                }
+                self.handle(&request)
+            }
+            Ok(None) => return Ok(()),
+            Err(e) => Response::error(400, format!("{e:#}")),
+        };
+        response.write_to(&mut &stream)?;
+        Ok(())
+    }
+
+    /// The answer to `request`, unless it is for a stream.
+    pub fn handle(&self, request: &Request) -> Response {
+        let segments: Vec<&str> = request.path.split('/').filter(|s| !s.is_empty()).collect();
+        let method = request.method.as_str();
+        if method == "OPTIONS" {
+            // a browser checking it may make the request
+            return Response::empty(204);
+        }
+        match segments[..] {
+            ["games"] => match method {
+                "GET" => {
+                    let games = self.games();
+                    let mut ids: Vec<u64> = games.games.keys().copied().collect();
+                    ids.sort_unstable();
+                    Response::json(200, &BTreeMap::from([("games", ids)]))
+                }
+                "POST" => self.create(&request.body),
+                _ => Response::error(405, "use GET or POST"),
+            },
+            ["games", id, ref rest @ ..] => {
+                let Ok(id) = id.parse::<u64>() else {
+                    return Response::error(404, format!("no game {id}"));
+                };
+                match (method, rest) {
+                    ("DELETE", []) => match self.games().games.remove(&id) {
+                        Some(_) => Response::empty(204),
+                        None => Response::error(404, format!("no game {id}")),
+                    },
+                    ("GET", ["analysis"]) => self.analyse(id, request),
+                    ("GET", ["stream"]) => Response::error(400, "streams are WebSockets"),
+                    ("GET", [] | ["moves"] | ["fen"] | ["pgn"]) => {
+                        self.with_entry(id, |entry| route_game(id, entry, method, rest))
+                    }
+                    ("POST", ["moves"]) => {
+                        let response = self.play_move(id, &request.body);
+                        self.play_solver(id);
+                        response
+                    }
+                    ("POST", ["undo"]) => {
+                        let response = self.with_entry(id, |entry| {
+                            let response = route_game(id, entry, method, rest);
+                            if response.status == 200 {
+                                entry.broadcast(Update::State(state(id, entry)));
+                            }
+                            response
+                        });
+                        self.play_solver(id);
+                        response
+                    }
+                    _ => Response::error(405, format!("can't {method} {}", request.path)),
+                }
+            }
+            _ => Response::error(404, format!("nothing at {}", request.path)),
+        }
+    }
+
+    fn create(&self, body: &[u8]) -> Response {
+        let new_game: NewGame = if body.iter().all(u8::is_ascii_whitespace) {
+            NewGame::default()
+        } else {
+            match serde_json::from_slice(body) {
+                Ok(new_game) => new_game,
+                Err(e) => return Response::error(400, e),
+            }
+        };
+        let variant = if new_game.chess960 {
+            Variant::Chess960
+        } else {
+            Variant::Standard
+        };
+        let builder = match &new_game.fen {
+            Some(fen) => ChessGame::builder().fen(fen).variant(variant),
+            None if new_game.chess960 => ChessGame::builder().random_chess960(),
+            None => ChessGame::builder(),
+        };
+        let mut game = match builder.build() {
+            Ok(game) => game,
+            Err(e) => return Response::error(422, e),
+        };
+        if let Some(time_control) = new_game.time {
+            game.set_time_control(time_control);
+        }
+        let entry = Entry {
+            game,
+            solver: new_game.solver,
+            thinking: false,
+            watchers: Vec::new(),
+        };
+        let id = {
+            let mut games = self.games();
+            games.next_id += 1;
+            let id = games.next_id;
+            games.games.insert(id, entry);
+            id
+        };
+        let response = self.with_entry(id, |entry| Response::json(201, &state(id, entry)));
+        self.play_solver(id);
+        response
+    }
+
+    /// The games, locked. A request that panicked while they were locked left
+    /// them as they were, so the others carry on with them rather than fail.
+    fn games(&self) -> MutexGuard<'_, Games> {
+        self.games.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// A copy of game `id`, to work on without keeping the games locked.
+    fn game(&self, id: u64) -> Option<ChessGame> {
+        self.games().games.get(&id).map(|entry| entry.game.clone())
+    }
+
+    /// Run `f` on game `id`, with the games locked.
+    fn with_entry(&self, id: u64, f: impl FnOnce(&mut Entry) -> Response) -> Response {
+        let mut games = self.games();
+        match games.games.get_mut(&id) {
+            Some(entry) => f(entry),
+            None => Response::error(404, format!("no game {id}")),
+        }
+    }
+
+    fn broadcast(&self, id: u64, update: Update) {
+        if let Some(entry) = self.games().games.get_mut(&id) {
+            entry.broadcast(update);
+        }
+    }
+
+    /// Play the move `body` gives in game `id`. It is read on a copy of the
+    /// game, so that nothing a client sends is parsed with the games locked.
+    fn play_move(&self, id: u64, body: &[u8]) -> Response {
+        let play: PlayMove = match serde_json::from_slice(body) {
+            Ok(play) => play,
+            Err(e) => return Response::error(400, e),
+        };
+        let Some((game, solver)) = self
+            .games()
+            .games
+            .get(&id)
+            .map(|entry| (entry.game.clone(), entry.solver.clone()))
+        else {
+            return Response::error(404, format!("no game {id}"));
+        };
+        if game.result().is_some() {
+            return Response::error(409, "the game is over");
+        }
+        if solver.contains(&game.get_board().turn) {
+            return Response::error(409, "it is the solver's move");
+        }
+        let mv = match input::parse_move(&play.mv, &game) {
+            Ok(mv) => mv,
+            Err(e) => return Response::error(422, e),
+        };
+        self.with_entry(id, |entry| {
+            if entry.game.played_moves() != game.played_moves() || entry.game.result().is_some() {
+                return Response::error(409, "the game changed meanwhile");
+            }
+            match entry.game.make_move(&mv) {
+                Ok(_) => {
+                    let state = state(id, entry);
+                    entry.broadcast(Update::State(state.clone()));
+                    Response::json(200, &state)
+                }
+                Err(e) => Response::error(422, e),
+            }
+        })
+    }
+
+    /// Search a copy of game `id`, so that the others can be played
+    /// meanwhile.
+    fn analyse(&self, id: u64, request: &Request) -> Response {
+        let Some(game) = self.game(id) else {
+            return Response::error(404, format!("no game {id}"));
+        };
+        if game.result().is_some() {
+            return Response::error(409, "the game is over");
+        }
+        let number = |name: &str| -> Result<Option<u64>, Response> {
+            request
+                .param(name)
+                .map(|value| {
+                    value
+                        .parse()
+                        .map_err(|_| Response::error(400, format!("{name} should be a number")))
+                })
+                .transpose()
+        };
+        let (movetime, depth, lines) = match (number("movetime"), number("depth"), number("lines"))
+        {
+            (Ok(movetime), Ok(depth), Ok(lines)) => (movetime, depth, lines),
+            (Err(response), ..) | (_, Err(response), _) | (.., Err(response)) => return response,
+        };
+        let mut options = self.search_options.clone();
+        options.max_time = Some(
+            movetime
+                .map_or(DEFAULT_MOVETIME, Duration::from_millis)
+                .min(MAX_MOVETIME),
+        );
+        if let Some(depth) = depth {
+            options.max_depth = depth.clamp(1, options.max_depth as u64) as usize;
+        }
+        options.multi_pv = lines.unwrap_or(1).clamp(1, 10) as usize;
+        // a book move has no score or line to show
+        options.book = OpeningBook::Disabled;
+        match self.search(id, &game, &options) {
+            Ok(search) => Response::json(200, &analysis(&game, &search)),
+            Err(e) => Response::error(500, e),
+        }
+    }
+
+    /// Search `game` a depth at a time, within the limits of `options`,
+    /// sending each iteration to the streams of game `id`.
+    fn search(
+        &self,
+        id: u64,
+        game: &ChessGame,
+        options: &SearchOptions,
+    ) -> Result<SearchResult, crate::core::ChessError> {
+        let started = Instant::now();
+        let mut tt = TranspositionTable::default();
+        let mut best = None;
+        for depth in 1..=options.max_depth.max(1) {
+            let max_time = options
+                .max_time
+                .map(|max_time| max_time.saturating_sub(started.elapsed()));
+            if best.is_some() && max_time.is_some_and(|time| time.is_zero()) {
+                break;
+            }
+            let iteration = SearchOptions {
+                max_depth: depth,
+                max_time,
+                ..options.clone()
+            };
+            let search = match solver::solve_next_move_with_options(game, &iteration, &mut tt) {
+                Ok(search) => search,
+                // the last depth completed stands
+                Err(e) if best.is_some() => {
+                    tracing::debug!("stopped searching game {id} at depth {depth}: {e}");
+                    break;
+                }
+                Err(e) => return Err(e),
+            };
+            // a shallower result ran out of time, or came from the book
+            let complete = search.depth >= depth;
+            if complete || best.is_none() {
+                self.broadcast(id, Update::Analysis(analysis(game, &search)));
+                best = Some(search);
+            }
+            if !complete {
+                break;
+            }
+        }
+        Ok(best.expect("searched at least once"))
+    }
+
+    /// Have the solver choose a move in game `id` on another thread, if it is
+    /// to move and isn't already.
+    fn play_solver(&self, id: u64) {
+        let game = {
+            let mut games = self.games();
+            let Some(entry) = games.games.get_mut(&id) else {
+                return;
+            };
+            let turn = entry.game.get_board().turn;
+            if entry.thinking || entry.game.result().is_some() || !entry.solver.contains(&turn) {
+                return;
+            }
+            entry.thinking = true;
+            entry.game.clone()
+        };
+        let server = self.clone();
+        std::thread::spawn(move || {
+            let search = server.search(id, &game, &server.search_options);
+            {
+                let mut games = server.games();
+                let Some(entry) = games.games.get_mut(&id) else {
+                    return;
+                };
+                entry.thinking = false;
+                // a move may have been taken back meanwhile
+                if entry.game.played_moves() == game.played_moves() {
+                    match search.and_then(|search| entry.game.make_move(&search.best_move)) {
+                        Ok(_) => entry.broadcast(Update::State(state(id, entry))),
+                        Err(e) => tracing::warn!("the solver couldn't move in game {id}: {e}"),
+                    }
+                }
+            }
+            server.play_solver(id);
+        });
+    }
+
+    /// Send game `id` to a WebSocket client until it leaves or the game is
+    /// deleted.
+    fn stream(
+        &self,
+        id: u64,
+        request: &Request,
+        stream: TcpStream,
+        mut reader: BufReader<TcpStream>,
+    ) -> anyhow::Result<()> {
+        let (sender, receiver) = mpsc::channel();
+        {
+            let mut games = self.games();
+            let Some(entry) = games.games.get_mut(&id) else {
+                Response::error(404, format!("no game {id}")).write_to(&mut &stream)?;
+                return Ok(());
+            };
+            if !websocket::is_upgrade(request) {
+                Response::error(400, "streams are WebSockets").write_to(&mut &stream)?;
+                return Ok(());
+            }
+            websocket::handshake(request, &mut &stream)?;
+            sender.send(StreamEvent::Update(Update::State(state(id, entry))))?;
+            entry.watchers.push(sender.clone());
+        }
+        // clients only ping and close, but have to be listened to for that
+        std::thread::spawn(move || {
+            loop {
+                match websocket::read_frame(&mut reader) {
+                    Ok(Frame::Ping(payload)) => {
+                        if sender.send(StreamEvent::Pong(payload)).is_err() {
+                            break;
+                        }
+                    }
+                    Ok(Frame::Close) | Err(_) => {
+                        let _ = sender.send(StreamEvent::Closed);
+                        break;
+                    }
+                    Ok(_) => {}
+                }
+            }
+        });
+        let result = self.send_stream(id, &stream, &receiver);
+        let _ = stream.shutdown(Shutdown::Both);
+        result
+    }
+
+    fn send_stream(
+        &self,
+        id: u64,
+        mut stream: &TcpStream,
+        receiver: &mpsc::Receiver<StreamEvent>,
+    ) -> anyhow::Result<()> {
+        let mut next_tick = Instant::now() + TICK;
+        loop {
+            match receiver.recv_timeout(next_tick.saturating_duration_since(Instant::now())) {
+                Ok(StreamEvent::Update(update)) => {
+                    let text = serde_json::to_string(&update)?;
+                    websocket::write_frame(&mut stream, &Frame::Text(text))?;
+                }
+                Ok(StreamEvent::Pong(payload)) => {
+                    websocket::write_frame(&mut stream, &Frame::Pong(payload))?;
+                }
+                Ok(StreamEvent::Closed) | Err(RecvTimeoutError::Disconnected) => {
+                    let _ = websocket::write_frame(&mut stream, &Frame::Close);
+                    return Ok(());
+                }
+                Err(RecvTimeoutError::Timeout) => {}
+            }
+            if Instant::now() < next_tick {
+                continue;
+            }
+            next_tick += TICK;
+            let clock = {
+                let mut games = self.games();
+                let Some(entry) = games.games.get_mut(&id) else {
+                    // deleted
+                    let _ = websocket::write_frame(&mut stream, &Frame::Close);
+                    return Ok(());
+                };
+                let ongoing = entry.game.result().is_none();
+                if ongoing && entry.game.check_timeout().is_some() {
+                    entry.broadcast(Update::State(state(id, entry)));
+                }
+                live_clock(&entry.game).filter(|_| ongoing)
+            };
+            if let Some(clock) = clock {
+                let text = serde_json::to_string(&Update::Clock(clock))?;
+                websocket::write_frame(&mut stream, &Frame::Text(text))?;
+            }
+        }
+    }
+}
+
+/// The game a request for `/games/{id}/stream` wants.
+fn stream_id(request: &Request) -> Option<u64> {
+    let id = request
+        .path
+        .strip_prefix("/games/")?
+        .strip_suffix("/stream")?;
+    (request.method == "GET").then(|| id.parse().ok())?
+}
+
+/// The answer to a request for game `id` other than creating, deleting,
+/// analysing or streaming it, or playing a move in it.
+fn route_game(id: u64, entry: &mut Entry, method: &str, rest: &[&str]) -> Response {
+    let game = &mut entry.game;
+    match (method, rest) {
+        ("GET", []) => Response::json(200, &state(id, entry)),
+        ("GET", ["moves"]) => {
+            Response::json(200, &BTreeMap::from([("moves", solver::legal_moves(game))]))
+        }
+        ("GET", ["fen"]) => Response::text(200, "text/plain", game.fen()),
+        ("GET", ["pgn"]) => match game.pgn() {
+            Ok(pgn) => Response::text(200, "application/x-chess-pgn", pgn),
+            Err(e) => Response::error(500, e),
+        },
+        ("POST", ["undo"]) => match game.undo() {
+            Ok(_) => Response::json(200, &state(id, entry)),
+            Err(e) => Response::error(409, e),
+        },
+        _ => unreachable!("routed by Server::handle"),
+    }
+}
+
+fn state(id: u64, entry: &Entry) -> GameState {
+    let game = &entry.game;
+    let moves = game.played_moves().to_vec();
+    // SAN needs the position before each move
+    let mut san = Vec::new();
+    if let Ok(mut replay) = game.start_position() {
+        for mv in &moves {
+            san.push(replay.san(mv).unwrap_or_else(|_| mv.to_uci()));
+            if replay.make_move(mv).is_err() {
+                break;
+            }
+        }
+    }
+    let board = game.get_board();
+    GameState {
+        id,
+        fen: game.fen(),
+        turn: board.turn,
+        moves,
+        san,
+        legal_moves: solver::legal_moves(game),
+        in_check: board.is_in_check(board.turn),
+        result: game.result().map(|result| result.score().to_string()),
+        termination: game.result().map(|result| result.termination().to_string()),
+        clock: live_clock(game),
+        solver: entry.solver.clone(),
+    }
+}
+
+/// The time each side has left, counting the side to move's time on this
+/// move while the game goes on.
+fn live_clock(game: &ChessGame) -> Option<ClockState> {
+    let clock = game.clock()?;
+    let turn = game.get_board().turn;
+    let mut state = ClockState::from(clock);
+    if game.result().is_none() {
+        let now = clock.remaining_now(turn).as_millis() as u64;
+        match turn {
+            ChessColour::White => state.white_ms = now,
+            ChessColour::Black => state.black_ms = now,
+        }
+    }
+    Some(state)
+}
+
+fn analysis(game: &ChessGame, search: &SearchResult) -> Analysis {
+    let turn = game.get_board().turn;
+    Analysis {
+        depth: search.depth,
+        nodes: search.nodes,
+        best_move: search.best_move,
+        lines: search
+            .lines
+            .iter()
+            .map(|line| AnalysisLine {
+                score: line.score.for_white(turn).into(),
+                pv: line.principal_variation.clone(),
+                san: game.san_line(&line.principal_variation),
+            })
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn json(response: &Response) -> serde_json::Value {
+        serde_json::from_slice(&response.body).unwrap()
+    }
+
+    #[test]
+    fn test_play_a_game() {
+        let server = Server::new(SearchOptions::default());
+        let created = server.handle(&Request::new("POST", "/games", ""));
+        assert_eq!(created.status, 201);
+        assert_eq!(json(&created)["id"], 1);
+        assert_eq!(json(&created)["legal_moves"].as_array().unwrap().len(), 20);
+
+        for mv in ["e4", "e7e5", "Bc4", "Nc6", "Qh5", "Nf6"] {
+            let played = server.handle(&Request::new(
+                "POST",
+                "/games/1/moves",
+                &format!(r#"{{"move": "{mv}"}}"#),
+            ));
+            assert_eq!(played.status, 200, "{mv}");
+        }
+        let illegal = server.handle(&Request::new("POST", "/games/1/moves", r#"{"move":"Ke3"}"#));
+        assert_eq!(illegal.status, 422);
+        assert!(json(&illegal)["error"].is_string());
+
+        let mate = server.handle(&Request::new(
+            "POST",
+            "/games/1/moves",
+            r#"{"move":"Qxf7"}"#,
+        ));
+        let state = json(&mate);
+        assert_eq!(state["san"][6], "Qxf7#");
+        assert_eq!(state["moves"][6], "h5f7");
+        assert_eq!(state["result"], "1-0");
+        assert_eq!(state["termination"], "checkmate");
+        assert_eq!(state["in_check"], true);
+
+        let pgn = server.handle(&Request::new("GET", "/games/1/pgn", ""));
+        assert!(
+            String::from_utf8(pgn.body)
+                .unwrap()
+                .contains("4. Qxf7# 1-0")
+        );
+        let undone = server.handle(&Request::new("POST", "/games/1/undo", ""));
+        assert_eq!(json(&undone)["result"], serde_json::Value::Null);
+
+        let deleted = server.handle(&Request::new("DELETE", "/games/1", ""));
+        assert_eq!(deleted.status, 204);
+        let gone = server.handle(&Request::new("GET", "/games/1/fen", ""));
+        assert_eq!(gone.status, 404);
+    }
+
+    #[test]
+    fn test_requests() {
+        let server = Server::new(SearchOptions::default());
+        let fen = "6k1/5ppp/8/8/8/8/5PPP/3R2K1 w - - 0 1";
+        let created = server.handle(&Request::new(
+            "POST",
+            "/games",
+            &format!(r#"{{"fen": "{fen}"}}"#),
+        ));
+        assert_eq!(created.status, 201);
+        assert_eq!(
+            server.handle(&Request::new("GET", "/games/1/fen", "")).body,
+            fen.as_bytes()
+        );
+        let analysis = server.handle(&Request::new(
+            "GET",
+            "/games/1/analysis?depth=3&lines=2",
+            "",
+        ));
+        let analysis = json(&analysis);
+        assert_eq!(analysis["best_move"], "d1d8");
+        assert_eq!(analysis["lines"][0]["score"]["mate"], 1);
+        assert_eq!(analysis["lines"][0]["san"], "Rd8#");
+        assert_eq!(analysis["lines"].as_array().unwrap().len(), 2);
+
+        server.handle(&Request::new("POST", "/games", r#"{"chess960": true}"#));
+        let list = server.handle(&Request::new("GET", "/games", ""));
+        assert_eq!(json(&list)["games"], serde_json::json!([1, 2]));
+
+        for (request, status) in [
+            (
+                Request::new("POST", "/games", r#"{"fen": "nonsense"}"#),
+                422,
+            ),
+            (
+                Request::new("POST", "/games", r#"{"colour": "white"}"#),
+                400,
+            ),
+            (Request::new("POST", "/games/1/moves", "e4"), 400),
+            (Request::new("GET", "/games/1/analysis?depth=deep", ""), 400),
+            (Request::new("GET", "/games/9", ""), 404),
+            (Request::new("GET", "/games/one", ""), 404),
+            (Request::new("GET", "/elsewhere", ""), 404),
+            (Request::new("PUT", "/games/1", ""), 405),
+            (Request::new("OPTIONS", "/games", ""), 204),
+        ] {
+            assert_eq!(server.handle(&request).status, status, "{request:?}");
+        }
+    }
+
+    #[test]
+    fn test_bad_requests_leave_the_server_up() {
+        let server = Server::new(SearchOptions::default());
+        server.handle(&Request::new("POST", "/games", ""));
+        let bad = server.handle(&Request::new("POST", "/games/1/moves", r#"{"move":"eé4"}"#));
+        assert_eq!(bad.status, 422);
+        assert_eq!(
+            server.handle(&Request::new("GET", "/games", "")).status,
+            200
+        );
+
+        // even a request that panics with the games locked
+        let games = Arc::clone(&server.games);
+        std::thread::spawn(move || {
+            let _games = games.lock().unwrap();
+            panic!("a request went wrong");
+        })
+        .join()
+        .unwrap_err();
+        assert_eq!(
+            server.handle(&Request::new("GET", "/games", "")).status,
+            200
+        );
+        let played = server.handle(&Request::new("POST", "/games/1/moves", r#"{"move":"e4"}"#));
+        assert_eq!(played.status, 200);
+    }
+
+    #[test]
+    fn test_quick_analysis() {
+        let server = Server::new(SearchOptions::default());
+        let kiwipete = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
+        server.handle(&Request::new(
+            "POST",
+            "/games",
+            &format!(r#"{{"fen": "{kiwipete}"}}"#),
+        ));
+        let legal =
+            json(&server.handle(&Request::new("GET", "/games/1/moves", "")))["moves"].clone();
+        for movetime in [1, 20, 200] {
+            let analysis = server.handle(&Request::new(
+                "GET",
+                &format!("/games/1/analysis?movetime={movetime}"),
+                "",
+            ));
+            assert_eq!(analysis.status, 200, "{movetime}");
+            let best_move = &json(&analysis)["best_move"];
+            assert!(legal.as_array().unwrap().contains(best_move), "{movetime}");
+        }
+    }
+
+    #[test]
+    fn test_serve() {
+        use std::io::{Read, Write};
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let server = Server::new(SearchOptions::default());
+        std::thread::spawn(move || server.run(listener));
+        let mut stream = TcpStream::connect(address).unwrap();
+        stream
+            .write_all(b"POST /games HTTP/1.1\r\nHost: localhost\r\nContent-Length: 0\r\n\r\n")
+            .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        assert!(response.starts_with("HTTP/1.1 201 Created\r\n"));
+        assert!(response.contains("\"id\":1"));
+    }
+
+    /// The next update a server sends, as a client reads it.
+    fn next_update(stream: &mut impl std::io::Read) -> serde_json::Value {
+        let mut header = [0; 2];
+        stream.read_exact(&mut header).unwrap();
+        assert_eq!(header[0], 0x81, "expected a text frame");
+        let len = match header[1] {
+            126 => {
+                let mut len = [0; 2];
+                stream.read_exact(&mut len).unwrap();
+                usize::from(u16::from_be_bytes(len))
+            }
+            len => usize::from(len),
+        };
+        let mut payload = vec![0; len];
+        stream.read_exact(&mut payload).unwrap();
+        serde_json::from_slice(&payload).unwrap()
+    }
+
+    #[test]
+    fn test_stream() {
+        use std::io::{BufRead, Write};
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let server = Server::new(SearchOptions::default());
+        let serving = server.clone();
+        std::thread::spawn(move || serving.run(listener));
+        server.handle(&Request::new("POST", "/games", r#"{"time": "5+0"}"#));
+
+        let mut stream = TcpStream::connect(address).unwrap();
+        stream
+            .write_all(
+                b"GET /games/1/stream HTTP/1.1\r\n\
+                  Upgrade: websocket\r\n\
+                  Connection: Upgrade\r\n\
+                  Sec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\n\
+                  Sec-WebSocket-Version: 13\r\n\r\n",
+            )
+            .unwrap();
+        let mut reader = BufReader::new(stream.try_clone().unwrap());
+        let mut line = String::new();
+        reader.read_line(&mut line).unwrap();
+        assert_eq!(line, "HTTP/1.1 101 Switching Protocols\r\n");
+        while line != "\r\n" {
+            line.clear();
+            reader.read_line(&mut line).unwrap();
+        }
+
+        let update = next_update(&mut reader);
+        assert_eq!(update["type"], "state");
+        assert_eq!(update["clock"]["black_ms"], 300_000);
+        server.handle(&Request::new("POST", "/games/1/moves", r#"{"move": "d4"}"#));
+        let update = next_update(&mut reader);
+        assert_eq!(
+            (&update["type"], &update["san"][0]),
+            (&"state".into(), &"d4".into())
+        );
+        let update = next_update(&mut reader);
+        assert_eq!(update["type"], "clock");
+        assert!(update["black_ms"].as_u64().unwrap() < 300_000, "{update}");
+
+        server.handle(&Request::new("GET", "/games/1/analysis?depth=2", ""));
+        let update = next_update(&mut reader);
+        assert_eq!(
+            (&update["type"], &update["depth"]),
+            (&"analysis".into(), &1.into())
+        );
+    }
+
+    #[test]
+    fn test_solver_plays() {
+        let server = Server::new(SearchOptions {
+            book: OpeningBook::Disabled,
+            ..SearchOptions::fixed_depth(2)
+        });
+        // each side mates in one, White first
+        let fen = "6k1/5ppp/8/8/8/8/5PPP/3R2K1 w - - 0 1";
+        let created = server.handle(&Request::new(
+            "POST",
+            "/games",
+            &format!(r#"{{"fen": "{fen}", "solver": ["white", "black"]}}"#),
+        ));
+        assert_eq!(
+            json(&created)["solver"],
+            serde_json::json!(["white", "black"])
+        );
+        let started = Instant::now();
+        let state = loop {
+            let state = json(&server.handle(&Request::new("GET", "/games/1", "")));
+            if !state["result"].is_null() || started.elapsed() > Duration::from_secs(10) {
+                break state;
+            }
+            std::thread::sleep(Duration::from_millis(10));
+        };
+        assert_eq!(state["san"], serde_json::json!(["Rd8#"]));
+        let refused = server.handle(&Request::new("POST", "/games/1/moves", r#"{"move": "h6"}"#));
+        assert_eq!(refused.status, 409);
+    }
+}
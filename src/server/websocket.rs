@@ -0,0 +1,244 @@
+//! Just enough WebSocket (RFC 6455) for [`super::Server`]'s streams: the
+//! handshake that turns an HTTP request into a WebSocket, and unfragmented
+//! frames of at most [`MAX_PAYLOAD`] bytes.
+
+use super::http::Request;
+use std::io::{self, Read, Write};
+
+/// Appended to the client's key to prove the server speaks WebSocket.
+const GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+
+/// The largest frame a client may send. They only send control frames and
+/// short messages.
+pub const MAX_PAYLOAD: usize = 64 * 1024;
+
+const CONTINUATION: u8 = 0x0;
+const TEXT: u8 = 0x1;
+const BINARY: u8 = 0x2;
+const CLOSE: u8 = 0x8;
+const PING: u8 = 0x9;
+const PONG: u8 = 0xa;
+
+/// A message in either direction.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Frame {
+    Text(String),
+    Binary(Vec<u8>),
+    Ping(Vec<u8>),
+    Pong(Vec<u8>),
+    Close,
+}
+
+/// Whether `request` asks to become a WebSocket.
+pub fn is_upgrade(request: &Request) -> bool {
+    request
+        .header("upgrade")
+        .is_some_and(|upgrade| upgrade.eq_ignore_ascii_case("websocket"))
+}
+
+/// Accept `request`'s upgrade to a WebSocket, failing if it isn't one.
+pub fn handshake(request: &Request, writer: &mut impl Write) -> anyhow::Result<()> {
+    anyhow::ensure!(is_upgrade(request), "not a WebSocket request");
+    let key = request
+        .header("sec-websocket-key")
+        .ok_or_else(|| anyhow::anyhow!("no Sec-WebSocket-Key"))?;
+    write!(
+        writer,
+        "HTTP/1.1 101 Switching Protocols\r\n\
+         Upgrade: websocket\r\n\
+         Connection: Upgrade\r\n\
+         Sec-WebSocket-Accept: {}\r\n\r\n",
+        accept_key(key)
+    )?;
+    writer.flush()?;
+    Ok(())
+}
+
+/// The `Sec-WebSocket-Accept` answer to a client's `Sec-WebSocket-Key`.
+pub fn accept_key(key: &str) -> String {
+    base64(&sha1(format!("{}{GUID}", key.trim()).as_bytes()))
+}
+
+/// Read the next frame from a client, whose frames are masked.
+pub fn read_frame(reader: &mut impl Read) -> io::Result<Frame> {
+    let mut header = [0; 2];
+    reader.read_exact(&mut header)?;
+    let fin = header[0] & 0x80 != 0;
+    let opcode = header[0] & 0x0f;
+    let masked = header[1] & 0x80 != 0;
+    let len = match header[1] & 0x7f {
+        126 => {
+            let mut len = [0; 2];
+            reader.read_exact(&mut len)?;
+            u64::from(u16::from_be_bytes(len))
+        }
+        127 => {
+            let mut len = [0; 8];
+            reader.read_exact(&mut len)?;
+            u64::from_be_bytes(len)
+        }
+        len => u64::from(len),
+    };
+    if !fin || opcode == CONTINUATION {
+        return Err(invalid("fragmented messages aren't supported"));
+    }
+    if !masked {
+        return Err(invalid("client frames must be masked"));
+    }
+    if len > MAX_PAYLOAD as u64 {
+        return Err(invalid("frame too large"));
+    }
+    let mut mask = [0; 4];
+    reader.read_exact(&mut mask)?;
+    let mut payload = vec![0; len as usize];
+    reader.read_exact(&mut payload)?;
+    for (i, byte) in payload.iter_mut().enumerate() {
+        *byte ^= mask[i % 4];
+    }
+    match opcode {
+        TEXT => String::from_utf8(payload)
+            .map(Frame::Text)
+            .map_err(|_| invalid("text that isn't UTF-8")),
+        BINARY => Ok(Frame::Binary(payload)),
+        CLOSE => Ok(Frame::Close),
+        PING => Ok(Frame::Ping(payload)),
+        PONG => Ok(Frame::Pong(payload)),
+        _ => Err(invalid("unknown opcode")),
+    }
+}
+
+/// Write `frame` to a client, unmasked as servers send them.
+pub fn write_frame(writer: &mut impl Write, frame: &Frame) -> io::Result<()> {
+    let (opcode, payload) = match frame {
+        Frame::Text(text) => (TEXT, text.as_bytes()),
+        Frame::Binary(bytes) => (BINARY, bytes.as_slice()),
+        Frame::Ping(bytes) => (PING, bytes.as_slice()),
+        Frame::Pong(bytes) => (PONG, bytes.as_slice()),
+        Frame::Close => (CLOSE, &[][..]),
+    };
+    let mut header = vec![0x80 | opcode];
+    match payload.len() {
+        len @ 0..126 => header.push(len as u8),
+        len @ 126..=0xffff => {
+            header.push(126);
+            header.extend_from_slice(&(len as u16).to_be_bytes());
+        }
+        len => {
+            header.push(127);
+            header.extend_from_slice(&(len as u64).to_be_bytes());
+        }
+    }
+    writer.write_all(&header)?;
+    writer.write_all(payload)?;
+    writer.flush()
+}
+
+fn invalid(reason: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, reason)
+}
+
+/// SHA-1, which the handshake needs and nothing else does.
+fn sha1(data: &[u8]) -> [u8; 20] {
+    let mut h: [u32; 5] = [0x67452301, 0xefcdab89, 0x98badcfe, 0x10325476, 0xc3d2e1f0];
+    let mut message = data.to_vec();
+    message.push(0x80);
+    while message.len() % 64 != 56 {
+        message.push(0);
+    }
+    message.extend_from_slice(&(data.len() as u64 * 8).to_be_bytes());
+    for block in message.chunks(64) {
+        let mut w = [0u32; 80];
+        for (i, word) in block.chunks(4).enumerate() {
+            w[i] = u32::from_be_bytes(word.try_into().unwrap());
+        }
+        for i in 16..80 {
+            w[i] = (w[i - 3] ^ w[i - 8] ^ w[i - 14] ^ w[i - 16]).rotate_left(1);
+        }
+        let [mut a, mut b, mut c, mut d, mut e] = h;
+        for (i, word) in w.iter().enumerate() {
+            let (f, k) = match i {
+                0..20 => ((b & c) | (!b & d), 0x5a827999),
+                20..40 => (b ^ c ^ d, 0x6ed9eba1),
+                40..60 => ((b & c) | (b & d) | (c & d), 0x8f1bbcdc),
+                _ => (b ^ c ^ d, 0xca62c1d6),
+            };
+            let temp = a
+                .rotate_left(5)
+                .wrapping_add(f)
+                .wrapping_add(e)
+                .wrapping_add(k)
+                .wrapping_add(*word);
+            e = d;
+            d = c;
+            c = b.rotate_left(30);
+            b = a;
+            a = temp;
+        }
+        for (h, x) in h.iter_mut().zip([a, b, c, d, e]) {
+            *h = h.wrapping_add(x);
+        }
+    }
+    let mut digest = [0; 20];
+    for (bytes, word) in digest.chunks_mut(4).zip(h) {
+        bytes.copy_from_slice(&word.to_be_bytes());
+    }
+    digest
+}
+
+fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut encoded = String::new();
+    for chunk in bytes.chunks(3) {
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |n, (i, byte)| n | u32::from(*byte) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(ALPHABET[(n >> (18 - 6 * i) & 0x3f) as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_accept_key() {
+        // the example from RFC 6455
+        assert_eq!(
+            accept_key("dGhlIHNhbXBsZSBub25jZQ=="),
+            "s3pPLMBiTxaQ9kYGzzhZRbK+xOo="
+        );
+        assert_eq!(base64(b"ab"), "YWI=");
+        assert_eq!(base64(b"a"), "YQ==");
+    }
+
+    #[test]
+    fn test_frames() {
+        // a masked "Hello" from RFC 6455
+        let hello = [
+            0x81, 0x85, 0x37, 0xfa, 0x21, 0x3d, 0x7f, 0x9f, 0x4d, 0x51, 0x58,
+        ];
+        assert_eq!(
+            read_frame(&mut &hello[..]).unwrap(),
+            Frame::Text("Hello".to_string())
+        );
+        let mut written = Vec::new();
+        write_frame(&mut written, &Frame::Text("Hello".to_string())).unwrap();
+        assert_eq!(written, [0x81, 0x05, b'H', b'e', b'l', b'l', b'o']);
+
+        let mut written = Vec::new();
+        write_frame(&mut written, &Frame::Binary(vec![0; 300])).unwrap();
+        assert_eq!(written[..4], [0x82, 126, 1, 44]);
+
+        // unmasked, and fragmented
+        assert!(read_frame(&mut &[0x81, 0x00][..]).is_err());
+        assert!(read_frame(&mut &[0x01, 0x80, 0, 0, 0, 0][..]).is_err());
+    }
+}
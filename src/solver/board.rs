@@ -0,0 +1,520 @@
+use crate::core::{
+    CastlingRights, CastlingSide, ChessBoard, ChessColour, ChessPiece, ChessPieceKind, Column,
+    KING_MOVES, KNIGHT_MOVES, Move, Position, RAYS, Square,
+};
+use crate::game::{ChessGame, GameResult, Termination};
+use crate::variant::Variant;
+use crate::zobrist;
+use alloc::vec::Vec;
+
+/// Everything needed to take back a move: the squares it changed, with what was
+/// on them before, and the state that can't be worked out from the board.
+pub(super) struct Undo {
+    squares: [(Position, Option<ChessPiece>); 4],
+    changed: usize,
+    castling: CastlingRights,
+    half_move_clock: u32,
+    key: u64,
+}
+
+/// A board the search makes and unmakes moves on in place, rather than cloning
+/// a [`ChessGame`] (with its move history) for every position it visits. The
+/// Zobrist hash is updated as pieces move, and after the first few positions
+/// the search allocates nothing.
+#[derive(Clone)]
+pub(super) struct SearchBoard {
+    board: ChessBoard,
+    variant: Variant,
+    key: u64,
+    half_move_clock: u32,
+    /// The key of every position so far, including those played in the game
+    /// before the search started, for spotting repetitions.
+    history: Vec<u64>,
+    /// Reusable move lists, one for each ply of the search.
+    move_buffers: Vec<Vec<Move>>,
+}
+
+impl SearchBoard {
+    pub fn new(game: &ChessGame) -> Self {
+        let board = game.get_board().clone();
+        Self {
+            key: zobrist::hash(&board),
+            board,
+            variant: game.variant(),
+            half_move_clock: game.half_move_clock(),
+            history: game.position_history().to_vec(),
+            move_buffers: Vec::new(),
+        }
+    }
+
+    /// A board for generating the moves in `game`'s position, without the
+    /// history the search needs to spot repetitions.
+    pub fn without_history(game: &ChessGame) -> Self {
+        let board = game.get_board().clone();
+        Self {
+            key: zobrist::hash(&board),
+            board,
+            variant: game.variant(),
+            half_move_clock: game.half_move_clock(),
+            history: Vec::new(),
+            move_buffers: Vec::new(),
+        }
+    }
+
+    pub fn board(&self) -> &ChessBoard {
+        &self.board
+    }
+
+    pub fn key(&self) -> u64 {
+        self.key
+    }
+
+    pub fn turn(&self) -> ChessColour {
+        self.board.turn
+    }
+
+    pub fn in_check(&self) -> bool {
+        self.board.is_in_check(self.board.turn)
+    }
+
+    pub fn piece_at(&self, pos: &Position) -> Option<ChessPiece> {
+        self.board.get(Square::from(*pos))
+    }
+
+    /// Take the move list for `ply`, to be handed back with
+    /// [`SearchBoard::return_moves`] once the ply is done with it.
+    pub fn take_moves(&mut self, ply: usize) -> Vec<Move> {
+        if self.move_buffers.len() <= ply {
+            self.move_buffers.resize_with(ply + 1, Vec::new);
+        }
+        ::core::mem::take(&mut self.move_buffers[ply])
+    }
+
+    pub fn return_moves(&mut self, ply: usize, mut moves: Vec<Move>) {
+        moves.clear();
+        self.move_buffers[ply] = moves;
+    }
+
+    /// Put `piece` on `pos`, recording what was there in `undo` and keeping the
+    /// hash up to date.
+    fn set(&mut self, undo: &mut Undo, pos: Position, piece: Option<ChessPiece>) {
+        let old = self.piece_at(&pos);
+        undo.squares[undo.changed] = (pos, old);
+        undo.changed += 1;
+        if let Some(old) = &old {
+            self.key ^= zobrist::piece_key(old, &pos);
+        }
+        if let Some(new) = &piece {
+            self.key ^= zobrist::piece_key(new, &pos);
+        }
+        self.board.set(Square::from(pos), piece);
+    }
+
+    /// Play `mv`, which must be legal in the current position.
+    pub fn make(&mut self, mv: &Move) -> Undo {
+        let colour = self.board.turn;
+        let piece = self.piece_at(&mv.from).expect("no piece to move");
+        let mut undo = Undo {
+            squares: [(mv.from, None); 4],
+            changed: 0,
+            castling: self.board.castling,
+            half_move_clock: self.half_move_clock,
+            key: self.key,
+        };
+        let moved = ChessPiece {
+            moved: true,
+            ..piece
+        };
+
+        let castling_side = [CastlingSide::King, CastlingSide::Queen]
+            .into_iter()
+            .find(|side| {
+                piece.kind == ChessPieceKind::King
+                    && self.castling_target(colour, *side) == Some(mv.to)
+            });
+        if let Some(side) = castling_side {
+            let row = colour.back_row();
+            let rook_column = self.board.castling.get(colour, side).unwrap();
+            let rook = Position {
+                row,
+                column: rook_column,
+            };
+            let rook_piece = self.piece_at(&rook);
+            self.set(&mut undo, mv.from, None);
+            self.set(&mut undo, rook, None);
+            let king_to = Position {
+                row,
+                column: side.king_destination(),
+            };
+            let rook_to = Position {
+                row,
+                column: side.rook_destination(),
+            };
+            self.set(&mut undo, king_to, Some(moved));
+            self.set(
+                &mut undo,
+                rook_to,
+                rook_piece.map(|p| ChessPiece { moved: true, ..p }),
+            );
+            self.board.castling.clear(colour);
+            self.half_move_clock += 1;
+        } else {
+            let captured = self.piece_at(&mv.to);
+            self.set(&mut undo, mv.from, None);
+            self.set(&mut undo, mv.to, Some(moved));
+            if piece.kind == ChessPieceKind::King {
+                self.board.castling.clear(colour);
+            }
+            self.board.castling.remove_rook(&mv.from);
+            self.board.castling.remove_rook(&mv.to);
+            if piece.kind == ChessPieceKind::Pawn || captured.is_some() {
+                self.half_move_clock = 0;
+            } else {
+                self.half_move_clock += 1;
+            }
+        }
+
+        self.key ^= zobrist::castling_hash(&undo.castling)
+            ^ zobrist::castling_hash(&self.board.castling)
+            ^ zobrist::black_to_move_key();
+        self.board.turn = colour.flip();
+        self.history.push(self.key);
+        undo
+    }
+
+    /// Take back the last move made, given the [`Undo`] it returned.
+    pub fn unmake(&mut self, undo: Undo) {
+        for (pos, piece) in undo.squares[..undo.changed].iter().rev() {
+            self.board.set(Square::from(*pos), *piece);
+        }
+        self.board.castling = undo.castling;
+        self.board.turn = self.board.turn.flip();
+        self.half_move_clock = undo.half_move_clock;
+        self.key = undo.key;
+        self.history.pop();
+    }
+
+    /// A draw by the fifty move rule, repetition or insufficient material. Mate
+    /// and stalemate are found by the search when there are no legal moves.
+    pub fn rule_draw(&self) -> Option<GameResult> {
+        if self.half_move_clock >= 100 {
+            return Some(GameResult::Draw(Termination::FiftyMoveRule));
+        }
+        // only positions since the last capture or pawn move can repeat
+        let start = self
+            .history
+            .len()
+            .saturating_sub(self.half_move_clock as usize + 1);
+        let reversible = &self.history[start..];
+        if reversible.iter().filter(|key| **key == self.key).count() >= 3 {
+            return Some(GameResult::Draw(Termination::Repetition));
+        }
+        if self.is_insufficient_material() {
+            return Some(GameResult::Draw(Termination::InsufficientMaterial));
+        }
+        None
+    }
+
+    /// The result when the side to move has no legal moves.
+    pub fn no_moves_result(&self) -> GameResult {
+        let turn = self.board.turn;
+        if self.board.is_in_check(turn) {
+            GameResult::win_for(turn.flip(), Termination::Checkmate)
+        } else {
+            GameResult::Draw(Termination::Stalemate)
+        }
+    }
+
+    fn is_insufficient_material(&self) -> bool {
+        let mut minor_pieces = 0;
+        for (_, cell) in self.board.pieces() {
+            match cell.piece.map(|p| p.kind) {
+                Some(ChessPieceKind::King) | None => {}
+                Some(ChessPieceKind::Bishop | ChessPieceKind::Knight) => minor_pieces += 1,
+                Some(_) => return false,
+            }
+        }
+        minor_pieces <= 1
+    }
+
+    /// Fill `moves` with every legal move for the side to move.
+    pub fn legal_moves(&mut self, moves: &mut Vec<Move>) {
+        moves.clear();
+        let colour = self.board.turn;
+        for from in Square::all() {
+            let Some(piece) = self.board.get(from).filter(|p| p.colour == colour) else {
+                continue;
+            };
+            let first = moves.len();
+            self.piece_moves(from, &piece, moves);
+            // drop the moves that leave the king in check, keeping the order
+            let mut kept = first;
+            for i in first..moves.len() {
+                if !self.leaves_king_in_check(&moves[i]) {
+                    moves[kept] = moves[i];
+                    kept += 1;
+                }
+            }
+            moves.truncate(kept);
+            if piece.kind == ChessPieceKind::King {
+                let from = Position::from(from);
+                for side in [CastlingSide::King, CastlingSide::Queen] {
+                    if self.can_castle(&from, colour, side)
+                        && let Some(to) = self.castling_target(colour, side)
+                    {
+                        moves.push(Move { from, to });
+                    }
+                }
+            }
+        }
+    }
+
+    /// The moves of `piece` on `from`, ignoring checks and castling.
+    pub fn piece_moves(&self, from: Square, piece: &ChessPiece, moves: &mut Vec<Move>) {
+        let start = Position::from(from);
+        let mut push_if_not_own = |to: Square| -> bool {
+            match self.board.get(to) {
+                Some(other) => {
+                    if other.colour != piece.colour {
+                        moves.push(Move {
+                            from: start,
+                            to: to.into(),
+                        });
+                    }
+                    false
+                }
+                None => {
+                    moves.push(Move {
+                        from: start,
+                        to: to.into(),
+                    });
+                    true
+                }
+            }
+        };
+        let mut slide = |directions: ::core::ops::Range<usize>| {
+            for rays in &RAYS[directions] {
+                for to in rays[from.index()].as_slice() {
+                    if !push_if_not_own(*to) {
+                        break;
+                    }
+                }
+            }
+        };
+        match piece.kind {
+            ChessPieceKind::Pawn => self.pawn_moves(from, piece, moves),
+            ChessPieceKind::Knight => {
+                for to in KNIGHT_MOVES[from.index()].as_slice() {
+                    push_if_not_own(*to);
+                }
+            }
+            ChessPieceKind::Bishop => slide(4..8),
+            ChessPieceKind::Rook => slide(0..4),
+            ChessPieceKind::Queen => slide(0..8),
+            ChessPieceKind::King => {
+                for to in KING_MOVES[from.index()].as_slice() {
+                    push_if_not_own(*to);
+                }
+            }
+        }
+    }
+
+    fn pawn_moves(&self, from: Square, piece: &ChessPiece, moves: &mut Vec<Move>) {
+        let direction = piece.colour.direction();
+        let start = Position::from(from);
+        let is_empty = |square: Square| self.board.get(square).is_none();
+        if let Some(next) = from.offset(direction, 0)
+            && is_empty(next)
+        {
+            moves.push(Move {
+                from: start,
+                to: next.into(),
+            });
+            let starting_row =
+                from.offset(-direction, 0).map(Square::row) == Some(piece.colour.back_row());
+            if starting_row
+                && let Some(double_move) = next.offset(direction, 0)
+                && is_empty(double_move)
+            {
+                moves.push(Move {
+                    from: start,
+                    to: double_move.into(),
+                });
+            }
+        }
+        for i in [-1, 1] {
+            if let Some(diagonal) = from.offset(direction, i)
+                && self
+                    .board
+                    .get(diagonal)
+                    .is_some_and(|p| p.colour != piece.colour)
+            {
+                moves.push(Move {
+                    from: start,
+                    to: diagonal.into(),
+                });
+            }
+        }
+    }
+
+    pub fn leaves_king_in_check(&mut self, mv: &Move) -> bool {
+        let (from, to) = (Square::from(mv.from), Square::from(mv.to));
+        let piece = self.board.get(from);
+        let captured = self.board.get(to);
+        self.board.set(from, None);
+        self.board.set(to, piece);
+        let in_check = self.board.is_in_check(self.board.turn);
+        self.board.set(to, captured);
+        self.board.set(from, piece);
+        in_check
+    }
+
+    /// The square the king is moved to in order to castle to the given side.
+    pub fn castling_target(&self, colour: ChessColour, side: CastlingSide) -> Option<Position> {
+        let rook_column = self.board.castling.get(colour, side)?;
+        let column = match self.variant {
+            Variant::Standard => side.king_destination(),
+            Variant::Chess960 => rook_column,
+        };
+        Some(Position {
+            row: colour.back_row(),
+            column,
+        })
+    }
+
+    /// The same rules as the game: the king and rook have to be able to reach
+    /// their squares with nothing in between, and the king may not start in,
+    /// pass through or land on an attacked square.
+    pub fn can_castle(&mut self, king: &Position, colour: ChessColour, side: CastlingSide) -> bool {
+        let Some(rook_column) = self.board.castling.get(colour, side) else {
+            return false;
+        };
+        let row = colour.back_row();
+        if king.row != row {
+            return false;
+        }
+        let rook = Position {
+            row,
+            column: rook_column,
+        };
+        let rook_piece = self.piece_at(&rook);
+        let is_own_rook =
+            rook_piece.is_some_and(|p| p.kind == ChessPieceKind::Rook && p.colour == colour);
+        if !is_own_rook || self.board.is_in_check(colour) {
+            return false;
+        }
+
+        let king_piece = self.piece_at(king);
+        self.board.set(Square::from(*king), None);
+        self.board.set(Square::from(rook), None);
+        let span = |a: Column, b: Column| {
+            let (a, b) = (usize::from(a), usize::from(b));
+            (a.min(b)..=a.max(b)).map(|c| Position {
+                row,
+                column: Column::try_from(c as isize).unwrap(),
+            })
+        };
+        let king_path = span(king.column, side.king_destination());
+        let rook_path = span(rook_column, side.rook_destination());
+        let allowed = king_path
+            .clone()
+            .chain(rook_path)
+            .all(|pos| self.piece_at(&pos).is_none())
+            && king_path
+                .into_iter()
+                .all(|pos| !self.board.is_square_attacked(&pos, colour.flip()));
+        self.board.set(Square::from(*king), king_piece);
+        self.board.set(Square::from(rook), rook_piece);
+        allowed
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn play(game: &mut ChessGame, board: &mut SearchBoard, moves: &[&str]) {
+        for mv in moves {
+            let mv = Move::from_uci(mv).unwrap();
+            game.make_move(&mv).unwrap();
+            board.make(&mv);
+        }
+    }
+
+    fn sorted_moves(game: &ChessGame, board: &mut SearchBoard) -> (Vec<String>, Vec<String>) {
+        // by the game's own rules, rather than the search board's
+        let turn = game.get_board().turn;
+        let mut expected: Vec<String> = game
+            .get_board()
+            .pieces()
+            .filter(|(_, cell)| cell.piece.is_some_and(|p| p.colour == turn))
+            .flat_map(|(from, _)| {
+                let destinations = game.get_available_moves(from).unwrap();
+                destinations.into_iter().map(move |to| Move { from, to })
+            })
+            .map(|mv| mv.to_string())
+            .collect();
+        let mut moves = Vec::new();
+        board.legal_moves(&mut moves);
+        let mut actual: Vec<String> = moves.iter().map(|mv| mv.to_string()).collect();
+        expected.sort();
+        actual.sort();
+        (expected, actual)
+    }
+
+    #[test]
+    fn test_matches_game_moves() {
+        let mut game = ChessGame::default();
+        let mut board = SearchBoard::new(&game);
+        let (expected, actual) = sorted_moves(&game, &mut board);
+        assert_eq!(expected, actual);
+
+        play(
+            &mut game,
+            &mut board,
+            &[
+                "e2e4", "e7e5", "g1f3", "b8c6", "f1c4", "f8c5", "d2d3", "g8f6",
+            ],
+        );
+        let (expected, actual) = sorted_moves(&game, &mut board);
+        assert_eq!(expected, actual);
+        assert!(actual.contains(&"e1->g1".to_string()));
+        assert_eq!(board.key(), zobrist::hash(game.get_board()));
+    }
+
+    #[test]
+    fn test_unmake_restores_position() {
+        let game = ChessGame::from_fen(
+            "r3k2r/ppp2ppp/2n5/3qp3/8/2N5/PPP2PPP/R3K2R w KQkq - 0 1",
+            Variant::Standard,
+        )
+        .unwrap();
+        let mut board = SearchBoard::new(&game);
+        let mut moves = Vec::new();
+        board.legal_moves(&mut moves);
+        for mv in &moves {
+            let undo = board.make(mv);
+            let mut after = game.clone();
+            after.make_move(mv).unwrap();
+            assert_eq!(board.key(), zobrist::hash(after.get_board()), "{mv}");
+            board.unmake(undo);
+            assert_eq!(board.board(), game.get_board(), "{mv}");
+            assert_eq!(board.key(), zobrist::hash(game.get_board()));
+        }
+    }
+
+    #[test]
+    fn test_repetition() {
+        let mut game = ChessGame::default();
+        let mut board = SearchBoard::new(&game);
+        let shuffle = ["g1f3", "g8f6", "f3g1", "f6g8"];
+        play(&mut game, &mut board, &shuffle);
+        assert_eq!(board.rule_draw(), None);
+        play(&mut game, &mut board, &shuffle);
+        assert_eq!(
+            board.rule_draw(),
+            Some(GameResult::Draw(Termination::Repetition))
+        );
+        assert_eq!(game.result(), board.rule_draw());
+    }
+}
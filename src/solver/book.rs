@@ -0,0 +1,163 @@
+use super::polyglot::PolyglotBook;
+use crate::core::Move;
+use crate::game::ChessGame;
+use crate::zobrist;
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+/// The book is only consulted for this many half moves into the game.
+pub const BOOK_DEPTH: usize = 16;
+
+/// Mainline openings in UCI notation, with how often each should be chosen
+/// relative to the others.
+const BOOK_LINES: &[(&str, u32)] = &[
+    // Ruy Lopez
+    (
+        "e2e4 e7e5 g1f3 b8c6 f1b5 a7a6 b5a4 g8f6 e1g1 f8e7 f1e1 b7b5 a4b3 d7d6",
+        10,
+    ),
+    // Italian Game
+    (
+        "e2e4 e7e5 g1f3 b8c6 f1c4 f8c5 c2c3 g8f6 d2d3 d7d6 e1g1 e8g8",
+        8,
+    ),
+    // Petrov Defence
+    ("e2e4 e7e5 g1f3 g8f6 f3e5 d7d6 e5f3 f6e4 d2d4 d6d5", 3),
+    // Sicilian Najdorf
+    (
+        "e2e4 c7c5 g1f3 d7d6 d2d4 c5d4 f3d4 g8f6 b1c3 a7a6 c1e3 e7e5",
+        8,
+    ),
+    // Sicilian Taimanov
+    ("e2e4 c7c5 g1f3 e7e6 d2d4 c5d4 f3d4 b8c6 b1c3 d8c7", 4),
+    // French Defence
+    ("e2e4 e7e6 d2d4 d7d5 b1c3 g8f6 c1g5 f8e7 e4e5 f6d7", 5),
+    // Caro-Kann Defence
+    ("e2e4 c7c6 d2d4 d7d5 b1c3 d5e4 c3e4 c8f5 e4g3 f5g6", 5),
+    // Scandinavian Defence
+    ("e2e4 d7d5 e4d5 d8d5 b1c3 d5a5 d2d4 g8f6", 2),
+    // Queen's Gambit Declined
+    ("d2d4 d7d5 c2c4 e7e6 b1c3 g8f6 c1g5 f8e7 e2e3 e8g8 g1f3", 8),
+    // Queen's Gambit Accepted
+    ("d2d4 d7d5 c2c4 d5c4 g1f3 g8f6 e2e3 e7e6 f1c4 c7c5", 3),
+    // Slav Defence
+    ("d2d4 d7d5 c2c4 c7c6 g1f3 g8f6 b1c3 d5c4 a2a4 c8f5", 5),
+    // King's Indian Defence
+    (
+        "d2d4 g8f6 c2c4 g7g6 b1c3 f8g7 e2e4 d7d6 g1f3 e8g8 f1e2 e7e5",
+        6,
+    ),
+    // Nimzo-Indian Defence
+    ("d2d4 g8f6 c2c4 e7e6 b1c3 f8b4 e2e3 e8g8 f1d3 d7d5", 6),
+    // London System
+    ("d2d4 d7d5 c1f4 g8f6 e2e3 e7e6 g1f3 c7c5 c2c3 b8c6", 3),
+    // English Opening
+    ("c2c4 e7e5 b1c3 g8f6 g1f3 b8c6 g2g3 d7d5 c4d5 f6d5", 4),
+    // Reti Opening
+    ("g1f3 d7d5 g2g3 g8f6 f1g2 e7e6 e1g1 f8e7 d2d3", 3),
+];
+
+type Book = HashMap<u64, Vec<(Move, u32)>>;
+
+/// The book keyed by position hash, so transpositions between lines are found.
+fn book() -> &'static Book {
+    static BOOK: OnceLock<Book> = OnceLock::new();
+    BOOK.get_or_init(|| {
+        let mut book: Book = HashMap::new();
+        for (line, weight) in BOOK_LINES {
+            let mut game = ChessGame::default();
+            for uci in line.split_whitespace() {
+                let mv = Move::from_uci(uci).expect("book moves are valid UCI");
+                let moves = book.entry(zobrist::hash(game.get_board())).or_default();
+                match moves.iter_mut().find(|(m, _)| *m == mv) {
+                    Some((_, total)) => *total += weight,
+                    None => moves.push((mv, *weight)),
+                }
+                game.make_move(&mv)
+                    .unwrap_or_else(|e| panic!("illegal book move {uci} in '{line}': {e}"));
+            }
+        }
+        book
+    })
+}
+
+/// The book moves for the current position and their weights.
+pub fn book_moves(game: &ChessGame) -> &'static [(Move, u32)] {
+    if game.played_moves().len() >= BOOK_DEPTH {
+        return &[];
+    }
+    book()
+        .get(&zobrist::hash(game.get_board()))
+        .map(Vec::as_slice)
+        .unwrap_or_default()
+}
+
+/// Pick a book move for the current position at random, weighted so the more
+/// popular lines are played more often, or `None` when out of book.
+pub fn book_move(game: &ChessGame) -> Option<Move> {
+    weighted_choice(book_moves(game))
+}
+
+/// Pick one of `moves` at random, in proportion to its weight.
+pub(super) fn weighted_choice(moves: &[(Move, u32)]) -> Option<Move> {
+    let total: u32 = moves.iter().map(|(_, weight)| weight).sum();
+    if total == 0 {
+        return None;
+    }
+    let mut pick = rand::random_range(0..total);
+    for (mv, weight) in moves {
+        if pick < *weight {
+            return Some(*mv);
+        }
+        pick -= weight;
+    }
+    None
+}
+
+/// Where the solver looks up opening moves before it starts searching.
+#[derive(Debug, Clone, Default)]
+pub enum OpeningBook {
+    /// The small book of mainline openings built into the engine.
+    #[default]
+    Builtin,
+    /// An external book loaded from a Polyglot `.bin` file.
+    Polyglot(PolyglotBook),
+    /// Always search, even in the opening.
+    Disabled,
+}
+
+impl OpeningBook {
+    pub fn book_move(&self, game: &ChessGame) -> Option<Move> {
+        match self {
+            OpeningBook::Builtin => book_move(game),
+            OpeningBook::Polyglot(book) => book.book_move(game),
+            OpeningBook::Disabled => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_book_covers_the_opening() {
+        let mut game = ChessGame::default();
+        let first_moves: Vec<Move> = book_moves(&game).iter().map(|(m, _)| *m).collect();
+        assert!(first_moves.contains(&Move::from_uci("e2e4").unwrap()));
+        assert!(first_moves.contains(&Move::from_uci("d2d4").unwrap()));
+
+        for _ in 0..6 {
+            let Some(mv) = book_move(&game) else { break };
+            game.make_move(&mv).unwrap();
+        }
+        assert!(!game.played_moves().is_empty());
+    }
+
+    #[test]
+    fn test_out_of_book() {
+        let mut game = ChessGame::default();
+        game.make_move(&Move::from_uci("h2h4").unwrap()).unwrap();
+        assert_eq!(book_move(&game), None);
+    }
+}
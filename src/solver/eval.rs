@@ -0,0 +1,408 @@
+use super::BoardScore;
+use super::pawns;
+use crate::core::{ChessBoard, ChessColour, ChessPieceKind, Position, Square};
+use ::core::fmt::{Debug, Display, Formatter};
+use alloc::vec::Vec;
+
+/// Centipawns per pawn, the unit [`ChessPieceKind::value`] is measured in.
+pub const PAWN_VALUE: BoardScore = 100;
+
+/// Non pawn material each side starts with, in pawns.
+const STARTING_PIECE_MATERIAL: BoardScore = 31;
+
+/// Penalty for each file around the king without a pawn directly in front of it.
+const MISSING_SHIELD_PAWN: BoardScore = 25;
+/// Penalty when the shield pawn has been pushed two squares.
+const ADVANCED_SHIELD_PAWN: BoardScore = 10;
+const SEMI_OPEN_FILE_NEAR_KING: BoardScore = 15;
+const OPEN_FILE_NEAR_KING: BoardScore = 25;
+
+/// How much of the attack weight counts, in percent, by number of attackers. A
+/// single attacker is rarely dangerous, several together are.
+const ATTACKER_SCALE: [BoardScore; 8] = [0, 0, 50, 75, 88, 94, 97, 99];
+/// Centipawns per unit of attack weight on the king zone.
+const ATTACK_UNIT: BoardScore = 10;
+
+/// Centipawns per square a piece can move to, by piece. Short range pieces gain
+/// the most from each extra square.
+fn mobility_weight(kind: ChessPieceKind) -> BoardScore {
+    match kind {
+        ChessPieceKind::Knight | ChessPieceKind::Bishop => 4,
+        ChessPieceKind::Rook => 2,
+        ChessPieceKind::Queen => 1,
+        ChessPieceKind::Pawn | ChessPieceKind::King => 0,
+    }
+}
+
+// Piece-square tables, from white's side of the board with the eighth rank
+// first, as the board is usually drawn. The king has none as king safety
+// already judges where it stands.
+#[rustfmt::skip]
+const PAWN_SQUARES: [[BoardScore; 8]; 8] = [
+    [  0,   0,   0,   0,   0,   0,   0,   0],
+    [ 50,  50,  50,  50,  50,  50,  50,  50],
+    [ 10,  10,  20,  30,  30,  20,  10,  10],
+    [  5,   5,  10,  25,  25,  10,   5,   5],
+    [  0,   0,   0,  20,  20,   0,   0,   0],
+    [  5,  -5, -10,   0,   0, -10,  -5,   5],
+    [  5,  10,  10, -20, -20,  10,  10,   5],
+    [  0,   0,   0,   0,   0,   0,   0,   0],
+];
+#[rustfmt::skip]
+const KNIGHT_SQUARES: [[BoardScore; 8]; 8] = [
+    [-50, -40, -30, -30, -30, -30, -40, -50],
+    [-40, -20,   0,   0,   0,   0, -20, -40],
+    [-30,   0,  10,  15,  15,  10,   0, -30],
+    [-30,   5,  15,  20,  20,  15,   5, -30],
+    [-30,   0,  15,  20,  20,  15,   0, -30],
+    [-30,   5,  10,  15,  15,  10,   5, -30],
+    [-40, -20,   0,   5,   5,   0, -20, -40],
+    [-50, -40, -30, -30, -30, -30, -40, -50],
+];
+#[rustfmt::skip]
+const BISHOP_SQUARES: [[BoardScore; 8]; 8] = [
+    [-20, -10, -10, -10, -10, -10, -10, -20],
+    [-10,   0,   0,   0,   0,   0,   0, -10],
+    [-10,   0,   5,  10,  10,   5,   0, -10],
+    [-10,   5,   5,  10,  10,   5,   5, -10],
+    [-10,   0,  10,  10,  10,  10,   0, -10],
+    [-10,  10,  10,  10,  10,  10,  10, -10],
+    [-10,   5,   0,   0,   0,   0,   5, -10],
+    [-20, -10, -10, -10, -10, -10, -10, -20],
+];
+#[rustfmt::skip]
+const ROOK_SQUARES: [[BoardScore; 8]; 8] = [
+    [  0,   0,   0,   0,   0,   0,   0,   0],
+    [  5,  10,  10,  10,  10,  10,  10,   5],
+    [ -5,   0,   0,   0,   0,   0,   0,  -5],
+    [ -5,   0,   0,   0,   0,   0,   0,  -5],
+    [ -5,   0,   0,   0,   0,   0,   0,  -5],
+    [ -5,   0,   0,   0,   0,   0,   0,  -5],
+    [ -5,   0,   0,   0,   0,   0,   0,  -5],
+    [  0,   0,   0,   5,   5,   0,   0,   0],
+];
+#[rustfmt::skip]
+const QUEEN_SQUARES: [[BoardScore; 8]; 8] = [
+    [-20, -10, -10,  -5,  -5, -10, -10, -20],
+    [-10,   0,   0,   0,   0,   0,   0, -10],
+    [-10,   0,   5,   5,   5,   5,   0, -10],
+    [ -5,   0,   5,   5,   5,   5,   0,  -5],
+    [  0,   0,   5,   5,   5,   5,   0,  -5],
+    [-10,   5,   5,   5,   5,   5,   0, -10],
+    [-10,   0,   5,   0,   0,   0,   0, -10],
+    [-20, -10, -10,  -5,  -5, -10, -10, -20],
+];
+
+/// Scores positions for the search, so the hand-crafted evaluation can be
+/// swapped for another, such as [`super::nnue::NnueEvaluator`].
+pub trait Evaluator: Debug + Send + Sync {
+    /// How good `board` is for `colour`, in centipawns.
+    fn evaluate(&self, board: &ChessBoard, colour: ChessColour) -> BoardScore;
+}
+
+/// The hand-crafted evaluation, see [`explain_evaluation`].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Classical;
+
+impl Evaluator for Classical {
+    fn evaluate(&self, board: &ChessBoard, colour: ChessColour) -> BoardScore {
+        super::score_board(board, &colour)
+    }
+}
+
+/// One term of the evaluation for both sides, in centipawns.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Copy, Default)]
+pub struct TermScore {
+    pub white: BoardScore,
+    pub black: BoardScore,
+}
+
+impl TermScore {
+    fn new(board: &ChessBoard, term: impl Fn(&ChessBoard, ChessColour) -> BoardScore) -> Self {
+        Self {
+            white: term(board, ChessColour::White),
+            black: term(board, ChessColour::Black),
+        }
+    }
+
+    pub fn for_colour(&self, colour: ChessColour) -> BoardScore {
+        match colour {
+            ChessColour::White => self.white,
+            ChessColour::Black => self.black,
+        }
+    }
+
+    /// How much more `colour` gets from this term than its opponent.
+    pub fn advantage(&self, colour: ChessColour) -> BoardScore {
+        self.for_colour(colour) - self.for_colour(colour.flip())
+    }
+}
+
+/// The hand-crafted evaluation split into its terms, to show why the solver
+/// thinks one side is better.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Copy, Default)]
+pub struct EvaluationBreakdown {
+    pub material: TermScore,
+    pub piece_squares: TermScore,
+    pub mobility: TermScore,
+    pub king_safety: TermScore,
+    pub pawn_structure: TermScore,
+}
+
+impl EvaluationBreakdown {
+    /// Every term with a name to show it by.
+    pub fn terms(&self) -> [(&'static str, TermScore); 5] {
+        [
+            ("Material", self.material),
+            ("Piece squares", self.piece_squares),
+            ("Mobility", self.mobility),
+            ("King safety", self.king_safety),
+            ("Pawn structure", self.pawn_structure),
+        ]
+    }
+
+    /// The whole evaluation for `colour`, in centipawns.
+    pub fn score(&self, colour: ChessColour) -> BoardScore {
+        self.terms()
+            .iter()
+            .map(|(_, term)| term.advantage(colour))
+            .sum()
+    }
+}
+
+impl Display for EvaluationBreakdown {
+    fn fmt(&self, f: &mut Formatter<'_>) -> ::core::fmt::Result {
+        let pawns = |score: BoardScore| f64::from(score) / f64::from(PAWN_VALUE);
+        writeln!(f, "{:<16}{:>8}{:>8}{:>8}", "", "White", "Black", "Total")?;
+        for (name, term) in self.terms() {
+            writeln!(
+                f,
+                "{name:<16}{:>8.2}{:>8.2}{:>+8.2}",
+                pawns(term.white),
+                pawns(term.black),
+                pawns(term.advantage(ChessColour::White))
+            )?;
+        }
+        write!(
+            f,
+            "{:<16}{:>16}{:>+8.2}",
+            "Total",
+            "",
+            pawns(self.score(ChessColour::White))
+        )
+    }
+}
+
+/// Score each term of the hand-crafted evaluation for both sides.
+pub fn explain_evaluation(board: &ChessBoard) -> EvaluationBreakdown {
+    EvaluationBreakdown {
+        material: TermScore::new(board, material),
+        piece_squares: TermScore::new(board, piece_squares),
+        mobility: TermScore::new(board, mobility),
+        king_safety: TermScore::new(board, king_safety),
+        pawn_structure: TermScore::new(board, pawns::pawn_structure),
+    }
+}
+
+/// The material of `colour` in centipawns.
+pub fn material(board: &ChessBoard, colour: ChessColour) -> BoardScore {
+    board
+        .pieces()
+        .filter_map(|(_, cell)| cell.piece)
+        .filter(|p| p.colour == colour)
+        .map(|p| p.kind.value() as BoardScore * PAWN_VALUE)
+        .sum()
+}
+
+/// How well placed the pieces of `colour` are, from the piece-square tables.
+pub fn piece_squares(board: &ChessBoard, colour: ChessColour) -> BoardScore {
+    board
+        .pieces()
+        .filter_map(|(pos, cell)| cell.piece.map(|p| (pos, p)))
+        .filter(|(_, p)| p.colour == colour)
+        .map(|(pos, p)| {
+            let table = match p.kind {
+                ChessPieceKind::Pawn => &PAWN_SQUARES,
+                ChessPieceKind::Knight => &KNIGHT_SQUARES,
+                ChessPieceKind::Bishop => &BISHOP_SQUARES,
+                ChessPieceKind::Rook => &ROOK_SQUARES,
+                ChessPieceKind::Queen => &QUEEN_SQUARES,
+                ChessPieceKind::King => return 0,
+            };
+            let square = Square::from(pos);
+            // the tables are drawn with white's far side first
+            let row = match colour {
+                ChessColour::White => 7 - square.rank(),
+                ChessColour::Black => square.rank(),
+            };
+            table[row][square.file()]
+        })
+        .sum()
+}
+
+/// A bonus for each square the pieces of `colour` attack and could move to.
+pub fn mobility(board: &ChessBoard, colour: ChessColour) -> BoardScore {
+    board
+        .pieces()
+        .filter_map(|(pos, cell)| cell.piece.map(|p| (pos, p)))
+        .filter(|(_, p)| p.colour == colour && mobility_weight(p.kind) > 0)
+        .map(|(pos, p)| {
+            let squares = board
+                .attacked_squares(&pos)
+                .iter()
+                .filter(|square| {
+                    board
+                        .get(Square::from(**square))
+                        .is_none_or(|other| other.colour != colour)
+                })
+                .count() as BoardScore;
+            squares * mobility_weight(p.kind)
+        })
+        .sum()
+}
+
+/// Non pawn, non king material of `colour` in pawns, used to judge how far into
+/// the endgame the position is.
+fn piece_material(board: &ChessBoard, colour: ChessColour) -> BoardScore {
+    board
+        .pieces()
+        .filter_map(|(_, cell)| cell.piece)
+        .filter(|p| {
+            p.colour == colour && !matches!(p.kind, ChessPieceKind::Pawn | ChessPieceKind::King)
+        })
+        .map(|p| p.kind.value() as BoardScore)
+        .sum()
+}
+
+fn attack_weight(kind: ChessPieceKind) -> BoardScore {
+    match kind {
+        ChessPieceKind::Knight | ChessPieceKind::Bishop => 2,
+        ChessPieceKind::Rook => 3,
+        ChessPieceKind::Queen => 5,
+        ChessPieceKind::Pawn | ChessPieceKind::King => 0,
+    }
+}
+
+/// How exposed the king of `colour` is, as a penalty in centipawns (zero or
+/// negative). Looks at the pawn shield in front of the king, open files next to
+/// it, and how many enemy pieces bear down on the squares around it.
+pub fn king_safety(board: &ChessBoard, colour: ChessColour) -> BoardScore {
+    let Some(king) = board.king_position(colour) else {
+        return 0;
+    };
+    let has_pawn = |pos: Result<Position, _>, pawn_colour: ChessColour| {
+        pos.ok()
+            .and_then(|p| board.get(Square::from(p)))
+            .is_some_and(|p| p.kind == ChessPieceKind::Pawn && p.colour == pawn_colour)
+    };
+    let direction = colour.direction();
+
+    let mut structure_penalty = 0;
+    for column_offset in -1..=1 {
+        let Ok(file) = king.add_offset(0, column_offset) else {
+            continue;
+        };
+        if has_pawn(file.add_offset(direction, 0), colour) {
+            // the shield is intact on this file
+        } else if has_pawn(file.add_offset(2 * direction, 0), colour) {
+            structure_penalty += ADVANCED_SHIELD_PAWN;
+        } else {
+            structure_penalty += MISSING_SHIELD_PAWN;
+        }
+
+        let pawns_on_file =
+            |pawn_colour| (-7..=7).any(|row| has_pawn(file.add_offset(row, 0), pawn_colour));
+        if !pawns_on_file(colour) {
+            structure_penalty += if pawns_on_file(colour.flip()) {
+                SEMI_OPEN_FILE_NEAR_KING
+            } else {
+                OPEN_FILE_NEAR_KING
+            };
+        }
+    }
+    // a broken shield matters less once the opponent's pieces are traded off
+    let structure_penalty =
+        structure_penalty * piece_material(board, colour.flip()) / STARTING_PIECE_MATERIAL;
+
+    // the king's square and every square around it
+    let zone: Vec<Position> = (-1..=1)
+        .flat_map(|i| (-1..=1).map(move |j| (i, j)))
+        .flat_map(|(i, j)| king.add_offset(i, j))
+        .collect();
+    let mut attackers = 0;
+    let mut weight = 0;
+    for (pos, cell) in board.pieces() {
+        let Some(piece) = cell.piece.filter(|p| p.colour != colour) else {
+            continue;
+        };
+        let hits = board
+            .attacked_squares(&pos)
+            .iter()
+            .filter(|square| zone.contains(square))
+            .count() as BoardScore;
+        if hits > 0 && attack_weight(piece.kind) > 0 {
+            attackers += 1;
+            weight += attack_weight(piece.kind) * hits;
+        }
+    }
+    let attack_penalty =
+        weight * ATTACK_UNIT * ATTACKER_SCALE[attackers.min(ATTACKER_SCALE.len() - 1)] / 100;
+
+    -(structure_penalty + attack_penalty)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::game::ChessGame;
+    use crate::variant::Variant;
+
+    fn board(fen: &str) -> ChessBoard {
+        ChessGame::from_fen(fen, Variant::Standard)
+            .unwrap()
+            .get_board()
+            .clone()
+    }
+
+    #[test]
+    fn test_explain_evaluation() {
+        let start = explain_evaluation(&ChessBoard::default());
+        for (_, term) in start.terms() {
+            assert_eq!(term.white, term.black);
+        }
+        assert_eq!(start.score(ChessColour::White), 0);
+
+        // the knight on the rim scores worse than the one in the centre, and
+        // can reach fewer squares
+        let rim = board("4k3/8/8/8/8/8/8/N3K3 w - - 0 1");
+        let centre = board("4k3/8/8/8/3N4/8/8/4K3 w - - 0 1");
+        let (rim, centre) = (explain_evaluation(&rim), explain_evaluation(&centre));
+        assert_eq!(rim.material, centre.material);
+        assert!(rim.piece_squares.white < centre.piece_squares.white);
+        assert!(rim.mobility.white < centre.mobility.white);
+        assert_eq!(
+            centre.score(ChessColour::Black),
+            -centre.score(ChessColour::White)
+        );
+        assert!(centre.to_string().contains("Mobility"));
+    }
+
+    #[test]
+    fn test_pawn_shield() {
+        let sheltered = board("r2q1rk1/ppp2ppp/8/8/8/8/PPP2PPP/R2Q1RK1 w - - 0 1");
+        let exposed = board("r2q1rk1/ppp2ppp/8/8/8/6PP/PPP2P2/R2Q1RK1 w - - 0 1");
+        assert_eq!(king_safety(&sheltered, ChessColour::White), 0);
+        assert!(king_safety(&exposed, ChessColour::White) < 0);
+    }
+
+    #[test]
+    fn test_attackers_on_king_zone() {
+        let quiet = board("6k1/5ppp/8/8/8/8/5PPP/3QR1K1 w - - 0 1");
+        let attacked = board("6k1/5ppp/8/5N2/8/1B6/5PPP/3QR1K1 b - - 0 1");
+        let attacked_with_queen = board("6k1/5ppp/6Q1/5N2/8/1B6/5PPP/4R1K1 b - - 0 1");
+        let quiet = king_safety(&quiet, ChessColour::Black);
+        let attacked = king_safety(&attacked, ChessColour::Black);
+        assert!(attacked < quiet);
+        assert!(king_safety(&attacked_with_queen, ChessColour::Black) < attacked);
+    }
+}
@@ -0,0 +1,84 @@
+use super::legal_moves;
+use crate::core::{ChessColour, Move};
+use crate::game::{ChessGame, GameResult, Termination};
+use alloc::vec::Vec;
+
+/// Search only for a forced checkmate by the side to move within `moves` of its
+/// own moves, returning the mating line (attacker's moves and the defence that
+/// holds out longest), or `None` if there is no such mate. Shorter mates are
+/// looked for first, so the line found is the quickest one.
+pub fn solve_mate(game: &ChessGame, moves: usize) -> Option<Vec<Move>> {
+    let attacker = game.get_board().turn;
+    (1..=moves).find_map(|n| mate_line(game, attacker, 2 * n - 1))
+}
+
+/// The moves leading to `attacker` giving mate within `plies` half moves,
+/// whatever the defender does.
+fn mate_line(game: &ChessGame, attacker: ChessColour, plies: usize) -> Option<Vec<Move>> {
+    if let Some(result) = game.result() {
+        return (result == GameResult::win_for(attacker, Termination::Checkmate)).then(Vec::new);
+    }
+    if plies == 0 {
+        return None;
+    }
+
+    let mut replies = legal_moves(game).into_iter().filter_map(|mv| {
+        let mut next = game.clone();
+        next.make_move(&mv).ok()?;
+        Some((mv, next))
+    });
+    if game.get_board().turn == attacker {
+        // any one move that forces mate will do
+        replies.find_map(|(mv, next)| {
+            let mut line = mate_line(&next, attacker, plies - 1)?;
+            line.insert(0, mv);
+            Some(line)
+        })
+    } else {
+        // every defence has to lose, so give up as soon as one holds
+        let mut longest: Option<Vec<Move>> = None;
+        for (mv, next) in replies {
+            let mut line = mate_line(&next, attacker, plies - 1)?;
+            line.insert(0, mv);
+            if longest.as_ref().is_none_or(|l| line.len() > l.len()) {
+                longest = Some(line);
+            }
+        }
+        longest
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::variant::Variant;
+
+    fn uci(line: &[&str]) -> Vec<Move> {
+        line.iter().map(|mv| Move::from_uci(mv).unwrap()).collect()
+    }
+
+    #[test]
+    fn test_mate_in_one() {
+        let game =
+            ChessGame::from_fen("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1", Variant::Standard).unwrap();
+        assert_eq!(solve_mate(&game, 3), Some(uci(&["a1a8"])));
+    }
+
+    #[test]
+    fn test_mate_in_two() {
+        // the king has to come up first, and whatever black does Ra8 mates
+        let game = ChessGame::from_fen("7k/8/5K2/8/8/8/8/R7 w - - 0 1", Variant::Standard).unwrap();
+        assert_eq!(solve_mate(&game, 1), None);
+
+        let line = solve_mate(&game, 2).unwrap();
+        assert_eq!(line.len(), 3);
+        let mut end = game.clone();
+        for mv in &line {
+            end.make_move(mv).unwrap();
+        }
+        assert_eq!(
+            end.result(),
+            Some(GameResult::WhiteWins(Termination::Checkmate))
+        );
+    }
+}
@@ -1,47 +1,311 @@
-use crate::core::{ChessBoard, ChessColour, ChessError, ChessPiece, Move, Position};
-use crate::game::ChessGame;
-use std::fmt::{Debug, Formatter};
+//! The built-in engine: an alpha-beta search with a transposition table,
+//! opening books and a choice of evaluations.
+
+mod board;
+#[cfg(feature = "std")]
+pub mod book;
+pub mod eval;
+pub mod mate;
+pub mod movegen;
+#[cfg(feature = "std")]
+pub mod nnue;
+pub mod ordering;
+pub mod pawns;
+pub mod perft;
+#[cfg(feature = "std")]
+pub mod polyglot;
+#[cfg(feature = "std")]
+pub mod ponder;
+pub mod tt;
+
+use crate::core::{ChessBoard, ChessColour, ChessError, Move};
+use crate::game::{ChessGame, GameResult};
+use crate::zobrist;
+use ::core::fmt::{Debug, Formatter};
+use ::core::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use ::core::time::Duration;
+use alloc::sync::Arc;
+use alloc::vec;
+use alloc::vec::Vec;
+use board::SearchBoard;
+#[cfg(feature = "std")]
+use book::OpeningBook;
+use eval::Evaluator;
+use movegen::MoveGen;
+use ordering::MoveOrdering;
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "std")]
+use std::time::Instant;
+use tt::{Bound, TranspositionTable, TtEntry};
 
 type BoardScore = i32;
 
+/// Score the board for `self_colour` in centipawns: how much better off we are
+/// than our opponent in each term of [`eval::explain_evaluation`].
 fn score_board(board: &ChessBoard, self_colour: &ChessColour) -> BoardScore {
-    // TODO: this is quite simplistic can improve later
-
-    let mut our_value = 0;
-    let mut their_value = 0;
-    for piece in board.pieces() {
-        match piece.1.piece {
-            Some(ref chess_piece) => {
-                let value = chess_piece.kind.value() as BoardScore;
-                if chess_piece.colour == *self_colour {
-                    our_value += value;
-                } else {
-                    their_value += value;
-                }
-            }
-            None => continue,
+    let score = eval::explain_evaluation(board).score(*self_colour);
+    tracing::trace!("Score of board for {}: {}", self_colour, score);
+    score
+}
+
+/// Score for delivering checkmate. Mates found closer to the root score higher
+/// so the solver prefers the quickest mate.
+const MATE_SCORE: BoardScore = 100_000;
+
+/// Scores beyond this are mates, which are stored in the transposition table
+/// relative to the position rather than the root of the search.
+const MATE_THRESHOLD: BoardScore = MATE_SCORE - 1000;
+
+/// The deepest the solver searches by default, in half moves.
+const MAX_DEPTH: usize = 10;
+
+/// How long the solver searches for by default.
+const MAX_SEARCH_TIME: Duration = Duration::from_secs(5);
+
+/// The strongest skill level, at which the solver plays as well as it can.
+pub const MAX_SKILL: u8 = 20;
+
+/// Limits on how much effort a search may spend, and how it picks its move.
+#[derive(Debug, Clone)]
+pub struct SearchOptions {
+    /// The deepest to search, in half moves.
+    pub max_depth: usize,
+    /// How long to search for, or `None` to always complete `max_depth`.
+    /// Without the `std` feature there is no clock to read, and this is
+    /// ignored.
+    pub max_time: Option<Duration>,
+    /// Stop after visiting this many positions.
+    pub max_nodes: Option<u64>,
+    /// How many threads to split the root moves between. Without the `std`
+    /// feature the search always runs on the calling thread.
+    pub threads: usize,
+    /// Pick at random between the moves scoring within this many centipawns of
+    /// the best, so the solver doesn't always play the same game. Without the
+    /// `std` feature there is nothing to draw random numbers from, and the
+    /// best of them is always played.
+    pub randomness: BoardScore,
+    /// Misjudge each move by a random amount of up to this many centipawns
+    /// either way before picking one. Forced mates are never misjudged, and
+    /// nothing is without the `std` feature.
+    pub noise: BoardScore,
+    /// How much worse than an equal position a draw is, in centipawns. Positive
+    /// values make the solver play on when it thinks it is the stronger side,
+    /// negative ones make it steer for draws against a stronger opponent.
+    pub contempt: BoardScore,
+    /// How many of the best root moves to report lines for, see
+    /// [`SearchResult::lines`].
+    pub multi_pv: usize,
+    /// Only consider these moves at the root, or every legal move if `None`.
+    pub search_moves: Option<Vec<Move>>,
+    /// Where to look up moves before searching.
+    #[cfg(feature = "std")]
+    pub book: OpeningBook,
+    /// How positions at the end of the search are scored.
+    pub evaluator: Arc<dyn Evaluator>,
+    /// Set from another thread to end the search early, which then returns the
+    /// result of the deepest iteration completed so far.
+    pub stop: Option<Arc<AtomicBool>>,
+}
+
+impl Default for SearchOptions {
+    fn default() -> Self {
+        Self {
+            max_depth: MAX_DEPTH,
+            max_time: Some(MAX_SEARCH_TIME),
+            max_nodes: None,
+            threads: 1,
+            randomness: 0,
+            noise: 0,
+            contempt: 0,
+            multi_pv: 1,
+            search_moves: None,
+            #[cfg(feature = "std")]
+            book: OpeningBook::default(),
+            evaluator: Arc::new(eval::Classical),
+            stop: None,
         }
     }
+}
 
-    let score = our_value - their_value;
-    tracing::info!("Score of board for {}: {}", self_colour, score);
-    score
+impl SearchOptions {
+    /// Always search `depth` half moves deep, however long it takes, e.g. where
+    /// there is no clock to limit the search by.
+    pub fn fixed_depth(depth: usize) -> Self {
+        Self {
+            max_depth: depth,
+            max_time: None,
+            ..Self::default()
+        }
+    }
+
+    /// Weaken the solver to `skill`, from 0 up to [`MAX_SKILL`], so that people
+    /// can beat it. Lower levels search less deeply, misjudge moves more and
+    /// pick more often from the moves close to the best.
+    pub fn with_skill(self, skill: u8) -> Self {
+        let weakness = BoardScore::from(MAX_SKILL - skill.min(MAX_SKILL));
+        Self {
+            max_depth: self.max_depth.min(1 + usize::from(skill) / 2),
+            randomness: self.randomness + 10 * weakness,
+            noise: self.noise + 15 * weakness,
+            ..self
+        }
+    }
+
+    /// Whether the search may play `mv`, given any root move restriction.
+    fn allows(&self, mv: &Move) -> bool {
+        self.search_moves
+            .as_ref()
+            .is_none_or(|moves| moves.contains(mv))
+    }
+}
+
+/// How good the position is for the side to move.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Copy, Serialize, Deserialize)]
+pub enum Score {
+    Centipawns(BoardScore),
+    /// Mate in this many moves, negative when the side to move is getting mated.
+    Mate(i32),
+}
+
+impl Score {
+    fn from_search(score: BoardScore) -> Self {
+        if score >= MATE_THRESHOLD {
+            Score::Mate((MATE_SCORE - score + 1) / 2)
+        } else if score <= -MATE_THRESHOLD {
+            Score::Mate(-(MATE_SCORE + score) / 2)
+        } else {
+            Score::Centipawns(score)
+        }
+    }
+
+    /// The score from White's side, given the side to move it was for.
+    pub fn for_white(self, side_to_move: ChessColour) -> Self {
+        match (self, side_to_move) {
+            (score, ChessColour::White) => score,
+            (Score::Centipawns(cp), ChessColour::Black) => Score::Centipawns(-cp),
+            (Score::Mate(moves), ChessColour::Black) => Score::Mate(-moves),
+        }
+    }
+}
+
+impl ::core::fmt::Display for Score {
+    fn fmt(&self, f: &mut Formatter<'_>) -> ::core::fmt::Result {
+        match self {
+            Score::Centipawns(cp) => write!(f, "{:+.2}", *cp as f64 / eval::PAWN_VALUE as f64),
+            Score::Mate(moves) => write!(f, "#{moves}"),
+        }
+    }
+}
+
+/// What the solver found, for showing what it is thinking alongside its move.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SearchResult {
+    pub best_move: Move,
+    pub score: Score,
+    /// The deepest iteration that completed, in half moves. Zero for book moves.
+    pub depth: usize,
+    /// How many positions were visited.
+    pub nodes: u64,
+    /// The line the solver expects to be played, starting with `best_move`.
+    pub principal_variation: Vec<Move>,
+    /// The best [`SearchOptions::multi_pv`] root moves, best first, each with
+    /// its score and the line expected to follow it. The first line is only
+    /// `best_move`'s when the solver isn't picking at random.
+    pub lines: Vec<SearchLine>,
+}
+
+/// One of the root moves the solver rates most highly, for comparing the
+/// alternatives.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SearchLine {
+    pub score: Score,
+    /// The line the solver expects, starting with the root move.
+    pub principal_variation: Vec<Move>,
+}
+
+/// When the search started, to hold it to [`SearchOptions::max_time`].
+/// Without the `std` feature there is no clock to read, and only the depth and
+/// node limits apply.
+#[derive(Clone, Copy)]
+struct SearchTimer {
+    #[cfg(feature = "std")]
+    start: Instant,
 }
 
-struct RecursionContext {
+impl SearchTimer {
+    fn start() -> Self {
+        Self {
+            #[cfg(feature = "std")]
+            start: Instant::now(),
+        }
+    }
+
+    #[cfg(feature = "std")]
+    fn has_run_for(&self, time: Duration) -> bool {
+        self.start.elapsed() > time
+    }
+
+    #[cfg(not(feature = "std"))]
+    fn has_run_for(&self, _time: Duration) -> bool {
+        false
+    }
+}
+
+#[derive(Clone, Copy)]
+struct RecursionContext<'a> {
     depth: usize,
-    start_time: std::time::Instant,
+    max_depth: usize,
+    timer: SearchTimer,
+    options: &'a SearchOptions,
+    /// Positions visited so far, shared between all search threads.
+    nodes: &'a AtomicU64,
 }
 
-impl RecursionContext {
+impl<'a> RecursionContext<'a> {
+    fn new(
+        options: &'a SearchOptions,
+        nodes: &'a AtomicU64,
+        max_depth: usize,
+        timer: SearchTimer,
+    ) -> Self {
+        Self {
+            depth: 0,
+            max_depth,
+            timer,
+            options,
+            nodes,
+        }
+    }
+
     fn should_recurse(&self) -> bool {
-        self.depth < 10 && self.start_time.elapsed().as_secs() <= 5
+        self.depth < self.max_depth
+    }
+
+    /// Count a visit to a position.
+    fn visit(&self) {
+        self.nodes.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Whether the search has used up its time or node budget.
+    fn should_stop(&self) -> bool {
+        let options = self.options;
+        let stopped = options
+            .stop
+            .as_ref()
+            .is_some_and(|stop| stop.load(Ordering::Relaxed));
+        let out_of_time = options
+            .max_time
+            .is_some_and(|max_time| self.timer.has_run_for(max_time));
+        let out_of_nodes = options
+            .max_nodes
+            .is_some_and(|max_nodes| self.nodes.load(Ordering::Relaxed) >= max_nodes);
+        stopped || out_of_time || out_of_nodes
     }
 
     fn recurse(&self) -> Self {
         Self {
             depth: self.depth + 1,
-            start_time: self.start_time,
+            ..*self
         }
     }
 }
@@ -50,83 +314,918 @@ struct MoveState {
     // TODO: we might want to track the history of the score to see if this is a spiral of doom...
     move_: Move,
     score: BoardScore,
-    game: ChessGame,
 }
 
 impl Debug for MoveState {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    fn fmt(&self, f: &mut Formatter<'_>) -> ::core::fmt::Result {
         write!(f, "Move({:?}, {:?})", self.move_, self.score)
     }
 }
 
-fn best_move_from_position(
-    game: &ChessGame,
+/// All legal moves for the side to move.
+pub fn legal_moves(game: &ChessGame) -> Vec<Move> {
+    MoveGen::new(game).collect()
+}
+
+/// Score a finished game from the point of view of `self_colour`. A draw is
+/// worth minus the contempt, so the solver avoids draws when contempt is
+/// positive.
+fn score_result(
+    result: GameResult,
     self_colour: &ChessColour,
-    context: RecursionContext,
-) -> Option<(Move, BoardScore)> {
+    depth: usize,
+    contempt: BoardScore,
+) -> BoardScore {
+    match result.winner() {
+        Some(winner) if winner == *self_colour => MATE_SCORE - depth as BoardScore,
+        Some(_) => -MATE_SCORE + depth as BoardScore,
+        None => -contempt,
+    }
+}
+
+/// Whether quiescence search also looks at quiet moves that give check on its
+/// first ply, which finds more tactics at the cost of a slower search.
+const QUIESCENCE_CHECKS: bool = true;
+
+/// The legal moves in `board`'s position, in the move list for `ply`, or the
+/// result of the game if it is over. The list must be handed back with
+/// [`SearchBoard::return_moves`].
+fn node_moves(board: &mut SearchBoard, ply: usize) -> Result<Vec<Move>, GameResult> {
+    let mut moves = board.take_moves(ply);
+    board.legal_moves(&mut moves);
+    let result = if moves.is_empty() {
+        Some(board.no_moves_result())
+    } else {
+        board.rule_draw()
+    };
+    match result {
+        Some(result) => {
+            board.return_moves(ply, moves);
+            Err(result)
+        }
+        None => Ok(moves),
+    }
+}
+
+/// Narrow `moves` down to the captures (and, if `include_checks`, checking
+/// moves), most valuable victim first and then least valuable attacker first.
+fn retain_noisy_moves(board: &mut SearchBoard, moves: &mut Vec<Move>, include_checks: bool) {
+    let turn = board.turn();
+    moves.retain(|mv| {
+        if ordering::capture_order(board.board(), mv).is_some() {
+            return true;
+        }
+        if !include_checks {
+            return false;
+        }
+        let undo = board.make(mv);
+        let gives_check = board.board().is_in_check(turn.flip());
+        board.unmake(undo);
+        gives_check
+    });
+    moves.sort_by_key(|mv| {
+        ::core::cmp::Reverse(ordering::capture_order(board.board(), mv).unwrap_or(0))
+    });
+}
+
+/// Extend the search past the horizon along capture sequences until the
+/// position is quiet, so the evaluation isn't taken half way through an
+/// exchange. The side to move may "stand pat" and take the static evaluation
+/// unless it is in check, in which case every move is searched.
+fn quiescence(
+    board: &mut SearchBoard,
+    self_colour: &ChessColour,
+    context: &RecursionContext<'_>,
+    ply: usize,
+    alpha: BoardScore,
+    beta: BoardScore,
+) -> Option<BoardScore> {
+    let buffer = context.depth + ply;
+    let mut moves = match node_moves(board, buffer) {
+        Ok(moves) => moves,
+        Err(result) => {
+            return Some(score_result(
+                result,
+                self_colour,
+                buffer,
+                context.options.contempt,
+            ));
+        }
+    };
+    let score = quiescence_moves(board, &mut moves, self_colour, context, ply, alpha, beta);
+    board.return_moves(buffer, moves);
+    score
+}
+
+fn quiescence_moves(
+    board: &mut SearchBoard,
+    moves: &mut Vec<Move>,
+    self_colour: &ChessColour,
+    context: &RecursionContext<'_>,
+    ply: usize,
+    mut alpha: BoardScore,
+    mut beta: BoardScore,
+) -> Option<BoardScore> {
+    context.visit();
+    if context.should_stop() {
+        return None;
+    }
+
+    let maximising = board.turn() == *self_colour;
+    let in_check = board.in_check();
+    let mut best = if in_check {
+        if maximising {
+            BoardScore::MIN
+        } else {
+            BoardScore::MAX
+        }
+    } else {
+        let stand_pat = context
+            .options
+            .evaluator
+            .evaluate(board.board(), *self_colour);
+        if maximising {
+            if stand_pat >= beta {
+                return Some(stand_pat);
+            }
+            alpha = alpha.max(stand_pat);
+        } else {
+            if stand_pat <= alpha {
+                return Some(stand_pat);
+            }
+            beta = beta.min(stand_pat);
+        }
+        stand_pat
+    };
+
+    if !in_check {
+        retain_noisy_moves(board, moves, QUIESCENCE_CHECKS && ply == 0);
+    }
+    for mv in moves.iter() {
+        let undo = board.make(mv);
+        let score = quiescence(board, self_colour, context, ply + 1, alpha, beta);
+        board.unmake(undo);
+        let score = score?;
+        if maximising {
+            best = best.max(score);
+            alpha = alpha.max(score);
+        } else {
+            best = best.min(score);
+            beta = beta.min(score);
+        }
+        if alpha >= beta {
+            break;
+        }
+    }
+    Some(best)
+}
+
+fn score_to_tt(score: BoardScore, ply: usize) -> BoardScore {
+    match score {
+        s if s >= MATE_THRESHOLD => s + ply as BoardScore,
+        s if s <= -MATE_THRESHOLD => s - ply as BoardScore,
+        s => s,
+    }
+}
+
+fn score_from_tt(score: BoardScore, ply: usize) -> BoardScore {
+    match score {
+        s if s >= MATE_THRESHOLD => s - ply as BoardScore,
+        s if s <= -MATE_THRESHOLD => s + ply as BoardScore,
+        s => s,
+    }
+}
+
+impl Bound {
+    /// The equivalent bound once the score is negated.
+    fn flip(&self) -> Bound {
+        match self {
+            Bound::Exact => Bound::Exact,
+            Bound::Lower => Bound::Upper,
+            Bound::Upper => Bound::Lower,
+        }
+    }
+}
+
+/// Depth limited minimax with alpha-beta pruning. Scores are always from the
+/// point of view of `self_colour`, which maximises while its opponent minimises.
+/// Returns `None` if the search ran out of time.
+fn minimax(
+    board: &mut SearchBoard,
+    self_colour: &ChessColour,
+    context: RecursionContext<'_>,
+    mut alpha: BoardScore,
+    mut beta: BoardScore,
+    tt: &mut TranspositionTable,
+    ordering: &mut MoveOrdering,
+) -> Option<BoardScore> {
     if !context.should_recurse() {
+        return quiescence(board, self_colour, &context, 0, alpha, beta);
+    }
+    context.visit();
+    if context.should_stop() {
         return None;
     }
-    let movable_pieces: Vec<(Position, ChessPiece)> = game
-        .get_board()
-        .pieces()
-        .filter_map(|(position, cell)| match &cell.piece {
-            Some(piece) => {
-                if piece.colour == *self_colour {
-                    Some((position, *piece))
-                } else {
-                    None
+    let mut moves = match node_moves(board, context.depth) {
+        Ok(moves) => moves,
+        Err(result) => {
+            return Some(score_result(
+                result,
+                self_colour,
+                context.depth,
+                context.options.contempt,
+            ));
+        }
+    };
+
+    let maximising = board.turn() == *self_colour;
+    // the table stores scores for the side to move, so negate them for the opponent
+    let sign = if maximising { 1 } else { -1 };
+    let key = board.key();
+    let remaining_depth = (context.max_depth - context.depth) as u8;
+    let alpha_original = alpha;
+    let mut tt_move = None;
+    if let Some(entry) = tt.probe(key) {
+        tt_move = entry.best_move;
+        if entry.depth >= remaining_depth {
+            let score = sign * score_from_tt(entry.score, context.depth);
+            let bound = if maximising {
+                entry.bound
+            } else {
+                entry.bound.flip()
+            };
+            let cutoff = match bound {
+                Bound::Exact => true,
+                Bound::Lower => {
+                    alpha = alpha.max(score);
+                    alpha >= beta
                 }
+                Bound::Upper => {
+                    beta = beta.min(score);
+                    alpha >= beta
+                }
+            };
+            if cutoff {
+                board.return_moves(context.depth, moves);
+                return Some(score);
             }
-            _ => None,
-        })
-        .collect();
+        }
+    }
 
-    // rough approx of what we should pre-allocation
-    let mut move_options = Vec::with_capacity(movable_pieces.len() * 4);
-
-    for (position, piece) in movable_pieces {
-        if let Ok(moves) = game.get_available_moves(position) {
-            for to_pos in moves {
-                let mut new_game = game.clone();
-                let mv = Move {
-                    from: position,
-                    to: to_pos,
-                };
-                if let Ok(state) = new_game.make_move(&mv) {
-                    let score = score_board(new_game.get_board(), self_colour);
-                    tracing::info!("Evaluating move: {} with score: {}", mv, score);
-                    move_options.push(MoveState {
-                        move_: mv,
-                        score,
-                        game: new_game,
-                    })
-                } else {
-                    // if the move is not valid, we skip it
-                    continue;
-                }
+    // the best move from an earlier visit goes first, as it is likely still good
+    ordering.order(board.board(), &mut moves, context.depth, tt_move);
+
+    let mut best = if maximising {
+        BoardScore::MIN
+    } else {
+        BoardScore::MAX
+    };
+    let mut best_move = None;
+    for &mv in &moves {
+        let undo = board.make(&mv);
+        let score = minimax(
+            board,
+            self_colour,
+            context.recurse(),
+            alpha,
+            beta,
+            tt,
+            ordering,
+        );
+        board.unmake(undo);
+        let Some(score) = score else {
+            board.return_moves(context.depth, moves);
+            return None;
+        };
+        let improved = if maximising {
+            score > best
+        } else {
+            score < best
+        };
+        if improved {
+            best = score;
+            best_move = Some(mv);
+        }
+        if maximising {
+            alpha = alpha.max(score);
+        } else {
+            beta = beta.min(score);
+        }
+        if alpha >= beta {
+            if ordering::capture_order(board.board(), &mv).is_none() {
+                ordering.record_cutoff(mv, context.depth, context.max_depth - context.depth);
             }
+            break;
         }
     }
 
-    move_options.sort_by(|a, b| {
+    board.return_moves(context.depth, moves);
+
+    let bound = if best <= alpha_original {
+        Bound::Upper
+    } else if best >= beta {
+        Bound::Lower
+    } else {
+        Bound::Exact
+    };
+    tt.store(TtEntry {
+        key,
+        depth: remaining_depth,
+        score: sign * score_to_tt(best, context.depth),
+        bound: if maximising { bound } else { bound.flip() },
+        best_move,
+    });
+    Some(best)
+}
+
+/// Search every root move to the context's depth, returning the best move and
+/// its score, or `None` if the search ran out of time. `root_moves` are searched
+/// in the given order, so passing the previous iteration's ranking improves
+/// pruning.
+fn best_move_from_position(
+    board: &mut SearchBoard,
+    self_colour: &ChessColour,
+    root_moves: &mut [MoveState],
+    context: RecursionContext<'_>,
+    tt: &mut TranspositionTable,
+    ordering: &mut MoveOrdering,
+) -> Option<(Move, BoardScore)> {
+    // the best scores so far, so that every move that might be reported as one
+    // of the best lines gets an exact score
+    let mut best_scores: Vec<BoardScore> = Vec::with_capacity(context.options.multi_pv + 1);
+    for option in root_moves.iter_mut() {
+        let alpha = if best_scores.len() < context.options.multi_pv.max(1) {
+            BoardScore::MIN
+        } else {
+            best_scores[best_scores.len() - 1]
+        };
+        let undo = board.make(&option.move_);
+        // widen the window by the randomness and noise so that every move close
+        // enough to the best to be picked gets an exact score rather than a bound
+        let margin = context.options.randomness + 2 * context.options.noise;
+        let score = minimax(
+            board,
+            self_colour,
+            context.recurse(),
+            alpha.saturating_sub(margin),
+            BoardScore::MAX,
+            tt,
+            ordering,
+        );
+        board.unmake(undo);
+        option.score = score?;
+        tracing::debug!(
+            "Evaluated move: {} with score: {}",
+            option.move_,
+            option.score
+        );
+        let index = best_scores.partition_point(|score| *score >= option.score);
+        best_scores.insert(index, option.score);
+        best_scores.truncate(context.options.multi_pv.max(1));
+    }
+
+    root_moves.sort_by(|a, b| {
         // sort by score, descending
         b.score.cmp(&a.score)
     });
-    move_options.first().map(|m| (m.move_, m.score))
+    root_moves.first().map(|m| (m.move_, m.score))
+}
+
+/// The board and tables of a search thread other than the caller's.
+#[cfg_attr(not(feature = "std"), allow(dead_code))]
+struct HelperThread {
+    board: SearchBoard,
+    tt: TranspositionTable,
+    ordering: MoveOrdering,
+}
+
+impl HelperThread {
+    fn new(board: &SearchBoard) -> Self {
+        Self {
+            board: board.clone(),
+            tt: TranspositionTable::default(),
+            ordering: MoveOrdering::default(),
+        }
+    }
+}
+
+/// Split the root moves between the search threads, each searching its share
+/// with its own transposition table, then rank all of them together. Returns
+/// `None` if any thread ran out of time.
+fn search_root(
+    board: &mut SearchBoard,
+    self_colour: &ChessColour,
+    root_moves: &mut [MoveState],
+    context: RecursionContext<'_>,
+    tt: &mut TranspositionTable,
+    ordering: &mut MoveOrdering,
+    helpers: &mut [HelperThread],
+) -> Option<(Move, BoardScore)> {
+    if helpers.is_empty() || root_moves.len() < 2 {
+        return best_move_from_position(board, self_colour, root_moves, context, tt, ordering);
+    }
+    split_root_moves(
+        board,
+        self_colour,
+        root_moves,
+        context,
+        tt,
+        ordering,
+        helpers,
+    )
+}
+
+/// The threaded half of [`search_root`].
+#[cfg(feature = "std")]
+fn split_root_moves(
+    board: &mut SearchBoard,
+    self_colour: &ChessColour,
+    root_moves: &mut [MoveState],
+    context: RecursionContext<'_>,
+    tt: &mut TranspositionTable,
+    ordering: &mut MoveOrdering,
+    helpers: &mut [HelperThread],
+) -> Option<(Move, BoardScore)> {
+    let chunk_size = root_moves.len().div_ceil(helpers.len() + 1);
+    let completed = std::thread::scope(|scope| {
+        let mut chunks = root_moves.chunks_mut(chunk_size);
+        let first = chunks.next();
+        let helpers: Vec<_> = chunks
+            .zip(helpers.iter_mut())
+            .map(|(chunk, helper)| {
+                scope.spawn(move || {
+                    best_move_from_position(
+                        &mut helper.board,
+                        self_colour,
+                        chunk,
+                        context,
+                        &mut helper.tt,
+                        &mut helper.ordering,
+                    )
+                    .is_some()
+                })
+            })
+            .collect();
+        let first_completed = first.is_none_or(|chunk| {
+            best_move_from_position(board, self_colour, chunk, context, tt, ordering).is_some()
+        });
+        let helpers_completed: Vec<bool> = helpers
+            .into_iter()
+            .map(|helper| helper.join().expect("search thread panicked"))
+            .collect();
+        first_completed && helpers_completed.into_iter().all(|completed| completed)
+    });
+    if !completed {
+        return None;
+    }
+    root_moves.sort_by_key(|m| ::core::cmp::Reverse(m.score));
+    root_moves.first().map(|m| (m.move_, m.score))
+}
+
+/// Without the `std` feature there are no threads to split the root moves
+/// between, and so never any helpers.
+#[cfg(not(feature = "std"))]
+fn split_root_moves(
+    _board: &mut SearchBoard,
+    _self_colour: &ChessColour,
+    _root_moves: &mut [MoveState],
+    _context: RecursionContext<'_>,
+    _tt: &mut TranspositionTable,
+    _ordering: &mut MoveOrdering,
+    _helpers: &mut [HelperThread],
+) -> Option<(Move, BoardScore)> {
+    unreachable!("search threads need the std feature")
+}
+
+/// Whether the solver, playing `colour`, would accept a draw offer. It accepts
+/// unless it believes it is ahead on material.
+pub fn accepts_draw(game: &ChessGame, colour: ChessColour) -> bool {
+    score_board(game.get_board(), &colour) <= 0
+}
+
+/// Follow the best moves stored in the transposition tables from the position
+/// after `first`, to recover the line the search expects.
+fn principal_variation(
+    game: &ChessGame,
+    first: Move,
+    tables: &[&TranspositionTable],
+    max_len: usize,
+) -> Vec<Move> {
+    let mut line = vec![first];
+    let mut game = game.clone();
+    let mut seen = Vec::new();
+    while game.make_move(line.last().unwrap()).is_ok() && line.len() < max_len {
+        let key = zobrist::hash(game.get_board());
+        // stop at repetitions, or the line would go round in circles
+        if seen.contains(&key) {
+            break;
+        }
+        seen.push(key);
+        let Some(mv) = tables
+            .iter()
+            .find_map(|table| table.probe(key).and_then(|entry| entry.best_move))
+            .filter(|mv| legal_moves(&game).contains(mv))
+        else {
+            break;
+        };
+        line.push(mv);
+    }
+    line
+}
+
+/// How long the solver thinks about a hint for the player.
+pub const HINT_TIME: Duration = Duration::from_secs(1);
+
+/// The move the solver recommends to the side to move after thinking for
+/// `think_time`, at full strength whatever skill `options` were weakened to.
+pub fn hint(
+    game: &ChessGame,
+    options: &SearchOptions,
+    think_time: Duration,
+) -> Result<SearchResult, ChessError> {
+    let options = SearchOptions {
+        max_depth: SearchOptions::default().max_depth,
+        max_time: Some(think_time),
+        randomness: 0,
+        noise: 0,
+        ..options.clone()
+    };
+    solve_next_move_with_options(game, &options, &mut TranspositionTable::default())
+}
+
+/// A misjudgement of up to `noise` either way, at random. Without the `std`
+/// feature there is nothing to draw random numbers from, so moves are judged
+/// exactly.
+#[cfg(feature = "std")]
+fn random_error(noise: BoardScore) -> BoardScore {
+    rand::random_range(-noise..=noise)
+}
+
+#[cfg(not(feature = "std"))]
+fn random_error(_noise: BoardScore) -> BoardScore {
+    0
+}
+
+/// One of the first `len` candidate moves at random, or without the `std`
+/// feature always the best.
+#[cfg(feature = "std")]
+fn random_index(len: usize) -> usize {
+    rand::random_range(0..len)
+}
+
+#[cfg(not(feature = "std"))]
+fn random_index(_len: usize) -> usize {
+    0
 }
 
 pub fn solve_next_move(game: &ChessGame) -> Result<Move, ChessError> {
+    solve_next_move_with_table(game, &mut TranspositionTable::default())
+}
+
+/// Like [`solve_next_move`], but using (and filling) the given transposition
+/// table, which can be kept between moves and sized by the caller.
+pub fn solve_next_move_with_table(
+    game: &ChessGame,
+    tt: &mut TranspositionTable,
+) -> Result<Move, ChessError> {
+    solve_next_move_with_options(game, &SearchOptions::default(), tt).map(|r| r.best_move)
+}
+
+/// Like [`solve_next_move_with_table`], with the caller controlling how much
+/// effort goes into the search, and returning what the solver thinks of the
+/// position along with its move. If the time or nodes run out before the
+/// first iteration finishes, the move ordered first is played, at depth 0.
+pub fn solve_next_move_with_options(
+    game: &ChessGame,
+    options: &SearchOptions,
+    tt: &mut TranspositionTable,
+) -> Result<SearchResult, ChessError> {
     let self_colour = game.get_board().turn;
-    let search_start_time = std::time::Instant::now();
-    let context = RecursionContext {
-        depth: 0,
-        start_time: search_start_time,
-    };
-    let best_move = best_move_from_position(game, &self_colour, context);
-    if let Some((mv, _score)) = best_move {
-        Ok(mv)
+    #[cfg(feature = "std")]
+    if let Some(mv) = options.book.book_move(game).filter(|mv| options.allows(mv)) {
+        tracing::info!("Playing book move {mv}");
+        let score = Score::Centipawns(options.evaluator.evaluate(game.get_board(), self_colour));
+        return Ok(SearchResult {
+            best_move: mv,
+            score,
+            depth: 0,
+            nodes: 0,
+            principal_variation: vec![mv],
+            lines: vec![SearchLine {
+                score,
+                principal_variation: vec![mv],
+            }],
+        });
+    }
+
+    let timer = SearchTimer::start();
+    let nodes = AtomicU64::new(0);
+    let mut root_moves: Vec<MoveState> = legal_moves(game)
+        .into_iter()
+        .filter(|mv| options.allows(mv))
+        .map(|move_| MoveState { move_, score: 0 })
+        .collect();
+    let mut board = SearchBoard::new(game);
+    let mut ordering = MoveOrdering::default();
+    let threads = if cfg!(feature = "std") {
+        options.threads
     } else {
-        Err(ChessError::SolverError("No valid moves found".to_string()))
+        1
+    };
+    let mut helpers: Vec<HelperThread> = (1..threads).map(|_| HelperThread::new(&board)).collect();
+
+    // iterative deepening: search one half move deeper each time round, keeping
+    // the result of the deepest search that completed in time
+    let mut ranked: Vec<(Move, BoardScore)> = Vec::new();
+    let mut depth = 0;
+    for max_depth in 1..=options.max_depth {
+        let context = RecursionContext::new(options, &nodes, max_depth, timer);
+        match search_root(
+            &mut board,
+            &self_colour,
+            &mut root_moves,
+            context,
+            tt,
+            &mut ordering,
+            &mut helpers,
+        ) {
+            Some((mv, score)) => {
+                tracing::info!("Depth {max_depth}: best move {mv} with score {score}");
+                ranked = root_moves.iter().map(|m| (m.move_, m.score)).collect();
+                depth = max_depth;
+                if score.abs() >= MATE_THRESHOLD {
+                    // a forced mate has been found, searching deeper won't change it
+                    break;
+                }
+            }
+            None => break,
+        }
+    }
+    tracing::debug!("Searched {} positions", nodes.load(Ordering::Relaxed));
+    if ranked.is_empty() {
+        // out of time or nodes before even depth 1 finished: a move the
+        // ordering likes is better than none
+        let mut moves: Vec<Move> = root_moves.iter().map(|m| m.move_).collect();
+        ordering.order(game.get_board(), &mut moves, 0, None);
+        let score = options.evaluator.evaluate(game.get_board(), self_colour);
+        ranked.extend(moves.first().map(|&mv| (mv, score)));
+    }
+
+    // each move as the solver judges it, which is off by up to the noise
+    let mut judged: Vec<(Move, BoardScore, BoardScore)> = ranked
+        .iter()
+        .map(|&(mv, score)| {
+            let error = if options.noise > 0 && score.abs() < MATE_THRESHOLD {
+                random_error(options.noise)
+            } else {
+                0
+            };
+            (mv, score, score + error)
+        })
+        .collect();
+    judged.sort_by_key(|(_, _, judged)| ::core::cmp::Reverse(*judged));
+    let Some(&(_, _, best_judged)) = judged.first() else {
+        return Err(ChessError::NoLegalMoves);
+    };
+    // moves that failed low only have an upper bound for their score, which can
+    // tie with the best, so only those strictly within the randomness qualify
+    let candidates: Vec<(Move, BoardScore)> = judged
+        .iter()
+        .enumerate()
+        .take_while(|(i, (_, _, judged))| {
+            *i == 0 || *judged > best_judged.saturating_sub(options.randomness)
+        })
+        .map(|(_, (mv, score, _))| (*mv, *score))
+        .collect();
+    let (best_move, score) = candidates[random_index(candidates.len())];
+
+    // a mate may have been found beyond the nominal depth by quiescence search
+    let pv_length = |score: BoardScore| {
+        if score.abs() >= MATE_THRESHOLD {
+            (MATE_SCORE - score.abs()) as usize
+        } else {
+            depth
+        }
+    };
+    let mut tables = vec![&*tt];
+    tables.extend(helpers.iter().map(|helper| &helper.tt));
+    let lines = ranked
+        .iter()
+        .take(options.multi_pv.max(1))
+        .map(|&(mv, score)| SearchLine {
+            score: Score::from_search(score),
+            principal_variation: principal_variation(game, mv, &tables, pv_length(score)),
+        })
+        .collect();
+    Ok(SearchResult {
+        best_move,
+        score: Score::from_search(score),
+        depth,
+        nodes: nodes.load(Ordering::Relaxed),
+        principal_variation: principal_variation(game, best_move, &tables, pv_length(score)),
+        lines,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::variant::Variant;
+
+    #[test]
+    fn test_finds_mate_in_one() {
+        let game =
+            ChessGame::from_fen("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1", Variant::Standard).unwrap();
+        assert_eq!(
+            solve_next_move(&game).unwrap(),
+            Move::from_uci("a1a8").unwrap()
+        );
+    }
+
+    #[test]
+    fn test_quiescence_sees_recapture() {
+        // at the horizon Rxd5 looks like winning a knight, but the pawn on e6
+        // takes back and the rook is lost
+        let game =
+            ChessGame::from_fen("4k3/8/4p3/3n4/8/8/3R4/4K3 w - - 0 1", Variant::Standard).unwrap();
+        let options = SearchOptions::default();
+        let nodes = AtomicU64::new(0);
+        let context = RecursionContext::new(&options, &nodes, 0, SearchTimer::start());
+        let mut after_capture = game.clone();
+        after_capture
+            .make_move(&Move::from_uci("d2d5").unwrap())
+            .unwrap();
+        let colour = ChessColour::White;
+        let static_score = score_board(after_capture.get_board(), &colour);
+        let quiet_score = quiescence(
+            &mut SearchBoard::new(&after_capture),
+            &colour,
+            &context,
+            0,
+            BoardScore::MIN,
+            BoardScore::MAX,
+        )
+        .unwrap();
+        assert!(static_score > 0);
+        assert!(quiet_score < 0);
+    }
+
+    #[test]
+    fn test_search_options() {
+        let game =
+            ChessGame::from_fen("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1", Variant::Standard).unwrap();
+        let mate = Move::from_uci("a1a8").unwrap();
+        let threaded = SearchOptions {
+            max_depth: 3,
+            max_time: None,
+            threads: 4,
+            ..Default::default()
+        };
+        let mut tt = TranspositionTable::default();
+        assert_eq!(
+            solve_next_move_with_options(&game, &threaded, &mut tt)
+                .unwrap()
+                .best_move,
+            mate
+        );
+
+        // a single node isn't even enough to finish the first iteration, but
+        // there is still a move
+        let starved = SearchOptions {
+            max_nodes: Some(1),
+            ..Default::default()
+        };
+        tt.clear();
+        let result = solve_next_move_with_options(&game, &starved, &mut tt).unwrap();
+        assert_eq!(result.depth, 0);
+        assert!(legal_moves(&game).contains(&result.best_move));
+        let start = ChessGame::default();
+        let result = solve_next_move_with_options(&start, &starved, &mut tt).unwrap();
+        assert!(legal_moves(&start).contains(&result.best_move));
+
+        // the root can be restricted to a few moves, even bad ones
+        let restricted = SearchOptions {
+            max_depth: 3,
+            max_time: None,
+            search_moves: Some(vec![Move::from_uci("a1a2").unwrap(), mate]),
+            ..Default::default()
+        };
+        tt.clear();
+        assert_eq!(
+            solve_next_move_with_options(&game, &restricted, &mut tt)
+                .unwrap()
+                .best_move,
+            mate
+        );
+        let restricted = SearchOptions {
+            search_moves: Some(vec![Move::from_uci("a1a2").unwrap()]),
+            ..restricted
+        };
+        tt.clear();
+        assert_eq!(
+            solve_next_move_with_options(&game, &restricted, &mut tt)
+                .unwrap()
+                .best_move,
+            Move::from_uci("a1a2").unwrap()
+        );
+    }
+
+    #[test]
+    fn test_search_result() {
+        // e.g. Kg6 Kg8 Ra8#
+        let game = ChessGame::from_fen("7k/8/5K2/8/8/8/8/R7 w - - 0 1", Variant::Standard).unwrap();
+        let options = SearchOptions::fixed_depth(4);
+        let result =
+            solve_next_move_with_options(&game, &options, &mut TranspositionTable::default())
+                .unwrap();
+        assert_eq!(result.score, Score::Mate(2));
+        assert_eq!(result.score.to_string(), "#2");
+        assert!(result.nodes > 0);
+        assert_eq!(result.principal_variation[0], result.best_move);
+        assert!(result.principal_variation.len() >= 2);
+        let mut line = game.clone();
+        for mv in &result.principal_variation {
+            line.make_move(mv).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_multi_pv() {
+        let game =
+            ChessGame::from_fen("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1", Variant::Standard).unwrap();
+        let options = SearchOptions {
+            max_depth: 3,
+            max_time: None,
+            multi_pv: 3,
+            book: OpeningBook::Disabled,
+            ..Default::default()
+        };
+        let result =
+            solve_next_move_with_options(&game, &options, &mut TranspositionTable::default())
+                .unwrap();
+        assert_eq!(result.lines.len(), 3);
+        assert_eq!(result.lines[0].score, Score::Mate(1));
+        assert_eq!(result.lines[0].principal_variation, vec![result.best_move]);
+        // the alternatives don't mate, and each starts with a different move
+        for pair in result.lines.windows(2) {
+            assert_ne!(
+                pair[0].principal_variation[0],
+                pair[1].principal_variation[0]
+            );
+        }
+        for line in &result.lines[1..] {
+            assert!(matches!(line.score, Score::Centipawns(_)));
+            let mut after = game.clone();
+            for mv in &line.principal_variation {
+                after.make_move(mv).unwrap();
+            }
+        }
+    }
+
+    #[test]
+    fn test_skill() {
+        let options = SearchOptions::default();
+        let full = options.clone().with_skill(MAX_SKILL);
+        assert_eq!(full.max_depth, options.max_depth);
+        assert_eq!((full.randomness, full.noise), (0, 0));
+
+        let weakest = options.with_skill(0);
+        assert_eq!(weakest.max_depth, 1);
+        assert!(weakest.randomness > 0 && weakest.noise > 0);
+
+        // even the weakest level doesn't miss a mate in one
+        let game =
+            ChessGame::from_fen("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1", Variant::Standard).unwrap();
+        let weakest = SearchOptions {
+            book: OpeningBook::Disabled,
+            ..weakest
+        };
+        for _ in 0..10 {
+            let result =
+                solve_next_move_with_options(&game, &weakest, &mut TranspositionTable::default())
+                    .unwrap();
+            assert_eq!(result.best_move, Move::from_uci("a1a8").unwrap());
+        }
+    }
+
+    #[test]
+    fn test_contempt() {
+        let draw = GameResult::Draw(crate::game::Termination::Repetition);
+        let white = ChessColour::White;
+        assert_eq!(score_result(draw, &white, 3, 0), 0);
+        assert_eq!(score_result(draw, &white, 3, 25), -25);
+        assert_eq!(score_result(draw, &white, 3, -25), 25);
+        let win = GameResult::WhiteWins(crate::game::Termination::Checkmate);
+        assert_eq!(score_result(win, &white, 3, 25), MATE_SCORE - 3);
+    }
+
+    #[test]
+    fn test_captures_hanging_queen() {
+        let game =
+            ChessGame::from_fen("4k3/8/8/3q4/8/8/3R4/4K3 w - - 0 1", Variant::Standard).unwrap();
+        assert_eq!(
+            solve_next_move(&game).unwrap(),
+            Move::from_uci("d2d5").unwrap()
+        );
     }
 }
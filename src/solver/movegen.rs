@@ -0,0 +1,138 @@
+//! Generating the legal moves in a position one at a time.
+
+use super::board::SearchBoard;
+use crate::core::{CastlingSide, ChessPieceKind, Move, Position, Square};
+use crate::game::ChessGame;
+use ::core::ops::Range;
+use alloc::vec::Vec;
+
+/// The legal moves for the side to move, generated a piece at a time as they
+/// are asked for, so that stopping early skips the work on the rest.
+/// [`MoveGen::captures`] and [`MoveGen::from_square`] narrow them down:
+///
+/// ```
+/// use chess::core::Position;
+/// use chess::game::ChessGame;
+/// use chess::solver::movegen::MoveGen;
+/// use std::str::FromStr;
+///
+/// let game = ChessGame::default();
+/// assert_eq!(MoveGen::new(&game).count(), 20);
+/// let knight = Position::from_str("g1").unwrap();
+/// assert_eq!(MoveGen::new(&game).from_square(knight).count(), 2);
+/// assert_eq!(MoveGen::new(&game).captures().next(), None);
+/// ```
+pub struct MoveGen {
+    board: SearchBoard,
+    /// The squares still to look for pieces on, numbered along the rows from a1.
+    squares: Range<usize>,
+    /// The moves of the piece last found, last first, not yet checked for
+    /// leaving the king in check.
+    pending: Vec<Move>,
+    /// Its castling moves, last first, which are legal as found.
+    castling: Vec<Move>,
+    captures_only: bool,
+}
+
+impl MoveGen {
+    pub fn new(game: &ChessGame) -> Self {
+        Self {
+            board: SearchBoard::without_history(game),
+            squares: 0..64,
+            pending: Vec::new(),
+            castling: Vec::new(),
+            captures_only: false,
+        }
+    }
+
+    /// Only the moves that take a piece.
+    pub fn captures(mut self) -> Self {
+        self.captures_only = true;
+        self
+    }
+
+    /// Only the moves of the piece on `square`.
+    pub fn from_square(mut self, square: Position) -> Self {
+        let index = Square::from(square).index();
+        self.squares = index..index + 1;
+        self
+    }
+
+    fn is_capture(&self, mv: &Move) -> bool {
+        self.board
+            .piece_at(&mv.to)
+            .is_some_and(|piece| piece.colour != self.board.turn())
+    }
+
+    /// Find the next piece of the side to move and queue up its moves, or
+    /// return false if there are no more pieces.
+    fn next_piece(&mut self) -> bool {
+        let turn = self.board.turn();
+        for index in self.squares.by_ref() {
+            let square = Square::from_index(index).expect("squares are on the board");
+            let from = Position::from(square);
+            let Some(piece) = self.board.piece_at(&from).filter(|p| p.colour == turn) else {
+                continue;
+            };
+            self.board.piece_moves(square, &piece, &mut self.pending);
+            self.pending.reverse();
+            if piece.kind == ChessPieceKind::King && !self.captures_only {
+                for side in [CastlingSide::Queen, CastlingSide::King] {
+                    if self.board.can_castle(&from, turn, side)
+                        && let Some(to) = self.board.castling_target(turn, side)
+                    {
+                        self.castling.push(Move { from, to });
+                    }
+                }
+            }
+            return true;
+        }
+        false
+    }
+}
+
+impl Iterator for MoveGen {
+    type Item = Move;
+
+    fn next(&mut self) -> Option<Move> {
+        loop {
+            while let Some(mv) = self.pending.pop() {
+                if self.captures_only && !self.is_capture(&mv) {
+                    continue;
+                }
+                if !self.board.leaves_king_in_check(&mv) {
+                    return Some(mv);
+                }
+            }
+            if let Some(mv) = self.castling.pop() {
+                return Some(mv);
+            }
+            if !self.next_piece() {
+                return None;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::variant::Variant;
+    use std::str::FromStr;
+
+    #[test]
+    fn test_filters() {
+        let game = ChessGame::from_fen(
+            "r3k2r/ppp2ppp/2n5/3qp3/8/2N5/PPP2PPP/R3K2R w KQkq - 0 1",
+            Variant::Standard,
+        )
+        .unwrap();
+        let uci = |moves: MoveGen| moves.map(Move::to_uci).collect::<Vec<_>>();
+        assert_eq!(uci(MoveGen::new(&game).captures()), ["c3d5"]);
+        let king = Position::from_str("e1").unwrap();
+        assert_eq!(
+            uci(MoveGen::new(&game).from_square(king)),
+            ["e1e2", "e1f1", "e1g1"]
+        );
+    }
+}
@@ -0,0 +1,309 @@
+//! An efficiently updatable neural network (NNUE) evaluation.
+//!
+//! The network has one input per (perspective, colour, piece kind, square), a
+//! single hidden layer computed once from each side's perspective, and one
+//! output. The hidden layer (the accumulator) only changes where pieces move, so
+//! rather than recomputing it for every position it is updated from the last
+//! position evaluated on the same thread, which during a search is usually only
+//! a move or two away.
+//!
+//! Networks are read from a little endian file: the magic bytes `CNUE`, the
+//! hidden layer size as a `u32`, then the `i16` input weights (feature major),
+//! `i16` hidden biases, `i16` output weights (our perspective then theirs) and
+//! finally the `i32` output bias.
+
+use super::BoardScore;
+use super::eval::Evaluator;
+use crate::core::{ChessBoard, ChessColour, ChessError, ChessPieceKind};
+use std::cell::RefCell;
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+const MAGIC: &[u8; 4] = b"CNUE";
+const FEATURES: usize = 2 * 6 * 64;
+
+/// Quantisation of the hidden layer, which is clipped to `0..=QA`.
+const QA: i32 = 255;
+/// Quantisation of the output weights.
+const QB: i32 = 64;
+/// Converts the network output into centipawns.
+const SCALE: i32 = 400;
+
+/// Beyond this many changed squares it is cheaper to rebuild the accumulator.
+const MAX_INCREMENTAL_CHANGES: usize = 8;
+
+/// Each network gets an id so the per thread accumulator cache can tell them
+/// apart.
+static NEXT_NETWORK_ID: AtomicU64 = AtomicU64::new(0);
+
+type Square = Option<(ChessPieceKind, ChessColour)>;
+
+#[derive(Debug)]
+pub struct NnueEvaluator {
+    id: u64,
+    hidden_size: usize,
+    input_weights: Vec<i16>,
+    hidden_bias: Vec<i16>,
+    output_weights: Vec<i16>,
+    output_bias: i32,
+}
+
+/// The hidden layer from white's and black's perspective, and the squares it
+/// was computed for.
+#[derive(Clone)]
+struct Accumulator {
+    network: u64,
+    squares: [Square; 64],
+    white: Vec<i16>,
+    black: Vec<i16>,
+}
+
+thread_local! {
+    static ACCUMULATOR: RefCell<Option<Accumulator>> = const { RefCell::new(None) };
+}
+
+fn kind_index(kind: ChessPieceKind) -> usize {
+    match kind {
+        ChessPieceKind::Pawn => 0,
+        ChessPieceKind::Knight => 1,
+        ChessPieceKind::Bishop => 2,
+        ChessPieceKind::Rook => 3,
+        ChessPieceKind::Queen => 4,
+        ChessPieceKind::King => 5,
+    }
+}
+
+/// The input for a piece on `square` as seen by `perspective`, which sees its
+/// own pieces first and the board from its own side.
+fn feature(
+    perspective: ChessColour,
+    kind: ChessPieceKind,
+    colour: ChessColour,
+    square: usize,
+) -> usize {
+    let side = usize::from(colour != perspective);
+    let square = match perspective {
+        ChessColour::White => square,
+        ChessColour::Black => square ^ 56,
+    };
+    side * 6 * 64 + kind_index(kind) * 64 + square
+}
+
+fn squares(board: &ChessBoard) -> [Square; 64] {
+    let mut squares = [None; 64];
+    for (pos, cell) in board.pieces() {
+        if let Some(piece) = cell.piece {
+            squares[crate::core::Square::from(pos).index()] = Some((piece.kind, piece.colour));
+        }
+    }
+    squares
+}
+
+impl NnueEvaluator {
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ChessError> {
+        let path = path.as_ref();
+        let bytes = std::fs::read(path).map_err(|source| ChessError::Io {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        Self::from_bytes(&bytes)
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, ChessError> {
+        let invalid = |msg: &str| ChessError::InvalidNetwork(msg.to_string());
+        let rest = bytes
+            .strip_prefix(MAGIC)
+            .ok_or_else(|| invalid("not an NNUE network file"))?;
+        let (size, rest) = rest
+            .split_first_chunk::<4>()
+            .ok_or_else(|| invalid("missing hidden layer size"))?;
+        let hidden_size = u32::from_le_bytes(*size) as usize;
+
+        let expected = (FEATURES * hidden_size + 3 * hidden_size) * 2 + 4;
+        if hidden_size == 0 || rest.len() != expected {
+            return Err(invalid(&format!(
+                "expected {expected} bytes of weights for a hidden layer of {hidden_size}, found {}",
+                rest.len()
+            )));
+        }
+        let mut values = rest
+            .chunks_exact(2)
+            .map(|chunk| i16::from_le_bytes([chunk[0], chunk[1]]));
+        let mut take = |n: usize| values.by_ref().take(n).collect::<Vec<i16>>();
+        let input_weights = take(FEATURES * hidden_size);
+        let hidden_bias = take(hidden_size);
+        let output_weights = take(2 * hidden_size);
+        let output_bias = i32::from_le_bytes(rest[rest.len() - 4..].try_into().unwrap());
+
+        Ok(Self {
+            id: NEXT_NETWORK_ID.fetch_add(1, Ordering::Relaxed),
+            hidden_size,
+            input_weights,
+            hidden_bias,
+            output_weights,
+            output_bias,
+        })
+    }
+
+    fn weights(&self, feature: usize) -> &[i16] {
+        &self.input_weights[feature * self.hidden_size..(feature + 1) * self.hidden_size]
+    }
+
+    fn refresh(&self, squares: [Square; 64]) -> Accumulator {
+        let mut accumulator = Accumulator {
+            network: self.id,
+            squares: [None; 64],
+            white: self.hidden_bias.clone(),
+            black: self.hidden_bias.clone(),
+        };
+        for (square, piece) in squares.iter().enumerate() {
+            if let Some((kind, colour)) = piece {
+                self.toggle(&mut accumulator, *kind, *colour, square, true);
+            }
+        }
+        accumulator.squares = squares;
+        accumulator
+    }
+
+    /// Add (or remove) the inputs for a piece in both perspectives.
+    fn toggle(
+        &self,
+        accumulator: &mut Accumulator,
+        kind: ChessPieceKind,
+        colour: ChessColour,
+        square: usize,
+        add: bool,
+    ) {
+        for (perspective, values) in [
+            (ChessColour::White, &mut accumulator.white),
+            (ChessColour::Black, &mut accumulator.black),
+        ] {
+            let weights = self.weights(feature(perspective, kind, colour, square));
+            for (value, weight) in values.iter_mut().zip(weights) {
+                if add {
+                    *value = value.wrapping_add(*weight);
+                } else {
+                    *value = value.wrapping_sub(*weight);
+                }
+            }
+        }
+    }
+
+    /// Bring `accumulator` up to date with `squares` by undoing the inputs of
+    /// the squares that changed and applying the new ones.
+    fn update(&self, accumulator: &mut Accumulator, squares: [Square; 64]) {
+        for (square, new) in squares.into_iter().enumerate() {
+            let old = accumulator.squares[square];
+            if old == new {
+                continue;
+            }
+            if let Some((kind, colour)) = old {
+                self.toggle(accumulator, kind, colour, square, false);
+            }
+            if let Some((kind, colour)) = new {
+                self.toggle(accumulator, kind, colour, square, true);
+            }
+        }
+        accumulator.squares = squares;
+    }
+
+    fn output(&self, accumulator: &Accumulator, colour: ChessColour) -> BoardScore {
+        let (ours, theirs) = match colour {
+            ChessColour::White => (&accumulator.white, &accumulator.black),
+            ChessColour::Black => (&accumulator.black, &accumulator.white),
+        };
+        let (our_weights, their_weights) = self.output_weights.split_at(self.hidden_size);
+        let layer = |values: &[i16], weights: &[i16]| -> i32 {
+            values
+                .iter()
+                .zip(weights)
+                .map(|(v, w)| i32::from(*v).clamp(0, QA) * i32::from(*w))
+                .sum()
+        };
+        let output = layer(ours, our_weights) + layer(theirs, their_weights) + self.output_bias;
+        output * SCALE / (QA * QB)
+    }
+}
+
+impl Evaluator for NnueEvaluator {
+    fn evaluate(&self, board: &ChessBoard, colour: ChessColour) -> BoardScore {
+        let squares = squares(board);
+        ACCUMULATOR.with_borrow_mut(|cached| {
+            let accumulator = match cached {
+                Some(accumulator)
+                    if accumulator.network == self.id
+                        && accumulator
+                            .squares
+                            .iter()
+                            .zip(&squares)
+                            .filter(|(old, new)| old != new)
+                            .count()
+                            <= MAX_INCREMENTAL_CHANGES =>
+                {
+                    self.update(accumulator, squares);
+                    accumulator
+                }
+                _ => cached.insert(self.refresh(squares)),
+            };
+            self.output(accumulator, colour)
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::core::Move;
+    use crate::game::ChessGame;
+
+    /// A network with one hidden neuron per perspective that counts material.
+    fn material_network() -> Vec<u8> {
+        let values = [1, 3, 3, 5, 9, 0];
+        let mut bytes = MAGIC.to_vec();
+        bytes.extend(1u32.to_le_bytes());
+        for feature in 0..FEATURES {
+            // our pieces raise the neuron, theirs have no effect on it
+            let kind = feature / 64 % 6;
+            let weight: i16 = if feature < 6 * 64 { values[kind] } else { 0 };
+            bytes.extend(weight.to_le_bytes());
+        }
+        bytes.extend(0i16.to_le_bytes());
+        bytes.extend((QB as i16).to_le_bytes());
+        bytes.extend((-QB as i16).to_le_bytes());
+        bytes.extend(0i32.to_le_bytes());
+        bytes
+    }
+
+    #[test]
+    fn test_rejects_bad_files() {
+        assert!(NnueEvaluator::from_bytes(b"nope").is_err());
+        let mut bytes = material_network();
+        bytes.pop();
+        assert!(NnueEvaluator::from_bytes(&bytes).is_err());
+    }
+
+    #[test]
+    fn test_incremental_matches_refresh() {
+        let network = NnueEvaluator::from_bytes(&material_network()).unwrap();
+        let mut game = ChessGame::default();
+        let start = network.evaluate(game.get_board(), ChessColour::White);
+        assert_eq!(start, 0);
+
+        for mv in ["e2e4", "d7d5", "e4d5", "d8d5"] {
+            game.make_move(&Move::from_uci(mv).unwrap()).unwrap();
+            let incremental = network.evaluate(game.get_board(), ChessColour::White);
+            let refreshed = network.output(
+                &network.refresh(squares(game.get_board())),
+                ChessColour::White,
+            );
+            assert_eq!(incremental, refreshed);
+            if mv == "e4d5" {
+                // white is a pawn up
+                assert!(incremental > 0);
+            }
+        }
+        // each side has lost a pawn
+        assert_eq!(network.evaluate(game.get_board(), ChessColour::White), 0);
+        assert_eq!(network.evaluate(game.get_board(), ChessColour::Black), 0);
+    }
+}
@@ -0,0 +1,119 @@
+use crate::core::{ChessBoard, Move, Position, Square};
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+
+/// Killer moves remembered for each ply.
+const KILLERS_PER_PLY: usize = 2;
+
+/// Once a history score passes this, every score is halved so that recent
+/// cutoffs keep counting for more than old ones.
+const HISTORY_LIMIT: u32 = 1 << 20;
+
+// Sort keys for each kind of move. Quiet moves are ordered by their history
+// score, which stays below the killers thanks to HISTORY_LIMIT.
+const TT_MOVE_ORDER: u64 = u64::MAX;
+const CAPTURE_ORDER: u64 = 1 << 40;
+const KILLER_ORDER: u64 = 1 << 30;
+
+/// Most valuable victim first, then least valuable attacker first, or `None`
+/// if `mv` doesn't capture anything.
+pub(super) fn capture_order(board: &ChessBoard, mv: &Move) -> Option<isize> {
+    let piece_value = |pos: &Position| board.get(Square::from(*pos)).map(|p| p.kind.value());
+    let victim = piece_value(&mv.to)?;
+    let attacker = piece_value(&mv.from).unwrap_or(0);
+    Some(victim * 1000 - attacker)
+}
+
+fn square_index(pos: &Position) -> usize {
+    Square::from(*pos).index()
+}
+
+/// What the search has learnt about which quiet moves tend to be good, used to
+/// try them earlier so more of the tree is pruned.
+///
+/// Killer moves are quiet moves that caused a cutoff at the same ply in a
+/// sibling position, and are likely to do so again. The history table counts
+/// cutoffs by (from, to) square across the whole search.
+pub struct MoveOrdering {
+    killers: Vec<[Option<Move>; KILLERS_PER_PLY]>,
+    history: Box<[[u32; 64]; 64]>,
+}
+
+impl Default for MoveOrdering {
+    fn default() -> Self {
+        Self {
+            killers: Vec::new(),
+            history: Box::new([[0; 64]; 64]),
+        }
+    }
+}
+
+impl MoveOrdering {
+    /// Sort `moves` so the most promising come first: the move from the
+    /// transposition table, captures, killer moves, then quiet moves by history.
+    pub fn order(&self, board: &ChessBoard, moves: &mut [Move], ply: usize, tt_move: Option<Move>) {
+        let killers = self.killers.get(ply);
+        moves.sort_by_key(|mv| {
+            let key = if Some(*mv) == tt_move {
+                TT_MOVE_ORDER
+            } else if let Some(order) = capture_order(board, mv) {
+                CAPTURE_ORDER + order as u64
+            } else if let Some(slot) =
+                killers.and_then(|killers| killers.iter().position(|k| *k == Some(*mv)))
+            {
+                KILLER_ORDER - slot as u64
+            } else {
+                u64::from(self.history[square_index(&mv.from)][square_index(&mv.to)])
+            };
+            ::core::cmp::Reverse(key)
+        });
+    }
+
+    /// Remember that the quiet move `mv` caused a beta cutoff at `ply`, with
+    /// `depth` half moves left to search below it.
+    pub fn record_cutoff(&mut self, mv: Move, ply: usize, depth: usize) {
+        if self.killers.len() <= ply {
+            self.killers.resize(ply + 1, [None; KILLERS_PER_PLY]);
+        }
+        let killers = &mut self.killers[ply];
+        if killers[0] != Some(mv) {
+            killers.rotate_right(1);
+            killers[0] = Some(mv);
+        }
+
+        let entry = &mut self.history[square_index(&mv.from)][square_index(&mv.to)];
+        // deep cutoffs save more work, so they count for more
+        *entry += (depth * depth) as u32;
+        if *entry > HISTORY_LIMIT {
+            self.history
+                .iter_mut()
+                .flatten()
+                .for_each(|score| *score /= 2);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn uci(moves: &[&str]) -> Vec<Move> {
+        moves.iter().map(|mv| Move::from_uci(mv).unwrap()).collect()
+    }
+
+    #[test]
+    fn test_ordering() {
+        let board = ChessBoard::default();
+        let mut ordering = MoveOrdering::default();
+        ordering.record_cutoff(Move::from_uci("g1f3").unwrap(), 3, 2);
+        ordering.record_cutoff(Move::from_uci("b1c3").unwrap(), 1, 4);
+
+        let mut moves = uci(&["a2a3", "b1c3", "g1f3", "e2e4"]);
+        ordering.order(&board, &mut moves, 3, Some(Move::from_uci("e2e4").unwrap()));
+        assert_eq!(moves, uci(&["e2e4", "g1f3", "b1c3", "a2a3"]));
+
+        // at another ply there are no killers, so only the history counts
+        ordering.order(&board, &mut moves, 0, None);
+        assert_eq!(moves, uci(&["b1c3", "g1f3", "e2e4", "a2a3"]));
+    }
+}
@@ -0,0 +1,197 @@
+use super::BoardScore;
+use crate::core::{ChessBoard, ChessColour, ChessPieceKind};
+use crate::zobrist;
+use alloc::vec;
+use alloc::vec::Vec;
+#[cfg(feature = "std")]
+use std::cell::RefCell;
+
+const DOUBLED_PAWN: BoardScore = 15;
+const ISOLATED_PAWN: BoardScore = 15;
+const BACKWARD_PAWN: BoardScore = 10;
+/// Bonus for a passed pawn by how far it has advanced, from its own back row.
+const PASSED_PAWN: [BoardScore; 8] = [0, 10, 15, 25, 40, 60, 90, 0];
+
+/// The number of cached pawn structures.
+const PAWN_TABLE_SIZE: usize = 4096;
+
+/// The pawn structure score for both sides, in centipawns.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Copy, Default)]
+pub struct PawnScore {
+    pub white: BoardScore,
+    pub black: BoardScore,
+}
+
+impl PawnScore {
+    pub fn for_colour(&self, colour: ChessColour) -> BoardScore {
+        match colour {
+            ChessColour::White => self.white,
+            ChessColour::Black => self.black,
+        }
+    }
+}
+
+/// A small cache of pawn structure scores keyed by the pawn-only Zobrist hash.
+/// Pawns move rarely compared to other pieces, so most lookups during a search
+/// hit positions whose pawn structure has already been analysed.
+pub struct PawnTable {
+    entries: Vec<Option<(u64, PawnScore)>>,
+}
+
+impl Default for PawnTable {
+    fn default() -> Self {
+        Self {
+            entries: vec![None; PAWN_TABLE_SIZE],
+        }
+    }
+}
+
+impl PawnTable {
+    pub fn score(&mut self, board: &ChessBoard) -> PawnScore {
+        let key = zobrist::pawn_hash(board);
+        let index = (key % self.entries.len() as u64) as usize;
+        match self.entries[index] {
+            Some((entry_key, score)) if entry_key == key => score,
+            _ => {
+                let score = evaluate_pawns(board);
+                self.entries[index] = Some((key, score));
+                score
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+thread_local! {
+    static PAWN_TABLE: RefCell<PawnTable> = RefCell::new(PawnTable::default());
+}
+
+/// The pawn structure score of `colour`, using this thread's pawn hash table.
+#[cfg(feature = "std")]
+pub fn pawn_structure(board: &ChessBoard, colour: ChessColour) -> BoardScore {
+    PAWN_TABLE.with(|table| table.borrow_mut().score(board).for_colour(colour))
+}
+
+/// The pawn structure score of `colour`. Without the `std` feature there are
+/// no thread locals to keep a pawn hash table in, so it is worked out afresh.
+#[cfg(not(feature = "std"))]
+pub fn pawn_structure(board: &ChessBoard, colour: ChessColour) -> BoardScore {
+    evaluate_pawns(board).for_colour(colour)
+}
+
+/// Analyse doubled, isolated, backward and passed pawns for both sides.
+pub fn evaluate_pawns(board: &ChessBoard) -> PawnScore {
+    // the rows of each side's pawns, indexed by column
+    let mut pawns: [[Vec<isize>; 8]; 2] = Default::default();
+    for (pos, cell) in board.pieces() {
+        if let Some(piece) = cell.piece.filter(|p| p.kind == ChessPieceKind::Pawn) {
+            let side = (piece.colour == ChessColour::Black) as usize;
+            pawns[side][usize::from(pos.column)].push(usize::from(pos.row) as isize);
+        }
+    }
+
+    let mut score = PawnScore::default();
+    for colour in [ChessColour::White, ChessColour::Black] {
+        let side = (colour == ChessColour::Black) as usize;
+        let ours = &pawns[side];
+        let theirs = &pawns[1 - side];
+        let direction = colour.direction();
+        // whether `row` is further up the board than `than` from our point of view
+        let ahead = |row: isize, than: isize| (row - than) * direction > 0;
+        let neighbours = |column: usize| {
+            [column.checked_sub(1), Some(column + 1).filter(|c| *c < 8)]
+                .into_iter()
+                .flatten()
+        };
+
+        let mut total = 0;
+        for (column, rows) in ours.iter().enumerate() {
+            if rows.len() > 1 {
+                total -= DOUBLED_PAWN * (rows.len() as BoardScore - 1);
+            }
+            for &row in rows {
+                let isolated = neighbours(column).all(|c| ours[c].is_empty());
+                if isolated {
+                    total -= ISOLATED_PAWN;
+                }
+
+                let passed = rows.iter().all(|r| !ahead(*r, row))
+                    && neighbours(column)
+                        .chain([column])
+                        .all(|c| theirs[c].iter().all(|r| !ahead(*r, row)));
+                if passed {
+                    let advanced = match colour {
+                        ChessColour::White => row,
+                        ChessColour::Black => 7 - row,
+                    };
+                    total += PASSED_PAWN[advanced as usize];
+                }
+
+                // backward: every neighbouring pawn has gone past it, and the
+                // square in front is covered by an enemy pawn
+                let stop = row + direction;
+                let supported = neighbours(column).any(|c| ours[c].iter().any(|r| !ahead(*r, row)));
+                let stop_attacked =
+                    neighbours(column).any(|c| theirs[c].contains(&(stop + direction)));
+                if !isolated && !passed && !supported && stop_attacked {
+                    total -= BACKWARD_PAWN;
+                }
+            }
+        }
+        match colour {
+            ChessColour::White => score.white = total,
+            ChessColour::Black => score.black = total,
+        }
+    }
+    score
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::game::ChessGame;
+    use crate::variant::Variant;
+
+    fn score(fen: &str) -> PawnScore {
+        evaluate_pawns(
+            ChessGame::from_fen(fen, Variant::Standard)
+                .unwrap()
+                .get_board(),
+        )
+    }
+
+    #[test]
+    fn test_starting_structure_is_neutral() {
+        assert_eq!(
+            score("4k3/pppppppp/8/8/8/8/PPPPPPPP/4K3 w - - 0 1"),
+            PawnScore::default()
+        );
+    }
+
+    #[test]
+    fn test_doubled_isolated_and_passed() {
+        // white has doubled, isolated c pawns, the front one passed; black has
+        // an isolated passed pawn on a3
+        let s = score("4k3/8/8/8/2P5/p1P5/8/4K3 w - - 0 1");
+        assert_eq!(s.white, -DOUBLED_PAWN - 2 * ISOLATED_PAWN + PASSED_PAWN[3]);
+        assert_eq!(s.black, -ISOLATED_PAWN + PASSED_PAWN[5]);
+    }
+
+    #[test]
+    fn test_backward_pawn() {
+        // white's d3 pawn has been left behind by c4, and d4 is covered by the
+        // black pawns on c5 and e5
+        let s = score("4k3/8/8/2p1p3/2P5/3P4/8/4K3 w - - 0 1");
+        assert_eq!(s.white, -BACKWARD_PAWN);
+        assert_eq!(s.black, -2 * ISOLATED_PAWN);
+    }
+
+    #[test]
+    fn test_pawn_table_caches_by_structure() {
+        let mut table = PawnTable::default();
+        let game = ChessGame::default();
+        let first = table.score(game.get_board());
+        assert_eq!(table.score(game.get_board()), first);
+        assert!(table.entries.iter().flatten().count() == 1);
+    }
+}
@@ -0,0 +1,59 @@
+//! Counting the positions reachable in a number of moves, to check move
+//! generation against the known totals.
+
+use super::legal_moves;
+use crate::core::Move;
+use crate::game::ChessGame;
+use alloc::vec::Vec;
+
+/// How many move sequences of `depth` half moves can be played from `game`.
+pub fn perft(game: &ChessGame, depth: u32) -> u64 {
+    if depth == 0 {
+        return 1;
+    }
+    divide(game, depth).iter().map(|(_, count)| count).sum()
+}
+
+/// [`perft`] split by the first move, so a wrong total can be narrowed down to
+/// the moves that lead to it.
+pub fn divide(game: &ChessGame, depth: u32) -> Vec<(Move, u64)> {
+    if depth == 0 || game.result().is_some() {
+        return Vec::new();
+    }
+    legal_moves(game)
+        .into_iter()
+        .map(|mv| {
+            let mut next = game.clone();
+            let count = match next.make_move(&mv) {
+                Ok(_) if depth == 1 => 1,
+                Ok(_) => perft(&next, depth - 1),
+                Err(_) => 0,
+            };
+            (mv, count)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::variant::Variant;
+
+    #[test]
+    fn test_perft() {
+        let game = ChessGame::default();
+        assert_eq!(perft(&game, 0), 1);
+        assert_eq!(perft(&game, 1), 20);
+        assert_eq!(perft(&game, 2), 400);
+        assert_eq!(perft(&game, 3), 8902);
+        let kiwipete = ChessGame::from_fen(
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            Variant::Standard,
+        )
+        .unwrap();
+        assert_eq!(perft(&kiwipete, 1), 48);
+        let divided = divide(&game, 2);
+        assert_eq!(divided.len(), 20);
+        assert!(divided.iter().all(|(_, count)| *count == 20));
+    }
+}
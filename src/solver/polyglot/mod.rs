@@ -0,0 +1,274 @@
+//! Reader for opening books in the Polyglot `.bin` format, the de facto standard
+//! for book files shared between engines. A book is a list of 16 byte entries
+//! sorted by the Polyglot hash of the position they apply to.
+
+mod random;
+
+use super::book::weighted_choice;
+use super::legal_moves;
+use crate::core::{
+    CastlingSide, ChessBoard, ChessColour, ChessError, ChessPieceKind, Column, Move, Position,
+    Square,
+};
+use crate::game::ChessGame;
+use crate::variant::Variant;
+use random::RANDOM64;
+use std::path::Path;
+
+const CASTLING_OFFSET: usize = 768;
+const EN_PASSANT_OFFSET: usize = 772;
+const WHITE_TO_MOVE_OFFSET: usize = 780;
+
+const ENTRY_SIZE: usize = 16;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Copy)]
+struct PolyglotEntry {
+    key: u64,
+    raw_move: u16,
+    weight: u16,
+}
+
+/// An opening book loaded from a Polyglot `.bin` file.
+#[derive(Debug, Clone, Default)]
+pub struct PolyglotBook {
+    entries: Vec<PolyglotEntry>,
+}
+
+impl PolyglotBook {
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, ChessError> {
+        let path = path.as_ref();
+        let bytes = std::fs::read(path).map_err(|source| ChessError::Io {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        Self::from_bytes(&bytes)
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, ChessError> {
+        if !bytes.len().is_multiple_of(ENTRY_SIZE) {
+            return Err(ChessError::InvalidBook(format!(
+                "book size {} is not a multiple of {ENTRY_SIZE} bytes",
+                bytes.len()
+            )));
+        }
+        let mut entries: Vec<PolyglotEntry> = bytes
+            .chunks_exact(ENTRY_SIZE)
+            .map(|chunk| PolyglotEntry {
+                key: u64::from_be_bytes(chunk[0..8].try_into().unwrap()),
+                raw_move: u16::from_be_bytes(chunk[8..10].try_into().unwrap()),
+                weight: u16::from_be_bytes(chunk[10..12].try_into().unwrap()),
+                // the remaining four bytes are for learning, which we don't use
+            })
+            .collect();
+        // books are written sorted, but lookups rely on it so don't trust the file
+        entries.sort_by_key(|entry| entry.key);
+        Ok(Self { entries })
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// The book moves for the current position and their weights. Entries that
+    /// don't decode to a legal move, e.g. after a hash collision, are skipped.
+    pub fn book_moves(&self, game: &ChessGame) -> Vec<(Move, u32)> {
+        let key = polyglot_hash(game);
+        let start = self.entries.partition_point(|entry| entry.key < key);
+        let legal = legal_moves(game);
+        self.entries[start..]
+            .iter()
+            .take_while(|entry| entry.key == key)
+            .filter_map(|entry| {
+                decode_move(game, entry.raw_move)
+                    .filter(|mv| legal.contains(mv))
+                    .map(|mv| (mv, u32::from(entry.weight)))
+            })
+            .collect()
+    }
+
+    /// Pick a book move for the current position at random, weighted by the
+    /// entries' weights, or `None` when out of book.
+    pub fn book_move(&self, game: &ChessGame) -> Option<Move> {
+        weighted_choice(&self.book_moves(game))
+    }
+}
+
+/// Turn a Polyglot move into a [`Move`] for this game. Castling is stored as the
+/// king taking its own rook, which is already how Chess960 castling is played
+/// but has to be mapped to the king's destination in standard chess. Promotions
+/// are not supported and give `None`.
+fn decode_move(game: &ChessGame, raw_move: u16) -> Option<Move> {
+    if raw_move >> 12 & 0x7 != 0 {
+        return None;
+    }
+    let square = |bits: u16| Square::from_index(usize::from(bits & 0x3f)).map(Position::from);
+    let from = square(raw_move >> 6)?;
+    let mut to = square(raw_move)?;
+
+    let board = game.get_board();
+    let piece_at = |pos: &Position| board.get(Square::from(*pos));
+    if game.variant() == Variant::Standard
+        && let (Some(king), Some(rook)) = (piece_at(&from), piece_at(&to))
+        && king.kind == ChessPieceKind::King
+        && rook.kind == ChessPieceKind::Rook
+        && king.colour == rook.colour
+    {
+        let side = if usize::from(to.column) > usize::from(from.column) {
+            CastlingSide::King
+        } else {
+            CastlingSide::Queen
+        };
+        to.column = side.king_destination();
+    }
+    Some(Move { from, to })
+}
+
+fn polyglot_kind(kind: ChessPieceKind) -> usize {
+    match kind {
+        ChessPieceKind::Pawn => 0,
+        ChessPieceKind::Knight => 1,
+        ChessPieceKind::Bishop => 2,
+        ChessPieceKind::Rook => 3,
+        ChessPieceKind::Queen => 4,
+        ChessPieceKind::King => 5,
+    }
+}
+
+/// The column of a pawn that can be taken en passant. Polyglot only counts it
+/// when a pawn of the side to move stands ready to make the capture.
+fn en_passant_column(game: &ChessGame) -> Option<Column> {
+    let board = game.get_board();
+    let last = game.played_moves().last()?;
+    let (from, to) = (Square::from(last.from), Square::from(last.to));
+    let pawn = board.get(to)?;
+    if pawn.kind != ChessPieceKind::Pawn || from.rank().abs_diff(to.rank()) != 2 {
+        return None;
+    }
+    let capturer_beside = [-1, 1].into_iter().any(|offset| {
+        to.offset(0, offset)
+            .and_then(|square| board.get(square))
+            .is_some_and(|p| p.kind == ChessPieceKind::Pawn && p.colour == board.turn)
+    });
+    capturer_beside.then_some(last.to.column)
+}
+
+fn castling_hash(board: &ChessBoard) -> u64 {
+    [
+        (ChessColour::White, CastlingSide::King),
+        (ChessColour::White, CastlingSide::Queen),
+        (ChessColour::Black, CastlingSide::King),
+        (ChessColour::Black, CastlingSide::Queen),
+    ]
+    .iter()
+    .enumerate()
+    .filter(|(_, (colour, side))| board.castling.get(*colour, *side).is_some())
+    .fold(0, |hash, (i, _)| hash ^ RANDOM64[CASTLING_OFFSET + i])
+}
+
+/// The Polyglot hash of the current position. This is a Zobrist hash like the
+/// one the game uses to spot repetitions, but with the fixed keys from the
+/// Polyglot format so that it matches the keys stored in book files.
+pub fn polyglot_hash(game: &ChessGame) -> u64 {
+    let board = game.get_board();
+    let mut hash = 0;
+    for (pos, cell) in board.pieces() {
+        if let Some(piece) = &cell.piece {
+            let colour = usize::from(piece.colour == ChessColour::White);
+            let kind = polyglot_kind(piece.kind) * 2 + colour;
+            hash ^= RANDOM64[64 * kind + Square::from(pos).index()];
+        }
+    }
+    hash ^= castling_hash(board);
+    if let Some(column) = en_passant_column(game) {
+        hash ^= RANDOM64[EN_PASSANT_OFFSET + usize::from(column)];
+    }
+    if board.turn == ChessColour::White {
+        hash ^= RANDOM64[WHITE_TO_MOVE_OFFSET];
+    }
+    hash
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn play(moves: &[&str]) -> ChessGame {
+        let mut game = ChessGame::default();
+        for mv in moves {
+            game.make_move(&Move::from_uci(mv).unwrap()).unwrap();
+        }
+        game
+    }
+
+    #[test]
+    fn test_polyglot_hash_reference_positions() {
+        // the example positions from the Polyglot format description
+        assert_eq!(polyglot_hash(&play(&[])), 0x463b96181691fc9c);
+        assert_eq!(polyglot_hash(&play(&["e2e4"])), 0x823c9b50fd114196);
+        assert_eq!(polyglot_hash(&play(&["e2e4", "d7d5"])), 0x0756b94461c50fb0);
+        assert_eq!(
+            polyglot_hash(&play(&["e2e4", "d7d5", "e4e5"])),
+            0x662fafb965db29d4
+        );
+        assert_eq!(
+            polyglot_hash(&play(&["e2e4", "d7d5", "e4e5", "f7f5"])),
+            0x22a48b5a8e47ff78
+        );
+        assert_eq!(
+            polyglot_hash(&play(&["e2e4", "d7d5", "e4e5", "f7f5", "e1e2"])),
+            0x652a607ca3f242c1
+        );
+        assert_eq!(
+            polyglot_hash(&play(&["e2e4", "d7d5", "e4e5", "f7f5", "e1e2", "e8f7"])),
+            0x00fdd303c946bdd9
+        );
+    }
+
+    fn entry_bytes(key: u64, uci: &str, weight: u16) -> Vec<u8> {
+        let mv = Move::from_uci(uci).unwrap();
+        let square = |pos: Position| Square::from(pos).index() as u16;
+        let raw_move = square(mv.from) << 6 | square(mv.to);
+        let mut bytes = key.to_be_bytes().to_vec();
+        bytes.extend(raw_move.to_be_bytes());
+        bytes.extend(weight.to_be_bytes());
+        bytes.extend(0u32.to_be_bytes());
+        bytes
+    }
+
+    #[test]
+    fn test_reads_book_entries() {
+        let start = polyglot_hash(&ChessGame::default());
+        let mut bytes = entry_bytes(start, "e2e4", 3);
+        bytes.extend(entry_bytes(1, "a2a3", 1));
+        bytes.extend(entry_bytes(start, "d2d4", 1));
+        // not a legal move, so it must be skipped
+        bytes.extend(entry_bytes(start, "e2e5", 1));
+        let book = PolyglotBook::from_bytes(&bytes).unwrap();
+        assert_eq!(book.len(), 4);
+
+        let moves = book.book_moves(&ChessGame::default());
+        assert_eq!(
+            moves,
+            vec![
+                (Move::from_uci("e2e4").unwrap(), 3),
+                (Move::from_uci("d2d4").unwrap(), 1)
+            ]
+        );
+        assert!(book.book_move(&play(&["h2h4"])).is_none());
+        assert!(PolyglotBook::from_bytes(&bytes[1..]).is_err());
+    }
+
+    #[test]
+    fn test_castling_is_king_takes_rook() {
+        let game = play(&["e2e4", "e7e5", "g1f3", "b8c6", "f1c4", "f8c5"]);
+        let raw_move = (4 << 6) | 7; // e1h1
+        assert_eq!(
+            decode_move(&game, raw_move),
+            Some(Move::from_uci("e1g1").unwrap())
+        );
+    }
+}
@@ -0,0 +1,117 @@
+use super::tt::TranspositionTable;
+use super::{SearchOptions, SearchResult, solve_next_move_with_options};
+use crate::core::{ChessError, Move};
+use crate::game::ChessGame;
+use crate::zobrist;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+/// How often to check whether a search that got a ponder hit has finished.
+const POLL_INTERVAL: Duration = Duration::from_millis(5);
+
+type PonderOutcome = (Result<SearchResult, ChessError>, TranspositionTable);
+
+/// A search of the position after the opponent's expected reply, running on a
+/// background thread while the opponent thinks.
+///
+/// Once the opponent has moved, [`Ponderer::finish`] either keeps the search
+/// going if the expected move was played (a ponder hit), or stops it so the
+/// real position can be searched instead.
+pub struct Ponderer {
+    expected_key: u64,
+    max_time: Option<Duration>,
+    stop: Arc<AtomicBool>,
+    handle: Option<JoinHandle<PonderOutcome>>,
+}
+
+impl Ponderer {
+    /// Start pondering on `game` after `expected` is played, searching with
+    /// `options` but without a time limit until the opponent has moved.
+    pub fn start(
+        game: &ChessGame,
+        expected: Move,
+        options: &SearchOptions,
+    ) -> Result<Self, ChessError> {
+        let mut game = game.clone();
+        game.make_move(&expected)?;
+        let stop = Arc::new(AtomicBool::new(false));
+        let ponder_options = SearchOptions {
+            max_time: None,
+            stop: Some(stop.clone()),
+            ..options.clone()
+        };
+        let expected_key = zobrist::hash(game.get_board());
+        let handle = std::thread::spawn(move || {
+            let mut tt = TranspositionTable::default();
+            let result = solve_next_move_with_options(&game, &ponder_options, &mut tt);
+            (result, tt)
+        });
+        Ok(Self {
+            expected_key,
+            max_time: options.max_time,
+            stop,
+            handle: Some(handle),
+        })
+    }
+
+    /// The opponent has moved, leaving `game`. On a ponder hit, let the search
+    /// run on for the normal search time, and return its result along with its
+    /// transposition table, which the caller should keep using. On a miss the
+    /// search is abandoned and `None` is returned.
+    pub fn finish(mut self, game: &ChessGame) -> Option<PonderOutcome> {
+        let handle = self.handle.take()?;
+        if zobrist::hash(game.get_board()) != self.expected_key {
+            self.stop.store(true, Ordering::Relaxed);
+            return None;
+        }
+        if let Some(max_time) = self.max_time {
+            let deadline = Instant::now() + max_time;
+            while !handle.is_finished() && Instant::now() < deadline {
+                std::thread::sleep(POLL_INTERVAL);
+            }
+        }
+        self.stop.store(true, Ordering::Relaxed);
+        Some(handle.join().expect("ponder thread panicked"))
+    }
+}
+
+impl Drop for Ponderer {
+    fn drop(&mut self) {
+        // don't leave a search running after the game has moved on
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::solver::book::OpeningBook;
+
+    fn options() -> SearchOptions {
+        SearchOptions {
+            max_time: Some(Duration::from_millis(50)),
+            book: OpeningBook::Disabled,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_ponder_hit() {
+        let mut game = ChessGame::default();
+        let expected = Move::from_uci("e2e4").unwrap();
+        let ponderer = Ponderer::start(&game, expected, &options()).unwrap();
+        game.make_move(&expected).unwrap();
+        let (result, _) = ponderer.finish(&game).unwrap();
+        assert!(result.unwrap().depth > 0);
+    }
+
+    #[test]
+    fn test_ponder_miss() {
+        let mut game = ChessGame::default();
+        let ponderer = Ponderer::start(&game, Move::from_uci("e2e4").unwrap(), &options()).unwrap();
+        game.make_move(&Move::from_uci("d2d4").unwrap()).unwrap();
+        assert!(ponderer.finish(&game).is_none());
+    }
+}
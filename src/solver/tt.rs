@@ -0,0 +1,121 @@
+use super::BoardScore;
+use crate::core::Move;
+use alloc::vec;
+use alloc::vec::Vec;
+
+/// How a stored score relates to the true value of the position, which depends
+/// on whether the search that produced it was cut off.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Copy)]
+pub enum Bound {
+    /// The score is the exact value of the position.
+    Exact,
+    /// The true value is at least the score (the search failed high).
+    Lower,
+    /// The true value is at most the score (the search failed low).
+    Upper,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Copy)]
+pub struct TtEntry {
+    pub key: u64,
+    /// The remaining search depth the score was computed with.
+    pub depth: u8,
+    /// The score from the point of view of the side to move in the position.
+    pub score: BoardScore,
+    pub bound: Bound,
+    pub best_move: Option<Move>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Copy, Default)]
+pub enum ReplacementPolicy {
+    /// Always overwrite the existing entry.
+    Always,
+    /// Keep the existing entry for a different position if it was searched deeper.
+    #[default]
+    DepthPreferred,
+}
+
+pub const DEFAULT_TABLE_SIZE_MB: usize = 16;
+
+/// A fixed size hash table of previously searched positions, keyed by Zobrist
+/// hash, so transpositions do not have to be searched again.
+#[derive(Clone)]
+pub struct TranspositionTable {
+    entries: Vec<Option<TtEntry>>,
+    policy: ReplacementPolicy,
+}
+
+impl Default for TranspositionTable {
+    fn default() -> Self {
+        Self::with_size_mb(DEFAULT_TABLE_SIZE_MB, ReplacementPolicy::default())
+    }
+}
+
+impl TranspositionTable {
+    pub fn with_size_mb(size_mb: usize, policy: ReplacementPolicy) -> Self {
+        let capacity = (size_mb * 1024 * 1024 / size_of::<Option<TtEntry>>()).max(1);
+        Self {
+            entries: vec![None; capacity],
+            policy,
+        }
+    }
+
+    fn index(&self, key: u64) -> usize {
+        (key % self.entries.len() as u64) as usize
+    }
+
+    pub fn probe(&self, key: u64) -> Option<&TtEntry> {
+        self.entries[self.index(key)]
+            .as_ref()
+            .filter(|entry| entry.key == key)
+    }
+
+    pub fn store(&mut self, entry: TtEntry) {
+        let index = self.index(entry.key);
+        let slot = &mut self.entries[index];
+        let replace = match (self.policy, slot.as_ref()) {
+            (ReplacementPolicy::Always, _) | (_, None) => true,
+            (ReplacementPolicy::DepthPreferred, Some(existing)) => {
+                existing.key == entry.key || entry.depth >= existing.depth
+            }
+        };
+        if replace {
+            *slot = Some(entry);
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.fill(None);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn entry(key: u64, depth: u8) -> TtEntry {
+        TtEntry {
+            key,
+            depth,
+            score: 0,
+            bound: Bound::Exact,
+            best_move: None,
+        }
+    }
+
+    #[test]
+    fn test_replacement_policies() {
+        let mut table = TranspositionTable::with_size_mb(1, ReplacementPolicy::DepthPreferred);
+        let len = table.entries.len() as u64;
+        table.store(entry(1, 5));
+        table.store(entry(1 + len, 2));
+        assert_eq!(table.probe(1).map(|e| e.depth), Some(5));
+        assert!(table.probe(1 + len).is_none());
+
+        let mut table = TranspositionTable::with_size_mb(1, ReplacementPolicy::Always);
+        table.store(entry(1, 5));
+        table.store(entry(1 + len, 2));
+        assert!(table.probe(1).is_none());
+        assert_eq!(table.probe(1 + len).map(|e| e.depth), Some(2));
+    }
+}
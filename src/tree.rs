@@ -0,0 +1,294 @@
+//! Every line tried in a game: the moves played, the variations branching off
+//! them and the comments, NAGs and evaluations annotating them, as analysis
+//! explores them and PGN writes them.
+
+use crate::core::Move;
+use crate::solver::Score;
+use crate::solver::eval::PAWN_VALUE;
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use serde::{Deserialize, Serialize};
+
+/// The symbols of NAGs 1 to 6, which can be written after a move in place
+/// of `$1` to `$6`.
+const NAG_SYMBOLS: [&str; 6] = ["!", "?", "!!", "??", "!?", "?!"];
+
+/// The moves of a game as a tree, with the main line kept as a line rather
+/// than a chain of nodes, so that only variations nest.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct GameTree {
+    /// A comment on the starting position.
+    pub comment: Option<String>,
+    pub main_line: Vec<MoveNode>,
+}
+
+/// A move in a [`GameTree`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MoveNode {
+    pub mv: Move,
+    /// A comment on the position after the move.
+    pub comment: Option<String>,
+    /// Numeric annotation glyphs judging the move, e.g. 1 for `!` and 4 for
+    /// `??`.
+    #[serde(default)]
+    pub nags: Vec<u8>,
+    /// How good the position after the move is for White, as PGN's
+    /// `[%eval]` gives it.
+    #[serde(default)]
+    pub evaluation: Option<Score>,
+    /// The lines played instead of this move, each starting with its
+    /// alternative.
+    pub variations: Vec<Vec<MoveNode>>,
+}
+
+impl MoveNode {
+    pub fn new(mv: Move) -> Self {
+        Self {
+            mv,
+            comment: None,
+            nags: Vec::new(),
+            evaluation: None,
+            variations: Vec::new(),
+        }
+    }
+
+    /// The comment as PGN writes it, with the evaluation first as an
+    /// `[%eval]` command.
+    pub fn pgn_comment(&self) -> Option<String> {
+        let evaluation = self
+            .evaluation
+            .map(|score| format!("[%eval {}]", format_evaluation(score)));
+        let comment: Vec<String> = evaluation.into_iter().chain(self.comment.clone()).collect();
+        (!comment.is_empty()).then(|| comment.join(" "))
+    }
+}
+
+/// The symbol NAG `nag` is written as, e.g. `!?` for 5, if it has one.
+pub fn nag_symbol(nag: u8) -> Option<&'static str> {
+    NAG_SYMBOLS.get(usize::from(nag).checked_sub(1)?).copied()
+}
+
+/// The NAG written as `symbol`, e.g. 4 for `??`.
+pub fn nag_from_symbol(symbol: &str) -> Option<u8> {
+    let index = NAG_SYMBOLS.iter().position(|nag| *nag == symbol)?;
+    Some(index as u8 + 1)
+}
+
+/// `score` as an `[%eval]` command gives it: in pawns, e.g. `0.17`, or as
+/// `#` and the moves to mate, e.g. `#-3` when Black mates.
+pub fn format_evaluation(score: Score) -> String {
+    match score {
+        Score::Centipawns(cp) => format!("{:.2}", f64::from(cp) / f64::from(PAWN_VALUE)),
+        Score::Mate(moves) => format!("#{moves}"),
+    }
+}
+
+/// The score in an `[%eval]` command, read as [`format_evaluation`] writes
+/// it.
+pub fn parse_evaluation(text: &str) -> Option<Score> {
+    if let Some(moves) = text.strip_prefix('#') {
+        return moves.parse().ok().map(Score::Mate);
+    }
+    let pawns: f64 = text.parse().ok()?;
+    // rounded to the nearest, which `as` alone would truncate
+    let centipawns = pawns * f64::from(PAWN_VALUE) + 0.5_f64.copysign(pawns);
+    Some(Score::Centipawns(centipawns as _))
+}
+
+impl GameTree {
+    /// The moves of the main line.
+    pub fn main_moves(&self) -> Vec<Move> {
+        self.main_line.iter().map(|node| node.mv).collect()
+    }
+
+    /// The moves that have been played after `path`, the main continuation
+    /// first, or none if `path` isn't in the tree.
+    pub fn continuations(&self, path: &[Move]) -> Vec<Move> {
+        let Some((line, next)) = self.find(path) else {
+            return Vec::new();
+        };
+        line.get(next)
+            .map(|node| {
+                let alternatives = node.variations.iter().map(|variation| variation[0].mv);
+                [node.mv].into_iter().chain(alternatives).collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// The comment on the position after `path`.
+    pub fn comment_at(&self, path: &[Move]) -> Option<&str> {
+        if path.is_empty() {
+            return self.comment.as_deref();
+        }
+        self.node_at(path)?.comment.as_deref()
+    }
+
+    /// The last move of `path`, with its annotations, or `None` if `path` is
+    /// empty or isn't in the tree.
+    pub fn node_at(&self, path: &[Move]) -> Option<&MoveNode> {
+        let (line, next) = self.find(path)?;
+        next.checked_sub(1).map(|last| &line[last])
+    }
+
+    /// The line the last move of `path` is in, and the index in it of the move
+    /// after it, or `None` if `path` isn't in the tree.
+    fn find(&self, path: &[Move]) -> Option<(&[MoveNode], usize)> {
+        let mut line = self.main_line.as_slice();
+        let mut next = 0;
+        for mv in path {
+            let node = line.get(next)?;
+            if node.mv != *mv {
+                line = node.variations.iter().find(|v| v[0].mv == *mv)?;
+                next = 0;
+            }
+            next += 1;
+        }
+        Some((line, next))
+    }
+
+    /// Like [`GameTree::find`], adding the moves of `path` that aren't in the
+    /// tree yet: at the end of a line to continue it, otherwise as a new
+    /// variation.
+    fn find_or_insert(&mut self, path: &[Move]) -> (&mut Vec<MoveNode>, usize) {
+        let mut line = &mut self.main_line;
+        let mut next = 0;
+        for mv in path {
+            if let Some(node) = line.get(next)
+                && node.mv != *mv
+            {
+                let here = line;
+                let variations = &mut here[next].variations;
+                let index = match variations.iter().position(|v| v[0].mv == *mv) {
+                    Some(index) => index,
+                    None => {
+                        variations.push(Vec::new());
+                        variations.len() - 1
+                    }
+                };
+                line = &mut variations[index];
+                next = 0;
+            }
+            if next == line.len() {
+                line.push(MoveNode::new(*mv));
+            }
+            next += 1;
+        }
+        (line, next)
+    }
+
+    /// Make sure every move of `path` is in the tree.
+    pub(crate) fn insert(&mut self, path: &[Move]) {
+        self.find_or_insert(path);
+    }
+
+    /// Set the comment on the position after `path`, adding the path if need
+    /// be.
+    pub(crate) fn set_comment(&mut self, path: &[Move], comment: Option<String>) {
+        if path.is_empty() {
+            self.comment = comment;
+            return;
+        }
+        let (line, next) = self.find_or_insert(path);
+        line[next - 1].comment = comment;
+    }
+
+    /// The last move of `path`, to annotate, adding the path if need be, or
+    /// `None` if `path` is empty.
+    pub(crate) fn node_mut(&mut self, path: &[Move]) -> Option<&mut MoveNode> {
+        if path.is_empty() {
+            return None;
+        }
+        let (line, next) = self.find_or_insert(path);
+        Some(&mut line[next - 1])
+    }
+
+    /// Delete the last move of `path` and everything after it. Its first
+    /// variation, if it had any, takes its place.
+    pub(crate) fn remove(&mut self, path: &[Move]) {
+        if path.is_empty() || self.find(path).is_none() {
+            return;
+        }
+        let (line, next) = self.find_or_insert(path);
+        let removed = line.split_off(next - 1).swap_remove(0);
+        let mut variations = removed.variations.into_iter();
+        if let Some(mut promoted) = variations.next() {
+            promoted[0].variations.extend(variations);
+            line.append(&mut promoted);
+        }
+        prune(&mut self.main_line);
+    }
+}
+
+/// Drop the variations left empty by [`GameTree::remove`].
+fn prune(line: &mut [MoveNode]) {
+    for node in line {
+        node.variations.retain(|variation| !variation.is_empty());
+        for variation in &mut node.variations {
+            prune(variation);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn moves(uci: &str) -> Vec<Move> {
+        uci.split_whitespace()
+            .map(|mv| Move::from_uci(mv).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_tree() {
+        let mut tree = GameTree::default();
+        tree.insert(&moves("e2e4 e7e5 g1f3"));
+        tree.insert(&moves("e2e4 c7c5"));
+        tree.insert(&moves("d2d4"));
+        assert_eq!(tree.main_moves(), moves("e2e4 e7e5 g1f3"));
+        assert_eq!(tree.continuations(&[]), moves("e2e4 d2d4"));
+        assert_eq!(tree.continuations(&moves("e2e4")), moves("e7e5 c7c5"));
+        assert!(tree.continuations(&moves("e2e4 e7e5 g1f3")).is_empty());
+        assert!(tree.continuations(&moves("a2a3")).is_empty());
+
+        tree.set_comment(&moves("e2e4 c7c5"), Some("the Sicilian".into()));
+        assert_eq!(tree.comment_at(&moves("e2e4 c7c5")), Some("the Sicilian"));
+        assert_eq!(tree.comment_at(&moves("e2e4")), None);
+        let node = tree.node_mut(&moves("e2e4 c7c5")).unwrap();
+        node.nags.push(5);
+        node.evaluation = Some(Score::Centipawns(31));
+        assert_eq!(
+            tree.node_at(&moves("e2e4 c7c5")).unwrap().pgn_comment(),
+            Some("[%eval 0.31] the Sicilian".into())
+        );
+        assert!(tree.node_at(&[]).is_none() && tree.node_mut(&[]).is_none());
+
+        // the first variation takes the place of a removed move
+        tree.remove(&moves("e2e4 e7e5"));
+        assert_eq!(tree.main_moves(), moves("e2e4 c7c5"));
+        assert_eq!(tree.continuations(&moves("e2e4")), moves("c7c5"));
+        tree.remove(&moves("d2d4"));
+        assert_eq!(tree.continuations(&[]), moves("e2e4"));
+    }
+
+    #[test]
+    fn test_annotations() {
+        assert_eq!(nag_symbol(4), Some("??"));
+        assert_eq!(nag_symbol(0), None);
+        assert_eq!(nag_symbol(146), None);
+        assert_eq!(nag_from_symbol("!?"), Some(5));
+        assert_eq!(nag_from_symbol("+-"), None);
+        for (text, score) in [
+            ("0.17", Score::Centipawns(17)),
+            ("-1.50", Score::Centipawns(-150)),
+            ("#3", Score::Mate(3)),
+            ("#-2", Score::Mate(-2)),
+        ] {
+            assert_eq!(parse_evaluation(text), Some(score));
+            assert_eq!(format_evaluation(score), text);
+        }
+        assert_eq!(parse_evaluation("+0.5"), Some(Score::Centipawns(50)));
+        assert_eq!(parse_evaluation("mate"), None);
+    }
+}
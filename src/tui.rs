@@ -0,0 +1,900 @@
+//! A full-screen terminal UI, with the board, the moves so far, the latest
+//! evaluation and the clocks on screen at once. It stands in for the printed
+//! board as the user's [`Player`], so any opponent can be played in it.
+//! Pieces are picked with the arrow keys and Enter, or moves and commands
+//! typed into the line at the bottom.
+
+use crate::clock::ClockTime;
+use crate::core::{ChessColour, Column, Move, Position, Row, Square};
+use crate::game::ChessGame;
+use crate::input::{
+    Command, candidate_names, choose_candidate, parse_move_candidates, parse_moves,
+};
+use crate::player::human::Hints;
+use crate::player::{Action, Player};
+use crate::render::{self, Annotation, RenderOptions, Rgb};
+use crate::solver::{self, Score};
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Paragraph};
+use ratatui::{DefaultTerminal, Frame};
+use std::rc::Rc;
+use std::sync::mpsc::{self, Receiver, Sender, TryRecvError};
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+/// How often to redraw while waiting for a key, so the clocks keep ticking.
+const TICK: Duration = Duration::from_millis(100);
+
+/// The board panel's size, with its border: three columns per square and two
+/// for the rank numbers, and a line for the file letters.
+const BOARD_WIDTH: u16 = 8 * 3 + 2 + 2;
+const BOARD_HEIGHT: u16 = 8 + 1 + 2;
+
+/// How many columns the evaluation bar is split into.
+const EVAL_BAR_WIDTH: usize = 20;
+
+const SELECTED: Color = Color::Rgb(106, 160, 220);
+const DESTINATION: Color = Color::Rgb(130, 190, 120);
+const CURSOR: Color = Color::Rgb(230, 120, 90);
+
+const HELP: &str = "Arrows and Enter to move, or type a move or a command, help to list them";
+
+const COMMANDS: &str =
+    "undo, hint, moves [square], fen, flip, threats, resign, draw, save <file>, quit";
+
+/// What the game tells the screen.
+enum Update {
+    /// It is the user's turn in the game as it stands.
+    Turn(ChessGame),
+    /// A move, `san`, left `game`, with what its player made of it, for
+    /// White, if it said.
+    Moved {
+        game: ChessGame,
+        san: String,
+        evaluation: Option<Score>,
+    },
+    /// The player of this colour offers the user a draw.
+    DrawOffer(ChessColour),
+    Message(String),
+    /// The game is over, or a player left it unfinished.
+    Over(ChessGame),
+    /// The game has stopped: the screen goes once the user has seen how it
+    /// ended.
+    Closed,
+}
+
+/// What the user answers on the screen.
+enum Reply {
+    Action(Action),
+    /// Ask for a hint, which only the player to move can give.
+    Hint,
+    /// Whether they accept a draw offer.
+    Draw(bool),
+}
+
+/// The terminal, taken over by the UI on a thread of its own while the game
+/// is played on this one.
+pub struct Screen {
+    updates: Sender<Update>,
+    replies: Rc<Receiver<Reply>>,
+    thread: JoinHandle<anyhow::Result<()>>,
+}
+
+impl Screen {
+    /// Show `game` drawn with `render`, turning the board to face whoever is
+    /// to move if `auto_flip`, until [`Screen::close`].
+    pub fn start(game: &ChessGame, render: RenderOptions, auto_flip: bool) -> Self {
+        let (updates, updates_rx) = mpsc::channel();
+        let (replies_tx, replies) = mpsc::channel();
+        let app = App::new(game.clone(), render, auto_flip, updates_rx, replies_tx);
+        let thread = std::thread::spawn(move || {
+            let mut terminal = ratatui::init();
+            let res = app.run(&mut terminal);
+            ratatui::restore();
+            res
+        });
+        Self {
+            updates,
+            replies: Rc::new(replies),
+            thread,
+        }
+    }
+
+    /// A side for the user to play on this screen, with `hints` for when
+    /// they ask. Both sides can be played on the same screen.
+    pub fn player(&self, hints: Hints) -> TuiPlayer {
+        TuiPlayer {
+            hints,
+            takebacks: true,
+            updates: self.updates.clone(),
+            replies: Rc::clone(&self.replies),
+        }
+    }
+
+    /// Give the terminal back, once the user has seen how the game ended.
+    pub fn close(self) -> anyhow::Result<()> {
+        self.updates.send(Update::Closed).ok();
+        self.thread
+            .join()
+            .map_err(|_| anyhow::anyhow!("the TUI panicked"))?
+    }
+}
+
+/// The user playing on a [`Screen`].
+pub struct TuiPlayer {
+    hints: Hints,
+    /// Whether they may take moves back.
+    takebacks: bool,
+    updates: Sender<Update>,
+    replies: Rc<Receiver<Reply>>,
+}
+
+impl TuiPlayer {
+    /// Refuse to take moves back, for a game the other side can't take back
+    /// moves in too.
+    pub fn without_takebacks(self) -> Self {
+        Self {
+            takebacks: false,
+            ..self
+        }
+    }
+
+    fn send(&self, update: Update) {
+        // the screen is gone if the user left it, which the next reply says
+        self.updates.send(update).ok();
+    }
+}
+
+impl Player for TuiPlayer {
+    /// Wait for the user to decide what to do on the screen, giving hints
+    /// meanwhile.
+    fn choose_move(&mut self, game: &ChessGame) -> anyhow::Result<Action> {
+        self.send(Update::Turn(game.clone()));
+        loop {
+            let Ok(reply) = self.replies.recv() else {
+                return Ok(Action::Quit);
+            };
+            match reply {
+                Reply::Action(Action::Undo) if !self.takebacks => {
+                    self.send(Update::Message(
+                        "Moves can't be taken back in this game".to_string(),
+                    ));
+                    self.send(Update::Turn(game.clone()));
+                }
+                Reply::Action(action) => return Ok(action),
+                Reply::Hint => {
+                    let message = match self.hints.suggest(game) {
+                        Ok((mv, score)) => format!("Try {} ({score})", game.san(&mv)?),
+                        Err(e) => format!("Could not find a hint: {e:#}"),
+                    };
+                    self.send(Update::Message(message));
+                }
+                // an answer to a draw offer that has already been settled
+                Reply::Draw(_) => {}
+            }
+        }
+    }
+
+    /// Ask on the screen whether to agree to the draw the other player
+    /// offered.
+    fn accepts_draw(&mut self, game: &ChessGame) -> anyhow::Result<bool> {
+        self.send(Update::DrawOffer(game.get_board().turn));
+        loop {
+            match self.replies.recv() {
+                Ok(Reply::Draw(accepted)) => return Ok(accepted),
+                Ok(Reply::Action(Action::Quit)) | Err(_) => return Ok(false),
+                Ok(_) => {}
+            }
+        }
+    }
+
+    fn observe(
+        &mut self,
+        game: &ChessGame,
+        san: &str,
+        evaluation: Option<Score>,
+    ) -> anyhow::Result<()> {
+        self.send(Update::Moved {
+            game: game.clone(),
+            san: san.to_string(),
+            evaluation,
+        });
+        Ok(())
+    }
+
+    fn notify(&mut self, message: &str) {
+        self.send(Update::Message(message.to_string()));
+    }
+
+    fn game_over(&mut self, game: &ChessGame) -> anyhow::Result<()> {
+        self.send(Update::Over(game.clone()));
+        Ok(())
+    }
+
+    fn is_human(&self) -> bool {
+        true
+    }
+}
+
+/// What the game is waiting for the user to answer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Asked {
+    Move,
+    Draw,
+}
+
+struct App {
+    /// The game as the players last saw it.
+    game: ChessGame,
+    render: RenderOptions,
+    /// Whether to turn the board to face whoever is to move.
+    auto_flip: bool,
+    /// The square the arrow keys move around.
+    cursor: Position,
+    /// The piece picked to move, and the squares it can move to.
+    selected: Option<(Position, Vec<Position>)>,
+    /// What has been typed into the command line so far.
+    input: String,
+    /// The moves the last input could have meant, until the user says which.
+    choices: Vec<Move>,
+    /// What happened last, e.g. the opponent's move or why a move was
+    /// refused.
+    message: String,
+    asked: Option<Asked>,
+    /// The last evaluation a player gave with its move, for White.
+    evaluation: Option<Score>,
+    /// Whether the game has ended, leaving the user to look at it until they
+    /// leave.
+    over: bool,
+    quit: bool,
+    updates: Receiver<Update>,
+    replies: Sender<Reply>,
+}
+
+impl App {
+    fn new(
+        game: ChessGame,
+        render: RenderOptions,
+        auto_flip: bool,
+        updates: Receiver<Update>,
+        replies: Sender<Reply>,
+    ) -> Self {
+        Self {
+            game,
+            cursor: Position {
+                row: if render.flipped { Row::Seven } else { Row::Two },
+                column: Column::E,
+            },
+            render,
+            auto_flip,
+            selected: None,
+            input: String::new(),
+            choices: Vec::new(),
+            message: String::new(),
+            asked: None,
+            evaluation: None,
+            over: false,
+            quit: false,
+            updates,
+            replies,
+        }
+    }
+
+    fn run(mut self, terminal: &mut DefaultTerminal) -> anyhow::Result<()> {
+        while !self.quit {
+            terminal.draw(|frame| self.draw(frame))?;
+            if event::poll(TICK)?
+                && let Event::Key(key) = event::read()?
+                && key.kind == KeyEventKind::Press
+            {
+                self.handle_key(key);
+            }
+            self.receive();
+            // the flag fell while the user thought: any answer lets the game
+            // see it
+            if self.asked == Some(Asked::Move) && self.game.check_timeout().is_some() {
+                self.act(Action::OfferDraw);
+            }
+        }
+        Ok(())
+    }
+
+    /// Catch up with whatever the game has told the screen.
+    fn receive(&mut self) {
+        loop {
+            match self.updates.try_recv() {
+                Ok(update) => self.update(update),
+                Err(TryRecvError::Empty) => return,
+                Err(TryRecvError::Disconnected) => {
+                    self.quit = true;
+                    return;
+                }
+            }
+        }
+    }
+
+    fn update(&mut self, update: Update) {
+        match update {
+            Update::Turn(game) => {
+                self.game = game;
+                self.asked = Some(Asked::Move);
+                if self.auto_flip {
+                    self.render.flipped = self.game.get_board().turn == ChessColour::Black;
+                }
+            }
+            Update::Moved {
+                game,
+                san,
+                evaluation,
+            } => {
+                self.message = format!("{} played {san}", game.get_board().turn.flip());
+                self.game = game;
+                self.selected = None;
+                if evaluation.is_some() {
+                    self.evaluation = evaluation;
+                }
+            }
+            Update::DrawOffer(colour) => {
+                self.asked = Some(Asked::Draw);
+                self.message = format!("{colour} offers a draw, do you accept? (yes or no)");
+            }
+            Update::Message(message) => self.message = message,
+            Update::Over(game) => {
+                self.game = game;
+                self.asked = None;
+                self.selected = None;
+                self.over = true;
+            }
+            Update::Closed => self.quit |= !self.over,
+        }
+    }
+
+    /// Answer the game with `action`, ending the user's turn.
+    fn act(&mut self, action: Action) {
+        self.asked = None;
+        self.selected = None;
+        self.reply(Reply::Action(action));
+    }
+
+    fn reply(&mut self, reply: Reply) {
+        // the game only stops listening once it is over
+        if self.replies.send(reply).is_err() {
+            self.quit = true;
+        }
+    }
+
+    fn handle_key(&mut self, key: KeyEvent) {
+        match key.code {
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => self.leave(),
+            KeyCode::Char(c) => self.input.push(c),
+            KeyCode::Backspace => {
+                self.input.pop();
+            }
+            KeyCode::Esc if self.over => self.quit = true,
+            KeyCode::Esc => {
+                self.input.clear();
+                self.selected = None;
+            }
+            KeyCode::Up => self.move_cursor(1, 0),
+            KeyCode::Down => self.move_cursor(-1, 0),
+            KeyCode::Left => self.move_cursor(0, -1),
+            KeyCode::Right => self.move_cursor(0, 1),
+            KeyCode::Enter if !self.input.is_empty() => {
+                let command = std::mem::take(&mut self.input);
+                self.command(command.trim());
+            }
+            KeyCode::Enter if self.over => self.quit = true,
+            KeyCode::Enter => self.select(),
+            _ => {}
+        }
+    }
+
+    /// Leave the screen, and the game unfinished if it isn't over.
+    fn leave(&mut self) {
+        if !self.over {
+            self.replies.send(Reply::Action(Action::Quit)).ok();
+        }
+        self.quit = true;
+    }
+
+    /// Move the cursor `rows` up and `columns` right as the board is shown.
+    fn move_cursor(&mut self, rows: isize, columns: isize) {
+        let (rows, columns) = if self.render.flipped {
+            (-rows, -columns)
+        } else {
+            (rows, columns)
+        };
+        if let Some(pos) = self.cursor.offset(rows, columns) {
+            self.cursor = pos;
+        }
+    }
+
+    /// Pick up the piece under the cursor, or move the piece already picked up
+    /// to the cursor.
+    fn select(&mut self) {
+        let cursor = self.cursor;
+        if let Some((from, to)) = self.selected.take()
+            && to.contains(&cursor)
+        {
+            self.play(&Move { from, to: cursor });
+            return;
+        }
+        let board = self.game.get_board();
+        let own_piece = board
+            .get(Square::from(cursor))
+            .is_some_and(|piece| piece.colour == board.turn);
+        if self.asked != Some(Asked::Move) || !own_piece {
+            return;
+        }
+        match self.game.get_available_moves(cursor) {
+            Ok(moves) if moves.is_empty() => {
+                self.message = format!("The piece on {cursor} can't move");
+            }
+            Ok(moves) => self.selected = Some((cursor, moves)),
+            Err(e) => self.message = e.to_string(),
+        }
+    }
+
+    fn command(&mut self, input: &str) {
+        if self.asked == Some(Asked::Draw) {
+            return self.answer_draw(input);
+        }
+        let choices = std::mem::take(&mut self.choices);
+        if let Some(mv) = choose_candidate(input, &choices, &self.game) {
+            return self.play(&mv);
+        }
+        let command = match Command::parse(input) {
+            Some(Ok(command)) => command,
+            Some(Err(e)) => {
+                self.message = e.to_string();
+                return;
+            }
+            None => {
+                if let Ok(candidates) = parse_move_candidates(input, &self.game)
+                    && candidates.len() > 1
+                {
+                    let names: Vec<_> = candidate_names(&candidates, &self.game)
+                        .iter()
+                        .enumerate()
+                        .map(|(number, name)| format!("{}: {name}", number + 1))
+                        .collect();
+                    self.message = format!("'{input}' could be {}, type which", names.join(", "));
+                    self.choices = candidates;
+                    return;
+                }
+                match parse_moves(input, &self.game) {
+                    Ok(moves) => self.play_all(&moves),
+                    Err(e) => self.message = e.to_string(),
+                }
+                return;
+            }
+        };
+        self.message.clear();
+        match command {
+            Command::Quit => self.leave(),
+            Command::Flip => self.render.flipped = !self.render.flipped,
+            Command::Threats => self.render.threats = !self.render.threats,
+            Command::Fen => self.message = self.game.fen(),
+            Command::Help => self.message = COMMANDS.to_string(),
+            Command::Moves(Some(square)) => {
+                self.selected = None;
+                self.cursor = square;
+                self.select();
+            }
+            Command::Moves(None) => {
+                let moves: Vec<_> = solver::legal_moves(&self.game)
+                    .iter()
+                    .filter_map(|mv| self.game.san(mv).ok())
+                    .collect();
+                self.message = moves.join(" ");
+            }
+            Command::Save(path) => {
+                let saved = self
+                    .game
+                    .pgn()
+                    .map_err(anyhow::Error::from)
+                    .and_then(|pgn| Ok(std::fs::write(&path, pgn)?));
+                self.message = match saved {
+                    Ok(()) => format!("Saved the game to {}", path.display()),
+                    Err(e) => format!("Could not save the game: {e}"),
+                };
+            }
+            _ if self.over => self.message = "The game is over".to_string(),
+            _ if self.asked != Some(Asked::Move) => self.message = self.waiting(),
+            Command::Undo => self.act(Action::Undo),
+            Command::Hint => {
+                self.message = "Thinking of a hint…".to_string();
+                self.reply(Reply::Hint);
+            }
+            Command::Resign => self.act(Action::Resign),
+            Command::Draw => {
+                self.message = "You offered a draw".to_string();
+                self.act(Action::OfferDraw);
+            }
+        }
+    }
+
+    fn answer_draw(&mut self, input: &str) {
+        let accepted = match input.to_lowercase().as_str() {
+            "y" | "yes" => true,
+            "n" | "no" => false,
+            _ => {
+                self.message = "Answer yes or no".to_string();
+                return;
+            }
+        };
+        self.message.clear();
+        self.asked = None;
+        self.reply(Reply::Draw(accepted));
+    }
+
+    /// Why the user can't move yet.
+    fn waiting(&self) -> String {
+        if self.over {
+            "The game is over".to_string()
+        } else {
+            format!("Wait for {} to move", self.game.get_board().turn)
+        }
+    }
+
+    /// Play the user's move, if it is theirs to make.
+    fn play(&mut self, mv: &Move) {
+        self.play_all(std::slice::from_ref(mv));
+    }
+
+    /// Play one move or several at once, e.g. pasted from a game's
+    /// movetext, if the game would take them.
+    fn play_all(&mut self, moves: &[Move]) {
+        self.selected = None;
+        if self.asked != Some(Asked::Move) {
+            self.message = self.waiting();
+            return;
+        }
+        // shown straight away, until the game says the move was played
+        let mut after = self.game.clone();
+        for mv in moves {
+            if let Err(e) = after.make_move(mv) {
+                self.message = e.to_string();
+                return;
+            }
+        }
+        self.game = after;
+        match moves {
+            [mv] => self.act(Action::Move(*mv)),
+            _ => self.act(Action::Moves(moves.to_vec())),
+        }
+    }
+
+    fn draw(&self, frame: &mut Frame) {
+        let [main, status, input] = Layout::vertical([
+            Constraint::Min(0),
+            Constraint::Length(1),
+            Constraint::Length(1),
+        ])
+        .areas(frame.area());
+        let [board, side] =
+            Layout::horizontal([Constraint::Length(BOARD_WIDTH), Constraint::Min(0)]).areas(main);
+        let [board, _] =
+            Layout::vertical([Constraint::Length(BOARD_HEIGHT), Constraint::Min(0)]).areas(board);
+        let [clocks, evaluation, moves] = Layout::vertical([
+            Constraint::Length(4),
+            Constraint::Length(7),
+            Constraint::Min(0),
+        ])
+        .areas(side);
+
+        frame.render_widget(
+            Paragraph::new(self.board_lines()).block(Block::bordered().title("Board")),
+            board,
+        );
+        frame.render_widget(
+            Paragraph::new(self.clock_lines()).block(Block::bordered().title("Clocks")),
+            clocks,
+        );
+        frame.render_widget(
+            Paragraph::new(self.evaluation_lines()).block(Block::bordered().title("Evaluation")),
+            evaluation,
+        );
+        let numbered = self.game.numbered_moves().unwrap_or_default();
+        // keep the latest moves in view
+        let scroll = numbered
+            .len()
+            .saturating_sub(moves.height.saturating_sub(2).into());
+        let numbered: Vec<Line> = numbered.into_iter().map(Line::from).collect();
+        let title = match self.game.opening() {
+            Some(opening) => format!("Moves: {opening}"),
+            None => "Moves".to_string(),
+        };
+        frame.render_widget(
+            Paragraph::new(numbered)
+                .block(Block::bordered().title(title))
+                .scroll((scroll as u16, 0)),
+            moves,
+        );
+
+        let message = if self.message.is_empty() {
+            HELP
+        } else {
+            &self.message
+        };
+        let status_line = match self.game.result() {
+            Some(result) if self.over => format!(
+                "{} Press Enter to leave.",
+                render::result_line(&result, self.render.ascii)
+            ),
+            Some(result) => render::result_line(&result, self.render.ascii),
+            None if self.over => "The game was left unfinished. Press Enter to leave.".to_string(),
+            None if render::checked_king(&self.game).is_some() => {
+                format!("{} is in check. {message}", self.game.get_board().turn)
+            }
+            None => message.to_string(),
+        };
+        frame.render_widget(Paragraph::new(status_line), status);
+        frame.render_widget(Paragraph::new(format!("> {}", self.input)), input);
+        frame.set_cursor_position((input.x + 2 + self.input.len() as u16, input.y));
+    }
+
+    fn board_lines(&self) -> Vec<Line<'static>> {
+        let board = self.game.get_board();
+        // from the top left corner of the board as shown
+        let order = |i: usize| if self.render.flipped { i } else { 7 - i };
+        let files = |i: usize| if self.render.flipped { 7 - i } else { i };
+        let annotations = self.annotations();
+        let mut lines = Vec::new();
+        for row in (0..8).map(order) {
+            let mut spans = vec![Span::raw(format!("{} ", row + 1))];
+            for column in (0..8).map(files) {
+                let square = Square::new(row, column).expect("on the board");
+                let pos = Position::from(square);
+                let cell = board.cell(square);
+                let (glyph, fg) = match &cell.piece {
+                    Some(piece) => (
+                        self.render.pieces.glyph(piece),
+                        colour(self.render.theme.piece(piece.colour)),
+                    ),
+                    None => (' ', Color::Reset),
+                };
+                let style = Style::new()
+                    .fg(fg)
+                    .bg(self.square_colour(&pos, &annotations))
+                    .add_modifier(Modifier::BOLD);
+                spans.push(Span::styled(format!(" {glyph} "), style));
+            }
+            lines.push(Line::from(spans));
+        }
+        let letters: String = (0..8)
+            .map(|i| format!(" {} ", char::from(b'a' + files(i) as u8)))
+            .collect();
+        lines.push(Line::from(format!("  {letters}")));
+        lines
+    }
+
+    /// Any threats, and the last moves.
+    fn annotations(&self) -> Vec<Annotation> {
+        let theme = &self.render.theme;
+        let mut annotations = if self.render.threats {
+            render::threats(&self.game, theme, self.render.hanging)
+        } else {
+            Vec::new()
+        };
+        annotations.extend(render::last_moves(&self.game, theme));
+        annotations
+    }
+
+    fn square_colour(&self, pos: &Position, annotations: &[Annotation]) -> Color {
+        let (row, column) = (usize::from(pos.row), usize::from(pos.column));
+        if *pos == self.cursor {
+            CURSOR
+        } else if render::checked_king(&self.game) == Some(*pos) {
+            colour(self.render.theme.check)
+        } else if let Some((from, to)) = &self.selected
+            && (from == pos || to.contains(pos))
+        {
+            if from == pos { SELECTED } else { DESTINATION }
+        } else if let Some(annotation) = annotations.iter().rev().find(|a| a.covers(row, column)) {
+            colour(annotation.colour())
+        } else {
+            colour(self.render.theme.square(row, column))
+        }
+    }
+
+    fn clock_lines(&self) -> Vec<Line<'static>> {
+        let Some(clock) = self.game.clock() else {
+            return vec![Line::from("No time control")];
+        };
+        let turn = self.game.get_board().turn;
+        [ChessColour::White, ChessColour::Black]
+            .into_iter()
+            .map(|colour| {
+                let running = colour == turn && self.game.result().is_none();
+                let remaining = if running {
+                    clock.remaining_now(colour)
+                } else {
+                    clock.remaining(colour)
+                };
+                let marker = if running { '▶' } else { ' ' };
+                Line::from(format!("{marker} {colour:<6} {}", ClockTime(remaining)))
+            })
+            .collect()
+    }
+
+    fn evaluation_lines(&self) -> Vec<Line<'static>> {
+        let mut lines = Vec::new();
+        if self.asked.is_none() && !self.over {
+            let turn = self.game.get_board().turn;
+            lines.push(Line::from(format!("Waiting for {turn}…")));
+        }
+        if let Some(score) = self.evaluation {
+            lines.push(self.eval_bar(score));
+            lines.push(Line::from(format!("Score {score}, for White")));
+        }
+        lines
+    }
+
+    /// A bar filled from the left with White's share of White's `score`.
+    fn eval_bar(&self, score: Score) -> Line<'static> {
+        let filled = (render::white_share(score) * EVAL_BAR_WIDTH as f64).round() as usize;
+        let theme = &self.render.theme;
+        Line::from(vec![
+            Span::styled(
+                " ".repeat(filled),
+                Style::new().bg(colour(theme.white_piece)),
+            ),
+            Span::styled(
+                " ".repeat(EVAL_BAR_WIDTH - filled),
+                Style::new().bg(colour(theme.black_piece)),
+            ),
+            Span::raw(format!(" {score}")),
+        ])
+    }
+}
+
+fn colour(Rgb(r, g, b): Rgb) -> Color {
+    Color::Rgb(r, g, b)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::controller::GameController;
+    use crate::game::{GameResult, Termination};
+    use crate::player::SolverPlayer;
+    use crate::solver::SearchOptions;
+    use ratatui::Terminal;
+    use ratatui::backend::TestBackend;
+
+    fn press(app: &mut App, code: KeyCode) {
+        app.handle_key(KeyEvent::new(code, KeyModifiers::NONE));
+    }
+
+    fn type_line(app: &mut App, line: &str) {
+        for c in line.chars() {
+            press(app, KeyCode::Char(c));
+        }
+        press(app, KeyCode::Enter);
+    }
+
+    #[test]
+    fn test_select_and_move() {
+        let (updates, updates_rx) = mpsc::channel();
+        let (replies_tx, replies) = mpsc::channel();
+        let game = ChessGame::default();
+        let mut app = App::new(
+            game.clone(),
+            RenderOptions::default(),
+            false,
+            updates_rx,
+            replies_tx,
+        );
+
+        // nothing can be picked up until it is the user's turn
+        press(&mut app, KeyCode::Enter);
+        assert!(app.selected.is_none());
+        updates.send(Update::Turn(game)).unwrap();
+        app.receive();
+        press(&mut app, KeyCode::Enter);
+        let (from, to) = app.selected.clone().unwrap();
+        assert_eq!(from.to_string(), "e2");
+        assert_eq!(to.len(), 2);
+        press(&mut app, KeyCode::Up);
+        press(&mut app, KeyCode::Up);
+        press(&mut app, KeyCode::Enter);
+        match replies.try_recv() {
+            Ok(Reply::Action(Action::Move(mv))) => assert_eq!(mv.to_uci(), "e2e4"),
+            _ => panic!("expected e2e4"),
+        }
+        assert_eq!(app.asked, None);
+        type_line(&mut app, "e5");
+        assert_eq!(app.message, "Wait for Black to move");
+
+        let mut after = app.game.clone();
+        after.make_move(&Move::from_uci("e7e5").unwrap()).unwrap();
+        updates
+            .send(Update::Moved {
+                game: after.clone(),
+                san: "e5".to_string(),
+                evaluation: Some(Score::Centipawns(20)),
+            })
+            .unwrap();
+        updates.send(Update::Turn(after)).unwrap();
+        app.receive();
+        assert_eq!(app.message, "Black played e5");
+        assert_eq!(app.asked, Some(Asked::Move));
+
+        type_line(&mut app, "flip");
+        assert!(app.render.flipped);
+        // up the screen is down the board once flipped
+        press(&mut app, KeyCode::Up);
+        assert_eq!(app.cursor.to_string(), "e3");
+
+        let mut terminal = Terminal::new(TestBackend::new(80, 24)).unwrap();
+        terminal.draw(|frame| app.draw(frame)).unwrap();
+        let screen = format!("{:?}", terminal.backend().buffer());
+        assert!(screen.contains("1. e4 e5"));
+        assert!(screen.contains("+0.20, for White"));
+
+        type_line(&mut app, "resign");
+        assert!(matches!(
+            replies.try_recv(),
+            Ok(Reply::Action(Action::Resign))
+        ));
+        updates.send(Update::Closed).unwrap();
+        app.receive();
+        assert!(app.quit);
+    }
+
+    #[test]
+    fn test_player_through_controller() {
+        let (updates, updates_rx) = mpsc::channel();
+        let (replies, replies_rx) = mpsc::channel();
+        let options = SearchOptions {
+            max_depth: 1,
+            ..SearchOptions::default()
+        };
+        let user = TuiPlayer {
+            hints: Hints::solver(options.clone(), Duration::from_millis(10)),
+            takebacks: false,
+            updates,
+            replies: Rc::new(replies_rx),
+        };
+        // the screen: ask for a hint and to take a move back, which leave it
+        // the user's turn, then resign
+        let screen = std::thread::spawn(move || {
+            let mut turns = vec![
+                vec![Reply::Action(Action::Resign)],
+                vec![Reply::Hint, Reply::Action(Action::Undo)],
+            ];
+            let mut messages = Vec::new();
+            for update in updates_rx {
+                match update {
+                    Update::Turn(_) => {
+                        for reply in turns.pop().unwrap() {
+                            replies.send(reply).unwrap();
+                        }
+                    }
+                    Update::Message(message) => messages.push(message),
+                    Update::Over(game) => return (messages, game),
+                    _ => {}
+                }
+            }
+            panic!("the game never ended");
+        });
+        let mut controller = GameController::new(
+            ChessGame::default(),
+            Box::new(user),
+            Box::new(SolverPlayer::new(options, false)),
+        );
+        assert!(controller.play().unwrap());
+        drop(controller);
+        let (messages, game) = screen.join().unwrap();
+        assert!(messages[0].starts_with("Try "), "{messages:?}");
+        assert_eq!(messages[1], "Moves can't be taken back in this game");
+        assert_eq!(
+            game.result(),
+            Some(GameResult::BlackWins(Termination::Resignation))
+        );
+    }
+}
@@ -0,0 +1,253 @@
+use super::EngineOutput;
+use super::go::GoParams;
+use super::info;
+use super::options::{self, EngineOption};
+use crate::clock::Clock;
+use crate::core::Move;
+use crate::engine::{RESPONSE_TIMEOUT, SEARCH_GRACE, TRAFFIC_TARGET};
+use crate::game::ChessGame;
+use crate::solver::SearchLine;
+use anyhow::Context;
+use std::process::Stdio;
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::process::{Child, ChildStdin, Command};
+use tokio::sync::mpsc::{self, Receiver};
+
+/// Like [`super::UciEngine`], but for callers running on tokio. Commands are
+/// written without blocking, and the engine's output arrives as a stream of
+/// [`EngineOutput`] from [`AsyncUciEngine::next_output`], so the caller can
+/// keep doing other work, such as drawing a board, while the engine thinks.
+pub struct AsyncUciEngine {
+    child: Child,
+    stdin: ChildStdin,
+    output: Receiver<EngineOutput>,
+    /// The options the engine advertised during the handshake.
+    options: Vec<EngineOption>,
+}
+
+impl AsyncUciEngine {
+    pub async fn new(engine_exec: &str) -> anyhow::Result<Self> {
+        let mut child = Command::new(engine_exec)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+            .with_context(|| format!("Failed to start engine at {engine_exec}"))?;
+        let stdin = child.stdin.take().unwrap();
+        let stdout = child.stdout.take().unwrap();
+        let (sender, output) = mpsc::channel(256);
+        tokio::spawn(async move {
+            let mut lines = BufReader::new(stdout).lines();
+            while let Ok(Some(line)) = lines.next_line().await {
+                tracing::debug!(target: TRAFFIC_TARGET, "< {line}");
+                if sender.send(EngineOutput::parse(&line)).await.is_err() {
+                    break;
+                }
+            }
+        });
+        let mut res = Self {
+            child,
+            stdin,
+            output,
+            options: Vec::new(),
+        };
+
+        res.send("uci").await?;
+        let mut options = Vec::new();
+        res.wait_for(
+            |output| match output {
+                EngineOutput::Option(option) => {
+                    options.push(option.clone());
+                    false
+                }
+                output => *output == EngineOutput::UciOk,
+            },
+            Some(RESPONSE_TIMEOUT),
+        )
+        .await?;
+        res.options = options;
+
+        Ok(res)
+    }
+
+    /// The next line of output from the engine, or `None` once it has exited.
+    pub async fn next_output(&mut self) -> Option<EngineOutput> {
+        self.output.recv().await
+    }
+
+    /// The options the engine supports.
+    pub fn options(&self) -> &[EngineOption] {
+        &self.options
+    }
+
+    /// Set one of the engine's options, checking first that it has the option
+    /// and that `value` suits it. Buttons are pressed with an empty value.
+    pub async fn set_option(&mut self, name: &str, value: &str) -> anyhow::Result<()> {
+        let option = options::find(&self.options, name)?;
+        option.validate(value)?;
+        let command = if value.is_empty() {
+            format!("setoption name {}", option.name)
+        } else {
+            format!("setoption name {} value {value}", option.name)
+        };
+        self.send(&command).await
+    }
+
+    pub async fn skill_level(&mut self, level: u8) -> anyhow::Result<()> {
+        if level > 20 {
+            return Err(anyhow::anyhow!("Skill level must be between 0 and 20"));
+        }
+
+        self.set_option("Skill Level", &level.to_string()).await
+    }
+
+    /// Weaken the engine to play at about `elo`, see [`options::elo_settings`].
+    pub async fn limit_elo(&mut self, elo: u32) -> anyhow::Result<()> {
+        for (name, value) in options::elo_settings(&self.options, elo)? {
+            self.set_option(&name, &value).await?;
+        }
+        Ok(())
+    }
+
+    /// Tell the engine whether to play Chess960, which also switches castling
+    /// moves to the king-takes-rook notation.
+    pub async fn chess960(&mut self, enabled: bool) -> anyhow::Result<()> {
+        self.set_option("UCI_Chess960", &enabled.to_string()).await
+    }
+
+    pub async fn is_ready(&mut self) -> anyhow::Result<()> {
+        self.send("isready").await?;
+        self.wait_for(
+            |output| *output == EngineOutput::ReadyOk,
+            Some(RESPONSE_TIMEOUT),
+        )
+        .await?;
+        Ok(())
+    }
+
+    pub async fn position(&mut self, fen: &str) -> anyhow::Result<()> {
+        self.send(&format!("position fen {fen}")).await
+    }
+
+    /// Set the position to `game`'s, sending its moves as well, see
+    /// [`super::position_command`].
+    pub async fn game_position(&mut self, game: &ChessGame) -> anyhow::Result<()> {
+        self.send(&super::position_command(game)).await
+    }
+
+    pub async fn new_game(&mut self) -> anyhow::Result<()> {
+        self.send("ucinewgame").await
+    }
+
+    /// Start a search with the given limits without waiting for it. The
+    /// engine's thoughts and finally its move arrive through
+    /// [`AsyncUciEngine::next_output`].
+    pub async fn go(&mut self, params: &GoParams) -> anyhow::Result<()> {
+        self.send(&params.to_string()).await
+    }
+
+    /// Ask the engine to finish its search and send its best move.
+    pub async fn stop(&mut self) -> anyhow::Result<()> {
+        self.send("stop").await
+    }
+
+    pub async fn best_move(&mut self) -> anyhow::Result<Move> {
+        self.search(&GoParams::new().movetime(Duration::from_millis(1000)))
+            .await
+    }
+
+    /// Search with the game's clock, letting the engine manage its own time.
+    pub async fn best_move_with_clock(&mut self, clock: &Clock) -> anyhow::Result<Move> {
+        self.search(&GoParams::new().clock(clock)).await
+    }
+
+    /// Search with the given limits, skipping the engine's output up to its
+    /// best move. An infinite search only ends after [`AsyncUciEngine::stop`].
+    pub async fn search(&mut self, params: &GoParams) -> anyhow::Result<Move> {
+        self.go(params).await?;
+        let timeout = params
+            .max_search_time()
+            .map(|search_time| search_time + SEARCH_GRACE);
+        let output = self
+            .wait_for(
+                |output| matches!(output, EngineOutput::BestMove { .. }),
+                timeout,
+            )
+            .await?;
+        match output {
+            EngineOutput::BestMove { best: Some(mv), .. } => Ok(mv),
+            _ => Err(anyhow::anyhow!("Engine did not send a valid best move")),
+        }
+    }
+
+    /// Search with the given limits for the best `lines` moves, returning
+    /// each with its score and expected continuation, best first. The engine
+    /// keeps showing that many lines until this is called again.
+    pub async fn analyse(
+        &mut self,
+        params: &GoParams,
+        lines: usize,
+    ) -> anyhow::Result<Vec<SearchLine>> {
+        // an engine without MultiPV can still show its single best line
+        if lines != 1 || options::find(&self.options, "MultiPV").is_ok() {
+            self.set_option("MultiPV", &lines.to_string()).await?;
+        }
+        self.go(params).await?;
+        let timeout = params
+            .max_search_time()
+            .map(|search_time| search_time + SEARCH_GRACE);
+        let mut infos = Vec::new();
+        self.wait_for(
+            |output| match output {
+                EngineOutput::Info(info) => {
+                    infos.push(info.clone());
+                    false
+                }
+                output => matches!(output, EngineOutput::BestMove { .. }),
+            },
+            timeout,
+        )
+        .await?;
+        Ok(info::ranked_lines(&infos))
+    }
+
+    /// Skip the engine's output until `done` matches a line, failing if that
+    /// takes longer than `timeout`.
+    async fn wait_for(
+        &mut self,
+        mut done: impl FnMut(&EngineOutput) -> bool,
+        timeout: Option<Duration>,
+    ) -> anyhow::Result<EngineOutput> {
+        let wait = async {
+            while let Some(output) = self.output.recv().await {
+                if done(&output) {
+                    return Ok(output);
+                }
+            }
+            Err(anyhow::anyhow!("Engine exited before replying"))
+        };
+        match timeout {
+            Some(timeout) => tokio::time::timeout(timeout, wait)
+                .await
+                .map_err(|_| anyhow::anyhow!("Engine did not reply within {timeout:?}"))?,
+            None => wait.await,
+        }
+    }
+
+    async fn send(&mut self, command: &str) -> anyhow::Result<()> {
+        self.stdin
+            .write_all(format!("{command}\n").as_bytes())
+            .await?;
+        self.stdin.flush().await?;
+        tracing::debug!(target: TRAFFIC_TARGET, "> {command}");
+        Ok(())
+    }
+
+    /// Ask the engine to quit and wait for it to exit.
+    pub async fn quit(mut self) -> anyhow::Result<()> {
+        self.send("quit").await?;
+        self.child.wait().await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,213 @@
+use crate::clock::Clock;
+use crate::core::ChessColour;
+use std::fmt::{Display, Formatter};
+use std::time::Duration;
+
+/// The limits for a UCI `go` command, built up from [`GoParams::new`]:
+///
+/// ```ignore
+/// let params = GoParams::new().depth(12).movetime(Duration::from_secs(2));
+/// ```
+///
+/// With no limits at all the engine decides for itself when to stop.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+pub struct GoParams {
+    depth: Option<u32>,
+    nodes: Option<u64>,
+    movetime: Option<Duration>,
+    infinite: bool,
+    ponder: bool,
+    wtime: Option<Duration>,
+    btime: Option<Duration>,
+    winc: Option<Duration>,
+    binc: Option<Duration>,
+    movestogo: Option<u32>,
+}
+
+impl GoParams {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Search this many half moves deep.
+    pub fn depth(self, depth: u32) -> Self {
+        Self {
+            depth: Some(depth),
+            ..self
+        }
+    }
+
+    /// Search this many positions.
+    pub fn nodes(self, nodes: u64) -> Self {
+        Self {
+            nodes: Some(nodes),
+            ..self
+        }
+    }
+
+    /// Search for exactly this long.
+    pub fn movetime(self, movetime: Duration) -> Self {
+        Self {
+            movetime: Some(movetime),
+            ..self
+        }
+    }
+
+    /// Search until told to stop.
+    pub fn infinite(self) -> Self {
+        Self {
+            infinite: true,
+            ..self
+        }
+    }
+
+    /// Search the position as if the expected reply had been played, until
+    /// told whether it was (`ponderhit`) or not (`stop`).
+    pub fn ponder(self) -> Self {
+        Self {
+            ponder: true,
+            ..self
+        }
+    }
+
+    /// Time left on each side's clock, for the engine to manage its own time.
+    pub fn time_left(self, wtime: Duration, btime: Duration) -> Self {
+        Self {
+            wtime: Some(wtime),
+            btime: Some(btime),
+            ..self
+        }
+    }
+
+    /// Time each side gains per move.
+    pub fn increment(self, winc: Duration, binc: Duration) -> Self {
+        Self {
+            winc: Some(winc),
+            binc: Some(binc),
+            ..self
+        }
+    }
+
+    /// Moves until the next time control.
+    pub fn movestogo(self, movestogo: u32) -> Self {
+        Self {
+            movestogo: Some(movestogo),
+            ..self
+        }
+    }
+
+    /// The time left and increments from a game's clock.
+    pub fn clock(self, clock: &Clock) -> Self {
+        let increment = clock.control().increment();
+        self.time_left(
+            clock.remaining(ChessColour::White),
+            clock.remaining(ChessColour::Black),
+        )
+        .increment(increment, increment)
+    }
+
+    pub fn depth_limit(&self) -> Option<u32> {
+        self.depth
+    }
+
+    pub fn movetime_limit(&self) -> Option<Duration> {
+        self.movetime
+    }
+
+    /// The time `colour` has left and gains per move, if searching with a
+    /// clock.
+    pub fn clock_for(&self, colour: ChessColour) -> Option<(Duration, Duration)> {
+        let (time, increment) = match colour {
+            ChessColour::White => (self.wtime, self.winc),
+            ChessColour::Black => (self.btime, self.binc),
+        };
+        Some((time?, increment.unwrap_or_default()))
+    }
+
+    pub fn is_infinite(&self) -> bool {
+        self.infinite
+    }
+
+    pub fn is_ponder(&self) -> bool {
+        self.ponder
+    }
+
+    /// The longest the search can be expected to take, or `None` if only a
+    /// depth or node limit (or nothing) bounds it. Pondering lasts until the
+    /// engine is told the outcome, so has no limit either.
+    pub fn max_search_time(&self) -> Option<Duration> {
+        if self.infinite || self.ponder {
+            return None;
+        }
+        // the engine can't think for longer than it has left on its clock
+        let clock = match (self.wtime, self.btime) {
+            (Some(wtime), Some(btime)) => Some(wtime.max(btime)),
+            (time, None) | (None, time) => time,
+        };
+        match (self.movetime, clock) {
+            (Some(movetime), Some(clock)) => Some(movetime.min(clock)),
+            (movetime, None) | (None, movetime) => movetime,
+        }
+    }
+}
+
+impl Display for GoParams {
+    /// Writes the full `go` command.
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "go")?;
+        if self.infinite {
+            write!(f, " infinite")?;
+        }
+        if self.ponder {
+            write!(f, " ponder")?;
+        }
+        let millis = |time: Option<Duration>| time.map(|time| time.as_millis() as u64);
+        let params = [
+            ("depth", self.depth.map(u64::from)),
+            ("nodes", self.nodes),
+            ("movetime", millis(self.movetime)),
+            ("wtime", millis(self.wtime)),
+            ("btime", millis(self.btime)),
+            ("winc", millis(self.winc)),
+            ("binc", millis(self.binc)),
+            ("movestogo", self.movestogo.map(u64::from)),
+        ];
+        for (name, value) in params {
+            if let Some(value) = value {
+                write!(f, " {name} {value}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::clock::TimeControl;
+
+    #[test]
+    fn test_go_command() {
+        assert_eq!(GoParams::new().to_string(), "go");
+        assert_eq!(
+            GoParams::new()
+                .depth(8)
+                .movetime(Duration::from_secs(2))
+                .to_string(),
+            "go depth 8 movetime 2000"
+        );
+        assert_eq!(GoParams::new().infinite().to_string(), "go infinite");
+
+        let clock = Clock::new("3+2".parse::<TimeControl>().unwrap());
+        let params = GoParams::new().clock(&clock).movestogo(10);
+        assert_eq!(
+            params.to_string(),
+            "go wtime 180000 btime 180000 winc 2000 binc 2000 movestogo 10"
+        );
+        assert_eq!(params.max_search_time(), Some(Duration::from_secs(180)));
+        assert_eq!(GoParams::new().depth(3).max_search_time(), None);
+        let ponder = params.ponder();
+        assert!(ponder.to_string().starts_with("go ponder wtime 180000"));
+        assert_eq!(ponder.max_search_time(), None);
+    }
+}
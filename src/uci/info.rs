@@ -0,0 +1,275 @@
+use crate::core::Move;
+use crate::solver::{Score, SearchLine};
+use std::fmt::{Display, Formatter};
+use std::time::Duration;
+
+/// What an engine reports about its search in an `info` line. Engines only send
+/// what has changed, so any field may be missing.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct AnalysisInfo {
+    pub depth: Option<u32>,
+    /// The deepest any line was searched, counting extensions.
+    pub seldepth: Option<u32>,
+    /// Which of the best lines this is, from 1, when showing several.
+    pub multipv: Option<u32>,
+    /// How good the position is for the side to move.
+    pub score: Option<Score>,
+    pub nodes: Option<u64>,
+    /// Nodes searched per second.
+    pub nps: Option<u64>,
+    /// How long the engine has been searching.
+    pub time: Option<Duration>,
+    /// The line the engine expects to be played.
+    pub pv: Vec<Move>,
+    /// Free text from `info string`, which runs to the end of the line.
+    pub string: Option<String>,
+}
+
+impl AnalysisInfo {
+    /// Parse an `info` line, with or without the leading `info`. Anything not
+    /// understood is skipped.
+    pub fn parse(line: &str) -> Self {
+        let mut info = Self::default();
+        let line = line.trim();
+        let line = line.strip_prefix("info").unwrap_or(line).trim_start();
+        let mut words = line.split_whitespace().peekable();
+        while let Some(word) = words.next() {
+            let mut number = || words.next().and_then(|value| value.parse::<u64>().ok());
+            match word {
+                "depth" => info.depth = number().and_then(|n| n.try_into().ok()),
+                "seldepth" => info.seldepth = number().and_then(|n| n.try_into().ok()),
+                "multipv" => info.multipv = number().and_then(|n| n.try_into().ok()),
+                "nodes" => info.nodes = number(),
+                "nps" => info.nps = number(),
+                "time" => info.time = number().map(Duration::from_millis),
+                "score" => {
+                    info.score = match (words.next(), words.next().and_then(|v| v.parse().ok())) {
+                        (Some("cp"), Some(cp)) => Some(Score::Centipawns(cp)),
+                        (Some("mate"), Some(moves)) => Some(Score::Mate(moves)),
+                        _ => None,
+                    };
+                    // whether the score is only a bound isn't kept
+                    words.next_if(|w| *w == "lowerbound" || *w == "upperbound");
+                }
+                "pv" => {
+                    while let Some(mv) = words.next_if(|w| Move::from_uci(w).is_ok()) {
+                        info.pv.push(Move::from_uci(mv).unwrap());
+                    }
+                }
+                "string" => {
+                    let start = line.find("string").unwrap_or_default() + "string".len();
+                    info.string = Some(line[start..].trim().to_string());
+                    break;
+                }
+                // e.g. currmove, hashfull and tbhits, which all take one value
+                _ => {
+                    words.next();
+                }
+            }
+        }
+        info
+    }
+}
+
+/// The lines from a MultiPV search's `info` output, best first. Each is the
+/// latest report with a score and a line for its `multipv` number, so a search
+/// cut off mid-depth mixes lines from the last two depths.
+pub fn ranked_lines<'a>(infos: impl IntoIterator<Item = &'a AnalysisInfo>) -> Vec<SearchLine> {
+    let mut lines: Vec<(u32, SearchLine)> = Vec::new();
+    for info in infos {
+        let Some(score) = info.score else { continue };
+        if info.pv.is_empty() {
+            continue;
+        }
+        let rank = info.multipv.unwrap_or(1);
+        let line = SearchLine {
+            score,
+            principal_variation: info.pv.clone(),
+        };
+        match lines.iter_mut().find(|(r, _)| *r == rank) {
+            Some((_, existing)) => *existing = line,
+            None => lines.push((rank, line)),
+        }
+    }
+    lines.sort_by_key(|(rank, _)| *rank);
+    lines.into_iter().map(|(_, line)| line).collect()
+}
+
+/// Stockfish's static evaluation of a position, from its non-standard `eval`
+/// command, in centipawns from White's point of view.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct StaticEval {
+    /// The evaluation a search would use, or `None` if the side to move is
+    /// in check, which Stockfish doesn't evaluate.
+    pub total: Option<i32>,
+    /// The evaluations the total is made from, by name, e.g. `NNUE` and
+    /// `Classical` for versions that blend the two.
+    pub terms: Vec<(String, i32)>,
+}
+
+impl StaticEval {
+    /// Parse the output of `eval`, which ends with e.g.
+    /// `Final evaluation       +0.09 (white side) [with scaled NNUE, ...]`.
+    /// Returns `None` if there is no final evaluation, as from an engine
+    /// without the command.
+    pub fn parse<'a>(lines: impl IntoIterator<Item = &'a str>) -> Option<Self> {
+        let mut eval = Self::default();
+        let mut found = false;
+        for line in lines {
+            let Some((name, value)) = line.trim().split_once(" evaluation") else {
+                continue;
+            };
+            let value = value.trim_start_matches(':').split_whitespace().next();
+            // in pawns, e.g. +0.09
+            let centipawns = value
+                .and_then(|value| value.parse::<f64>().ok())
+                .map(|pawns| (pawns * 100.0).round() as i32);
+            match name {
+                // older versions call it the total
+                "Final" | "Total" => {
+                    eval.total = centipawns;
+                    found = true;
+                }
+                _ => {
+                    if let Some(centipawns) = centipawns {
+                        eval.terms.push((name.to_string(), centipawns));
+                    }
+                }
+            }
+        }
+        found.then_some(eval)
+    }
+}
+
+impl Display for AnalysisInfo {
+    /// Writes the line as an engine would send it, including the `info`.
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "info")?;
+        let numbers = [
+            ("depth", self.depth.map(u64::from)),
+            ("seldepth", self.seldepth.map(u64::from)),
+            ("multipv", self.multipv.map(u64::from)),
+        ];
+        for (name, value) in numbers {
+            if let Some(value) = value {
+                write!(f, " {name} {value}")?;
+            }
+        }
+        match self.score {
+            Some(Score::Centipawns(cp)) => write!(f, " score cp {cp}")?,
+            Some(Score::Mate(moves)) => write!(f, " score mate {moves}")?,
+            None => {}
+        }
+        let numbers = [
+            ("nodes", self.nodes),
+            ("nps", self.nps),
+            ("time", self.time.map(|time| time.as_millis() as u64)),
+        ];
+        for (name, value) in numbers {
+            if let Some(value) = value {
+                write!(f, " {name} {value}")?;
+            }
+        }
+        if !self.pv.is_empty() {
+            write!(f, " pv")?;
+            for mv in &self.pv {
+                write!(f, " {}", mv.to_uci())?;
+            }
+        }
+        if let Some(string) = &self.string {
+            write!(f, " string {string}")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_info() {
+        let info = AnalysisInfo::parse(
+            "info depth 12 seldepth 17 multipv 1 score cp -35 upperbound nodes 52431 nps 1048620 hashfull 12 tbhits 0 time 50 pv e7e5 g1f3 b8c6",
+        );
+        assert_eq!(
+            info,
+            AnalysisInfo {
+                depth: Some(12),
+                seldepth: Some(17),
+                multipv: Some(1),
+                score: Some(Score::Centipawns(-35)),
+                nodes: Some(52431),
+                nps: Some(1048620),
+                time: Some(Duration::from_millis(50)),
+                pv: ["e7e5", "g1f3", "b8c6"]
+                    .iter()
+                    .map(|mv| Move::from_uci(mv).unwrap())
+                    .collect(),
+                string: None,
+            }
+        );
+
+        let mate = AnalysisInfo::parse("info depth 3 score mate -2");
+        assert_eq!(mate.score, Some(Score::Mate(-2)));
+        let string = AnalysisInfo::parse("info string NNUE evaluation enabled");
+        assert_eq!(string.string.as_deref(), Some("NNUE evaluation enabled"));
+    }
+
+    #[test]
+    fn test_ranked_lines() {
+        let infos = [
+            "info depth 1 multipv 1 score cp 30 pv e2e4",
+            "info depth 1 multipv 2 score cp 20 pv d2d4",
+            "info depth 2 multipv 2 score cp 40 pv g1f3 g8f6",
+            "info depth 2 currmove c2c4 currmovenumber 3",
+            "info depth 2 multipv 1 score cp 35 pv e2e4 e7e5",
+        ]
+        .map(AnalysisInfo::parse);
+        let lines = ranked_lines(&infos);
+        let first_moves: Vec<_> = lines
+            .iter()
+            .map(|line| line.principal_variation[0].to_uci())
+            .collect();
+        assert_eq!(first_moves, ["e2e4", "g1f3"]);
+        assert_eq!(lines[0].score, Score::Centipawns(35));
+        assert_eq!(lines[1].principal_variation.len(), 2);
+        assert!(ranked_lines(&[]).is_empty());
+    }
+
+    #[test]
+    fn test_parse_static_eval() {
+        let output = "
+ NNUE network contributions (White to move)
++------------+------------+------------+------------+
+|   Bucket   |  Material  | Positional |   Total    |
++------------+------------+------------+------------+
+|  7         |   +0.00    |   +0.16    |   +0.16    | <-- this bucket is used
++------------+------------+------------+------------+
+
+Classical evaluation   +0.08 (white side)
+NNUE evaluation        +0.16 (white side)
+Final evaluation       +0.13 (white side) [with scaled NNUE, hybrid, ...]
+readyok";
+        let eval = StaticEval::parse(output.lines()).unwrap();
+        assert_eq!(eval.total, Some(13));
+        assert_eq!(
+            eval.terms,
+            [("Classical".to_string(), 8), ("NNUE".to_string(), 16)]
+        );
+
+        let in_check = StaticEval::parse(["Final evaluation: none (in check)"]).unwrap();
+        assert_eq!(in_check.total, None);
+        let old = StaticEval::parse(["Total evaluation: -1.25 (white side)"]).unwrap();
+        assert_eq!(old.total, Some(-125));
+        assert_eq!(StaticEval::parse(["readyok"]), None);
+    }
+
+    #[test]
+    fn test_display_round_trips() {
+        let line = "info depth 5 multipv 2 score mate 3 nodes 1000 time 20 pv a1a8 g8h7";
+        let info = AnalysisInfo::parse(line);
+        assert_eq!(info.to_string(), line);
+        assert_eq!(AnalysisInfo::parse(&info.to_string()), info);
+    }
+}
@@ -0,0 +1,750 @@
+//! The UCI protocol: a client for engines such as Stockfish, and a server
+//! that lets chess GUIs play the solver.
+
+#[cfg(feature = "async")]
+pub mod async_engine;
+pub mod go;
+pub mod info;
+pub mod options;
+pub mod server;
+
+use crate::clock::Clock;
+use crate::config::EngineConfig;
+use crate::core::Move;
+use crate::engine::{
+    EngineClient, EngineError, EngineProcess, EngineReply, RESPONSE_TIMEOUT, SEARCH_GRACE, is_exit,
+};
+use crate::game::ChessGame;
+use crate::solver::SearchLine;
+use crate::variant::Variant;
+use anyhow::Context;
+use go::GoParams;
+use info::{AnalysisInfo, StaticEval};
+use options::EngineOption;
+use std::fmt;
+use std::sync::mpsc::Receiver;
+use std::time::{Duration, Instant};
+
+/// A line of output from a UCI engine.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EngineOutput {
+    /// `id name ...` or `id author ...`, with the `id` removed.
+    Id(String),
+    UciOk,
+    ReadyOk,
+    /// The engine's move, which is `None` if it has none or sent one that
+    /// couldn't be parsed, and the reply it expects.
+    BestMove {
+        best: Option<Move>,
+        ponder: Option<Move>,
+    },
+    /// What the engine is thinking.
+    Info(AnalysisInfo),
+    /// An option the engine supports.
+    Option(EngineOption),
+    Other(String),
+}
+
+impl EngineOutput {
+    pub fn parse(line: &str) -> Self {
+        let line = line.trim();
+        let (command, rest) = line.split_once(' ').unwrap_or((line, ""));
+        let rest = rest.trim_start().to_string();
+        match command {
+            "id" => EngineOutput::Id(rest),
+            "uciok" => EngineOutput::UciOk,
+            "readyok" => EngineOutput::ReadyOk,
+            "bestmove" => {
+                let mut words = rest.split_whitespace();
+                let best = words.next().and_then(|mv| Move::from_uci(mv).ok());
+                let ponder = match (words.next(), words.next()) {
+                    (Some("ponder"), Some(mv)) => Move::from_uci(mv).ok(),
+                    _ => None,
+                };
+                EngineOutput::BestMove { best, ponder }
+            }
+            "info" => EngineOutput::Info(AnalysisInfo::parse(&rest)),
+            "option" => match EngineOption::parse(&rest) {
+                Some(option) => EngineOutput::Option(option),
+                None => EngineOutput::Other(line.to_string()),
+            },
+            _ => EngineOutput::Other(line.to_string()),
+        }
+    }
+}
+
+/// The `position` command for `game`: where it started and the moves played
+/// since, rather than just the current FEN, so the engine knows the history
+/// for repetitions and the fifty move rule.
+pub fn position_command(game: &ChessGame) -> String {
+    let mut command = match game.start_fen() {
+        Some(fen) => format!("position fen {fen}"),
+        None => "position startpos".to_string(),
+    };
+    if !game.played_moves().is_empty() {
+        command.push_str(" moves");
+        for mv in game.played_moves() {
+            command.push(' ');
+            command.push_str(&mv.to_uci());
+        }
+    }
+    command
+}
+
+type InfoHandler = Box<dyn FnMut(&AnalysisInfo) + Send>;
+
+pub struct UciEngine {
+    /// How the engine was started, to start it again after it crashes.
+    config: EngineConfig,
+    process: EngineProcess,
+    info_handler: Option<InfoHandler>,
+    /// The options the engine advertised during the handshake.
+    options: Vec<EngineOption>,
+    /// The latest value of each option set, to replay after a restart.
+    settings: Vec<(String, String)>,
+    /// The last `position` command sent, to replay after a restart.
+    position: Option<String>,
+    /// Whether a search that finds the engine has exited restarts it and tries
+    /// again.
+    auto_restart: bool,
+    /// Whether an infinite search is running, waiting for [`UciEngine::stop`],
+    /// or the engine is pondering.
+    searching: bool,
+    /// The reply the engine expected to its last best move.
+    ponder_move: Option<Move>,
+    /// The move the engine is pondering on, while it ponders.
+    pondering: Option<Move>,
+}
+
+impl Default for UciEngine {
+    fn default() -> Self {
+        Self::new("stockfish").expect("could not start stockfish binary")
+    }
+}
+
+impl UciEngine {
+    pub fn new(stockfish_exec: &str) -> anyhow::Result<Self> {
+        Self::from_config(&EngineConfig::from_path(stockfish_exec))
+    }
+
+    /// Start the engine `config` describes and set its options.
+    pub fn from_config(config: &EngineConfig) -> anyhow::Result<Self> {
+        let mut res = Self {
+            config: config.clone(),
+            process: EngineProcess::spawn(config)?,
+            info_handler: None,
+            options: Vec::new(),
+            settings: Vec::new(),
+            position: None,
+            auto_restart: false,
+            searching: false,
+            ponder_move: None,
+            pondering: None,
+        };
+        res.handshake()?;
+        for (name, value) in &config.options {
+            res.set_option(name, &value.to_string())
+                .with_context(|| format!("could not set {name} from the engine's config"))?;
+        }
+        Ok(res)
+    }
+
+    fn handshake(&mut self) -> anyhow::Result<()> {
+        let handshake = self.command(format_args!("uci\n"), "uciok", Some(RESPONSE_TIMEOUT))?;
+        self.options = handshake
+            .iter()
+            .filter_map(|line| match EngineOutput::parse(line) {
+                EngineOutput::Option(option) => Some(option),
+                _ => None,
+            })
+            .collect();
+        Ok(())
+    }
+
+    /// Start a fresh engine in place of this one, e.g. after it crashed,
+    /// setting the same options and position so the game can carry on.
+    pub fn restart(&mut self) -> anyhow::Result<()> {
+        tracing::warn!("Restarting the engine");
+        // the old engine has most likely exited already
+        self.process.kill();
+        self.searching = false;
+        self.pondering = None;
+        self.process = EngineProcess::spawn(&self.config)?;
+        self.handshake()?;
+        for (name, value) in std::mem::take(&mut self.settings) {
+            self.set_option(&name, &value)?;
+        }
+        self.new_game()?;
+        if let Some(position) = self.position.clone() {
+            self.command_without_response(format_args!("{position}\n"))?;
+        }
+        self.is_ready()
+    }
+
+    /// Whether to restart the engine and search again when it turns out to
+    /// have exited during a search, rather than failing.
+    pub fn set_auto_restart(&mut self, enabled: bool) {
+        self.auto_restart = enabled;
+    }
+
+    /// Call `handler` with each `info` line the engine sends while it searches,
+    /// to show what it is thinking.
+    pub fn set_info_handler(&mut self, handler: impl FnMut(&AnalysisInfo) + Send + 'static) {
+        self.info_handler = Some(Box::new(handler));
+    }
+
+    /// Receive each `info` line the engine sends while it searches, e.g. on
+    /// another thread to draw the engine's thoughts as they arrive. Replaces
+    /// any info handler, and the lines stop once the receiver is dropped.
+    pub fn info_channel(&mut self) -> Receiver<AnalysisInfo> {
+        let (sender, receiver) = std::sync::mpsc::channel();
+        self.set_info_handler(move |info| {
+            sender.send(info.clone()).ok();
+        });
+        receiver
+    }
+
+    /// The options the engine supports.
+    pub fn options(&self) -> &[EngineOption] {
+        &self.options
+    }
+
+    /// Set one of the engine's options, checking first that it has the option
+    /// and that `value` suits it. Buttons are pressed with an empty value.
+    pub fn set_option(&mut self, name: &str, value: &str) -> anyhow::Result<()> {
+        let option = options::find(&self.options, name)?;
+        option.validate(value)?;
+        let name = option.name.clone();
+        if value.is_empty() {
+            return self.command_without_response(format_args!("setoption name {name}\n"));
+        }
+        self.command_without_response(format_args!("setoption name {name} value {value}\n"))?;
+        self.settings.retain(|(setting, _)| *setting != name);
+        self.settings.push((name, value.to_string()));
+        Ok(())
+    }
+
+    pub fn skill_level(&mut self, level: u8) -> anyhow::Result<()> {
+        if level > 20 {
+            return Err(anyhow::anyhow!("Skill level must be between 0 and 20"));
+        }
+
+        self.set_option("Skill Level", &level.to_string())
+    }
+
+    /// Weaken the engine to play at about `elo`, see [`options::elo_settings`].
+    pub fn limit_elo(&mut self, elo: u32) -> anyhow::Result<()> {
+        for (name, value) in options::elo_settings(&self.options, elo)? {
+            self.set_option(&name, &value)?;
+        }
+        Ok(())
+    }
+
+    /// Tell the engine whether to play Chess960, which also switches castling
+    /// moves to the king-takes-rook notation.
+    pub fn chess960(&mut self, enabled: bool) -> anyhow::Result<()> {
+        self.set_option("UCI_Chess960", &enabled.to_string())
+    }
+
+    pub fn is_ready(&mut self) -> anyhow::Result<()> {
+        self.command(format_args!("isready\n"), "readyok", Some(RESPONSE_TIMEOUT))?;
+        Ok(())
+    }
+
+    pub fn position(&mut self, fen: &str) -> anyhow::Result<()> {
+        self.send_position(format!("position fen {fen}"))
+    }
+
+    /// Set the position to `game`'s, sending its moves as well, see
+    /// [`position_command`].
+    pub fn game_position(&mut self, game: &ChessGame) -> anyhow::Result<()> {
+        self.send_position(position_command(game))
+    }
+
+    fn send_position(&mut self, position: String) -> anyhow::Result<()> {
+        self.command_without_response(format_args!("{position}\n"))?;
+        self.position = Some(position);
+        Ok(())
+    }
+
+    pub fn new_game(&mut self) -> anyhow::Result<()> {
+        self.command_without_response(format_args!("ucinewgame\n"))?;
+        Ok(())
+    }
+
+    pub fn best_move(&mut self) -> anyhow::Result<Move> {
+        self.go(&GoParams::new().movetime(Duration::from_millis(1000)))
+    }
+
+    /// Search with the game's clock, letting the engine manage its own time.
+    pub fn best_move_with_clock(&mut self, clock: &Clock) -> anyhow::Result<Move> {
+        self.go(&GoParams::new().clock(clock))
+    }
+
+    /// Search with the given limits and wait for the engine's best move. An
+    /// infinite search would never return, so start those with
+    /// [`UciEngine::go_infinite`] instead.
+    pub fn go(&mut self, params: &GoParams) -> anyhow::Result<Move> {
+        let output = self.search(params)?;
+        self.read_best_move(&output)
+    }
+
+    /// The reply the engine expected to the best move it last sent, if it
+    /// said, which is what [`UciEngine::start_pondering`] thinks about.
+    pub fn ponder_move(&self) -> Option<Move> {
+        self.ponder_move
+    }
+
+    /// Have the engine think on the opponent's time, assuming they reply to
+    /// `game`'s last move with [`UciEngine::ponder_move`]. `params` should
+    /// describe the clock as it will be once the reply is made. Returns false,
+    /// without pondering, if the engine didn't expect a reply. Finish with
+    /// [`UciEngine::finish_pondering`] once the opponent has moved.
+    pub fn start_pondering(&mut self, game: &ChessGame, params: &GoParams) -> anyhow::Result<bool> {
+        if self.searching {
+            return Err(anyhow::anyhow!("The engine is already searching"));
+        }
+        let Some(expected) = self.ponder_move.take() else {
+            return Ok(false);
+        };
+        let mut position = position_command(game);
+        if game.played_moves().is_empty() {
+            position.push_str(" moves");
+        }
+        self.command_without_response(format_args!("{position} {}\n", expected.to_uci()))?;
+        self.command_without_response(format_args!("{}\n", params.clone().ponder()))?;
+        self.searching = true;
+        self.pondering = Some(expected);
+        Ok(true)
+    }
+
+    pub fn is_pondering(&self) -> bool {
+        self.pondering.is_some()
+    }
+
+    /// Get the engine's reply to the opponent's move, the last one in `game`.
+    /// If it was the move pondered on the engine carries on with the search it
+    /// already started, limited by `params`. Otherwise that search is thrown
+    /// away and the engine starts again from `game`.
+    pub fn finish_pondering(
+        &mut self,
+        game: &ChessGame,
+        params: &GoParams,
+    ) -> anyhow::Result<Move> {
+        let Some(expected) = self.pondering.take() else {
+            return Err(anyhow::anyhow!("The engine is not pondering"));
+        };
+        self.searching = false;
+        if game.played_moves().last() == Some(&expected) {
+            tracing::debug!("Ponder hit on {expected}");
+            self.position = Some(position_command(game));
+            let timeout = params
+                .max_search_time()
+                .map(|search_time| search_time + SEARCH_GRACE);
+            let output = self.command(format_args!("ponderhit\n"), "bestmove", timeout)?;
+            return self.read_best_move(&output);
+        }
+        tracing::debug!("Ponder miss, expected {expected}");
+        self.command(format_args!("stop\n"), "bestmove", Some(RESPONSE_TIMEOUT))?;
+        self.game_position(game)?;
+        self.go(params)
+    }
+
+    /// Search with the given limits for the best `lines` moves, returning
+    /// each with its score and expected continuation, best first. The engine
+    /// keeps showing that many lines until this is called again.
+    pub fn analyse(&mut self, params: &GoParams, lines: usize) -> anyhow::Result<Vec<SearchLine>> {
+        // an engine without MultiPV can still show its single best line
+        if lines != 1 || options::find(&self.options, "MultiPV").is_ok() {
+            self.set_option("MultiPV", &lines.to_string())?;
+        }
+        let output = self.search(params)?;
+        let infos: Vec<_> = output
+            .iter()
+            .filter_map(|line| match EngineOutput::parse(line) {
+                EngineOutput::Info(info) => Some(info),
+                _ => None,
+            })
+            .collect();
+        Ok(info::ranked_lines(&infos))
+    }
+
+    /// Stockfish's static evaluation of the current position, without
+    /// searching, from its `eval` command. Other engines don't have the
+    /// command, so this fails for them.
+    pub fn static_eval(&mut self) -> anyhow::Result<StaticEval> {
+        if self.searching {
+            return Err(anyhow::anyhow!("The engine is already searching"));
+        }
+        // eval has no closing line, so ask whether the engine is ready to
+        // know when it has finished
+        let output = self.command(
+            format_args!("eval\nisready\n"),
+            "readyok",
+            Some(RESPONSE_TIMEOUT),
+        )?;
+        StaticEval::parse(output.iter().map(String::as_str))
+            .context("The engine did not send an evaluation, only Stockfish supports eval")
+    }
+
+    /// Start searching the current position until [`UciEngine::stop`] is
+    /// called, e.g. to analyse while waiting for the user's move. Nothing else
+    /// can be sent to the engine until then.
+    pub fn go_infinite(&mut self) -> anyhow::Result<()> {
+        if self.searching {
+            return Err(anyhow::anyhow!("The engine is already searching"));
+        }
+        self.command_without_response(format_args!("{}\n", GoParams::new().infinite()))?;
+        self.searching = true;
+        Ok(())
+    }
+
+    /// The `info` lines the engine has sent so far during an infinite search
+    /// or while pondering, waiting up to `wait` for the first if there are
+    /// none yet. Each also goes to the info handler. Call this regularly,
+    /// e.g. once a frame, to show the analysis while it runs.
+    pub fn poll_info(&mut self, wait: Duration) -> anyhow::Result<Vec<AnalysisInfo>> {
+        if !self.searching {
+            return Err(anyhow::anyhow!("The engine is not searching"));
+        }
+        let mut infos = Vec::new();
+        let mut timeout = wait;
+        loop {
+            let line = match self
+                .process
+                .next_line(Instant::now(), Some(timeout), "info")
+            {
+                Ok(line) => line,
+                Err(EngineError::Timeout { .. }) => break,
+                Err(error) => return Err(error.into()),
+            };
+            if let EngineOutput::Info(info) = EngineOutput::parse(&line) {
+                if let Some(handler) = &mut self.info_handler {
+                    handler(&info);
+                }
+                infos.push(info);
+            }
+            // only wait for the first line, then take what has already arrived
+            timeout = Duration::ZERO;
+        }
+        Ok(infos)
+    }
+
+    /// Stop the search started by [`UciEngine::go_infinite`] and return the best
+    /// move found so far. The engine's `info` lines since the search started
+    /// go to the info handler first.
+    pub fn stop(&mut self) -> anyhow::Result<Move> {
+        if !self.searching {
+            return Err(anyhow::anyhow!("The engine is not searching"));
+        }
+        self.searching = false;
+        let output = self.command(format_args!("stop\n"), "bestmove", Some(RESPONSE_TIMEOUT))?;
+        self.read_best_move(&output)
+    }
+
+    /// The best move from the output of a search, remembering the reply the
+    /// engine expects to ponder on.
+    fn read_best_move(&mut self, output: &[String]) -> anyhow::Result<Move> {
+        // the last line is e.g. "bestmove c2c4 ponder e7e5"
+        let res = output.last().map(String::as_str).unwrap_or_default();
+        match EngineOutput::parse(res) {
+            EngineOutput::BestMove {
+                best: Some(mv),
+                ponder,
+            } => {
+                self.ponder_move = ponder;
+                Ok(mv)
+            }
+            _ => Err(anyhow::anyhow!(
+                "Failed to parse best move from Stockfish response: {}",
+                res
+            )),
+        }
+    }
+
+    /// Run a search, returning everything the engine sent up to its best move.
+    fn search(&mut self, params: &GoParams) -> anyhow::Result<Vec<String>> {
+        if params.is_infinite() {
+            return Err(anyhow::anyhow!(
+                "An infinite search would never return its best move, use go_infinite"
+            ));
+        }
+        if params.is_ponder() {
+            return Err(anyhow::anyhow!(
+                "Pondering would never return its best move, use start_pondering"
+            ));
+        }
+        if self.searching {
+            return Err(anyhow::anyhow!("The engine is already searching"));
+        }
+        let timeout = params
+            .max_search_time()
+            .map(|search_time| search_time + SEARCH_GRACE);
+        match self.command(format_args!("{params}\n"), "bestmove", timeout) {
+            Err(error) if self.auto_restart && is_exit(&error) => {
+                self.restart()?;
+                self.command(format_args!("{params}\n"), "bestmove", timeout)
+            }
+            res => res,
+        }
+    }
+
+    fn command_without_response(&mut self, command: fmt::Arguments) -> anyhow::Result<()> {
+        Ok(self.process.send(command)?)
+    }
+
+    /// Send `command` and collect the engine's output up to and including the
+    /// line starting with `terminator`, failing if that takes over `timeout`.
+    fn command(
+        &mut self,
+        command: fmt::Arguments,
+        terminator: &str,
+        timeout: Option<Duration>,
+    ) -> anyhow::Result<Vec<String>> {
+        self.command_without_response(command)?;
+
+        tracing::debug!("Reading response from Stockfish...");
+        let sent = Instant::now();
+        let mut output = vec![];
+        loop {
+            let line = self.process.next_line(sent, timeout, terminator)?;
+            if let Some(handler) = &mut self.info_handler
+                && let EngineOutput::Info(info) = EngineOutput::parse(&line)
+            {
+                handler(&info);
+            }
+            let line = line.trim().to_string();
+            let done = line.split_whitespace().next() == Some(terminator);
+            output.push(line);
+            if done {
+                break;
+            }
+        }
+
+        tracing::debug!("Received response from Stockfish: {}", output.join("\n"));
+        Ok(output)
+    }
+}
+
+impl EngineClient for UciEngine {
+    fn set_variant(&mut self, variant: Variant) -> anyhow::Result<()> {
+        // engines that only play standard chess don't have the option
+        if variant == Variant::Standard && options::find(&self.options, "UCI_Chess960").is_err() {
+            return Ok(());
+        }
+        self.chess960(variant == Variant::Chess960)
+    }
+
+    fn new_game(&mut self) -> anyhow::Result<()> {
+        UciEngine::new_game(self)?;
+        self.is_ready()
+    }
+
+    fn play(&mut self, game: &ChessGame, params: &GoParams) -> anyhow::Result<EngineReply> {
+        self.game_position(game)?;
+        let output = self.search(params)?;
+        let best_move = self.read_best_move(&output)?;
+        // the last score sent is from the deepest search
+        let score = output
+            .iter()
+            .rev()
+            .find_map(|line| match EngineOutput::parse(line) {
+                EngineOutput::Info(info) => info.score,
+                _ => None,
+            });
+        Ok(EngineReply { best_move, score })
+    }
+}
+
+impl Drop for UciEngine {
+    fn drop(&mut self) {
+        self.process.quit("quit");
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    #[cfg(unix)]
+    use crate::engine::fake_engine;
+
+    #[test]
+    fn test_parse_engine_output() {
+        assert_eq!(
+            EngineOutput::parse("bestmove e2e4 ponder e7e5\n"),
+            EngineOutput::BestMove {
+                best: Some(Move::from_uci("e2e4").unwrap()),
+                ponder: Some(Move::from_uci("e7e5").unwrap()),
+            }
+        );
+        assert_eq!(
+            EngineOutput::parse("bestmove (none)"),
+            EngineOutput::BestMove {
+                best: None,
+                ponder: None
+            }
+        );
+        assert_eq!(EngineOutput::parse("readyok"), EngineOutput::ReadyOk);
+        assert_eq!(
+            EngineOutput::parse("id name Stockfish 17"),
+            EngineOutput::Id("name Stockfish 17".to_string())
+        );
+        assert_eq!(
+            EngineOutput::parse("info depth 1 score cp 20"),
+            EngineOutput::Info(AnalysisInfo {
+                depth: Some(1),
+                score: Some(crate::solver::Score::Centipawns(20)),
+                ..Default::default()
+            })
+        );
+        assert_eq!(
+            EngineOutput::parse("Stockfish 17 by the Stockfish developers"),
+            EngineOutput::Other("Stockfish 17 by the Stockfish developers".to_string())
+        );
+    }
+
+    #[test]
+    fn test_position_command() {
+        let mut game = ChessGame::default();
+        assert_eq!(position_command(&game), "position startpos");
+        for mv in ["e2e4", "e7e5", "g1f3"] {
+            game.make_move(&Move::from_uci(mv).unwrap()).unwrap();
+        }
+        assert_eq!(
+            position_command(&game),
+            "position startpos moves e2e4 e7e5 g1f3"
+        );
+
+        let fen = "4k3/8/8/8/8/8/4P3/4K3 w - - 0 1";
+        let mut game = ChessGame::from_fen(fen, crate::variant::Variant::Standard).unwrap();
+        game.make_move(&Move::from_uci("e2e4").unwrap()).unwrap();
+        assert_eq!(
+            position_command(&game),
+            format!("position fen {fen} moves e2e4")
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_engine_exit() {
+        // an engine that finishes the handshake and then dies
+        let path = fake_engine("crashing-engine", "read line\necho uciok\nread line\n");
+        let mut engine = UciEngine::new(path.to_str().unwrap()).unwrap();
+        let error = engine.is_ready().unwrap_err();
+        assert!(is_exit(&error), "{error}");
+        std::fs::remove_file(&path).ok();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_stop_infinite_search() {
+        let path = fake_engine(
+            "analysing-engine",
+            "while read command rest; do
+                case $command in
+                    uci) echo uciok ;;
+                    go) echo 'info depth 1 score cp 15 pv d2d4' ;;
+                    stop) echo 'info depth 2 score cp 20 pv e2e4 e7e5'; echo 'bestmove e2e4' ;;
+                    quit) exit ;;
+                esac
+            done
+            ",
+        );
+        let mut engine = UciEngine::new(path.to_str().unwrap()).unwrap();
+        let depths = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let seen = depths.clone();
+        engine.set_info_handler(move |info| seen.lock().unwrap().extend(info.depth));
+
+        assert!(engine.stop().is_err());
+        engine.go_infinite().unwrap();
+        assert!(engine.go_infinite().is_err());
+        assert!(engine.best_move().is_err());
+        assert_eq!(engine.stop().unwrap(), Move::from_uci("e2e4").unwrap());
+        assert_eq!(*depths.lock().unwrap(), [1, 2]);
+        std::fs::remove_file(&path).ok();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_live_analysis() {
+        let path = fake_engine(
+            "live-engine",
+            "while read command rest; do
+                case $command in
+                    uci) echo uciok ;;
+                    go) echo 'info depth 1 score cp 15 pv d2d4'; echo 'info depth 2 score cp 20 pv e2e4 e7e5' ;;
+                    stop) echo 'info depth 3 score cp 25 pv e2e4 e7e5 g1f3'; echo 'bestmove e2e4' ;;
+                    quit) exit ;;
+                esac
+            done
+            ",
+        );
+        let mut engine = UciEngine::new(path.to_str().unwrap()).unwrap();
+        let infos = engine.info_channel();
+
+        assert!(engine.poll_info(Duration::ZERO).is_err());
+        engine.go_infinite().unwrap();
+        let mut polled = Vec::new();
+        while polled.len() < 2 {
+            polled.extend(engine.poll_info(RESPONSE_TIMEOUT).unwrap());
+        }
+        let depths: Vec<_> = polled.iter().filter_map(|info| info.depth).collect();
+        assert_eq!(depths, [1, 2]);
+        assert!(
+            engine
+                .poll_info(Duration::from_millis(50))
+                .unwrap()
+                .is_empty()
+        );
+        engine.stop().unwrap();
+
+        let depths: Vec<_> = infos.try_iter().filter_map(|info| info.depth).collect();
+        assert_eq!(depths, [1, 2, 3]);
+        std::fs::remove_file(&path).ok();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_pondering() {
+        let path = fake_engine(
+            "pondering-engine",
+            "while read command rest; do
+                case $command in
+                    uci) echo uciok ;;
+                    go) case $rest in ponder*) ;; *) echo 'bestmove e2e4 ponder e7e5' ;; esac ;;
+                    ponderhit) echo 'bestmove g1f3 ponder b8c6' ;;
+                    stop) echo 'bestmove a2a3' ;;
+                    quit) exit ;;
+                esac
+            done
+            ",
+        );
+        let mut engine = UciEngine::new(path.to_str().unwrap()).unwrap();
+        let params = GoParams::new().movetime(Duration::from_millis(100));
+        let play = |game: &mut ChessGame, mv: &str| {
+            game.make_move(&Move::from_uci(mv).unwrap()).unwrap();
+        };
+        let mut game = ChessGame::default();
+
+        assert_eq!(engine.go(&params).unwrap().to_uci(), "e2e4");
+        play(&mut game, "e2e4");
+        assert!(engine.start_pondering(&game, &params).unwrap());
+        assert!(engine.go(&params).is_err());
+        play(&mut game, "e7e5");
+        assert_eq!(
+            engine.finish_pondering(&game, &params).unwrap().to_uci(),
+            "g1f3"
+        );
+
+        play(&mut game, "g1f3");
+        assert!(engine.start_pondering(&game, &params).unwrap());
+        play(&mut game, "d7d6");
+        // the pondered search's a2a3 is thrown away
+        assert_eq!(
+            engine.finish_pondering(&game, &params).unwrap().to_uci(),
+            "e2e4"
+        );
+        assert!(engine.finish_pondering(&game, &params).is_err());
+        std::fs::remove_file(&path).ok();
+    }
+}
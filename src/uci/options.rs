@@ -0,0 +1,253 @@
+use std::fmt::{Display, Formatter};
+
+/// The words that start each part of an `option` line.
+const KEYWORDS: [&str; 6] = ["name", "type", "default", "min", "max", "var"];
+
+/// The type of a UCI option, with its default value and allowed values.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum OptionKind {
+    Check {
+        default: bool,
+    },
+    Spin {
+        default: i64,
+        min: i64,
+        max: i64,
+    },
+    Combo {
+        default: String,
+        choices: Vec<String>,
+    },
+    String {
+        default: String,
+    },
+    Button,
+}
+
+/// An option an engine advertised during the `uci` handshake.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct EngineOption {
+    pub name: String,
+    pub kind: OptionKind,
+}
+
+impl EngineOption {
+    /// Parse an `option` line, with or without the leading `option`, e.g.
+    /// `option name Skill Level type spin default 20 min 0 max 20`. Returns
+    /// `None` if the line is malformed.
+    pub fn parse(line: &str) -> Option<Self> {
+        let line = line.trim();
+        let line = line.strip_prefix("option").unwrap_or(line);
+        // each keyword's value runs up to the next keyword, as names and string
+        // defaults can contain spaces
+        let mut parts: Vec<(&str, Vec<&str>)> = Vec::new();
+        for word in line.split_whitespace() {
+            match parts.last_mut() {
+                Some((_, value)) if !KEYWORDS.contains(&word) => value.push(word),
+                None if !KEYWORDS.contains(&word) => return None,
+                _ => parts.push((word, Vec::new())),
+            }
+        }
+        let value = |keyword: &str| {
+            parts
+                .iter()
+                .find(|(k, _)| *k == keyword)
+                .map(|(_, value)| value.join(" "))
+        };
+        let number = |keyword: &str| value(keyword)?.parse::<i64>().ok();
+        // an empty string is sent as <empty>
+        let text = |keyword: &str| {
+            value(keyword)
+                .filter(|value| value != "<empty>")
+                .unwrap_or_default()
+        };
+
+        let name = value("name").filter(|name| !name.is_empty())?;
+        let kind = match value("type")?.as_str() {
+            "check" => OptionKind::Check {
+                default: value("default")?.parse().ok()?,
+            },
+            "spin" => OptionKind::Spin {
+                default: number("default")?,
+                min: number("min")?,
+                max: number("max")?,
+            },
+            "combo" => OptionKind::Combo {
+                default: text("default"),
+                choices: parts
+                    .iter()
+                    .filter(|(k, _)| *k == "var")
+                    .map(|(_, value)| value.join(" "))
+                    .collect(),
+            },
+            "string" => OptionKind::String {
+                default: text("default"),
+            },
+            "button" => OptionKind::Button,
+            _ => return None,
+        };
+        Some(Self { name, kind })
+    }
+
+    /// Check that `value` is allowed for this option.
+    pub fn validate(&self, value: &str) -> anyhow::Result<()> {
+        let valid = match &self.kind {
+            OptionKind::Check { .. } => value == "true" || value == "false",
+            OptionKind::Spin { min, max, .. } => value
+                .parse::<i64>()
+                .is_ok_and(|value| (*min..=*max).contains(&value)),
+            OptionKind::Combo { choices, .. } => choices
+                .iter()
+                .any(|choice| choice.eq_ignore_ascii_case(value)),
+            OptionKind::String { .. } => true,
+            OptionKind::Button => value.is_empty(),
+        };
+        if valid {
+            Ok(())
+        } else {
+            Err(anyhow::anyhow!("'{value}' is not a valid value for {self}"))
+        }
+    }
+}
+
+impl Display for EngineOption {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name)?;
+        match &self.kind {
+            OptionKind::Check { default } => write!(f, " (check, default {default})"),
+            OptionKind::Spin { default, min, max } => {
+                write!(f, " (spin, default {default}, {min} to {max})")
+            }
+            OptionKind::Combo { default, choices } => {
+                write!(
+                    f,
+                    " (combo, default {default}, one of {})",
+                    choices.join(", ")
+                )
+            }
+            OptionKind::String { default } => write!(f, " (string, default '{default}')"),
+            OptionKind::Button => write!(f, " (button)"),
+        }
+    }
+}
+
+/// The Elo ratings the lowest and highest skill levels roughly play at, for
+/// engines that only have a `Skill Level` option.
+const SKILL_ELO_RANGE: (i64, i64) = (1350, 2850);
+
+/// The options to set, in order, for an engine with `options` to play at about
+/// `elo`. Uses `UCI_LimitStrength` and `UCI_Elo` where the engine has them, and
+/// otherwise the closest `Skill Level`. Ratings outside what the engine
+/// supports are clamped to its range.
+pub fn elo_settings(options: &[EngineOption], elo: u32) -> anyhow::Result<Vec<(String, String)>> {
+    let elo = i64::from(elo);
+    let clamp = |option: &EngineOption, value: i64| match option.kind {
+        OptionKind::Spin { min, max, .. } => value.clamp(min, max),
+        _ => value,
+    };
+    if find(options, "UCI_LimitStrength").is_ok()
+        && let Ok(elo_option) = find(options, "UCI_Elo")
+    {
+        return Ok(vec![
+            ("UCI_LimitStrength".to_string(), "true".to_string()),
+            (elo_option.name.clone(), clamp(elo_option, elo).to_string()),
+        ]);
+    }
+    let skill_option = find(options, "Skill Level")
+        .map_err(|_| anyhow::anyhow!("The engine has no way to limit its strength"))?;
+    let (low, high) = SKILL_ELO_RANGE;
+    let skill = (elo.clamp(low, high) - low) * 20 / (high - low);
+    Ok(vec![(
+        skill_option.name.clone(),
+        clamp(skill_option, skill).to_string(),
+    )])
+}
+
+/// Find the option called `name` in `options`. Option names aren't case
+/// sensitive.
+pub fn find<'a>(options: &'a [EngineOption], name: &str) -> anyhow::Result<&'a EngineOption> {
+    options
+        .iter()
+        .find(|option| option.name.eq_ignore_ascii_case(name))
+        .ok_or_else(|| anyhow::anyhow!("The engine has no option called {name}"))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_options() {
+        let skill =
+            EngineOption::parse("option name Skill Level type spin default 20 min 0 max 20")
+                .unwrap();
+        assert_eq!(skill.name, "Skill Level");
+        assert_eq!(
+            skill.kind,
+            OptionKind::Spin {
+                default: 20,
+                min: 0,
+                max: 20
+            }
+        );
+        assert!(skill.validate("7").is_ok());
+        assert!(skill.validate("21").is_err());
+        assert!(skill.validate("strong").is_err());
+
+        let style = EngineOption::parse(
+            "option name Style type combo default Normal var Solid var Normal var Risky",
+        )
+        .unwrap();
+        assert!(style.validate("risky").is_ok());
+        assert!(style.validate("Reckless").is_err());
+
+        let path =
+            EngineOption::parse("option name SyzygyPath type string default <empty>").unwrap();
+        assert_eq!(
+            path.kind,
+            OptionKind::String {
+                default: String::new()
+            }
+        );
+        let clear = EngineOption::parse("option name Clear Hash type button").unwrap();
+        assert_eq!(clear.kind, OptionKind::Button);
+        let chess960 =
+            EngineOption::parse("option name UCI_Chess960 type check default false").unwrap();
+        assert!(chess960.validate("true").is_ok());
+
+        assert_eq!(EngineOption::parse("option type spin default 1"), None);
+        assert_eq!(EngineOption::parse("option name Foo type dial"), None);
+        assert!(find(&[skill, style], "skill level").is_ok());
+    }
+
+    #[test]
+    fn test_elo_settings() {
+        let option = |line: &str| EngineOption::parse(line).unwrap();
+        let skill = option("option name Skill Level type spin default 20 min 0 max 20");
+        let limit = option("option name UCI_LimitStrength type check default false");
+        let elo = option("option name UCI_Elo type spin default 1320 min 1320 max 3190");
+
+        let settings = |options: &[EngineOption], rating| {
+            elo_settings(options, rating)
+                .unwrap()
+                .into_iter()
+                .map(|(name, value)| format!("{name}={value}"))
+                .collect::<Vec<_>>()
+        };
+        let all = [skill.clone(), limit, elo];
+        assert_eq!(
+            settings(&all, 1800),
+            ["UCI_LimitStrength=true", "UCI_Elo=1800"]
+        );
+        assert_eq!(
+            settings(&all, 800),
+            ["UCI_LimitStrength=true", "UCI_Elo=1320"]
+        );
+
+        let skill_only = [skill];
+        assert_eq!(settings(&skill_only, 1000), ["Skill Level=0"]);
+        assert_eq!(settings(&skill_only, 2100), ["Skill Level=10"]);
+        assert_eq!(settings(&skill_only, 3500), ["Skill Level=20"]);
+        assert!(elo_settings(&[], 1500).is_err());
+    }
+}
@@ -0,0 +1,424 @@
+//! The solver as a UCI engine, so it can be driven by a chess GUI or a
+//! tournament manager such as cutechess-cli over stdin and stdout.
+
+use super::info::AnalysisInfo;
+use crate::clock::{self, DEFAULT_MOVES_TO_GO};
+use crate::core::{ChessColour, ChessError, Move};
+use crate::game::ChessGame;
+use crate::solver::tt::{DEFAULT_TABLE_SIZE_MB, ReplacementPolicy, TranspositionTable};
+use crate::solver::{self, MAX_SKILL, SearchOptions, SearchResult};
+use crate::variant::Variant;
+use std::io::{BufRead, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+/// Time held back on every move for the GUI to receive it.
+const MOVE_OVERHEAD: Duration = Duration::from_millis(50);
+
+/// How often an infinite search that has finished checks whether it has been
+/// told to stop.
+const POLL_INTERVAL: Duration = Duration::from_millis(5);
+
+/// The words that start a new parameter of the `go` command, and so end the
+/// list of moves following `searchmoves`.
+const GO_PARAMETERS: [&str; 11] = [
+    "searchmoves",
+    "ponder",
+    "wtime",
+    "btime",
+    "winc",
+    "binc",
+    "movestogo",
+    "depth",
+    "nodes",
+    "movetime",
+    "infinite",
+];
+
+/// A search running on a background thread, which sends its own `bestmove`
+/// and hands back the transposition table once it is done.
+struct Search {
+    stop: Arc<AtomicBool>,
+    handle: JoinHandle<TranspositionTable>,
+}
+
+/// The state of the engine between UCI commands.
+pub struct UciServer<W> {
+    output: Arc<Mutex<W>>,
+    game: ChessGame,
+    variant: Variant,
+    options: SearchOptions,
+    skill: u8,
+    tt: TranspositionTable,
+    search: Option<Search>,
+}
+
+/// Serve UCI commands read from `input` until `quit`, or until the input ends
+/// and any search has finished, searching with `options` unless the GUI
+/// changes them.
+pub fn run<W: Write + Send + 'static>(
+    input: impl BufRead,
+    output: W,
+    options: SearchOptions,
+) -> anyhow::Result<()> {
+    let mut server = UciServer::new(output, options);
+    for line in input.lines() {
+        if !server.handle(&line?)? {
+            return Ok(());
+        }
+    }
+    server.wait();
+    Ok(())
+}
+
+/// Write one line of output, flushing it straight away as the GUI is waiting
+/// on it.
+fn send<W: Write>(output: &Mutex<W>, line: &str) -> std::io::Result<()> {
+    tracing::debug!("Sending to GUI: {line}");
+    let mut output = output.lock().expect("output lock poisoned");
+    writeln!(output, "{line}")?;
+    output.flush()
+}
+
+impl<W: Write + Send + 'static> UciServer<W> {
+    pub fn new(output: W, options: SearchOptions) -> Self {
+        Self {
+            output: Arc::new(Mutex::new(output)),
+            game: ChessGame::default(),
+            variant: Variant::Standard,
+            options,
+            skill: MAX_SKILL,
+            tt: TranspositionTable::default(),
+            search: None,
+        }
+    }
+
+    /// Act on one line from the GUI, returning `false` once it has asked the
+    /// engine to quit. Commands that can't be carried out are reported back to
+    /// the GUI and otherwise ignored, as the protocol expects.
+    pub fn handle(&mut self, line: &str) -> anyhow::Result<bool> {
+        tracing::debug!("Received from GUI: {line}");
+        let mut words = line.split_whitespace();
+        let result = match words.next() {
+            Some("uci") => self.identify(),
+            Some("isready") => self.send("readyok").map_err(Into::into),
+            Some("ucinewgame") => {
+                self.finish_search();
+                self.tt.clear();
+                self.game = ChessGame::default();
+                Ok(())
+            }
+            Some("setoption") => self.set_option(&words.collect::<Vec<_>>()),
+            Some("position") => self.set_position(&words.collect::<Vec<_>>()),
+            Some("go") => self.go(&words.collect::<Vec<_>>()),
+            Some("stop") => {
+                self.finish_search();
+                Ok(())
+            }
+            Some("quit") => {
+                self.finish_search();
+                return Ok(false);
+            }
+            Some(command) => Err(anyhow::anyhow!("unknown command '{command}'")),
+            None => Ok(()),
+        };
+        if let Err(e) = result {
+            tracing::warn!("Could not handle '{line}': {e}");
+            self.send(&format!("info string {e}"))?;
+        }
+        Ok(true)
+    }
+
+    fn send(&self, line: &str) -> std::io::Result<()> {
+        send(&self.output, line)
+    }
+
+    fn identify(&self) -> anyhow::Result<()> {
+        self.send(concat!("id name chess ", env!("CARGO_PKG_VERSION")))?;
+        self.send("id author the chess authors")?;
+        for option in [
+            format!("option name Hash type spin default {DEFAULT_TABLE_SIZE_MB} min 1 max 4096"),
+            "option name Threads type spin default 1 min 1 max 64".to_string(),
+            "option name MultiPV type spin default 1 min 1 max 64".to_string(),
+            "option name Contempt type spin default 0 min -1000 max 1000".to_string(),
+            format!("option name Skill Level type spin default {MAX_SKILL} min 0 max {MAX_SKILL}"),
+            "option name UCI_Chess960 type check default false".to_string(),
+        ] {
+            self.send(&option)?;
+        }
+        self.send("uciok")?;
+        Ok(())
+    }
+
+    /// `setoption name <name> [value <value>]`, where both may contain spaces.
+    fn set_option(&mut self, words: &[&str]) -> anyhow::Result<()> {
+        let value_at = words.iter().position(|w| *w == "value");
+        let name = words[..value_at.unwrap_or(words.len())]
+            .strip_prefix(&["name"])
+            .ok_or_else(|| anyhow::anyhow!("setoption is missing the option name"))?
+            .join(" ");
+        let value = value_at
+            .map(|i| words[i + 1..].join(" "))
+            .unwrap_or_default();
+        let invalid = || anyhow::anyhow!("invalid value '{value}' for option {name}");
+        match name.to_lowercase().as_str() {
+            "hash" => {
+                let size_mb = value.parse().map_err(|_| invalid())?;
+                self.finish_search();
+                self.tt = TranspositionTable::with_size_mb(size_mb, ReplacementPolicy::default());
+            }
+            "threads" => self.options.threads = value.parse().map_err(|_| invalid())?,
+            "multipv" => self.options.multi_pv = value.parse().map_err(|_| invalid())?,
+            "contempt" => self.options.contempt = value.parse().map_err(|_| invalid())?,
+            "skill level" => self.skill = value.parse().map_err(|_| invalid())?,
+            "uci_chess960" => {
+                self.variant = if value.parse().map_err(|_| invalid())? {
+                    Variant::Chess960
+                } else {
+                    Variant::Standard
+                };
+            }
+            _ => return Err(anyhow::anyhow!("unknown option '{name}'")),
+        }
+        Ok(())
+    }
+
+    /// `position (startpos | fen <fen>) [moves <move>...]`
+    fn set_position(&mut self, words: &[&str]) -> anyhow::Result<()> {
+        let moves_at = words.iter().position(|w| *w == "moves");
+        let (setup, moves) = words.split_at(moves_at.unwrap_or(words.len()));
+        let mut game = match setup {
+            ["startpos"] => ChessGame::default(),
+            ["fen", fen @ ..] => ChessGame::from_fen(&fen.join(" "), self.variant)?,
+            _ => return Err(anyhow::anyhow!("expected startpos or fen")),
+        };
+        for mv in moves.iter().skip(1) {
+            game.make_move(&Move::from_uci(mv)?)?;
+        }
+        self.game = game;
+        Ok(())
+    }
+
+    /// Start searching the current position in the background, with the limits
+    /// given by `go`'s parameters.
+    fn go(&mut self, words: &[&str]) -> anyhow::Result<()> {
+        self.finish_search();
+        let mut options = self.options.clone().with_skill(self.skill);
+        let stop = Arc::new(AtomicBool::new(false));
+        options.stop = Some(stop.clone());
+
+        let turn = self.game.get_board().turn;
+        let mut remaining = None;
+        let mut increment = Duration::ZERO;
+        let mut moves_to_go = DEFAULT_MOVES_TO_GO;
+        let mut infinite = false;
+        let mut words = words.iter().peekable();
+        while let Some(word) = words.next() {
+            let mut number = || -> anyhow::Result<u64> {
+                let value = words
+                    .next()
+                    .ok_or_else(|| anyhow::anyhow!("go {word} is missing its value"))?;
+                value
+                    .parse()
+                    .map_err(|_| anyhow::anyhow!("invalid value '{value}' for go {word}"))
+            };
+            let millis = |value: u64| Duration::from_millis(value);
+            match *word {
+                "wtime" | "btime" => {
+                    let time = millis(number()?);
+                    if (*word == "wtime") == (turn == ChessColour::White) {
+                        remaining = Some(time);
+                    }
+                }
+                "winc" | "binc" => {
+                    let inc = millis(number()?);
+                    if (*word == "winc") == (turn == ChessColour::White) {
+                        increment = inc;
+                    }
+                }
+                "movestogo" => moves_to_go = (number()? as u32).max(1),
+                "depth" => options.max_depth = number()? as usize,
+                "nodes" => options.max_nodes = Some(number()?),
+                "movetime" => {
+                    options.max_time = Some(millis(number()?).saturating_sub(MOVE_OVERHEAD));
+                }
+                "infinite" => {
+                    infinite = true;
+                    options.max_time = None;
+                }
+                "searchmoves" => {
+                    let mut moves = Vec::new();
+                    while let Some(mv) = words.next_if(|w| !GO_PARAMETERS.contains(w)) {
+                        moves.push(Move::from_uci(mv)?);
+                    }
+                    options.search_moves = Some(moves);
+                }
+                // the background search already is a ponder search
+                "ponder" => {}
+                other => return Err(anyhow::anyhow!("unknown go parameter '{other}'")),
+            }
+        }
+        if let Some(remaining) = remaining {
+            let budget = clock::move_budget(remaining, increment, moves_to_go);
+            options.max_time = Some(
+                budget
+                    .min(remaining.saturating_sub(MOVE_OVERHEAD))
+                    .saturating_sub(MOVE_OVERHEAD),
+            );
+        }
+
+        let game = self.game.clone();
+        let output = self.output.clone();
+        let mut tt = std::mem::take(&mut self.tt);
+        let search_stop = stop.clone();
+        let handle = std::thread::spawn(move || {
+            let result = solver::solve_next_move_with_options(&game, &options, &mut tt);
+            // the GUI expects an infinite search to keep going until told to stop
+            while infinite && !search_stop.load(Ordering::Relaxed) {
+                std::thread::sleep(POLL_INTERVAL);
+            }
+            if let Err(e) = report(&output, &game, result) {
+                tracing::error!("Could not send search result: {e}");
+            }
+            tt
+        });
+        self.search = Some(Search { stop, handle });
+        Ok(())
+    }
+
+    /// Stop any running search, which then sends its best move.
+    fn finish_search(&mut self) {
+        if let Some(search) = &self.search {
+            search.stop.store(true, Ordering::Relaxed);
+        }
+        self.wait();
+    }
+
+    /// Wait for any running search to finish.
+    fn wait(&mut self) {
+        if let Some(search) = self.search.take() {
+            self.tt = search.handle.join().expect("search thread panicked");
+        }
+    }
+}
+
+/// Send the `info` lines and `bestmove` for a finished search. If it was stopped
+/// before finding anything, any legal move is better than none.
+fn report<W: Write>(
+    output: &Mutex<W>,
+    game: &ChessGame,
+    result: Result<SearchResult, ChessError>,
+) -> std::io::Result<()> {
+    let result = match result {
+        Ok(result) => result,
+        Err(e) => {
+            tracing::warn!("Search failed: {e}");
+            let best = solver::legal_moves(game)
+                .first()
+                .map_or_else(|| "0000".to_string(), |mv| mv.to_uci());
+            return send(output, &format!("bestmove {best}"));
+        }
+    };
+    for (i, line) in result.lines.iter().enumerate() {
+        let info = AnalysisInfo {
+            depth: Some(result.depth as u32),
+            multipv: (result.lines.len() > 1).then_some(i as u32 + 1),
+            score: Some(line.score),
+            nodes: Some(result.nodes),
+            pv: line.principal_variation.clone(),
+            ..Default::default()
+        };
+        send(output, &info.to_string())?;
+    }
+    let mut best = format!("bestmove {}", result.best_move.to_uci());
+    if let Some(ponder) = result.principal_variation.get(1) {
+        best.push_str(&format!(" ponder {}", ponder.to_uci()));
+    }
+    send(output, &best)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::solver::book::OpeningBook;
+
+    /// Output the test can read back while the server still holds it.
+    #[derive(Clone, Default)]
+    struct SharedOutput(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for SharedOutput {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl SharedOutput {
+        fn lines(&self) -> Vec<String> {
+            String::from_utf8(self.0.lock().unwrap().clone())
+                .unwrap()
+                .lines()
+                .map(str::to_string)
+                .collect()
+        }
+    }
+
+    fn serve(input: &str) -> Vec<String> {
+        let output = SharedOutput::default();
+        let options = SearchOptions {
+            book: OpeningBook::Disabled,
+            ..Default::default()
+        };
+        run(input.as_bytes(), output.clone(), options).unwrap();
+        output.lines()
+    }
+
+    #[test]
+    fn test_handshake() {
+        let lines = serve("uci\nisready\n");
+        assert!(lines[0].starts_with("id name"));
+        assert!(
+            lines
+                .iter()
+                .any(|l| l.starts_with("option name Skill Level"))
+        );
+        assert_eq!(lines[lines.len() - 2..], ["uciok", "readyok"]);
+    }
+
+    #[test]
+    fn test_finds_mate() {
+        let lines = serve("position fen 6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1\ngo depth 3\n");
+        assert!(lines.iter().any(|l| l.contains("score mate 1")));
+        assert_eq!(lines.last().unwrap(), "bestmove a1a8");
+    }
+
+    #[test]
+    fn test_position_moves_and_searchmoves() {
+        let lines = serve(
+            "position startpos moves e2e4 e7e5\ngo depth 2 searchmoves g1f3 b1c3 wtime 1000 btime 1000\n",
+        );
+        let best = lines.last().unwrap();
+        assert!(
+            best.starts_with("bestmove g1f3") || best.starts_with("bestmove b1c3"),
+            "{best}"
+        );
+    }
+
+    #[test]
+    fn test_stop_infinite_search() {
+        let lines = serve("position startpos\ngo infinite\nstop\nquit\n");
+        assert!(lines.last().unwrap().starts_with("bestmove "));
+    }
+
+    #[test]
+    fn test_reports_errors() {
+        let lines = serve("position startpos moves e2e5\nfoo\n");
+        assert_eq!(lines.len(), 2);
+        assert!(lines.iter().all(|l| l.starts_with("info string ")));
+    }
+}
@@ -0,0 +1,129 @@
+//! Standard chess and Chess960, with its 960 starting positions.
+
+use crate::core::{ChessError, ChessPieceKind};
+use serde::{Deserialize, Serialize};
+
+/// The number of distinct Chess960 starting positions.
+pub const CHESS960_POSITIONS: u16 = 960;
+
+/// The pieces on each side's back row at the start of a standard game.
+pub const STANDARD_BACK_ROW: [ChessPieceKind; 8] = [
+    ChessPieceKind::Rook,
+    ChessPieceKind::Knight,
+    ChessPieceKind::Bishop,
+    ChessPieceKind::Queen,
+    ChessPieceKind::King,
+    ChessPieceKind::Bishop,
+    ChessPieceKind::Knight,
+    ChessPieceKind::Rook,
+];
+
+/// The Chess960 index of the standard chess starting position.
+pub const STANDARD_CHESS960_INDEX: u16 = 518;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Copy, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Variant {
+    #[default]
+    Standard,
+    /// Fischer Random chess: the back row is shuffled and castling is defined by
+    /// the rooks' starting columns rather than fixed squares.
+    Chess960,
+}
+
+/// Generate the back row for the Chess960 starting position with the given
+/// index, using the standard Scharnagl numbering (518 is the normal setup).
+pub fn chess960_back_row(index: u16) -> Result<[ChessPieceKind; 8], ChessError> {
+    if index >= CHESS960_POSITIONS {
+        return Err(ChessError::InvalidChess960Index(index));
+    }
+    let mut row: [Option<ChessPieceKind>; 8] = [None; 8];
+    let mut n = index as usize;
+
+    // bishops go on opposite coloured squares
+    row[2 * (n % 4) + 1] = Some(ChessPieceKind::Bishop);
+    n /= 4;
+    row[2 * (n % 4)] = Some(ChessPieceKind::Bishop);
+    n /= 4;
+
+    let place_on_empty = |row: &mut [Option<ChessPieceKind>; 8], nth: usize, kind| {
+        let column = row
+            .iter()
+            .enumerate()
+            .filter(|(_, c)| c.is_none())
+            .nth(nth)
+            .map(|(i, _)| i)
+            .expect("there is always an empty column left");
+        row[column] = Some(kind);
+    };
+
+    place_on_empty(&mut row, n % 6, ChessPieceKind::Queen);
+    n /= 6;
+
+    let (first, second) = [
+        (0, 1),
+        (0, 2),
+        (0, 3),
+        (0, 4),
+        (1, 2),
+        (1, 3),
+        (1, 4),
+        (2, 3),
+        (2, 4),
+        (3, 4),
+    ][n];
+    // place the second knight first so the first one's index is not shifted
+    place_on_empty(&mut row, second, ChessPieceKind::Knight);
+    place_on_empty(&mut row, first, ChessPieceKind::Knight);
+
+    // the king always sits between the two rooks
+    for kind in [
+        ChessPieceKind::Rook,
+        ChessPieceKind::King,
+        ChessPieceKind::Rook,
+    ] {
+        place_on_empty(&mut row, 0, kind);
+    }
+
+    Ok(row.map(|c| c.expect("every column has been filled")))
+}
+
+/// Pick a random Chess960 starting position index.
+#[cfg(feature = "std")]
+pub fn random_chess960_index() -> u16 {
+    rand::random_range(0..CHESS960_POSITIONS)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_standard_index_is_normal_setup() {
+        assert_eq!(
+            chess960_back_row(STANDARD_CHESS960_INDEX).unwrap(),
+            STANDARD_BACK_ROW
+        );
+    }
+
+    #[test]
+    fn test_all_positions_are_valid_and_distinct() {
+        let mut seen = std::collections::HashSet::new();
+        for index in 0..CHESS960_POSITIONS {
+            let row = chess960_back_row(index).unwrap();
+            let columns = |kind| {
+                row.iter()
+                    .enumerate()
+                    .filter(move |(_, k)| **k == kind)
+                    .map(|(i, _)| i)
+            };
+            let bishops: Vec<_> = columns(ChessPieceKind::Bishop).collect();
+            assert_ne!(bishops[0] % 2, bishops[1] % 2, "index {index}");
+            let rooks: Vec<_> = columns(ChessPieceKind::Rook).collect();
+            let king = columns(ChessPieceKind::King).next().unwrap();
+            assert!(rooks[0] < king && king < rooks[1], "index {index}");
+            assert!(seen.insert(row));
+        }
+        assert!(chess960_back_row(CHESS960_POSITIONS).is_err());
+    }
+}
@@ -0,0 +1,142 @@
+//! A game driven entirely through strings and numbers, the shape a browser
+//! front end talks to the crate in: positions as FEN, moves as UCI and
+//! errors as messages to show the player. With the `wasm` feature these are
+//! exported to JavaScript by `wasm-bindgen`.
+
+use crate::core::Move;
+use crate::game::{ChessGame, GameResult};
+use crate::solver::tt::{ReplacementPolicy, TranspositionTable};
+use crate::solver::{self, SearchOptions};
+use crate::variant::Variant;
+use ::core::time::Duration;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::wasm_bindgen;
+
+/// The size of the solver's table, kept small for a browser tab.
+const TABLE_SIZE_MB: usize = 2;
+
+/// A standard game, with the solver to play against:
+///
+/// ```
+/// use chess::web::WebGame;
+///
+/// let mut game = WebGame::new();
+/// assert_eq!(game.legal_moves().len(), 20);
+/// game.make_move("e2e4").unwrap();
+/// assert!(game.make_move("e2e4").is_err());
+/// let reply = game.best_move(2, 0, None).unwrap();
+/// game.make_move(&reply).unwrap();
+/// ```
+#[derive(Clone)]
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+pub struct WebGame {
+    game: ChessGame,
+    /// Kept between searches, so the solver's next move starts from what it
+    /// learnt finding the last.
+    table: TranspositionTable,
+}
+
+impl Default for WebGame {
+    fn default() -> Self {
+        Self::with_game(ChessGame::default())
+    }
+}
+
+impl WebGame {
+    fn with_game(game: ChessGame) -> Self {
+        Self {
+            game,
+            table: TranspositionTable::with_size_mb(TABLE_SIZE_MB, ReplacementPolicy::default()),
+        }
+    }
+}
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+impl WebGame {
+    #[cfg_attr(feature = "wasm", wasm_bindgen(constructor))]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Start from a FEN string, as [`ChessGame::from_fen`] reads it.
+    #[cfg_attr(feature = "wasm", wasm_bindgen(js_name = fromFen))]
+    pub fn from_fen(fen: &str) -> Result<Self, String> {
+        ChessGame::from_fen(fen, Variant::Standard)
+            .map(Self::with_game)
+            .map_err(|e| e.to_string())
+    }
+
+    pub fn fen(&self) -> String {
+        self.game.fen()
+    }
+
+    /// Every legal move for the side to move, in UCI.
+    #[cfg_attr(feature = "wasm", wasm_bindgen(js_name = legalMoves))]
+    pub fn legal_moves(&self) -> Vec<String> {
+        solver::legal_moves(&self.game)
+            .into_iter()
+            .map(Move::to_uci)
+            .collect()
+    }
+
+    /// Play `mv`, given in UCI.
+    #[cfg_attr(feature = "wasm", wasm_bindgen(js_name = makeMove))]
+    pub fn make_move(&mut self, mv: &str) -> Result<(), String> {
+        let mv = Move::from_uci(mv).map_err(|e| e.to_string())?;
+        self.game
+            .make_move(&mv)
+            .map(|_| ())
+            .map_err(|e| e.to_string())
+    }
+
+    /// Take back the last move, returning it in UCI.
+    pub fn undo(&mut self) -> Result<String, String> {
+        self.game
+            .undo()
+            .map(Move::to_uci)
+            .map_err(|e| e.to_string())
+    }
+
+    /// The result once the game is over, e.g. `1-0 (checkmate)`.
+    pub fn result(&self) -> Option<String> {
+        self.game.result().as_ref().map(GameResult::to_string)
+    }
+
+    /// The solver's move for the side to move, in UCI, searching at most
+    /// `max_depth` half moves, `max_nodes` positions unless that is 0, and
+    /// `max_time_ms` milliseconds if given. A budget too small to finish one
+    /// half move still gives a move. Without the `std` feature there is no
+    /// clock, and only the depth and node budgets apply.
+    #[cfg_attr(feature = "wasm", wasm_bindgen(js_name = bestMove))]
+    pub fn best_move(
+        &mut self,
+        max_depth: u32,
+        max_nodes: u32,
+        max_time_ms: Option<u32>,
+    ) -> Result<String, String> {
+        let options = SearchOptions {
+            max_nodes: (max_nodes > 0).then_some(u64::from(max_nodes)),
+            max_time: max_time_ms.map(|ms| Duration::from_millis(u64::from(ms))),
+            ..SearchOptions::fixed_depth(max_depth.max(1) as usize)
+        };
+        solver::solve_next_move_with_options(&self.game, &options, &mut self.table)
+            .map(|result| result.best_move.to_uci())
+            .map_err(|e| e.to_string())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_tiny_budget() {
+        let mut game = WebGame::new();
+        for (max_nodes, max_time_ms) in [(1, None), (0, Some(0)), (1, Some(1))] {
+            let mv = game.best_move(10, max_nodes, max_time_ms).unwrap();
+            assert!(game.legal_moves().contains(&mv), "{mv}");
+        }
+    }
+}
@@ -0,0 +1,368 @@
+//! A client for engines speaking the XBoard protocol, also known as CECP, such
+//! as GNU Chess and Crafty, so they can be played against like UCI engines.
+
+use crate::config::EngineConfig;
+use crate::core::{ChessColour, Move};
+use crate::engine::{
+    EngineClient, EngineError, EngineProcess, EngineReply, RESPONSE_TIMEOUT, SEARCH_GRACE,
+};
+use crate::game::ChessGame;
+use crate::solver::Score;
+use crate::uci::go::GoParams;
+use crate::variant::Variant;
+use std::time::{Duration, Instant};
+
+/// How long to wait for `feature` lines after `protover 2`. Engines that only
+/// speak version 1 of the protocol never send any, so this is kept short.
+const FEATURE_TIMEOUT: Duration = Duration::from_secs(2);
+
+pub struct XBoardEngine {
+    process: EngineProcess,
+    /// The features the engine asked for during the handshake, e.g. whether it
+    /// understands `setboard`.
+    features: Vec<(String, String)>,
+    variant: Variant,
+    /// The position the engine's board was set up from and the moves played on
+    /// it since, so a later position that follows on only needs the new moves.
+    synced: Option<(Option<String>, Vec<Move>)>,
+    /// The number of the last `ping` sent.
+    pings: u32,
+}
+
+/// Parse the `name=value` pairs of a `feature` line, with or without the
+/// leading `feature`. Values may be quoted to include spaces.
+pub fn parse_features(line: &str) -> Vec<(String, String)> {
+    let line = line.trim();
+    let mut rest = line.strip_prefix("feature").unwrap_or(line).trim_start();
+    let mut features = Vec::new();
+    while let Some((name, value)) = rest.split_once('=') {
+        let (value, remainder) = match value.strip_prefix('"') {
+            Some(quoted) => quoted.split_once('"').unwrap_or((quoted, "")),
+            None => value.split_once(' ').unwrap_or((value, "")),
+        };
+        features.push((name.trim().to_string(), value.to_string()));
+        rest = remainder.trim_start();
+    }
+    features
+}
+
+impl XBoardEngine {
+    pub fn new(engine_exec: &str) -> anyhow::Result<Self> {
+        Self::from_config(&EngineConfig::from_path(engine_exec))
+    }
+
+    /// Start the engine `config` describes. XBoard has no standard way to set
+    /// options, so any in `config` are ignored.
+    pub fn from_config(config: &EngineConfig) -> anyhow::Result<Self> {
+        let mut res = Self {
+            process: EngineProcess::spawn(config)?,
+            features: Vec::new(),
+            variant: Variant::Standard,
+            synced: None,
+            pings: 0,
+        };
+        res.send("xboard")?;
+        res.send("protover 2")?;
+        res.read_features()?;
+        Ok(res)
+    }
+
+    /// Collect the engine's `feature` lines, accepting each one this client
+    /// supports, until it says it is done or goes quiet.
+    fn read_features(&mut self) -> anyhow::Result<()> {
+        let mut started = Instant::now();
+        let mut timeout = FEATURE_TIMEOUT;
+        loop {
+            let line = match self.process.next_line(started, Some(timeout), "done=1") {
+                Ok(line) => line,
+                Err(EngineError::Timeout { .. }) => return Ok(()),
+                Err(error) => return Err(error.into()),
+            };
+            if !line.starts_with("feature") {
+                continue;
+            }
+            for (name, value) in parse_features(&line) {
+                // moves in SAN are rejected so that the engine sends and
+                // expects coordinates instead, and interrupting the engine
+                // with signals is never needed
+                let accepted = !matches!((name.as_str(), value.as_str()), ("san" | "sigint", "1"));
+                let reply = if accepted { "accepted" } else { "rejected" };
+                self.send(&format!("{reply} {name}"))?;
+                match (name.as_str(), value.as_str()) {
+                    ("done", "1") => return Ok(()),
+                    // the engine needs more time to start up
+                    ("done", "0") => {
+                        started = Instant::now();
+                        timeout = RESPONSE_TIMEOUT * 6;
+                    }
+                    _ => self.features.push((name, value)),
+                }
+            }
+        }
+    }
+
+    /// The value the engine gave `name` during the handshake.
+    pub fn feature(&self, name: &str) -> Option<&str> {
+        self.features
+            .iter()
+            .find(|(feature, _)| feature == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// The engine's name, if it gave one.
+    pub fn name(&self) -> Option<&str> {
+        self.feature("myname")
+    }
+
+    /// Clear the engine's board and stop it playing either side, so that it
+    /// only moves when told to.
+    fn reset(&mut self) -> anyhow::Result<()> {
+        self.send("new")?;
+        if self.variant == Variant::Chess960 {
+            self.send("variant fischerandom")?;
+        }
+        self.send("force")?;
+        // show thinking, for the scores, but don't think on the opponent's time
+        self.send("post")?;
+        self.send("easy")?;
+        self.synced = None;
+        Ok(())
+    }
+
+    /// Bring the engine's board up to `game`'s position.
+    fn sync(&mut self, game: &ChessGame) -> anyhow::Result<()> {
+        let start = game.start_fen().map(str::to_string);
+        let moves = game.played_moves();
+        let sent = match &self.synced {
+            Some((synced_start, sent)) if *synced_start == start && moves.starts_with(sent) => {
+                sent.len()
+            }
+            _ => {
+                self.reset()?;
+                if let Some(fen) = &start {
+                    if self.feature("setboard") != Some("1") {
+                        return Err(anyhow::anyhow!(
+                            "The engine can't be given a position other than the starting one"
+                        ));
+                    }
+                    self.send(&format!("setboard {fen}"))?;
+                }
+                0
+            }
+        };
+        for mv in &moves[sent..] {
+            if self.feature("usermove") == Some("1") {
+                self.send(&format!("usermove {}", mv.to_uci()))?;
+            } else {
+                self.send(&mv.to_uci())?;
+            }
+        }
+        self.synced = Some((start, moves.to_vec()));
+        self.ping()
+    }
+
+    /// Wait for the engine to catch up with everything sent so far, if it
+    /// supports `ping`, failing if it complained about any of it.
+    fn ping(&mut self) -> anyhow::Result<()> {
+        if self.feature("ping") != Some("1") {
+            return Ok(());
+        }
+        self.pings += 1;
+        let pong = format!("pong {}", self.pings);
+        self.send(&format!("ping {}", self.pings))?;
+        let sent = Instant::now();
+        loop {
+            let line = self
+                .process
+                .next_line(sent, Some(RESPONSE_TIMEOUT), &pong)?;
+            if line.trim() == pong {
+                return Ok(());
+            }
+            if line.starts_with("Illegal move") || line.starts_with("Error") {
+                // the engine's board no longer matches the game's
+                self.synced = None;
+                return Err(anyhow::anyhow!("The engine rejected the position: {line}"));
+            }
+        }
+    }
+
+    /// Tell the engine how long it may think for this move.
+    fn set_limits(&mut self, turn: ChessColour, params: &GoParams) -> anyhow::Result<()> {
+        if let Some(depth) = params.depth_limit() {
+            self.send(&format!("sd {depth}"))?;
+        }
+        let centiseconds = |time: Duration| time.as_millis() / 10;
+        if let Some((time, increment)) = params.clock_for(turn) {
+            let secs = time.as_secs();
+            self.send(&format!(
+                "level 0 {}:{:02} {}",
+                secs / 60,
+                secs % 60,
+                increment.as_secs()
+            ))?;
+            self.send(&format!("time {}", centiseconds(time)))?;
+            if let Some((opponent_time, _)) = params.clock_for(turn.flip()) {
+                self.send(&format!("otim {}", centiseconds(opponent_time)))?;
+            }
+        } else if let Some(movetime) = params.movetime_limit() {
+            // st only takes whole seconds
+            self.send(&format!("st {}", movetime.as_secs().max(1)))?;
+        }
+        Ok(())
+    }
+
+    fn send(&mut self, command: &str) -> anyhow::Result<()> {
+        Ok(self.process.send(format_args!("{command}\n"))?)
+    }
+}
+
+/// The score from a line of thinking output, `ply score time nodes pv...`.
+fn thinking_score(line: &str) -> Option<Score> {
+    let mut words = line.split_whitespace();
+    let ply = words.next()?;
+    // some engines mark the ply with '.' or '&'
+    ply.trim_end_matches(['.', '&']).parse::<u32>().ok()?;
+    let score = words.next()?.parse().ok()?;
+    words.next()?.parse::<u64>().ok()?;
+    words.next()?.parse::<u64>().ok()?;
+    Some(Score::Centipawns(score))
+}
+
+impl EngineClient for XBoardEngine {
+    fn set_variant(&mut self, variant: Variant) -> anyhow::Result<()> {
+        if variant == Variant::Chess960
+            && !self
+                .feature("variants")
+                .is_some_and(|variants| variants.split(',').any(|v| v == "fischerandom"))
+        {
+            return Err(anyhow::anyhow!("The engine doesn't play Chess960"));
+        }
+        self.variant = variant;
+        self.synced = None;
+        Ok(())
+    }
+
+    fn new_game(&mut self) -> anyhow::Result<()> {
+        self.reset()?;
+        self.ping()
+    }
+
+    fn play(&mut self, game: &ChessGame, params: &GoParams) -> anyhow::Result<EngineReply> {
+        if params.is_infinite() || params.is_ponder() {
+            return Err(anyhow::anyhow!(
+                "The engine must be given a limit to play a move"
+            ));
+        }
+        self.sync(game)?;
+        self.set_limits(game.get_board().turn, params)?;
+        self.send("go")?;
+
+        let timeout = params
+            .max_search_time()
+            .map(|search_time| search_time + SEARCH_GRACE);
+        let sent = Instant::now();
+        let mut score = None;
+        let best_move = loop {
+            let line = self.process.next_line(sent, timeout, "move")?;
+            let mut words = line.split_whitespace();
+            match (words.next(), words.next()) {
+                (Some("move"), Some(mv)) => break Move::from_uci(mv)?,
+                (Some("resign"), _) => {
+                    self.send("force")?;
+                    return Err(EngineError::Resigned.into());
+                }
+                (Some("Illegal" | "Error"), _) => {
+                    self.synced = None;
+                    return Err(anyhow::anyhow!("The engine rejected the position: {line}"));
+                }
+                _ => score = thinking_score(&line).or(score),
+            }
+        };
+        // stop the engine moving again once it sees a reply
+        self.send("force")?;
+        if let Some((_, moves)) = &mut self.synced {
+            moves.push(best_move);
+        }
+        Ok(EngineReply { best_move, score })
+    }
+}
+
+impl Drop for XBoardEngine {
+    fn drop(&mut self) {
+        self.process.quit("quit");
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    #[cfg(unix)]
+    use crate::engine::fake_engine;
+
+    #[test]
+    fn test_parse_features() {
+        let features =
+            parse_features("feature ping=1 myname=\"GNU Chess 6.2.9\" setboard=1 done=0");
+        let features: Vec<_> = features
+            .iter()
+            .map(|(name, value)| format!("{name}={value}"))
+            .collect();
+        assert_eq!(
+            features,
+            ["ping=1", "myname=GNU Chess 6.2.9", "setboard=1", "done=0"]
+        );
+        assert!(parse_features("feature").is_empty());
+
+        assert_eq!(
+            thinking_score("9 -35 120 10473 e7e5 g1f3"),
+            Some(Score::Centipawns(-35))
+        );
+        assert_eq!(
+            thinking_score("9. 12 120 10473 e7e5"),
+            Some(Score::Centipawns(12))
+        );
+        assert_eq!(thinking_score("move e7e5"), None);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_play_xboard_engine() {
+        let log = std::env::temp_dir().join(format!("xboard-log-{}", std::process::id()));
+        let path = fake_engine(
+            "xboard-engine",
+            &format!(
+                "while read command rest; do
+                    echo \"$command $rest\" >> {log}
+                    case $command in
+                        protover) echo 'feature myname=\"Fake Engine\" san=1 usermove=1 ping=1 done=1' ;;
+                        ping) echo \"pong $rest\" ;;
+                        go) echo '5 37 12 1234 e7e5 g1f3'; echo 'move e7e5' ;;
+                        quit) exit ;;
+                    esac
+                done
+                ",
+                log = log.display()
+            ),
+        );
+        let mut engine = XBoardEngine::new(path.to_str().unwrap()).unwrap();
+        assert_eq!(engine.name(), Some("Fake Engine"));
+        assert_eq!(engine.feature("san"), Some("1"));
+        engine.new_game().unwrap();
+
+        let mut game = ChessGame::default();
+        game.make_move(&Move::from_uci("e2e4").unwrap()).unwrap();
+        let params = GoParams::new().movetime(Duration::from_secs(1));
+        let reply = engine.play(&game, &params).unwrap();
+        assert_eq!(reply.best_move, Move::from_uci("e7e5").unwrap());
+        assert_eq!(reply.score, Some(Score::Centipawns(37)));
+        drop(engine);
+
+        let sent = std::fs::read_to_string(&log).unwrap();
+        let sent: Vec<_> = sent.lines().map(str::trim).collect();
+        for command in ["rejected san", "accepted usermove", "usermove e2e4", "st 1"] {
+            assert!(sent.contains(&command), "{command} not in {sent:?}");
+        }
+        std::fs::remove_file(&path).ok();
+        std::fs::remove_file(&log).ok();
+    }
+}
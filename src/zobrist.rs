@@ -0,0 +1,121 @@
+//! Zobrist hashing: each piece on each square, the side to move and each castling
+//! right gets a fixed random key, and a position's hash is the XOR of the keys
+//! that apply to it. Keys are generated at compile time so hashes are stable
+//! between runs.
+
+use crate::core::{
+    CastlingRights, CastlingSide, ChessBoard, ChessColour, ChessPiece, ChessPieceKind, Position,
+    Square,
+};
+
+const fn splitmix64(state: u64) -> u64 {
+    let mut z = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+const fn generate_keys<const N: usize>(seed: u64) -> [u64; N] {
+    let mut keys = [0; N];
+    let mut i = 0;
+    while i < N {
+        keys[i] = splitmix64(seed.wrapping_add((i as u64).wrapping_mul(0x9e37_79b9_7f4a_7c15)));
+        i += 1;
+    }
+    keys
+}
+
+/// One key per (piece kind, colour, square).
+const PIECE_KEYS: [u64; 12 * 64] = generate_keys(0x5a0b_1157);
+const BLACK_TO_MOVE_KEY: u64 = splitmix64(0xb1ac_7070);
+/// One key per (colour, side, rook column), so Chess960 rights hash distinctly.
+const CASTLING_KEYS: [u64; 2 * 2 * 8] = generate_keys(0xca57_1e00);
+
+fn colour_index(colour: ChessColour) -> usize {
+    match colour {
+        ChessColour::White => 0,
+        ChessColour::Black => 1,
+    }
+}
+
+fn kind_index(kind: ChessPieceKind) -> usize {
+    match kind {
+        ChessPieceKind::Pawn => 0,
+        ChessPieceKind::Knight => 1,
+        ChessPieceKind::Bishop => 2,
+        ChessPieceKind::Rook => 3,
+        ChessPieceKind::Queen => 4,
+        ChessPieceKind::King => 5,
+    }
+}
+
+/// The key for `piece` standing on `pos`.
+pub fn piece_key(piece: &ChessPiece, pos: &Position) -> u64 {
+    let piece_index = kind_index(piece.kind) * 2 + colour_index(piece.colour);
+    PIECE_KEYS[piece_index * 64 + Square::from(*pos).index()]
+}
+
+/// The key for the side to move, included in the hash when it is black.
+pub fn black_to_move_key() -> u64 {
+    BLACK_TO_MOVE_KEY
+}
+
+/// The combined key of all the castling rights in `castling`.
+pub fn castling_hash(castling: &CastlingRights) -> u64 {
+    let mut hash = 0;
+    for colour in [ChessColour::White, ChessColour::Black] {
+        for (side_index, side) in [CastlingSide::King, CastlingSide::Queen].iter().enumerate() {
+            if let Some(column) = castling.get(colour, *side) {
+                let index = (colour_index(colour) * 2 + side_index) * 8 + usize::from(column);
+                hash ^= CASTLING_KEYS[index];
+            }
+        }
+    }
+    hash
+}
+
+/// Compute the Zobrist hash of a board from scratch.
+pub fn hash(board: &ChessBoard) -> u64 {
+    let mut hash = 0;
+    for (pos, cell) in board.pieces() {
+        if let Some(piece) = &cell.piece {
+            hash ^= piece_key(piece, &pos);
+        }
+    }
+    if board.turn == ChessColour::Black {
+        hash ^= BLACK_TO_MOVE_KEY;
+    }
+    hash ^ castling_hash(&board.castling)
+}
+
+/// Hash only the pawns on the board, for caching pawn structure evaluation.
+pub fn pawn_hash(board: &ChessBoard) -> u64 {
+    board
+        .pieces()
+        .filter_map(|(pos, cell)| cell.piece.map(|piece| (pos, piece)))
+        .filter(|(_, piece)| piece.kind == ChessPieceKind::Pawn)
+        .fold(0, |hash, (pos, piece)| hash ^ piece_key(&piece, &pos))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::core::Move;
+    use crate::game::ChessGame;
+
+    #[test]
+    fn test_transpositions_hash_equally() {
+        let play = |moves: &[&str]| {
+            let mut game = ChessGame::default();
+            for mv in moves {
+                game.make_move(&Move::from_uci(mv).unwrap()).unwrap();
+            }
+            hash(game.get_board())
+        };
+        let a = play(&["g1f3", "g8f6", "b1c3"]);
+        let b = play(&["b1c3", "g8f6", "g1f3"]);
+        assert_eq!(a, b);
+        assert_ne!(a, play(&["b1c3", "g8f6"]));
+        assert_ne!(hash(&ChessBoard::default()), 0);
+    }
+}